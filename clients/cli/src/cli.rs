@@ -1,8 +1,11 @@
 use {
+    crate::config::{Config, Error},
     clap::{
         builder::{PossibleValuesParser, TypedValueParser},
-        ArgGroup, ArgMatches, Args, Parser, Subcommand,
+        ArgGroup, ArgMatches, Args, Parser, Subcommand, ValueEnum,
     },
+    reqwest::header::{HeaderName, HeaderValue},
+    serde::{Deserialize, Serialize},
     solana_clap_v3_utils::{
         input_parsers::{
             parse_url_or_moniker,
@@ -12,14 +15,22 @@ use {
         keypair::pubkey_from_path,
     },
     solana_cli_output::OutputFormat,
+    solana_commitment_config::CommitmentConfig,
+    solana_hash::Hash,
+    solana_native_token::LAMPORTS_PER_SOL,
     solana_pubkey::Pubkey,
+    solana_signature::Signature,
     spl_single_pool::{self, find_pool_address},
+    spl_single_pool_client::DEFAULT_MAX_CONCURRENT_REQUESTS,
+    std::{path::PathBuf, str::FromStr},
 };
 
 #[derive(Clone, Debug, Parser)]
 #[clap(author, version, about, long_about = None)]
 pub struct Cli {
-    /// Configuration file to use
+    /// Configuration file to use. Single-pool defaults for the compute unit
+    /// price, commitment, and RPC retries are read from `single-pool.yml` in
+    /// the same directory, if it exists. Flags override them
     #[clap(global(true), short = 'C', long = "config", id = "PATH")]
     pub config_file: Option<String>,
 
@@ -43,6 +54,16 @@ pub struct Cli {
     )]
     pub json_rpc_url: Option<String>,
 
+    /// Send an HTTP header with every RPC request, as `KEY: VALUE`, eg an API
+    /// key for an RPC provider that gates access by header. May be repeated
+    #[clap(
+        global(true),
+        long = "rpc-header",
+        value_name = "KEY: VALUE",
+        value_parser = parse_rpc_header,
+    )]
+    pub rpc_headers: Vec<(HeaderName, HeaderValue)>,
+
     /// Specify the fee-payer account. This may be a keypair file, the ASK
     /// keyword or the pubkey of an offline signer, provided an appropriate
     /// --signer argument is also passed. Defaults to the client keypair.
@@ -54,15 +75,130 @@ pub struct Cli {
     )]
     pub fee_payer: Option<SignerSource>,
 
+    /// Use the supplied blockhash rather than fetching the latest one
+    #[clap(
+        global(true),
+        long,
+        value_parser = |s: &str| Hash::from_str(s).map_err(|e| e.to_string()),
+    )]
+    pub blockhash: Option<Hash>,
+
+    /// Sign each transaction and print the signatures instead of submitting it,
+    /// one entry per transaction. Chain state is still read to build the
+    /// transactions, but the blockhash must be supplied with --blockhash
+    #[clap(global(true), long, id = "sign_only", requires = "blockhash")]
+    pub sign_only: bool,
+
+    /// Print each transaction as base64-encoded bincode instead of submitting
+    /// it. With --sign-only, the transaction is only partially signed, for
+    /// the remaining signers to complete
+    #[clap(global(true), long)]
+    pub dump_transaction: bool,
+
+    /// Print the instructions of each transaction as JSON instead of signing and
+    /// submitting it, eg to import into a multisig, one entry per transaction.
+    /// Signers passed as pubkeys still require --sign-only
+    #[clap(global(true), long, conflicts_with = "dump-transaction")]
+    pub instructions_only: bool,
+
+    /// Provide a signature made offline, as PUBKEY=SIGNATURE. Pass the pubkey
+    /// in place of the corresponding keypair to use it
+    #[clap(
+        global(true),
+        long = "signer",
+        id = "signer",
+        value_name = "PUBKEY=SIGNATURE",
+        value_parser = parse_presigner,
+    )]
+    pub signers: Vec<String>,
+
+    /// Before submitting a withdrawal, ask for the pool address to be typed
+    /// back. When not running interactively, --yes is required instead
+    #[clap(global(true), long)]
+    pub confirm: bool,
+
+    /// Skip the confirmation prompt requested by --confirm
+    #[clap(global(true), long, requires = "confirm")]
+    pub yes: bool,
+
+    /// Set a compute unit price, in micro-lamports, to pay a priority fee on
+    /// every transaction sent. `auto` uses the 75th percentile of fees recently
+    /// paid to write the same accounts, or zero if the RPC cannot report them.
+    /// Defaults to `compute_unit_price` in the config file, if any
+    #[clap(global(true), long, value_name = "MICROLAMPORTS|auto", value_parser = parse_compute_unit_price)]
+    pub with_compute_unit_price: Option<ComputeUnitPrice>,
+
+    /// Set a compute unit limit on every transaction sent
+    #[clap(global(true), long, value_name = "UNITS")]
+    pub with_compute_unit_limit: Option<u32>,
+
+    /// Retry RPC requests that fail in transport, eg from a flaky public
+    /// endpoint, this many times. Errors returned by the cluster, such as a
+    /// failed transaction, are never retried. Defaults to `rpc_retries` in
+    /// the config file, or 0
+    #[clap(global(true), long, value_name = "COUNT")]
+    pub rpc_retries: Option<u32>,
+
+    /// Milliseconds to wait before the first RPC retry. The wait doubles
+    /// after each retry. Defaults to `rpc_retry_delay` in the config file, or
+    /// 500
+    #[clap(global(true), long, value_name = "MILLISECONDS")]
+    pub rpc_retry_delay: Option<u64>,
+
+    /// Commitment level to read chain state at, and to wait for after sending
+    /// a transaction. `processed` is fastest, while `finalized` cannot be
+    /// rolled back. Defaults to `commitment` in the config file, or confirmed
+    #[clap(global(true), long, value_enum)]
+    pub commitment: Option<Commitment>,
+
+    /// Print the estimated fee of each transaction before sending it, along
+    /// with any lamports the fee payer transfers out, eg rent for a new pool.
+    /// Always on with --verbose
+    #[clap(global(true), long)]
+    pub estimate_fee: bool,
+
+    /// Seconds to wait for a sent transaction to reach the commitment level
+    /// before giving up. A transaction that times out may still land
+    #[clap(global(true), long, value_name = "SECONDS")]
+    pub confirm_timeout: Option<u64>,
+
+    /// Return each transaction signature as soon as the transaction is sent,
+    /// without waiting for confirmation. Confirming the transaction is then up
+    /// to the caller, and amounts that depend on its outcome are not reported
+    #[clap(global(true), long, conflicts_with = "confirm-timeout")]
+    pub no_wait: bool,
+
+    /// Associated token account program to derive and create pool token
+    /// accounts with, for clusters where it is not deployed at the canonical
+    /// address. Defaults to the canonical program
+    #[clap(
+        global(true),
+        long = "associated-token-program",
+        value_name = "PROGRAM_ID",
+        value_parser = |p: &str| parse_address(p, "associated_token_program_id"),
+    )]
+    pub associated_token_program_id: Option<Pubkey>,
+
     /// Return information in specified output format
     #[clap(
         global(true),
         long = "output",
         id = "FORMAT",
         conflicts_with = "verbose",
-        value_parser = PossibleValuesParser::new(["json", "json-compact"]).map(|o| parse_output_format(&o)),
+        value_parser = PossibleValuesParser::new(["json", "json-compact", "csv", "prometheus"]).map(|o| parse_output_format(&o)),
     )]
-    pub output_format: Option<OutputFormat>,
+    pub output_format: Option<CliOutputFormat>,
+
+    /// Write the command's result to this file, in the format given by
+    /// --output, instead of printing it. This includes the output of
+    /// --sign-only, --dump-transaction and --instructions-only. Status messages
+    /// are still printed. The file is overwritten unless --append is passed
+    #[clap(global(true), long, value_name = "PATH")]
+    pub output_file: Option<PathBuf>,
+
+    /// Append the result to --output-file rather than overwriting it
+    #[clap(global(true), long, requires = "output-file")]
+    pub append: bool,
 
     #[clap(subcommand)]
     pub command: Command,
@@ -90,6 +226,45 @@ pub enum Command {
     /// Deposit liquid sol into a pool in exchange for pool tokens, less a one percent
     /// fee.
     DepositSol(DepositSolCli),
+
+    /// Show how many pool tokens a stake deposit would yield, or how much stake
+    /// a token withdrawal would yield, at the current exchange rate. Sends nothing
+    Quote(QuoteCli),
+
+    /// Show a wallet's pool token balance and the stake it is currently worth.
+    /// Sends nothing
+    Position(PositionCli),
+
+    /// List a wallet's stake accounts delegated to a pool's validator, with
+    /// their activation state and stake, to find accounts to deposit. Sends
+    /// nothing
+    ListStakeAccounts(ListStakeAccountsCli),
+
+    /// Audit a pool's accounts: check that each one exists at its derived
+    /// address, with the authorities and delegation the program expects, and
+    /// that outstanding tokens are backed by stake. Sends nothing
+    Verify(VerifyCli),
+
+    /// Find the pool, vote account, and validator identity for a pool mint.
+    /// This scans every pool, so it may be slow on clusters with many pools
+    Lookup(LookupCli),
+
+    /// Decode single-validator stake pool instruction data, eg from a failed
+    /// transaction, and print the instruction and its fields. Sends nothing
+    DecodeInstruction(DecodeInstructionCli),
+
+    /// Print the borsh schema of the pool account and every instruction as JSON,
+    /// for generating clients. Sends nothing
+    ExportSchema,
+
+    /// Print the CLI version, and optionally the version of the program
+    /// deployed on the cluster. Sends nothing
+    Version(VersionCli),
+
+    /// Show the lamports needed to fund a new pool's accounts at the current
+    /// rent and minimum delegation. Token metadata rent and transaction fees
+    /// are not included. Sends nothing
+    EstimateInitCost,
 }
 
 #[derive(Clone, Debug, Parser)]
@@ -112,6 +287,11 @@ pub enum ManageCommand {
     /// lamports from the main account in the on-ramp account.
     ReplenishPool(ReplenishCli),
 
+    /// Permissionlessly replenish every single-validator stake pool that has
+    /// work to do, packing as many pools into each transaction as fit. Failures
+    /// are reported per pool rather than aborting the run.
+    ReplenishAll(ReplenishAllCli),
+
     /// Permissionlessly create default MPL token metadata for the pool mint.
     /// Normally this is done automatically upon initialization, so this
     /// does not need to be called.
@@ -122,6 +302,11 @@ pub enum ManageCommand {
     /// authority
     UpdateTokenMetadata(UpdateMetadataCli),
 
+    /// Display the vote account authorized withdrawer currently required to
+    /// update the MPL token metadata for a pool. This changes if the
+    /// validator rotates their withdraw authority
+    ShowMetadataAuthority(ShowMetadataAuthorityCli),
+
     /// Permissionlessly create the on-ramp account for an existing single-
     /// validator stake pool, necessary for calling `ReplenishPool`.
     /// This does NOT need to be called after `Initialize`: initialization
@@ -133,12 +318,111 @@ pub enum ManageCommand {
 #[derive(Clone, Debug, Args)]
 pub struct InitializeCli {
     /// The vote account to create the pool for
-    #[clap(value_parser = |p: &str| parse_address(p, "vote_account_address"))]
-    pub vote_account_address: Pubkey,
+    #[clap(
+        required_unless_present = "from-file",
+        value_parser = |p: &str| parse_address(p, "vote_account_address"),
+    )]
+    pub vote_account_address: Option<Pubkey>,
+
+    /// Create pools for every vote account in a file, one address per line.
+    /// Each pool is created in its own transaction. Vote accounts that already
+    /// have a pool are skipped, and failures are reported rather than aborting
+    /// the run
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = &["vote-account-address", "token-name"],
+    )]
+    pub from_file: Option<PathBuf>,
 
     /// Do not create MPL metadata for the pool mint
     #[clap(long)]
     pub skip_metadata: bool,
+
+    /// Do not create the on-ramp account, as pools created by `1.0.x` did.
+    /// The pool cannot be replenished until `create-on-ramp` is run
+    #[clap(long)]
+    pub skip_onramp: bool,
+
+    /// Create the pool mint under Token-2022 rather than the original token program
+    #[clap(long = "token-2022")]
+    pub token_2022: bool,
+
+    /// Name for the pool token, set in the same transaction that creates the
+    /// pool. Requires the vote account authorized withdrawer to sign
+    #[clap(long, requires = "token-symbol", conflicts_with = "skip-metadata", validator = is_valid_token_name)]
+    pub token_name: Option<String>,
+
+    /// Ticker symbol for the pool token, set along with --token-name
+    #[clap(long, requires = "token-name", validator = is_valid_token_symbol)]
+    pub token_symbol: Option<String>,
+
+    /// Optional external URI for the pool token, set along with --token-name
+    #[clap(long, requires = "token-name", validator = is_valid_token_uri)]
+    pub token_uri: Option<String>,
+
+    /// Authorized withdrawer for the vote account, to prove validator
+    /// ownership when setting token metadata. Defaults to the client keypair
+    #[clap(long, id = "AUTHORIZED_WITHDRAWER_KEYPAIR", requires = "token-name", value_parser = SignerSourceParserBuilder::default().allow_all().build())]
+    pub authorized_withdrawer: Option<SignerSource>,
+
+    /// Do not check that the fee payer can cover the pool funding and fee before submitting
+    #[clap(long)]
+    pub skip_balance_check: bool,
+
+    /// Fund the pool stake account with this minimum pool balance, in lamports,
+    /// rather than the one derived from the cluster's minimum delegation. For
+    /// custom clusters and testing. A value below the program's own is
+    /// rejected. A value above it is accepted, but the extra lamports are
+    /// locked in the pool stake account and can never be withdrawn
+    #[clap(long, value_name = "LAMPORTS")]
+    pub minimum_pool_balance: Option<u64>,
+
+    /// Commitment level to wait for before reporting success. Initialization
+    /// cannot be undone, so cautious operators may prefer `finalized`
+    #[clap(long = "await", value_enum, default_value = "confirmed")]
+    pub await_commitment: AwaitCommitment,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum AwaitCommitment {
+    Confirmed,
+    Finalized,
+}
+
+impl From<AwaitCommitment> for CommitmentConfig {
+    fn from(commitment: AwaitCommitment) -> Self {
+        match commitment {
+            AwaitCommitment::Confirmed => CommitmentConfig::confirmed(),
+            AwaitCommitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl From<Commitment> for CommitmentConfig {
+    fn from(commitment: Commitment) -> Self {
+        match commitment {
+            Commitment::Processed => CommitmentConfig::processed(),
+            Commitment::Confirmed => CommitmentConfig::confirmed(),
+            Commitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct ReplenishAllCli {
+    /// Report what replenishing would do for each pool, whether reactivating
+    /// its stake, moving stake, moving lamports, or nothing, without sending
+    #[clap(long)]
+    pub simulate: bool,
 }
 
 #[derive(Clone, Debug, Args)]
@@ -151,15 +435,47 @@ pub struct ReplenishCli {
     /// The vote account corresponding to the pool to replenish
     #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
     pub vote_account_address: Option<Pubkey>,
+
+    /// The validator identity whose vote account corresponds to the pool to replenish
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
+
+    /// Create the pool's onramp account first if it does not exist, as for
+    /// pools initialized before onramps were introduced. The fee payer funds
+    /// its rent
+    #[clap(long)]
+    pub create_onramp_if_missing: bool,
+
+    /// Transfer this many lamports from the client keypair into the pool's main
+    /// stake account before replenishing, eg to pass MEV or tips on to pool
+    /// token holders. No tokens are minted for them. Accepts a unit suffix, eg
+    /// `1.5sol`
+    #[clap(long, value_parser = parse_lamports)]
+    pub amount: Option<u64>,
 }
 
 #[derive(Clone, Debug, Args)]
 #[clap(group(pool_source_group().required(false)))]
 pub struct DepositCli {
-    /// The stake account to deposit from. Must be in the same activation state
-    /// as the pool's stake account
-    #[clap(value_parser = |p: &str| parse_address(p, "stake_account_address"))]
-    pub stake_account_address: Pubkey,
+    /// The stake account(s) to deposit from. All must be delegated to the same
+    /// validator, and should be in the same activation state as the pool's
+    /// stake account. If several are given, any in a different state are
+    /// skipped with a warning
+    #[clap(
+        required_unless_present_any = &["batch-file", "from-all-delegated"],
+        value_parser = |p: &str| parse_address(p, "stake_account_address"),
+    )]
+    pub stake_account_addresses: Vec<Pubkey>,
+
+    /// Deposit every stake account delegated to the pool's validator whose
+    /// withdraw authority is the stake authority. Requires a pool or vote
+    /// account address
+    #[clap(
+        long,
+        requires = "pool-source",
+        conflicts_with_all = &["stake-account-addresses", "batch-file"],
+    )]
+    pub from_all_delegated: bool,
 
     /// The pool to deposit into. Optional for validation
     #[clap(short, long = "pool", value_parser = |p: &str| parse_address(p, "pool_address"))]
@@ -169,29 +485,94 @@ pub struct DepositCli {
     #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
     pub vote_account_address: Option<Pubkey>,
 
+    /// The validator identity whose vote account corresponds to the pool to deposit into. Optional for validation
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
+
+    /// Abort unless the stake is delegated to this vote account. Useful as a
+    /// safeguard when the pool is otherwise derived from the stake account
+    #[clap(long = "expect-vote", conflicts_with = "batch-file", value_parser = |p: &str| parse_address(p, "expected_vote_account_address"))]
+    pub expected_vote_account_address: Option<Pubkey>,
+
     /// Signing authority on the stake account to be deposited. Defaults to the
     /// client keypair
     #[clap(long = "withdraw-authority", id = "STAKE_WITHDRAW_AUTHORITY_KEYPAIR", value_parser = SignerSourceParserBuilder::default().allow_all().build(),)]
     pub stake_withdraw_authority: Option<SignerSource>,
 
+    /// Stake authority on the stake account(s) to be deposited, if different
+    /// from the withdraw authority. Defaults to the withdraw authority
+    #[clap(long = "staker-authority", id = "STAKE_STAKER_AUTHORITY_KEYPAIR", conflicts_with = "batch-file", value_parser = SignerSourceParserBuilder::default().allow_all().build(),)]
+    pub stake_staker_authority: Option<SignerSource>,
+
     /// The token account to mint to. Defaults to the client keypair's
     /// associated token account
     #[clap(long = "token-account", value_parser = |p: &str| parse_address(p, "token_account_address"))]
     pub token_account_address: Option<Pubkey>,
 
+    /// The wallet whose associated token account to mint to, eg a treasury.
+    /// The account is created if it does not exist. Defaults to the client
+    /// keypair
+    #[clap(
+        long = "token-owner",
+        conflicts_with_all = &["token-account-address", "batch-file"],
+        value_parser = |p: &str| parse_address(p, "token_owner_address"),
+    )]
+    pub token_owner_address: Option<Pubkey>,
+
     /// The wallet to refund stake account rent to. Defaults to the client
     /// keypair's pubkey
     #[clap(long = "recipient", value_parser = |p: &str| parse_address(p, "lamport_recipient_address"))]
     pub lamport_recipient_address: Option<Pubkey>,
+
+    /// Deposit only this much stake, in SOL, by splitting it into a new stake
+    /// account and leaving the remainder delegated in the original. Both sides
+    /// must meet the minimum delegation. The fee payer funds rent for the new
+    /// account, which is refunded to the lamport recipient on deposit
+    #[clap(
+        long,
+        value_parser = Amount::parse_decimal,
+        conflicts_with_all = &["batch-file", "from-all-delegated"],
+    )]
+    pub amount: Option<Amount>,
+
+    /// Deposit on behalf of several users from a file of `STAKE_ACCOUNT,TOKEN_OWNER`
+    /// pairs, one per line. All stake accounts must share the same withdraw
+    /// authority. Tokens are minted to each owner's associated token account,
+    /// and stake account rent is refunded to the owner. The fee payer pays for
+    /// everything else
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = &["stake-account-addresses", "token-account-address", "lamport-recipient-address"],
+    )]
+    pub batch_file: Option<PathBuf>,
+
+    /// If the stake account is in a different activation state than the pool,
+    /// wait for the next epoch and retry rather than failing
+    #[clap(long, conflicts_with = "batch-file")]
+    pub wait_for_epoch: bool,
+
+    /// Give up waiting for the next epoch after this many seconds
+    #[clap(long, value_name = "SECONDS", requires = "wait-for-epoch")]
+    pub timeout: Option<u64>,
 }
 
 #[derive(Clone, Debug, Args)]
 #[clap(group(pool_source_group()))]
+#[clap(group(ArgGroup::new("withdrawn-stake").args(&["deactivate", "redelegate-vote-account-address", "keep-stake-active"])))]
 pub struct WithdrawCli {
-    /// Amount of tokens to burn for withdrawal
+    /// Amount of tokens to burn for withdrawal, or amount of stake to receive
+    /// if `--denominate stake` is passed
     #[clap(value_parser = Amount::parse_decimal_or_all)]
     pub token_amount: Amount,
 
+    /// Whether the withdrawal amount is given in pool tokens or in sol worth of
+    /// stake. A stake amount is converted to tokens at the current pool ratio,
+    /// rounding up, so the new stake account receives at least the requested
+    /// amount. ALL always burns every token in the account
+    #[clap(long, value_enum, default_value = "tokens")]
+    pub denominate: Denomination,
+
     /// The token account to withdraw from. Defaults to the associated token
     /// account for the pool mint
     #[clap(long = "token-account", value_parser = |p: &str| parse_address(p, "token_account_address"))]
@@ -205,18 +586,66 @@ pub struct WithdrawCli {
     #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
     pub vote_account_address: Option<Pubkey>,
 
+    /// The validator identity whose vote account corresponds to the pool to withdraw from
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
+
     /// Signing authority on the token account. Defaults to the client keypair
     #[clap(long = "token-authority", id = "TOKEN_AUTHORITY_KEYPAIR", value_parser = SignerSourceParserBuilder::default().allow_all().build())]
     pub token_authority: Option<SignerSource>,
 
-    /// Authority to assign to the new stake account. Defaults to the pubkey of
-    /// the client keypair
-    #[clap(long = "stake-authority", value_parser = |p: &str| parse_address(p, "stake_authority_address"))]
-    pub stake_authority_address: Option<Pubkey>,
+    /// Authority to assign to the new stake account. Defaults to the client
+    /// keypair. A bare address suffices unless the authority must sign, ie
+    /// with --deactivate, --redelegate, or --merge-into
+    #[clap(long = "stake-authority", id = "STAKE_AUTHORITY_KEYPAIR", value_parser = SignerSourceParserBuilder::default().allow_all().build())]
+    pub stake_authority: Option<SignerSource>,
 
     /// Deactivate stake account after withdrawal
     #[clap(long)]
     pub deactivate: bool,
+
+    /// Delegate the new stake account to this vote account after withdrawal.
+    /// Only possible while the pool's stake is not yet active, since active
+    /// stake must be deactivated before it can move. The stake authority must
+    /// sign
+    #[clap(long = "redelegate", value_parser = |p: &str| parse_address(p, "redelegate_vote_account_address"))]
+    pub redelegate_vote_account_address: Option<Pubkey>,
+
+    /// Leave the new stake account delegated to the pool's validator. This is
+    /// the default, and only serves to make it explicit
+    #[clap(long)]
+    pub keep_stake_active: bool,
+
+    /// Merge the new stake account into this existing one after withdrawal. It
+    /// must be fully active, delegated to the pool's vote account, not locked,
+    /// and have both authorities set to the stake authority, which must sign
+    #[clap(long = "merge-into", conflicts_with_all = &["deactivate", "redelegate-vote-account-address"], value_parser = |p: &str| parse_address(p, "merge_into_address"))]
+    pub merge_into_address: Option<Pubkey>,
+
+    /// Close the token account after withdrawing, returning its rent to the
+    /// account owner. Only possible when burning every token in the account,
+    /// eg with ALL
+    #[clap(long)]
+    pub close_token_account: bool,
+
+    /// Keypair for the new stake account. Defaults to a random keypair. Must
+    /// be provided when signing offline, so every signer signs the same
+    /// transaction. May instead be the address of an existing uninitialized
+    /// stake account, which is withdrawn into rather than created
+    #[clap(long = "stake-account", id = "STAKE_ACCOUNT_KEYPAIR", value_parser = SignerSourceParserBuilder::default().allow_all().build())]
+    pub stake_account: Option<SignerSource>,
+
+    /// Keypair file for the new stake account, to keep for later use. If the
+    /// file does not exist, a new keypair is generated and written there first,
+    /// unless nothing is submitted
+    #[clap(long, value_name = "PATH", conflicts_with = "STAKE_ACCOUNT_KEYPAIR")]
+    pub stake_account_keypair: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Denomination {
+    Tokens,
+    Stake,
 }
 
 #[derive(Clone, Debug, Args)]
@@ -229,6 +658,10 @@ pub struct CreateMetadataCli {
     /// The vote account corresponding to the pool to create metadata for
     #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
     pub vote_account_address: Option<Pubkey>,
+
+    /// The validator identity whose vote account corresponds to the pool to create metadata for
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
 }
 
 #[derive(Clone, Debug, Args)]
@@ -255,12 +688,32 @@ pub struct UpdateMetadataCli {
     #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
     pub vote_account_address: Option<Pubkey>,
 
+    /// The validator identity whose vote account corresponds to the pool to create metadata for
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
+
     /// Authorized withdrawer for the vote account, to prove validator
     /// ownership. Defaults to the client keypair
     #[clap(long, id = "AUTHORIZED_WITHDRAWER_KEYPAIR", value_parser = SignerSourceParserBuilder::default().allow_all().build())]
     pub authorized_withdrawer: Option<SignerSource>,
 }
 
+#[derive(Clone, Debug, Args)]
+#[clap(group(pool_source_group()))]
+pub struct ShowMetadataAuthorityCli {
+    /// The pool to show the metadata authority for
+    #[clap(short, long = "pool", value_parser = |p: &str| parse_address(p, "pool_address"))]
+    pub pool_address: Option<Pubkey>,
+
+    /// The vote account corresponding to the pool to show the metadata authority for
+    #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
+    pub vote_account_address: Option<Pubkey>,
+
+    /// The validator identity whose vote account corresponds to the pool to show the metadata authority for
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
+}
+
 #[derive(Clone, Debug, Args)]
 #[clap(group(pool_source_group().arg("all")))]
 pub struct DisplayCli {
@@ -272,9 +725,163 @@ pub struct DisplayCli {
     #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
     pub vote_account_address: Option<Pubkey>,
 
+    /// The validator identity whose vote account corresponds to the pool to display
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
+
     /// Display all pools
     #[clap(long)]
     pub all: bool,
+
+    /// With `--all`, display at most this many pools
+    #[clap(long, requires = "all")]
+    pub limit: Option<usize>,
+
+    /// With `--all`, skip this many pools before displaying. Pools are ordered
+    /// by address, so a window is stable as long as no pools are created
+    #[clap(long, requires = "all")]
+    pub offset: Option<usize>,
+
+    /// Also show an estimated APY for each pool, from its validator's recent
+    /// vote credits and commission and current network inflation. This is a
+    /// projection of past performance, not a guarantee
+    #[clap(long)]
+    pub with_apy: bool,
+
+    /// Maximum number of account requests to keep in flight at once for each
+    /// kind of pool account fetched. Lower this if the RPC node rate-limits you
+    #[clap(long, value_name = "COUNT", default_value_t = DEFAULT_MAX_CONCURRENT_REQUESTS)]
+    pub max_concurrent_requests: usize,
+}
+
+#[derive(Clone, Debug, Args)]
+#[clap(group(pool_source_group()))]
+pub struct QuoteCli {
+    /// Amount of stake to deposit, or amount of tokens to burn if
+    /// `--direction token-to-stake` is passed
+    #[clap(value_parser = Amount::parse_decimal)]
+    pub amount: Amount,
+
+    /// Whether to convert stake into tokens, as in a deposit, or tokens into
+    /// stake, as in a withdrawal
+    #[clap(long, value_enum, default_value = "stake-to-token")]
+    pub direction: QuoteDirection,
+
+    /// The pool to quote
+    #[clap(short, long = "pool", value_parser = |p: &str| parse_address(p, "pool_address"))]
+    pub pool_address: Option<Pubkey>,
+
+    /// The vote account corresponding to the pool to quote
+    #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
+    pub vote_account_address: Option<Pubkey>,
+
+    /// The validator identity whose vote account corresponds to the pool to quote
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, Args)]
+#[clap(group(pool_source_group()))]
+pub struct PositionCli {
+    /// The wallet whose position to show, by its associated token account.
+    /// Defaults to the client keypair
+    #[clap(long = "owner", value_parser = |p: &str| parse_address(p, "owner_address"))]
+    pub owner_address: Option<Pubkey>,
+
+    /// The pool to show a position in
+    #[clap(short, long = "pool", value_parser = |p: &str| parse_address(p, "pool_address"))]
+    pub pool_address: Option<Pubkey>,
+
+    /// The vote account corresponding to the pool to show a position in
+    #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
+    pub vote_account_address: Option<Pubkey>,
+
+    /// The validator identity whose vote account corresponds to the pool to show a position in
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, Args)]
+#[clap(group(pool_source_group()))]
+pub struct ListStakeAccountsCli {
+    /// The withdraw authority of the stake accounts to list. Defaults to the
+    /// client keypair
+    #[clap(long = "owner", value_parser = |p: &str| parse_address(p, "owner_address"))]
+    pub owner_address: Option<Pubkey>,
+
+    /// The pool whose validator the stake accounts are delegated to
+    #[clap(short, long = "pool", value_parser = |p: &str| parse_address(p, "pool_address"))]
+    pub pool_address: Option<Pubkey>,
+
+    /// The vote account the stake accounts are delegated to
+    #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
+    pub vote_account_address: Option<Pubkey>,
+
+    /// The validator identity whose vote account the stake accounts are delegated to
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, Args)]
+#[clap(group(pool_source_group()))]
+pub struct VerifyCli {
+    /// The pool to verify
+    #[clap(value_parser = |p: &str| parse_address(p, "pool_address"))]
+    pub pool_address: Option<Pubkey>,
+
+    /// The vote account corresponding to the pool to verify
+    #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
+    pub vote_account_address: Option<Pubkey>,
+
+    /// The validator identity whose vote account corresponds to the pool to verify
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct LookupCli {
+    /// The pool mint to look up, eg from a token account in your wallet
+    #[clap(long = "mint", value_parser = |p: &str| parse_address(p, "mint_address"))]
+    pub mint_address: Pubkey,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct VersionCli {
+    /// Also query the deployed program for its version, by simulating a
+    /// `GetVersion` instruction. Requires a fee payer to simulate with
+    #[clap(long)]
+    pub onchain: bool,
+
+    /// Program to query, if not the canonical single-validator stake pool program
+    #[clap(
+        long = "program-id",
+        requires = "onchain",
+        value_parser = |p: &str| parse_address(p, "program_id"),
+    )]
+    pub program_id: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct DecodeInstructionCli {
+    /// Instruction data, as hex or base64
+    pub data: String,
+
+    /// Encoding of the instruction data. If omitted, hex is tried first, then base64
+    #[clap(long, value_enum)]
+    pub encoding: Option<DataEncoding>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DataEncoding {
+    Hex,
+    Base64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuoteDirection {
+    StakeToToken,
+    TokenToStake,
 }
 
 #[derive(Clone, Debug, Args)]
@@ -287,12 +894,18 @@ pub struct CreateOnRampCli {
     /// The vote account corresponding to the pool to create the on-ramp for
     #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
     pub vote_account_address: Option<Pubkey>,
+
+    /// The validator identity whose vote account corresponds to the pool to create the on-ramp for
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
 }
 
 #[derive(Clone, Debug, Args)]
 #[clap(group(pool_source_group()))]
 pub struct DepositSolCli {
-    /// Lamports to deposit into pool
+    /// Lamports to deposit into pool. Accepts a unit suffix, eg `1.5sol`,
+    /// `1000000lamports`, or `500kL`
+    #[clap(value_parser = parse_lamports)]
     pub lamports: u64,
 
     /// The pool to deposit into
@@ -303,6 +916,10 @@ pub struct DepositSolCli {
     #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
     pub vote_account_address: Option<Pubkey>,
 
+    /// The validator identity whose vote account corresponds to the pool to deposit into
+    #[clap(long = "validator-identity", value_parser = |p: &str| parse_address(p, "validator_identity"))]
+    pub validator_identity: Option<Pubkey>,
+
     /// The wallet to deposit lamports from. Defaults to the client
     /// keypair
     #[clap(long, id = "DEPOSIT_SOURCE_KEYPAIR", value_parser = SignerSourceParserBuilder::default().allow_all().build())]
@@ -314,9 +931,11 @@ pub struct DepositSolCli {
     pub token_account_address: Option<Pubkey>,
 }
 fn pool_source_group() -> ArgGroup<'static> {
-    ArgGroup::new("pool-source")
-        .required(true)
-        .args(&["pool-address", "vote-account-address"])
+    ArgGroup::new("pool-source").required(true).args(&[
+        "pool-address",
+        "vote-account-address",
+        "validator-identity",
+    ])
 }
 
 fn parse_address(path: &str, name: &str) -> Result<Pubkey, String> {
@@ -325,10 +944,122 @@ fn parse_address(path: &str, name: &str) -> Result<Pubkey, String> {
         .map_err(|_| format!("Failed to load pubkey {} at {}", name, path))
 }
 
-pub fn parse_output_format(output_format: &str) -> OutputFormat {
+// bare integers are lamports, for compatibility. suffixes are case-insensitive
+pub fn parse_lamports(amount: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid lamport amount {}", amount);
+
+    let split = amount
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(amount.len());
+    let (number, unit) = amount.split_at(split);
+
+    let scale: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "l" | "lamport" | "lamports" => 1,
+        "kl" => 1_000,
+        "ml" => 1_000_000,
+        "sol" => LAMPORTS_PER_SOL,
+        _ => return Err(invalid()),
+    };
+
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(invalid());
+    }
+
+    let whole = if whole.is_empty() {
+        0
+    } else {
+        whole.parse::<u64>().map_err(|_| invalid())?
+    };
+    let whole_lamports = whole.checked_mul(scale).ok_or_else(invalid)?;
+
+    // the fractional part must resolve to a whole number of lamports
+    let fraction_lamports = if fraction.is_empty() {
+        0
+    } else {
+        let denominator = u32::try_from(fraction.len())
+            .ok()
+            .and_then(|digits| 10u128.checked_pow(digits))
+            .ok_or_else(invalid)?;
+        let numerator = fraction
+            .parse::<u128>()
+            .ok()
+            .and_then(|fraction| fraction.checked_mul(u128::from(scale)))
+            .ok_or_else(invalid)?;
+
+        if numerator % denominator != 0 {
+            return Err(format!("{} is not a whole number of lamports", amount));
+        }
+
+        u64::try_from(numerator / denominator).map_err(|_| invalid())?
+    };
+
+    whole_lamports
+        .checked_add(fraction_lamports)
+        .ok_or_else(invalid)
+}
+
+// we only validate here. solana-clap-v3-utils reads the raw values to build presigners
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComputeUnitPrice {
+    MicroLamports(u64),
+    Auto,
+}
+
+pub fn parse_compute_unit_price(price: &str) -> Result<ComputeUnitPrice, String> {
+    if price.eq_ignore_ascii_case("auto") {
+        Ok(ComputeUnitPrice::Auto)
+    } else {
+        price
+            .parse::<u64>()
+            .map(ComputeUnitPrice::MicroLamports)
+            .map_err(|_| {
+                format!(
+                    "Invalid compute unit price {}, expected MICROLAMPORTS or auto",
+                    price
+                )
+            })
+    }
+}
+
+fn parse_presigner(presigner: &str) -> Result<String, String> {
+    presigner
+        .split_once('=')
+        .filter(|(pubkey, signature)| {
+            Pubkey::from_str(pubkey).is_ok() && Signature::from_str(signature).is_ok()
+        })
+        .map(|_| presigner.to_string())
+        .ok_or_else(|| format!("Invalid presigner {}, expected PUBKEY=SIGNATURE", presigner))
+}
+
+fn parse_rpc_header(header: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or("Invalid RPC header, expected KEY: VALUE")?;
+    let name = name.trim();
+
+    Ok((
+        HeaderName::from_str(name).map_err(|_| format!("Invalid RPC header name {}", name))?,
+        HeaderValue::from_str(value.trim())
+            .map_err(|_| format!("Invalid value for RPC header {}", name))?,
+    ))
+}
+
+/// Machine-readable output formats. CSV and Prometheus are only supported by
+/// `display`
+#[derive(Clone, Debug, PartialEq)]
+pub enum CliOutputFormat {
+    Json(OutputFormat),
+    Csv,
+    Prometheus,
+}
+
+pub fn parse_output_format(output_format: &str) -> CliOutputFormat {
     match output_format {
-        "json" => OutputFormat::Json,
-        "json-compact" => OutputFormat::JsonCompact,
+        "json" => CliOutputFormat::Json(OutputFormat::Json),
+        "json-compact" => CliOutputFormat::Json(OutputFormat::JsonCompact),
+        "csv" => CliOutputFormat::Csv,
+        "prometheus" => CliOutputFormat::Prometheus,
         _ => unreachable!(),
     }
 }
@@ -357,21 +1088,109 @@ pub fn is_valid_token_uri(s: &str) -> Result<(), String> {
     }
 }
 
-pub fn pool_address_from_args(maybe_pool: Option<Pubkey>, maybe_vote: Option<Pubkey>) -> Pubkey {
+pub async fn resolve_pool_address(
+    config: &Config,
+    maybe_pool: Option<Pubkey>,
+    maybe_vote: Option<Pubkey>,
+    maybe_identity: Option<Pubkey>,
+) -> Result<Option<Pubkey>, Error> {
     if let Some(pool_address) = maybe_pool {
-        pool_address
+        Ok(Some(pool_address))
     } else if let Some(vote_account_address) = maybe_vote {
-        find_pool_address(&spl_single_pool::id(), &vote_account_address)
+        Ok(Some(find_pool_address(
+            &spl_single_pool::id(),
+            &vote_account_address,
+        )))
+    } else if let Some(validator_identity) = maybe_identity {
+        let vote_account_addresses = config
+            .client
+            .get_vote_accounts_by_identity(&validator_identity)
+            .await?;
+
+        match vote_account_addresses.as_slice() {
+            [] => Err(format!(
+                "No vote account found for validator identity {}",
+                validator_identity
+            )
+            .into()),
+            [vote_account_address] => Ok(Some(find_pool_address(
+                &spl_single_pool::id(),
+                vote_account_address,
+            ))),
+            _ => Err(format!(
+                "Validator identity {} has multiple vote accounts: {}. Specify one with --vote-account",
+                validator_identity,
+                vote_account_addresses
+                    .iter()
+                    .map(|address| address.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .into()),
+        }
     } else {
-        unreachable!()
+        Ok(None)
     }
 }
 
+pub async fn pool_address_from_args(
+    config: &Config,
+    maybe_pool: Option<Pubkey>,
+    maybe_vote: Option<Pubkey>,
+    maybe_identity: Option<Pubkey>,
+) -> Result<Pubkey, Error> {
+    // clap guarantees exactly one pool source is present
+    Ok(
+        resolve_pool_address(config, maybe_pool, maybe_vote, maybe_identity)
+            .await?
+            .unwrap(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     // if this test fails, we changed the fee. fix the comment on Command::DepositSol
     #[test]
     fn test_deposit_sol_fee() {
         assert_eq!(spl_single_pool::DEPOSIT_SOL_FEE_BPS, 100);
     }
+
+    #[test]
+    fn test_parse_lamports() {
+        assert_eq!(parse_lamports("1000"), Ok(1000));
+        assert_eq!(parse_lamports("1000000lamports"), Ok(1_000_000));
+        assert_eq!(parse_lamports("1lamport"), Ok(1));
+        assert_eq!(parse_lamports("500kL"), Ok(500_000));
+        assert_eq!(parse_lamports("2ML"), Ok(2_000_000));
+        assert_eq!(parse_lamports("1.5sol"), Ok(1_500_000_000));
+        assert_eq!(parse_lamports("1.5 SOL"), Ok(1_500_000_000));
+        assert_eq!(parse_lamports(".000000001sol"), Ok(1));
+        assert_eq!(parse_lamports("0.5kL"), Ok(500));
+
+        assert!(parse_lamports("").is_err());
+        assert!(parse_lamports("sol").is_err());
+        assert!(parse_lamports("1.5").is_err());
+        assert!(parse_lamports("0.0000000001sol").is_err());
+        assert!(parse_lamports("1.2.3sol").is_err());
+        assert!(parse_lamports("1eth").is_err());
+        assert!(parse_lamports("-1sol").is_err());
+        assert!(parse_lamports("18446744073709551616").is_err());
+        assert!(parse_lamports("18446744074sol").is_err());
+    }
+
+    #[test]
+    fn test_parse_compute_unit_price() {
+        assert_eq!(
+            parse_compute_unit_price("1000"),
+            Ok(ComputeUnitPrice::MicroLamports(1000))
+        );
+        assert_eq!(parse_compute_unit_price("auto"), Ok(ComputeUnitPrice::Auto));
+        assert_eq!(parse_compute_unit_price("AUTO"), Ok(ComputeUnitPrice::Auto));
+
+        assert!(parse_compute_unit_price("").is_err());
+        assert!(parse_compute_unit_price("-1").is_err());
+        assert!(parse_compute_unit_price("automatic").is_err());
+    }
 }
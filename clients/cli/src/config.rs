@@ -1,20 +1,42 @@
 use {
     crate::cli::*,
     clap::ArgMatches,
+    serde::{Deserialize, Serialize},
     solana_account::Account,
     solana_clap_v3_utils::keypair::{signer_from_path, signer_from_source},
     solana_cli_output::OutputFormat,
-    solana_client::nonblocking::rpc_client::RpcClient,
-    solana_commitment_config::CommitmentConfig,
+    solana_client::{
+        client_error::{ClientError, ClientErrorKind},
+        nonblocking::rpc_client::RpcClient,
+    },
+    solana_compute_budget_interface::ComputeBudgetInstruction,
+    solana_hash::Hash,
+    solana_instruction::Instruction,
     solana_pubkey::Pubkey,
     solana_remote_wallet::remote_wallet::RemoteWalletManager,
+    solana_rpc_client::{http_sender::HttpSender, rpc_client::RpcClientConfig},
     solana_signer::Signer,
-    std::{process::exit, rc::Rc, sync::Arc},
+    spl_associated_token_account_interface::instruction::create_associated_token_account,
+    spl_single_pool_client::SinglePoolClient,
+    std::{
+        cell::RefCell,
+        future::Future,
+        io::{IsTerminal, Write},
+        path::Path,
+        process::exit,
+        rc::Rc,
+        sync::Arc,
+        time::Duration,
+    },
 };
 
-pub type Error = Box<dyn std::error::Error + Send + Sync>;
+pub use spl_single_pool_client::Error;
 
 pub fn println_display(config: &Config, message: String) {
+    if config.csv_output || config.prometheus_output {
+        return;
+    }
+
     match config.output_format {
         OutputFormat::Display | OutputFormat::DisplayVerbose => {
             println!("{}", message);
@@ -23,12 +45,113 @@ pub fn println_display(config: &Config, message: String) {
     }
 }
 
+// with --confirm, require the user to type `expected` back before we submit anything.
+// nothing is submitted in a dry run or when only signing, so there is nothing to confirm
+pub fn confirm_submission(config: &Config, expected: &str, action: &str) -> Result<(), Error> {
+    if !config.confirm
+        || config.yes
+        || config.dry_run
+        || config.sign_only
+        || config.instructions_only
+    {
+        return Ok(());
+    }
+
+    let interactive = std::io::stdin().is_terminal()
+        && matches!(
+            config.output_format,
+            OutputFormat::Display | OutputFormat::DisplayVerbose
+        );
+    if !interactive {
+        return Err(format!(
+            "Cannot confirm {} interactively; pass --yes to proceed without a prompt",
+            action
+        )
+        .into());
+    }
+
+    eprint!("To confirm {}, type {}: ", action, expected);
+    std::io::stderr().flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+
+    if input.trim() == expected {
+        Ok(())
+    } else {
+        Err(format!("Confirmation did not match; {} aborted", action).into())
+    }
+}
+
+// the request may never have reached the cluster, so it is safe to try again
+fn is_transport_error(error: &ClientError) -> bool {
+    matches!(
+        error.kind(),
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_)
+    )
+}
+
+pub const SINGLE_POOL_CONFIG_FILE_NAME: &str = "single-pool.yml";
+
+// matches the timeout of the default rpc client sender
+const RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Single-pool defaults for flags that would otherwise be passed on every run,
+/// read from `single-pool.yml` beside the Solana config file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SinglePoolConfigFile {
+    /// Compute unit price in micro-lamports, or `auto`
+    pub compute_unit_price: Option<String>,
+    pub commitment: Option<Commitment>,
+    pub rpc_retries: Option<u32>,
+    /// Milliseconds
+    pub rpc_retry_delay: Option<u64>,
+}
+
+impl SinglePoolConfigFile {
+    // A missing file means no defaults, but a malformed one is an error
+    pub fn load_beside(config_file_path: &str) -> Self {
+        let path = Path::new(config_file_path).with_file_name(SINGLE_POOL_CONFIG_FILE_NAME);
+        if !path.exists() {
+            return Self::default();
+        }
+
+        solana_cli_config::load_config_file(&path).unwrap_or_else(|e| {
+            eprintln!(
+                "error: Could not load config file `{}`: {}",
+                path.display(),
+                e
+            );
+            exit(1);
+        })
+    }
+}
+
 pub struct Config {
     pub rpc_client: Arc<RpcClient>,
+    pub client: SinglePoolClient,
     pub default_signer: Option<Arc<dyn Signer>>,
     pub fee_payer: Option<Arc<dyn Signer>>,
     pub output_format: OutputFormat,
+    pub csv_output: bool,
+    pub prometheus_output: bool,
     pub dry_run: bool,
+    pub sign_only: bool,
+    pub dump_transaction: bool,
+    pub instructions_only: bool,
+    pub confirm: bool,
+    pub yes: bool,
+    pub blockhash: Option<Hash>,
+    pub compute_unit_price: Option<ComputeUnitPrice>,
+    pub compute_unit_limit: Option<u32>,
+    pub rpc_retries: u32,
+    pub rpc_retry_delay: Duration,
+    pub confirm_timeout: Option<Duration>,
+    pub estimate_fee: bool,
+    pub no_wait: bool,
+    pub associated_token_program_id: Pubkey,
+    pub offline_output: RefCell<Vec<String>>,
 }
 impl Config {
     pub fn new(
@@ -48,11 +171,58 @@ impl Config {
             solana_cli_config::Config::default()
         };
 
-        // create rpc client
-        let rpc_client = Arc::new(RpcClient::new_with_commitment(
-            cli.json_rpc_url.unwrap_or(cli_config.json_rpc_url),
-            CommitmentConfig::confirmed(),
-        ));
+        // single-pool settings live beside the solana config file, and flags override them
+        let config_file_path = cli
+            .config_file
+            .as_deref()
+            .or(solana_cli_config::CONFIG_FILE.as_deref());
+        let single_pool_config = config_file_path
+            .map(SinglePoolConfigFile::load_beside)
+            .unwrap_or_default();
+
+        let compute_unit_price = cli.with_compute_unit_price.or_else(|| {
+            single_pool_config
+                .compute_unit_price
+                .as_deref()
+                .map(|price| {
+                    parse_compute_unit_price(price).unwrap_or_else(|e| {
+                        eprintln!("error: {} in `{}`", e, SINGLE_POOL_CONFIG_FILE_NAME);
+                        exit(1);
+                    })
+                })
+        });
+        let commitment = cli
+            .commitment
+            .or(single_pool_config.commitment)
+            .unwrap_or(Commitment::Confirmed);
+        let rpc_retries = cli
+            .rpc_retries
+            .or(single_pool_config.rpc_retries)
+            .unwrap_or(0);
+        let rpc_retry_delay = cli
+            .rpc_retry_delay
+            .or(single_pool_config.rpc_retry_delay)
+            .unwrap_or(500);
+
+        // create rpc client. extra headers need an http client of our own
+        let json_rpc_url = cli.json_rpc_url.unwrap_or(cli_config.json_rpc_url);
+        let rpc_client = Arc::new(if cli.rpc_headers.is_empty() {
+            RpcClient::new_with_commitment(json_rpc_url, commitment.into())
+        } else {
+            let http_client = reqwest::Client::builder()
+                .default_headers(cli.rpc_headers.iter().cloned().collect())
+                .timeout(RPC_TIMEOUT)
+                .build()
+                .unwrap_or_else(|e| {
+                    eprintln!("error: Could not build RPC client: {}", e);
+                    exit(1);
+                });
+
+            RpcClient::new_sender(
+                HttpSender::new_with_client(json_rpc_url, http_client),
+                RpcClientConfig::with_commitment(commitment.into()),
+            )
+        });
 
         // resolve default signer
         let default_keypair = cli_config.keypair_path;
@@ -72,18 +242,76 @@ impl Config {
             .or(default_signer.clone());
 
         // determine output format
+        let csv_output = cli.output_format == Some(CliOutputFormat::Csv);
+        let prometheus_output = cli.output_format == Some(CliOutputFormat::Prometheus);
         let output_format = match (cli.output_format, cli.verbose) {
-            (Some(json_format), _) => json_format,
+            (Some(CliOutputFormat::Json(json_format)), _) => json_format,
+            (Some(CliOutputFormat::Csv | CliOutputFormat::Prometheus), _) | (None, false) => {
+                OutputFormat::Display
+            }
             (None, true) => OutputFormat::DisplayVerbose,
-            (None, false) => OutputFormat::Display,
         };
 
         Self {
+            client: SinglePoolClient::new(rpc_client.clone()),
             rpc_client,
             default_signer,
             fee_payer,
             output_format,
+            csv_output,
+            prometheus_output,
             dry_run: cli.dry_run,
+            sign_only: cli.sign_only,
+            dump_transaction: cli.dump_transaction,
+            instructions_only: cli.instructions_only,
+            confirm: cli.confirm,
+            yes: cli.yes,
+            blockhash: cli.blockhash,
+            compute_unit_price,
+            compute_unit_limit: cli.with_compute_unit_limit,
+            rpc_retries,
+            rpc_retry_delay: Duration::from_millis(rpc_retry_delay),
+            confirm_timeout: cli.confirm_timeout.map(Duration::from_secs),
+            estimate_fee: cli.estimate_fee,
+            no_wait: cli.no_wait,
+            associated_token_program_id: cli
+                .associated_token_program_id
+                .unwrap_or(spl_associated_token_account_interface::program::id()),
+            offline_output: RefCell::default(),
+        }
+    }
+
+    // Record output that stands in for submitting a transaction, eg signatures with
+    // `--sign-only`. A command may build several transactions, so it is all printed at the end
+    pub fn push_offline_output(&self, output: String) {
+        self.offline_output.borrow_mut().push(output);
+    }
+
+    // Run an RPC request, retrying transport failures up to `rpc_retries` times with
+    // exponential backoff. Anything the cluster itself returned is definitive
+    pub async fn with_rpc_retries<T, F, Fut>(&self, mut rpc_request: F) -> Result<T, ClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ClientError>>,
+    {
+        let mut delay = self.rpc_retry_delay;
+        let mut retries = 0;
+
+        loop {
+            match rpc_request().await {
+                Err(e) if retries < self.rpc_retries && is_transport_error(&e) => {
+                    eprintln!(
+                        "RPC request failed, retrying in {}ms: {}",
+                        delay.as_millis(),
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+
+                    delay = delay.saturating_mul(2);
+                    retries += 1;
+                }
+                result => return result,
+            }
         }
     }
 
@@ -111,16 +339,98 @@ impl Config {
         }
     }
 
+    // Returns the user-provided blockhash if any, otherwise the latest blockhash
+    pub async fn get_blockhash(&self) -> Result<Hash, Error> {
+        if let Some(blockhash) = self.blockhash {
+            Ok(blockhash)
+        } else {
+            Ok(self.rpc_client.get_latest_blockhash().await?)
+        }
+    }
+
+    // Returns the given instructions, preceded by any requested compute budget instructions
+    pub async fn with_compute_budget(&self, instructions: &[Instruction]) -> Vec<Instruction> {
+        let compute_unit_price = match self.compute_unit_price {
+            Some(ComputeUnitPrice::MicroLamports(compute_unit_price)) => Some(compute_unit_price),
+            Some(ComputeUnitPrice::Auto) => {
+                Some(crate::estimate_compute_unit_price(self, instructions).await)
+            }
+            None => None,
+        };
+
+        self.with_compute_unit_price(instructions, compute_unit_price)
+    }
+
+    // Like `with_compute_budget()`, but with the compute unit price already resolved
+    pub fn with_compute_unit_price(
+        &self,
+        instructions: &[Instruction],
+        compute_unit_price: Option<u64>,
+    ) -> Vec<Instruction> {
+        let mut budgeted_instructions = vec![];
+
+        if let Some(compute_unit_limit) = self.compute_unit_limit {
+            budgeted_instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(
+                compute_unit_limit,
+            ));
+        }
+
+        if let Some(compute_unit_price) = compute_unit_price {
+            budgeted_instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+                compute_unit_price,
+            ));
+        }
+
+        budgeted_instructions.extend_from_slice(instructions);
+        budgeted_instructions
+    }
+
+    // Associated token account address under the configured associated token program
+    pub fn associated_token_address(
+        &self,
+        wallet_address: &Pubkey,
+        mint_address: &Pubkey,
+        token_program_id: &Pubkey,
+    ) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                wallet_address.as_ref(),
+                token_program_id.as_ref(),
+                mint_address.as_ref(),
+            ],
+            &self.associated_token_program_id,
+        )
+        .0
+    }
+
+    // Creates an associated token account with the configured associated token program
+    pub fn create_associated_token_account(
+        &self,
+        funding_address: &Pubkey,
+        wallet_address: &Pubkey,
+        mint_address: &Pubkey,
+        token_program_id: &Pubkey,
+    ) -> Instruction {
+        let mut instruction = create_associated_token_account(
+            funding_address,
+            wallet_address,
+            mint_address,
+            token_program_id,
+        );
+
+        // the builder derives the account from the canonical program, so both must change
+        instruction.program_id = self.associated_token_program_id;
+        instruction.accounts[1].pubkey =
+            self.associated_token_address(wallet_address, mint_address, token_program_id);
+
+        instruction
+    }
+
     pub fn verbose(&self) -> bool {
         self.output_format == OutputFormat::DisplayVerbose
     }
 
     pub async fn get_initialized_account(&self, pubkey: Pubkey) -> Result<Option<Account>, Error> {
-        Ok(self
-            .rpc_client
-            .get_account_with_commitment(&pubkey, self.rpc_client.commitment())
-            .await?
-            .value
-            .filter(|account| !account.data.is_empty()))
+        self.client.get_initialized_account(pubkey).await
     }
 }
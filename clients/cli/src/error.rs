@@ -0,0 +1,26 @@
+use {solana_pubkey::Pubkey, thiserror::Error};
+
+/// Failures scripts may want to branch on. Each has its own exit code; any
+/// other error exits with 1, and clap usage errors exit with 2.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum CliError {
+    /// No account exists at the pool address
+    #[error("Pool {0} has not been initialized")]
+    PoolNotInitialized(Pubkey),
+    /// An account exists at the pool address, but the SVSP program does not own it
+    #[error("{0} is not owned by the SVSP program")]
+    PoolNotOwnedByProgram(Pubkey),
+    /// The SVSP program owns the account, but it does not parse as a pool
+    #[error("{0} is owned by the SVSP program but not a valid pool account")]
+    InvalidPoolAccount(Pubkey),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::PoolNotInitialized(_) => 3,
+            Self::PoolNotOwnedByProgram(_) => 4,
+            Self::InvalidPoolAccount(_) => 5,
+        }
+    }
+}
@@ -2,30 +2,74 @@
 #![allow(clippy::uninlined_format_args)]
 
 use {
+    base64::{prelude::BASE64_STANDARD, Engine},
     clap::{ArgMatches, CommandFactory, Parser},
-    solana_borsh::v1::try_from_slice_unchecked,
-    solana_clap_v3_utils::{input_parsers::Amount, keypair::signer_from_source},
+    solana_account::Account,
+    solana_account_decoder::UiAccountEncoding,
+    solana_clap_v3_utils::{
+        input_parsers::{
+            signer::{SignerSource, SignerSourceKind},
+            Amount,
+        },
+        keypair::signer_from_source,
+    },
+    solana_cli_output::{return_signers_with_config, OutputFormat, ReturnSignersConfig},
     solana_client::{
-        rpc_config::RpcProgramAccountsConfig,
+        client_error::ClientError,
+        rpc_config::{
+            RpcProgramAccountsConfig, RpcSimulateTransactionAccountsConfig,
+            RpcSimulateTransactionConfig,
+        },
         rpc_filter::{Memcmp, RpcFilterType},
     },
-    solana_keypair::Keypair,
+    solana_commitment_config::CommitmentConfig,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_instruction_error::InstructionError,
+    solana_keypair::{read_keypair_file, write_keypair_file, Keypair},
+    solana_program_pack::Pack,
     solana_pubkey::Pubkey,
     solana_remote_wallet::remote_wallet::RemoteWalletManager,
+    solana_rent::Rent,
+    solana_sdk_ids::compute_budget,
     solana_signature::Signature,
     solana_signer::Signer,
-    solana_stake_interface::{self as stake, state::StakeStateV2},
+    solana_stake_interface::{
+        self as stake,
+        state::{Lockup, StakeStateV2},
+    },
+    solana_system_interface::{
+        instruction::{self as system_instruction, SystemInstruction},
+        program as system_program,
+    },
     solana_transaction::Transaction,
+    solana_transaction_error::TransactionError,
     solana_vote_interface::{program as vote_program, state::VoteStateV4},
-    spl_associated_token_account_interface::{
-        address::get_associated_token_address, instruction::create_associated_token_account,
-    },
     spl_single_pool::{
-        self, find_pool_address, find_pool_mint_address, find_pool_onramp_address,
-        find_pool_stake_address, instruction::SinglePoolInstruction, state::SinglePool,
+        self,
+        error::SinglePoolError,
+        find_pool_address, find_pool_mint_address, find_pool_onramp_address,
+        find_pool_stake_address,
+        inline_mpl_token_metadata::{self, pda::find_metadata_account},
+        inline_spl_token_2022,
+        instruction::{BatchDeposit, SinglePoolInstruction},
+        state::SinglePool,
+        PoolAddresses, ProgramVersion,
+    },
+    spl_token_interface::{
+        self as spl_token,
+        state::{Account as TokenAccount, Mint},
+    },
+    std::{
+        collections::HashMap,
+        fs,
+        io::Write,
+        path::Path,
+        process::exit,
+        rc::Rc,
+        str::FromStr,
+        sync::Arc,
+        time::{Duration, Instant},
     },
-    spl_token_interface as spl_token,
-    std::{rc::Rc, sync::Arc},
 };
 
 mod config;
@@ -34,10 +78,25 @@ use config::*;
 mod cli;
 use cli::*;
 
+mod error;
+use error::*;
+
 mod output;
 use output::*;
 
-mod quarantine;
+mod schema;
+
+// maximum serialized size of a legacy transaction
+const PACKET_DATA_SIZE: usize = 1232;
+
+// most accounts `getRecentPrioritizationFees` accepts
+const MAX_PRIORITIZATION_FEE_ADDRESSES: usize = 128;
+
+// how often to check whether a new epoch has begun
+const EPOCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// leading `Key` byte of an mpl metadata account once it has been created
+const MPL_KEY_METADATA_V1: u8 = 4;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
@@ -49,11 +108,53 @@ async fn main() -> Result<(), Error> {
 
     solana_logger::setup_with_default("solana=info");
 
-    let res = cli
+    if config.csv_output && !matches!(cli.command, Command::Display(_)) {
+        return Err("CSV output is only supported by `display`".into());
+    }
+
+    if config.prometheus_output && !matches!(cli.command, Command::Display(_)) {
+        return Err("Prometheus output is only supported by `display`".into());
+    }
+
+    let res = match cli
         .command
         .execute(&config, &matches, &mut wallet_manager)
-        .await?;
-    println!("{}", res);
+        .await
+    {
+        Ok(res) => res,
+        Err(error) => match error.downcast_ref::<CliError>() {
+            Some(cli_error) => {
+                eprintln!("Error: {}", cli_error);
+                exit(cli_error.exit_code());
+            }
+            None => return Err(error),
+        },
+    };
+
+    // transactions we only signed, dumped, or decompiled, one entry each. with --sign-only or
+    // --instructions-only, this output stands in for the command output, which would describe
+    // transactions that were never sent. either way it goes wherever the command output goes
+    let offline_output = config.offline_output.take();
+    let res = if offline_output.is_empty() {
+        res
+    } else if config.sign_only || config.instructions_only {
+        offline_output.join("\n")
+    } else {
+        format!("{}\n{}", offline_output.join("\n"), res)
+    };
+
+    if let Some(output_file) = &cli.output_file {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(cli.append)
+            .truncate(!cli.append)
+            .open(output_file)
+            .map_err(|e| format!("Failed to open {}: {}", output_file.display(), e))?;
+        writeln!(file, "{}", res)?;
+    } else {
+        println!("{}", res);
+    }
 
     Ok(())
 }
@@ -70,17 +171,23 @@ impl Command {
         match self {
             Command::Manage(command) => match command.manage {
                 ManageCommand::Initialize(command_config) => {
-                    command_initialize(config, command_config).await
+                    command_initialize(config, command_config, matches, wallet_manager).await
                 }
                 ManageCommand::ReplenishPool(command_config) => {
                     command_replenish_pool(config, command_config).await
                 }
+                ManageCommand::ReplenishAll(command_config) => {
+                    command_replenish_all(config, command_config).await
+                }
                 ManageCommand::CreateTokenMetadata(command_config) => {
                     command_create_metadata(config, command_config).await
                 }
                 ManageCommand::UpdateTokenMetadata(command_config) => {
                     command_update_metadata(config, command_config, matches, wallet_manager).await
                 }
+                ManageCommand::ShowMetadataAuthority(command_config) => {
+                    command_show_metadata_authority(config, command_config).await
+                }
                 ManageCommand::CreateOnRamp(command_config) => {
                     command_create_onramp(config, command_config).await
                 }
@@ -95,14 +202,147 @@ impl Command {
             Command::DepositSol(command_config) => {
                 command_deposit_sol(config, command_config, matches, wallet_manager).await
             }
+            Command::Quote(command_config) => command_quote(config, command_config).await,
+            Command::Position(command_config) => command_position(config, command_config).await,
+            Command::ListStakeAccounts(command_config) => {
+                command_list_stake_accounts(config, command_config).await
+            }
+            Command::Verify(command_config) => command_verify(config, command_config).await,
+            Command::Lookup(command_config) => command_lookup(config, command_config).await,
+            Command::DecodeInstruction(command_config) => {
+                command_decode_instruction(config, command_config)
+            }
+            Command::ExportSchema => command_export_schema(config),
+            Command::Version(command_config) => command_version(config, command_config).await,
+            Command::EstimateInitCost => command_estimate_init_cost(config).await,
         }
     }
 }
 
 // initialize a new stake pool for a vote account
-async fn command_initialize(config: &Config, command_config: InitializeCli) -> CommandResult {
+async fn command_initialize(
+    config: &Config,
+    command_config: InitializeCli,
+    matches: &ArgMatches,
+    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+) -> CommandResult {
+    if let Some(path) = &command_config.from_file {
+        return command_initialize_from_file(
+            config,
+            path,
+            &command_config,
+            matches,
+            wallet_manager,
+        )
+        .await;
+    }
+
+    // clap guarantees a vote account if there is no file
+    let vote_account_address = command_config.vote_account_address.unwrap();
+    let output = initialize_pool(
+        config,
+        &command_config,
+        vote_account_address,
+        matches,
+        wallet_manager,
+    )
+    .await?;
+
+    Ok(format_output(config, "Initialize".to_string(), output))
+}
+
+// initialize a pool for every vote account in a file, one transaction each so one bad
+// vote account does not hold up the rest
+async fn command_initialize_from_file(
+    config: &Config,
+    path: &Path,
+    command_config: &InitializeCli,
+    matches: &ArgMatches,
+    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+) -> CommandResult {
+    if config.sign_only || config.instructions_only {
+        return Err(
+            "Cannot initialize from a file with --sign-only or --instructions-only; \
+            initialize each pool individually"
+                .into(),
+        );
+    }
+
+    let vote_account_addresses = parse_vote_account_file(path)?;
+    if vote_account_addresses.is_empty() {
+        return Err(format!("No vote accounts found in {}", path.display()).into());
+    }
+
+    let mut results = vec![];
+    for vote_account_address in vote_account_addresses {
+        let pool_address = find_pool_address(&spl_single_pool::id(), &vote_account_address);
+
+        let result = if config
+            .get_initialized_account(pool_address)
+            .await?
+            .is_some()
+        {
+            println_display(
+                config,
+                format!(
+                    "Warning: skipping vote account {}, whose pool {} already exists",
+                    vote_account_address, pool_address
+                ),
+            );
+
+            InitializeResult {
+                vote_account_address,
+                pool_address,
+                status: InitializeStatus::Skipped,
+                signature: None,
+                error: None,
+            }
+        } else {
+            match initialize_pool(
+                config,
+                command_config,
+                vote_account_address,
+                matches,
+                wallet_manager,
+            )
+            .await
+            {
+                Ok(output) => InitializeResult {
+                    vote_account_address,
+                    pool_address,
+                    status: InitializeStatus::Created,
+                    signature: output.signature,
+                    error: None,
+                },
+                Err(e) => InitializeResult {
+                    vote_account_address,
+                    pool_address,
+                    status: InitializeStatus::Failed,
+                    signature: None,
+                    error: Some(e.to_string()),
+                },
+            }
+        };
+
+        results.push(result);
+    }
+
+    Ok(format_output(
+        config,
+        "InitializeFromFile".to_string(),
+        InitializeFromFileOutput { results },
+    ))
+}
+
+// build, check, and submit the transaction creating one pool
+async fn initialize_pool(
+    config: &Config,
+    command_config: &InitializeCli,
+    vote_account_address: Pubkey,
+    matches: &ArgMatches,
+    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+) -> Result<StakePoolOutput, Error> {
     let payer = config.fee_payer()?;
-    let vote_account_address = command_config.vote_account_address;
 
     println_display(
         config,
@@ -133,17 +373,62 @@ async fn command_initialize(config: &Config, command_config: InitializeCli) -> C
         .into());
     };
 
-    let minimum_pool_balance = quarantine::get_minimum_pool_balance(config).await?;
-    let minimum_delegation = config.rpc_client.get_stake_minimum_delegation().await?;
+    // the program derives the minimum pool balance from the live minimum delegation
+    let minimum_delegation = config.client.stake_client().minimum_delegation().await?;
+    let required_pool_balance = spl_single_pool::minimum_pool_balance(minimum_delegation);
+    let minimum_pool_balance = match command_config.minimum_pool_balance {
+        Some(minimum_pool_balance) => {
+            println_display(
+                config,
+                format!(
+                    "Using minimum pool balance override of {} lamports\n",
+                    minimum_pool_balance
+                ),
+            );
+
+            // a lower override is caught by the funding check below. a higher one is accepted
+            // by the program, but the excess can never be withdrawn, so warn regardless of format
+            if minimum_pool_balance > required_pool_balance {
+                eprintln!(
+                    "Warning: minimum pool balance override of {} lamports exceeds the {} \
+                    lamports the program requires. The extra {} lamports will be locked in the \
+                    pool stake account permanently",
+                    minimum_pool_balance,
+                    required_pool_balance,
+                    minimum_pool_balance - required_pool_balance,
+                );
+            }
+
+            minimum_pool_balance
+        }
+        None => required_pool_balance,
+    };
+
+    let token_program_id = if command_config.token_2022 {
+        inline_spl_token_2022::id()
+    } else {
+        spl_token::id()
+    };
 
-    let mut instructions = spl_single_pool::instruction::initialize(
+    let rent = config.client.stake_client().get_rent().await?;
+    let mut instructions = spl_single_pool::instruction::initialize_with_token_program(
         &spl_single_pool::id(),
+        &token_program_id,
         &vote_account_address,
         &payer.pubkey(),
-        &quarantine::get_rent(config).await?,
+        &rent,
         minimum_pool_balance,
     );
 
+    // check our funding against what the program will require, rather than what we asked for
+    let pool_stake_address = find_pool_stake_address(&spl_single_pool::id(), &pool_address);
+    let pool_stake_funding = check_pool_stake_funding(
+        &instructions,
+        &pool_stake_address,
+        &rent,
+        minimum_delegation,
+    )?;
+
     // get rid of the CreateMetadata instruction if desired, eg if mpl breaks compat
     if command_config.skip_metadata {
         assert_eq!(
@@ -154,39 +439,145 @@ async fn command_initialize(config: &Config, command_config: InitializeCli) -> C
         instructions.pop();
     }
 
+    // leave the on-ramp out, as `1.0.x` did. neither its funding nor its creation is
+    // needed by anything else in the transaction
+    if command_config.skip_onramp {
+        let onramp_address = find_pool_onramp_address(&spl_single_pool::id(), &pool_address);
+        let initialize_onramp_data =
+            borsh::to_vec(&SinglePoolInstruction::InitializePoolOnRamp).unwrap();
+
+        instructions.retain(|instruction| {
+            let funds_onramp = instruction.program_id == system_program::id()
+                && instruction
+                    .accounts
+                    .get(1)
+                    .is_some_and(|account| account.pubkey == onramp_address);
+            let initializes_onramp = instruction.program_id == spl_single_pool::id()
+                && instruction.data == initialize_onramp_data;
+
+            !funds_onramp && !initializes_onramp
+        });
+
+        println_display(
+            config,
+            "Warning: skipping on-ramp creation. The pool cannot be replenished until \
+            `spl-single-pool manage create-on-ramp` is run\n"
+                .to_string(),
+        );
+    }
+
+    let mut signers = vec![payer.clone()];
+
+    // brand the pool in the same transaction, which the vote account withdrawer must sign
+    if let (Some(token_name), Some(token_symbol)) =
+        (&command_config.token_name, &command_config.token_symbol)
+    {
+        let owner = config.default_signer()?;
+        let authorized_withdrawer = command_config
+            .authorized_withdrawer
+            .as_ref()
+            .map(|source| resolve_signer(matches, source, "authorized_withdrawer", wallet_manager))
+            .transpose()?
+            .unwrap_or(owner);
+
+        let vote_authorized_withdrawer =
+            get_vote_authorized_withdrawer(config, vote_account_address).await?;
+        if authorized_withdrawer.pubkey() != vote_authorized_withdrawer {
+            return Err(format!(
+                "Invalid authorized withdrawer: got {}, actual {}",
+                authorized_withdrawer.pubkey(),
+                vote_authorized_withdrawer,
+            )
+            .into());
+        }
+
+        instructions.push(spl_single_pool::instruction::update_token_metadata(
+            &spl_single_pool::id(),
+            &vote_account_address,
+            &authorized_withdrawer.pubkey(),
+            token_name.clone(),
+            token_symbol.clone(),
+            command_config.token_uri.clone().unwrap_or_default(),
+        ));
+
+        if !signers.contains(&authorized_withdrawer) {
+            signers.push(authorized_withdrawer);
+        }
+    }
+
     let transaction = Transaction::new_signed_with_payer(
-        &instructions,
+        &config.with_compute_budget(&instructions).await,
         Some(&payer.pubkey()),
-        &vec![payer],
-        config.rpc_client.get_latest_blockhash().await?,
+        &signers,
+        config.get_blockhash().await?,
     );
 
-    let signature = process_transaction(config, transaction).await?;
+    if bincode::serialized_size(&transaction)? as usize > PACKET_DATA_SIZE {
+        return Err(
+            "Initialize transaction is too large with this token metadata; initialize without \
+            it, then run `spl-single-pool manage update-token-metadata ...`"
+                .into(),
+        );
+    }
 
-    Ok(format_output(
+    // an offline signer may not be funded yet, so only check when submitting
+    if !command_config.skip_balance_check && !config.sign_only && !config.instructions_only {
+        check_fee_payer_balance(config, &payer.pubkey(), &instructions, &transaction).await?;
+    }
+
+    let signature = process_transaction_with_commitment(
         config,
-        "Initialize".to_string(),
-        StakePoolOutput {
-            pool_address,
-            vote_account_address,
-            net_asset_value: minimum_pool_balance,
-            undelegated_lamports: 0,
-            token_supply: quarantine::PHANTOM_TOKENS,
-            main_stake_dedelegated: false,
-            onramp_exists: true,
-            minimum_delegation,
-            signature,
-        },
-    ))
+        transaction,
+        (!config.no_wait).then(|| command_config.await_commitment.into()),
+    )
+    .await
+    .map_err(|e| {
+        if is_single_pool_error(&e, SinglePoolError::WrongRentAmount) {
+            format!(
+                "The program rejected the pool funding. The pool stake account needs {} \
+                lamports: rent plus a minimum pool balance of {}, the greater of 1 SOL and the \
+                stake minimum delegation of {}. If the minimum delegation just changed, try again",
+                pool_stake_funding, required_pool_balance, minimum_delegation,
+            )
+            .into()
+        } else {
+            e
+        }
+    })?;
+
+    Ok(StakePoolOutput {
+        pool_address,
+        vote_account_address,
+        net_asset_value: minimum_pool_balance,
+        available_stake: 0,
+        undelegated_lamports: 0,
+        token_supply: spl_single_pool_client::PHANTOM_TOKEN_AMOUNT,
+        exchange_rate: exchange_rate(
+            minimum_pool_balance,
+            spl_single_pool_client::PHANTOM_TOKEN_AMOUNT,
+        ),
+        main_stake_dedelegated: false,
+        onramp_exists: !command_config.skip_onramp,
+        onramp_lamports: 0,
+        onramp_activating: false,
+        status: PoolStatus::Activating,
+        estimated_apy: None,
+        show_apy: false,
+        minimum_delegation,
+        signature,
+    })
 }
 
 // replenish pool
 async fn command_replenish_pool(config: &Config, command_config: ReplenishCli) -> CommandResult {
     let payer = config.fee_payer()?;
     let pool_address = pool_address_from_args(
+        config,
         command_config.pool_address,
         command_config.vote_account_address,
-    );
+        command_config.validator_identity,
+    )
+    .await?;
 
     println_display(
         config,
@@ -195,13 +586,71 @@ async fn command_replenish_pool(config: &Config, command_config: ReplenishCli) -
 
     let vote_account_address = get_vote_address_from_pool(config, pool_address).await?;
 
-    let instruction =
-        spl_single_pool::instruction::replenish_pool(&spl_single_pool::id(), &vote_account_address);
+    let mut instructions = vec![];
+    let mut signers = vec![payer.clone()];
+
+    if command_config.create_onramp_if_missing {
+        let onramp_address = find_pool_onramp_address(&spl_single_pool::id(), &pool_address);
+        if config
+            .get_initialized_account(onramp_address)
+            .await?
+            .is_none()
+        {
+            println_display(
+                config,
+                format!("Creating onramp account {}\n", onramp_address),
+            );
+
+            instructions.extend(spl_single_pool::instruction::create_pool_onramp(
+                &spl_single_pool::id(),
+                &pool_address,
+                &payer.pubkey(),
+                &config.client.stake_client().get_rent().await?,
+            ));
+        }
+    }
+
+    // the transfer lands as excess lamports in the main account, which replenish then moves
+    // to the onramp and delegates, the same as it does for any other rewards
+    if let Some(amount) = command_config.amount {
+        if amount == 0 {
+            return Err("Cannot fund a pool with zero lamports".into());
+        }
+
+        pool_is_initialized(config, pool_address).await?;
+
+        let owner = config.default_signer()?;
+        let pool_stake_address = find_pool_stake_address(&spl_single_pool::id(), &pool_address);
+
+        println_display(
+            config,
+            format!(
+                "Funding pool stake account {} with {} lamports\n",
+                pool_stake_address, amount,
+            ),
+        );
+
+        instructions.push(system_instruction::transfer(
+            &owner.pubkey(),
+            &pool_stake_address,
+            amount,
+        ));
+
+        if !signers.contains(&owner) {
+            signers.push(owner);
+        }
+    }
+
+    instructions.push(spl_single_pool::instruction::replenish_pool(
+        &spl_single_pool::id(),
+        &vote_account_address,
+    ));
+
     let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
+        &config.with_compute_budget(&instructions).await,
         Some(&payer.pubkey()),
-        &vec![payer],
-        config.rpc_client.get_latest_blockhash().await?,
+        &signers,
+        config.get_blockhash().await?,
     );
 
     let signature = process_transaction(config, transaction).await?;
@@ -213,164 +662,634 @@ async fn command_replenish_pool(config: &Config, command_config: ReplenishCli) -
     ))
 }
 
-// deposit stake
-async fn command_deposit(
-    config: &Config,
-    command_config: DepositCli,
-    matches: &ArgMatches,
-    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
-) -> CommandResult {
-    let payer = config.fee_payer()?;
-    let owner = config.default_signer()?;
-    let stake_account_address = command_config.stake_account_address;
-    let stake_authority = command_config
-        .stake_withdraw_authority
-        .and_then(|source| {
-            signer_from_source(matches, &source, "stake_authority", wallet_manager)
-                .ok()
-                .map(Arc::from)
-        })
-        .unwrap_or(owner.clone());
-    let lamport_recipient = command_config
-        .lamport_recipient_address
-        .unwrap_or_else(|| owner.pubkey());
+// replenish every pool, packing as many replenishments into each transaction as fit
+async fn command_replenish_all(config: &Config, command_config: ReplenishAllCli) -> CommandResult {
+    let pool_and_vote_addresses = get_all_pool_and_vote_addresses(config).await?;
 
-    let current_epoch = config.rpc_client.get_epoch_info().await?.epoch;
+    // classify every pool up front, so pools with nothing to do cost no fees
+    let stake_rent_exempt_reserve = config
+        .with_rpc_retries(|| {
+            config
+                .rpc_client
+                .get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())
+        })
+        .await?;
+    let current_epoch = config
+        .with_rpc_retries(|| config.rpc_client.get_epoch_info())
+        .await?
+        .epoch;
+    let minimum_delegation = config.client.stake_client().minimum_delegation().await?;
 
-    // we originally accepted this because there was the notion of a "canonical"
-    // stake account keyed off each wallet/pool combination. now we just derive
-    // the pool address from the stake account delegation, but we still allow
-    // the pool or vote address to be supplied for optional validation
-    let provided_pool_address = command_config.pool_address.or_else(|| {
-        command_config
-            .vote_account_address
-            .map(|address| find_pool_address(&spl_single_pool::id(), &address))
-    });
+    let pool_addresses = pool_and_vote_addresses
+        .iter()
+        .map(|(pool_address, _)| {
+            PoolAddresses::derive_from_pool(&spl_single_pool::id(), pool_address)
+        })
+        .collect::<Vec<_>>();
+    let stake_addresses = pool_addresses
+        .iter()
+        .map(|addresses| addresses.stake)
+        .collect::<Vec<_>>();
+    let onramp_addresses = pool_addresses
+        .iter()
+        .map(|addresses| addresses.onramp)
+        .collect::<Vec<_>>();
+
+    let (stake_summaries, onramp_summaries) = futures::try_join!(
+        config.client.get_stake_summaries(
+            &stake_addresses,
+            stake_rent_exempt_reserve,
+            current_epoch
+        ),
+        config.client.get_stake_summaries(
+            &onramp_addresses,
+            stake_rent_exempt_reserve,
+            current_epoch
+        ),
+    )?;
 
-    // now we validate the stake account and definitively resolve the pool address
-    let (pool_address, user_stake_active) = if let Some((meta, stake)) =
-        quarantine::get_stake_info(config, stake_account_address).await?
+    let mut results = vec![];
+    let mut pending = vec![];
+    let mut planned_actions = HashMap::new();
+    for (((pool_address, vote_account_address), stake_summary), onramp_summary) in
+        pool_and_vote_addresses
+            .into_iter()
+            .zip(stake_summaries)
+            .zip(onramp_summaries)
     {
-        let derived_pool_address =
-            find_pool_address(&spl_single_pool::id(), &stake.delegation.voter_pubkey);
-
-        if let Some(provided_pool_address) = provided_pool_address {
-            if provided_pool_address != derived_pool_address {
-                return Err(format!(
-                    "Provided pool address {} does not match stake account-derived address {}",
-                    provided_pool_address, derived_pool_address,
-                )
-                .into());
-            }
+        if !onramp_summary.exists {
+            results.push(ReplenishResult {
+                pool_address,
+                signature: None,
+                actions: vec![],
+                error: Some(
+                    "On-ramp account does not exist; create it with `manage create-on-ramp`"
+                        .to_string(),
+                ),
+            });
+            continue;
         }
 
-        if meta.authorized.withdrawer != stake_authority.pubkey() {
-            return Err(format!(
-                "Incorrect withdraw authority for stake account {}: got {}, expected {}",
-                stake_account_address,
-                meta.authorized.withdrawer,
-                stake_authority.pubkey(),
-            )
-            .into());
-        }
+        let actions = ReplenishAction::from_plan(spl_single_pool_client::ReplenishPlan::new(
+            stake_summary,
+            onramp_summary,
+            minimum_delegation,
+        ));
 
-        if stake.delegation.deactivation_epoch < u64::MAX {
-            return Err(format!(
-                "Stake account {} is deactivating or deactivated",
-                stake_account_address
-            )
-            .into());
+        if command_config.simulate || actions.is_empty() {
+            results.push(ReplenishResult {
+                pool_address,
+                signature: None,
+                actions,
+                error: None,
+            });
+        } else {
+            planned_actions.insert(pool_address, actions);
+            pending.push((pool_address, vote_account_address));
         }
+    }
 
-        (
-            derived_pool_address,
-            stake.delegation.activation_epoch <= current_epoch,
-        )
-    } else {
-        return Err(format!("Could not find stake account {}", stake_account_address).into());
-    };
+    if command_config.simulate {
+        return Ok(format_output(
+            config,
+            "ReplenishAll".to_string(),
+            ReplenishAllOutput {
+                simulated: true,
+                results,
+            },
+        ));
+    }
+
+    let payer = config.fee_payer()?;
 
     println_display(
         config,
         format!(
-            "Depositing stake from account {} into pool {}\n",
-            stake_account_address, pool_address
+            "Replenishing stake accounts for {} of {} pools\n",
+            pending.len(),
+            pool_addresses.len(),
         ),
     );
 
-    pool_is_initialized(config, pool_address).await?;
+    let mut batches = vec![];
+    let mut batch: Vec<(Pubkey, Instruction)> = vec![];
+    for (pool_address, vote_account_address) in pending {
+        batch.push((
+            pool_address,
+            spl_single_pool::instruction::replenish_pool(
+                &spl_single_pool::id(),
+                &vote_account_address,
+            ),
+        ));
 
-    let pool_stake_address = find_pool_stake_address(&spl_single_pool::id(), &pool_address);
-    let pool_stake_active = quarantine::get_stake_info(config, pool_stake_address)
-        .await?
-        .unwrap()
-        .1
-        .delegation
-        .activation_epoch
-        <= current_epoch;
+        let instructions = batch
+            .iter()
+            .map(|(_, instruction)| instruction.clone())
+            .collect::<Vec<_>>();
+        // the price does not change the transaction size, so there is no need to estimate it
+        let transaction = Transaction::new_with_payer(
+            &config.with_compute_unit_price(&instructions, config.compute_unit_price.map(|_| 0)),
+            Some(&payer.pubkey()),
+        );
 
-    if user_stake_active != pool_stake_active {
-        return Err("Activation status mismatch; try again next epoch".into());
+        if batch.len() > 1 && bincode::serialized_size(&transaction)? as usize > PACKET_DATA_SIZE {
+            let overflow = batch.pop().unwrap();
+            batches.push(std::mem::replace(&mut batch, vec![overflow]));
+        }
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
     }
 
-    let mut instructions = vec![];
+    for batch in batches {
+        let instructions = batch
+            .iter()
+            .map(|(_, instruction)| instruction.clone())
+            .collect::<Vec<_>>();
 
-    // use token account provided, or get/create the associated account for the client keypair
-    let pool_mint_address = find_pool_mint_address(&spl_single_pool::id(), &pool_address);
-    let token_account_address = if let Some(account) = command_config.token_account_address {
-        account
-    } else {
-        let ata_address = get_associated_token_address(&owner.pubkey(), &pool_mint_address);
-        if quarantine::get_token_info(config, ata_address, pool_mint_address)
-            .await?
-            .is_none()
-        {
-            instructions.push(create_associated_token_account(
-                &payer.pubkey(),
-                &owner.pubkey(),
-                &pool_mint_address,
-                &spl_token::id(),
+        match process_replenish_batch(config, &payer, &instructions).await {
+            Ok(signature) => {
+                results.extend(batch.iter().map(|(pool_address, _)| ReplenishResult {
+                    pool_address: *pool_address,
+                    signature,
+                    actions: planned_actions[pool_address].clone(),
+                    error: None,
+                }));
+            }
+            // one bad pool fails the whole transaction, so retry individually to find it
+            Err(_) if batch.len() > 1 => {
+                for (pool_address, instruction) in batch {
+                    let result = process_replenish_batch(config, &payer, &[instruction]).await;
+                    results.push(ReplenishResult {
+                        pool_address,
+                        signature: result.as_ref().ok().copied().flatten(),
+                        actions: planned_actions[&pool_address].clone(),
+                        error: result.err().map(|e| e.to_string()),
+                    });
+                }
+            }
+            Err(e) => {
+                results.push(ReplenishResult {
+                    pool_address: batch[0].0,
+                    signature: None,
+                    actions: planned_actions[&batch[0].0].clone(),
+                    error: Some(e.to_string()),
+                });
+            }
+        }
+    }
+
+    results.sort_unstable_by_key(|result| result.pool_address);
+
+    Ok(format_output(
+        config,
+        "ReplenishAll".to_string(),
+        ReplenishAllOutput {
+            simulated: false,
+            results,
+        },
+    ))
+}
+
+async fn process_replenish_batch(
+    config: &Config,
+    payer: &Arc<dyn Signer>,
+    instructions: &[Instruction],
+) -> Result<Option<Signature>, Error> {
+    let transaction = Transaction::new_signed_with_payer(
+        &config.with_compute_budget(instructions).await,
+        Some(&payer.pubkey()),
+        &vec![payer.clone()],
+        config.get_blockhash().await?,
+    );
+
+    process_transaction(config, transaction).await
+}
+
+// deposit stake
+async fn command_deposit(
+    config: &Config,
+    command_config: DepositCli,
+    matches: &ArgMatches,
+    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+) -> CommandResult {
+    let payer = config.fee_payer()?;
+    let owner = config.default_signer()?;
+    let stake_authority = command_config
+        .stake_withdraw_authority
+        .map(|source| resolve_signer(matches, &source, "stake_authority", wallet_manager))
+        .transpose()?
+        .unwrap_or(owner.clone());
+    let expected_staker = command_config.stake_staker_authority.is_some();
+    let staker_authority = command_config
+        .stake_staker_authority
+        .map(|source| resolve_signer(matches, &source, "staker_authority", wallet_manager))
+        .transpose()?
+        .unwrap_or(stake_authority.clone());
+
+    // we originally accepted this because there was the notion of a "canonical"
+    // stake account keyed off each wallet/pool combination. now we just derive
+    // the pool address from the stake account delegation, but we still allow
+    // the pool or vote address to be supplied for optional validation
+    let provided_pool_address = resolve_pool_address(
+        config,
+        command_config.pool_address,
+        command_config.vote_account_address,
+        command_config.validator_identity,
+    )
+    .await?;
+
+    if let Some(batch_file) = command_config.batch_file {
+        return command_deposit_batch(config, &batch_file, provided_pool_address, stake_authority)
+            .await;
+    }
+
+    let mut current_epoch = config.rpc_client.get_epoch_info().await?.epoch;
+
+    let stake_account_addresses = if command_config.from_all_delegated {
+        // clap guarantees a pool source is present with this flag
+        let pool_address = provided_pool_address.unwrap();
+        let vote_account_address = get_vote_address_from_pool(config, pool_address).await?;
+
+        let mut stake_account_addresses = vec![];
+        for (stake_account_address, _, stake) in config
+            .client
+            .get_delegated_stake_accounts(&vote_account_address, &stake_authority.pubkey())
+            .await?
+        {
+            if stake.delegation.deactivation_epoch < u64::MAX {
+                println_display(
+                    config,
+                    format!(
+                        "Warning: skipping stake account {}, which is deactivating or deactivated",
+                        stake_account_address
+                    ),
+                );
+            } else {
+                stake_account_addresses.push(stake_account_address);
+            }
+        }
+
+        if stake_account_addresses.is_empty() {
+            return Err(format!(
+                "No active or activating stake accounts delegated to {} with withdraw authority {}",
+                vote_account_address,
+                stake_authority.pubkey()
+            )
+            .into());
+        }
+
+        stake_account_addresses
+    } else {
+        // clap guarantees at least one is present if there is no batch file
+        command_config.stake_account_addresses
+    };
+
+    if command_config.amount.is_some() && stake_account_addresses.len() != 1 {
+        return Err("A deposit amount can only be used with a single stake account".into());
+    }
+
+    let lamport_recipient = command_config
+        .lamport_recipient_address
+        .unwrap_or_else(|| owner.pubkey());
+
+    let timeout = command_config.timeout.map(Duration::from_secs);
+    let (pool_address, stake_activity, pool_stake_active) = loop {
+        // now we validate the stake accounts and definitively resolve the pool address.
+        // every stake account must resolve to the same pool as the first
+        let mut pool_address = provided_pool_address;
+        let mut stake_activity = vec![];
+        for stake_account_address in &stake_account_addresses {
+            let (derived_pool_address, user_stake_active) = check_deposit_stake_account(
+                config,
+                *stake_account_address,
+                expected_staker.then(|| staker_authority.pubkey()),
+                &stake_authority.pubkey(),
+                pool_address,
+                current_epoch,
+            )
+            .await?;
+
+            pool_address = Some(derived_pool_address);
+            stake_activity.push((*stake_account_address, user_stake_active));
+        }
+        let pool_address = pool_address.unwrap();
+
+        pool_is_initialized(config, pool_address).await?;
+
+        // a lone stake account in the wrong state is an error, but we don't want one
+        // freshly delegated account to hold up the rest of a multi-account deposit
+        let pool_stake_active = pool_stake_is_active(config, pool_address, current_epoch).await?;
+        if stake_activity.len() == 1 && stake_activity[0].1 != pool_stake_active {
+            if command_config.wait_for_epoch {
+                current_epoch = wait_for_next_epoch(config, current_epoch, timeout).await?;
+                continue;
+            }
+
+            return Err("Activation status mismatch; try again next epoch".into());
+        }
+
+        break (pool_address, stake_activity, pool_stake_active);
+    };
+
+    // the pool comes from the stake delegation unless one was given, so say where it came from
+    let vote_account_address = get_vote_address_from_pool(config, pool_address).await?;
+    if let Some(expected_vote_account_address) = command_config.expected_vote_account_address {
+        if vote_account_address != expected_vote_account_address {
+            return Err(format!(
+                "Stake is delegated to vote account {}, not the expected {}",
+                vote_account_address, expected_vote_account_address,
+            )
+            .into());
+        }
+    } else if provided_pool_address.is_none() {
+        println_display(
+            config,
+            format!(
+                "Note: depositing into the pool for vote account {}, derived from the stake \
+                delegation. Pass --expect-vote to guard against depositing into the wrong pool",
+                vote_account_address
+            ),
+        );
+    }
+
+    let mut skipped_stake_accounts = vec![];
+    let mut stake_account_addresses = vec![];
+    for (stake_account_address, user_stake_active) in stake_activity {
+        if user_stake_active == pool_stake_active {
+            stake_account_addresses.push(stake_account_address);
+        } else {
+            println_display(
+                config,
+                format!(
+                    "Warning: skipping stake account {}, which has a different activation status \
+                    than the pool; try again next epoch",
+                    stake_account_address
+                ),
+            );
+            skipped_stake_accounts.push(stake_account_address);
+        }
+    }
+
+    if stake_account_addresses.is_empty() {
+        return Err(
+            "Activation status mismatch for all stake accounts; try again next epoch".into(),
+        );
+    }
+
+    println_display(
+        config,
+        if let [stake_account_address] = stake_account_addresses[..] {
+            format!(
+                "Depositing stake from account {} into pool {}\n",
+                stake_account_address, pool_address
+            )
+        } else {
+            format!(
+                "Depositing stake from {} accounts into pool {}\n",
+                stake_account_addresses.len(),
+                pool_address
+            )
+        },
+    );
+
+    let mut instructions = vec![];
+
+    // use token account provided, or get/create the associated account for the token owner,
+    // which is the client keypair unless otherwise specified
+    let pool_mint_address = find_pool_mint_address(&spl_single_pool::id(), &pool_address);
+    let token_program_id = config.client.get_token_program(&pool_address).await?;
+    let token_account_address = if let Some(account) = command_config.token_account_address {
+        account
+    } else {
+        let token_owner = command_config
+            .token_owner_address
+            .unwrap_or_else(|| owner.pubkey());
+        let ata_address =
+            config.associated_token_address(&token_owner, &pool_mint_address, &token_program_id);
+        if config
+            .client
+            .get_token_info(ata_address, pool_mint_address)
+            .await?
+            .is_none()
+        {
+            instructions.push(config.create_associated_token_account(
+                &payer.pubkey(),
+                &token_owner,
+                &pool_mint_address,
+                &token_program_id,
             ));
         }
         ata_address
     };
 
     let previous_token_amount =
-        quarantine::get_token_info(config, token_account_address, pool_mint_address)
-            .await?
-            .map(|token_account| token_account.amount)
-            .unwrap_or(0);
-
-    instructions.extend(spl_single_pool::instruction::deposit(
-        &spl_single_pool::id(),
-        &pool_address,
-        &stake_account_address,
-        &token_account_address,
-        &lamport_recipient,
-        &stake_authority.pubkey(),
-    ));
+        get_deposit_token_amount(config, token_account_address, pool_mint_address).await?;
 
     let mut signers = vec![];
-    for signer in [payer.clone(), stake_authority] {
+    for signer in [
+        payer.clone(),
+        stake_authority.clone(),
+        staker_authority.clone(),
+    ] {
         if !signers.contains(&signer) {
             signers.push(signer);
         }
     }
 
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &signers,
-        config.rpc_client.get_latest_blockhash().await?,
-    );
+    // for a partial deposit, we split the requested amount into a fresh stake account
+    // and deposit that instead, leaving the original account delegated
+    let deposit_addresses = if let Some(amount) = command_config.amount {
+        let Amount::Raw(split_amount) = amount.sol_to_lamport() else {
+            unreachable!();
+        };
 
-    let signature = process_transaction(config, transaction).await?;
+        let stake_account_address = stake_account_addresses[0];
+        let split_stake_account = Keypair::new();
+
+        let stake = match config
+            .client
+            .stake_client()
+            .get_stake_state(&stake_account_address)
+            .await?
+        {
+            Some(StakeStateV2::Stake(_, stake, _)) => stake,
+            Some(_) => {
+                return Err(
+                    format!("Stake account {} is not delegated", stake_account_address).into(),
+                )
+            }
+            None => {
+                return Err(
+                    format!("Could not find stake account {}", stake_account_address).into(),
+                )
+            }
+        };
+        let minimum_delegation = config.client.stake_client().minimum_delegation().await?;
+
+        if split_amount < minimum_delegation {
+            return Err(format!(
+                "Deposit amount {} is less than the minimum delegation {}",
+                split_amount, minimum_delegation
+            )
+            .into());
+        }
+
+        if split_amount >= stake.delegation.stake {
+            return Err(format!(
+                "Deposit amount {} must be less than the delegation {} of stake account {}; \
+                omit --amount to deposit the whole account",
+                split_amount, stake.delegation.stake, stake_account_address
+            )
+            .into());
+        }
+
+        let remaining_stake = stake.delegation.stake - split_amount;
+        if remaining_stake < minimum_delegation {
+            return Err(format!(
+                "Depositing {} would leave {} delegated in stake account {}, \
+                less than the minimum delegation {}",
+                split_amount, remaining_stake, stake_account_address, minimum_delegation
+            )
+            .into());
+        }
+
+        // the split destination must already be rent-exempt if the source is delegated
+        let stake_rent = config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())
+            .await?;
+
+        instructions.push(system_instruction::transfer(
+            &payer.pubkey(),
+            &split_stake_account.pubkey(),
+            stake_rent,
+        ));
+        instructions.extend(stake::instruction::split(
+            &stake_account_address,
+            &staker_authority.pubkey(),
+            split_amount,
+            &split_stake_account.pubkey(),
+        ));
+
+        println_display(
+            config,
+            format!(
+                "Splitting {} lamports from stake account {} into new stake account {}\n",
+                split_amount,
+                stake_account_address,
+                split_stake_account.pubkey()
+            ),
+        );
+
+        let split_stake_address = split_stake_account.pubkey();
+        signers.push(Arc::new(split_stake_account));
+
+        vec![split_stake_address]
+    } else {
+        stake_account_addresses.clone()
+    };
+
+    // the program rejects a deposit that mints no tokens, so catch it before anything is sent
+    let (net_asset_value, token_supply) = config.client.get_exchange_rate(&pool_address).await?;
+    let minimum_deposit =
+        spl_single_pool_client::minimum_deposit_amount(token_supply, net_asset_value);
+    for stake_account_address in &stake_account_addresses {
+        let deposit_amount = if let Some(amount) = command_config.amount {
+            let Amount::Raw(split_amount) = amount.sol_to_lamport() else {
+                unreachable!();
+            };
+            split_amount
+        } else if let Some(StakeStateV2::Stake(_, stake, _)) = config
+            .client
+            .stake_client()
+            .get_stake_state(stake_account_address)
+            .await?
+        {
+            stake.delegation.stake
+        } else {
+            continue;
+        };
+
+        if deposit_amount < minimum_deposit {
+            return Err(format!(
+                "Depositing {} lamports from stake account {} would mint no pool tokens; \
+                the pool requires at least {} lamports of stake per token",
+                deposit_amount, stake_account_address, minimum_deposit
+            )
+            .into());
+        }
+    }
+
+    // in a dry run, we also total up the token and stake changes each simulation predicts
+    let pool_stake_address = find_pool_stake_address(&spl_single_pool::id(), &pool_address);
+    let mut simulated_changes = Some((0, 0));
+
+    // deposits share a token account and lamport recipient, so a chunk takes no more
+    // space than a batch of independent deposits, leaving room for the token account
+    // and a separate stake authority
+    let mut signatures = vec![];
+    for chunk in deposit_addresses.chunks(spl_single_pool::instruction::MAX_DEPOSIT_BATCH_SIZE) {
+        for stake_account_address in chunk {
+            instructions.extend(spl_single_pool::instruction::deposit_with_stake_authority(
+                &spl_single_pool::id(),
+                &token_program_id,
+                &pool_address,
+                stake_account_address,
+                &token_account_address,
+                &lamport_recipient,
+                &staker_authority.pubkey(),
+                &stake_authority.pubkey(),
+            ));
+        }
+
+        let transaction = Transaction::new_signed_with_payer(
+            &config.with_compute_budget(&instructions).await,
+            Some(&payer.pubkey()),
+            &signers,
+            config.get_blockhash().await?,
+        );
+
+        if config.dry_run {
+            let chunk_changes = simulate_deposit_changes(
+                config,
+                &transaction,
+                token_account_address,
+                pool_stake_address,
+            )
+            .await?;
+
+            simulated_changes = simulated_changes.zip(chunk_changes).map(
+                |((tokens, stake), (chunk_tokens, chunk_stake))| {
+                    (tokens + chunk_tokens, stake + chunk_stake)
+                },
+            );
+        }
+
+        signatures.extend(process_transaction(config, transaction).await?);
+        instructions.clear();
+    }
 
-    let token_amount = if config.dry_run {
+    let token_amount = if config.no_wait {
         None
+    } else if config.dry_run {
+        // fall back to logs alone if the rpc node could not return simulated accounts
+        if let Some((token_gain, stake_added)) = simulated_changes {
+            println_display(
+                config,
+                format!(
+                    "\nExpected pool token gain: {}\nExpected stake moved into pool: {} lamports",
+                    token_gain, stake_added
+                ),
+            );
+        }
+
+        simulated_changes.map(|(token_gain, _)| token_gain)
     } else {
         Some(
-            quarantine::get_token_info(config, token_account_address, pool_mint_address)
+            config
+                .client
+                .get_token_info(token_account_address, pool_mint_address)
                 .await?
                 .map(|token_account| token_account.amount)
                 .unwrap_or(0)
@@ -383,691 +1302,2467 @@ async fn command_deposit(
         "Deposit".to_string(),
         DepositOutput {
             pool_address,
+            stake_account_addresses,
+            skipped_stake_accounts,
             token_amount,
-            signature,
+            signatures,
         },
     ))
 }
 
-// withdraw stake
-async fn command_withdraw(
+// deposit many stake accounts into one pool on behalf of their respective token owners
+async fn command_deposit_batch(
     config: &Config,
-    command_config: WithdrawCli,
-    matches: &ArgMatches,
-    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+    batch_file: &Path,
+    provided_pool_address: Option<Pubkey>,
+    stake_authority: Arc<dyn Signer>,
 ) -> CommandResult {
     let payer = config.fee_payer()?;
-    let owner = config.default_signer()?;
-    let token_authority = command_config
-        .token_authority
-        .and_then(|source| {
-            signer_from_source(matches, &source, "token_authority", wallet_manager)
-                .ok()
-                .map(Arc::from)
-        })
-        .unwrap_or(owner.clone());
-    let stake_authority_address = command_config
-        .stake_authority_address
-        .unwrap_or_else(|| owner.pubkey());
+    let entries = parse_batch_file(batch_file)?;
+    if entries.is_empty() {
+        return Err(format!("No deposits found in {}", batch_file.display()).into());
+    }
 
-    let stake_account = Keypair::new();
-    let stake_account_address = stake_account.pubkey();
+    let current_epoch = config.rpc_client.get_epoch_info().await?.epoch;
 
-    // since we can't infer pool from token account, the withdraw invocation is
-    // rather simpler first get the pool address
-    let pool_address = pool_address_from_args(
-        command_config.pool_address,
-        command_config.vote_account_address,
+    // every stake account must resolve to the same pool as the first
+    let mut pool_address = provided_pool_address;
+    let mut stake_activity = vec![];
+    for (stake_account_address, _) in &entries {
+        let (derived_pool_address, user_stake_active) = check_deposit_stake_account(
+            config,
+            *stake_account_address,
+            None,
+            &stake_authority.pubkey(),
+            pool_address,
+            current_epoch,
+        )
+        .await?;
+
+        pool_address = Some(derived_pool_address);
+        stake_activity.push((*stake_account_address, user_stake_active));
+    }
+    let pool_address = pool_address.unwrap();
+
+    println_display(
+        config,
+        format!(
+            "Depositing stake from {} accounts into pool {}\n",
+            entries.len(),
+            pool_address
+        ),
     );
 
     pool_is_initialized(config, pool_address).await?;
 
+    let pool_stake_active = pool_stake_is_active(config, pool_address, current_epoch).await?;
+    for (stake_account_address, user_stake_active) in stake_activity {
+        if user_stake_active != pool_stake_active {
+            return Err(format!(
+                "Activation status mismatch for stake account {}; try again next epoch",
+                stake_account_address
+            )
+            .into());
+        }
+    }
+
+    let mut signers = vec![];
+    for signer in [payer.clone(), stake_authority.clone()] {
+        if !signers.contains(&signer) {
+            signers.push(signer);
+        }
+    }
+
     let pool_mint_address = find_pool_mint_address(&spl_single_pool::id(), &pool_address);
-    let token_account_address = command_config
-        .token_account_address
-        .unwrap_or_else(|| get_associated_token_address(&owner.pubkey(), &pool_mint_address));
+    let token_program_id = config.client.get_token_program(&pool_address).await?;
+    let mut signatures = vec![];
+    for chunk in entries.chunks(spl_single_pool::instruction::MAX_DEPOSIT_BATCH_SIZE) {
+        // the fee payer creates any missing associated token accounts up front, so
+        // the deposit transaction itself can hold as many deposits as possible
+        let mut ata_instructions = vec![];
+        let mut created_atas = vec![];
+        let mut deposits = vec![];
+        for (stake_account_address, token_owner) in chunk {
+            let ata_address =
+                config.associated_token_address(token_owner, &pool_mint_address, &token_program_id);
+            if !created_atas.contains(&ata_address)
+                && config
+                    .client
+                    .get_token_info(ata_address, pool_mint_address)
+                    .await?
+                    .is_none()
+            {
+                created_atas.push(ata_address);
+                ata_instructions.push(config.create_associated_token_account(
+                    &payer.pubkey(),
+                    token_owner,
+                    &pool_mint_address,
+                    &token_program_id,
+                ));
+            }
+
+            deposits.push(BatchDeposit {
+                user_stake_account: *stake_account_address,
+                user_token_account: ata_address,
+                user_lamport_account: *token_owner,
+                user_withdraw_authority: stake_authority.pubkey(),
+            });
+        }
+
+        if !ata_instructions.is_empty() {
+            let transaction = Transaction::new_signed_with_payer(
+                &config.with_compute_budget(&ata_instructions).await,
+                Some(&payer.pubkey()),
+                &vec![payer.clone()],
+                config.get_blockhash().await?,
+            );
+
+            // the deposits need these accounts to exist, so wait for them even with --no-wait
+            signatures.extend(
+                process_transaction_with_commitment(
+                    config,
+                    transaction,
+                    Some(config.rpc_client.commitment()),
+                )
+                .await?,
+            );
+        }
+
+        let transaction = Transaction::new_signed_with_payer(
+            &config
+                .with_compute_budget(
+                    &spl_single_pool::instruction::deposit_batch_with_token_program(
+                        &spl_single_pool::id(),
+                        &token_program_id,
+                        &pool_address,
+                        &deposits,
+                    ),
+                )
+                .await,
+            Some(&payer.pubkey()),
+            &signers,
+            config.get_blockhash().await?,
+        );
+
+        signatures.extend(process_transaction(config, transaction).await?);
+    }
+
+    Ok(format_output(
+        config,
+        "DepositBatch".to_string(),
+        DepositBatchOutput {
+            pool_address,
+            deposit_count: entries.len(),
+            signatures,
+        },
+    ))
+}
+
+// withdraw stake
+async fn command_withdraw(
+    config: &Config,
+    command_config: WithdrawCli,
+    matches: &ArgMatches,
+    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+) -> CommandResult {
+    let payer = config.fee_payer()?;
+    let owner = config.default_signer()?;
+    let token_authority = command_config
+        .token_authority
+        .map(|source| resolve_signer(matches, &source, "token_authority", wallet_manager))
+        .transpose()?
+        .unwrap_or(owner.clone());
+
+    // the stake authority only needs to sign if it acts on the new stake account, so a bare
+    // address is not resolved to a signer unless it must be. any other source already is one
+    let stake_authority_must_sign = command_config.deactivate
+        || command_config.redelegate_vote_account_address.is_some()
+        || command_config.merge_into_address.is_some();
+    let (stake_authority_address, stake_authority_signer) = match command_config.stake_authority {
+        Some(SignerSource {
+            kind: SignerSourceKind::Pubkey(stake_authority_address),
+            ..
+        }) if !stake_authority_must_sign => (stake_authority_address, None),
+        Some(source) => {
+            let signer = resolve_signer(matches, &source, "stake_authority", wallet_manager)?;
+            (signer.pubkey(), Some(signer))
+        }
+        None => (owner.pubkey(), Some(owner.clone())),
+    };
+
+    // an existing stake account is only split into, so it may be given as a bare address.
+    // otherwise we need a signer to create the new account
+    let (stake_account_address, stake_account) = match command_config.stake_account {
+        Some(SignerSource {
+            kind: SignerSourceKind::Pubkey(stake_account_address),
+            ..
+        }) => (stake_account_address, None),
+        maybe_source => {
+            let stake_account = match (maybe_source, &command_config.stake_account_keypair) {
+                (Some(source), _) => {
+                    resolve_signer(matches, &source, "stake_account", wallet_manager)?
+                }
+                (None, Some(path)) => read_or_write_keypair_file(config, path)?,
+                (None, None) => Arc::new(Keypair::new()),
+            };
+            (stake_account.pubkey(), Some(stake_account))
+        }
+    };
+
+    // withdraw into the account as-is if it is already an uninitialized stake account
+    let create_stake_account = match config
+        .client
+        .stake_client()
+        .get_stake_state(&stake_account_address)
+        .await?
+    {
+        Some(StakeStateV2::Uninitialized) => false,
+        Some(_) => {
+            return Err(format!(
+                "Stake account {} is already initialized",
+                stake_account_address
+            )
+            .into())
+        }
+        None if stake_account.is_none() => {
+            return Err(format!(
+                "Stake account {} does not exist. Provide a keypair to create it",
+                stake_account_address
+            )
+            .into())
+        }
+        None => true,
+    };
+
+    // since we can't infer pool from token account, the withdraw invocation is
+    // rather simpler first get the pool address
+    let pool_address = pool_address_from_args(
+        config,
+        command_config.pool_address,
+        command_config.vote_account_address,
+        command_config.validator_identity,
+    )
+    .await?;
+
+    pool_is_initialized(config, pool_address).await?;
+
+    let pool_mint_address = find_pool_mint_address(&spl_single_pool::id(), &pool_address);
+    let token_program_id = config.client.get_token_program(&pool_address).await?;
+    let token_account_address = command_config.token_account_address.unwrap_or_else(|| {
+        config.associated_token_address(&owner.pubkey(), &pool_mint_address, &token_program_id)
+    });
+
+    let Some(token_account) = config
+        .client
+        .get_token_info(token_account_address, pool_mint_address)
+        .await?
+    else {
+        return Err(format!("Token account {} does not exist", token_account_address).into());
+    };
+
+    // tokens are scaled by the mint decimals, and stake by those of sol
+    let decimals = match command_config.denominate {
+        Denomination::Tokens => get_mint_decimals(config, pool_mint_address).await?,
+        Denomination::Stake => spl_token::native_mint::DECIMALS,
+    };
+    let token_amount = match (
+        amount_to_base_units(command_config.token_amount, decimals),
+        command_config.denominate,
+    ) {
+        (None, _) => token_account.amount,
+        (Some(amount), Denomination::Tokens) => amount,
+        (Some(stake_amount), Denomination::Stake) => {
+            let (net_asset_value, token_supply) =
+                config.client.get_exchange_rate(&pool_address).await?;
+
+            spl_single_pool_client::calculate_burn_amount(
+                token_supply,
+                net_asset_value,
+                stake_amount,
+            )
+            .ok_or_else(|| format!("Pool {} has no value to withdraw", pool_address))?
+        }
+    };
+
+    println_display(
+        config,
+        format!(
+            "Withdrawing from pool {} into {} stake account {}; burning {} tokens from {}\n",
+            pool_address,
+            if create_stake_account {
+                "new"
+            } else {
+                "existing"
+            },
+            stake_account_address,
+            token_amount,
+            token_account_address,
+        ),
+    );
+
+    if token_amount == 0 {
+        return Err("Cannot withdraw zero tokens".into());
+    }
+
+    if token_amount > token_account.amount {
+        return Err(format!(
+            "Withdraw amount {} exceeds tokens in account ({})",
+            token_amount, token_account.amount,
+        )
+        .into());
+    }
+
+    // note a delegate authority is not allowed here because we must authorize the pool authority
+    if token_account.owner != token_authority.pubkey() {
+        return Err(format!(
+            "Invalid token authority: got {}, actual {}",
+            token_account.owner,
+            token_authority.pubkey(),
+        )
+        .into());
+    }
+
+    // the token program only closes empty accounts
+    if command_config.close_token_account && token_amount != token_account.amount {
+        return Err(format!(
+            "Cannot close token account {} after burning {} of its {} tokens; \
+            withdraw ALL to close it",
+            token_account_address, token_amount, token_account.amount,
+        )
+        .into());
+    }
+
+    // create a blank stake account to withdraw into, unless we were given one
+    let mut instructions = vec![];
+    if create_stake_account {
+        instructions.push(
+            config
+                .client
+                .stake_client()
+                .create_uninitialized_stake_account_instruction(
+                    &payer.pubkey(),
+                    &stake_account_address,
+                )
+                .await?,
+        );
+    }
+
+    // perform the withdrawal
+    instructions.extend(spl_single_pool::instruction::withdraw_with_token_program(
+        &spl_single_pool::id(),
+        &token_program_id,
+        &pool_address,
+        &stake_account_address,
+        &stake_authority_address,
+        &token_account_address,
+        &token_authority.pubkey(),
+        token_amount,
+    ));
+
+    // possibly close the emptied token account. pool mints never use extensions, so the
+    // original token program builder produces a valid instruction for either program
+    if command_config.close_token_account {
+        let mut close_instruction = spl_token::instruction::close_account(
+            &spl_token::id(),
+            &token_account_address,
+            &token_account.owner,
+            &token_authority.pubkey(),
+            &[],
+        )?;
+        close_instruction.program_id = token_program_id;
+        instructions.push(close_instruction);
+    }
+
+    // possibly deactivate the new stake account. stake split from a pool being retired is
+    // already deactivating, and the stake program rejects deactivating it again
+    if command_config.deactivate {
+        if pool_stake_is_dedelegated(config, pool_address).await? {
+            println_display(
+                config,
+                "Note: the pool stake is deactivating, so the withdrawn stake already is\n"
+                    .to_string(),
+            );
+        } else {
+            instructions.push(stake::instruction::deactivate_stake(
+                &stake_account_address,
+                &stake_authority_address,
+            ));
+        }
+    }
+
+    // possibly delegate the new stake account to another validator. the stake program only
+    // allows this before the stake takes effect, so active stake must be deactivated first
+    if let Some(redelegate_vote_account_address) = command_config.redelegate_vote_account_address {
+        let current_epoch = config.rpc_client.get_epoch_info().await?.epoch;
+        if pool_stake_is_active(config, pool_address, current_epoch).await? {
+            return Err(format!(
+                "Withdrawn stake is active, so it cannot be delegated to {} until it is \
+                deactivated. Withdraw with --deactivate and delegate it once inactive",
+                redelegate_vote_account_address
+            )
+            .into());
+        }
+
+        instructions.push(stake::instruction::delegate_stake(
+            &stake_account_address,
+            &stake_authority_address,
+            &redelegate_vote_account_address,
+        ));
+    }
+
+    // possibly merge the new stake account into an existing one. we remember how much stake
+    // the destination already had, so we can report only what the withdrawal added
+    let merge_destination = if let Some(merge_into_address) = command_config.merge_into_address {
+        let vote_account_address = config
+            .client
+            .get_pool(&pool_address)
+            .await?
+            .vote_account_address;
+        let existing_stake = check_merge_destination(
+            config,
+            merge_into_address,
+            &stake_authority_address,
+            &vote_account_address,
+        )
+        .await?;
+
+        instructions.extend(stake::instruction::merge(
+            &merge_into_address,
+            &stake_account_address,
+            &stake_authority_address,
+        ));
+
+        Some((merge_into_address, existing_stake))
+    } else {
+        None
+    };
+
+    confirm_submission(config, &pool_address.to_string(), "withdrawal")?;
+
+    let mut signers = vec![];
+    let stake_account = stake_account.filter(|_| create_stake_account);
+    let stake_authority_signer = stake_authority_signer.filter(|_| stake_authority_must_sign);
+    for signer in [
+        Some(payer.clone()),
+        Some(token_authority),
+        stake_account,
+        stake_authority_signer,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if !signers.contains(&signer) {
+            signers.push(signer);
+        }
+    }
+
+    let transaction = Transaction::new_signed_with_payer(
+        &config.with_compute_budget(&instructions).await,
+        Some(&payer.pubkey()),
+        &signers,
+        config.get_blockhash().await?,
+    );
+
+    let signature = process_transaction(config, transaction).await?;
+
+    let (final_stake_account_address, existing_stake) =
+        merge_destination.unwrap_or((stake_account_address, 0));
+    let stake_amount = if config.dry_run || config.no_wait {
+        None
+    } else if let Some(StakeStateV2::Stake(_, stake, _)) = config
+        .client
+        .stake_client()
+        .get_stake_state(&final_stake_account_address)
+        .await?
+    {
+        Some(stake.delegation.stake.saturating_sub(existing_stake))
+    } else {
+        Some(0)
+    };
+
+    Ok(format_output(
+        config,
+        "Withdraw".to_string(),
+        WithdrawOutput {
+            pool_address,
+            stake_account_address,
+            merged_into_address: merge_destination.map(|(address, _)| address),
+            stake_amount,
+            closed_token_account_address: command_config
+                .close_token_account
+                .then_some(token_account_address),
+            signature,
+        },
+    ))
+}
+
+// create token metadata
+async fn command_create_metadata(
+    config: &Config,
+    command_config: CreateMetadataCli,
+) -> CommandResult {
+    let payer = config.fee_payer()?;
+
+    // first get the pool address
+    let pool_address = pool_address_from_args(
+        config,
+        command_config.pool_address,
+        command_config.vote_account_address,
+        command_config.validator_identity,
+    )
+    .await?;
+
+    println_display(
+        config,
+        format!(
+            "Creating default token metadata for pool {}\n",
+            pool_address
+        ),
+    );
+
+    pool_is_initialized(config, pool_address).await?;
+
+    // creation fails on chain if metadata already exists, so catch that here with a clearer error
+    let pool_mint_address = find_pool_mint_address(&spl_single_pool::id(), &pool_address);
+    let (metadata_address, _) = find_metadata_account(&pool_mint_address);
+    if token_metadata_exists(config, metadata_address).await? {
+        return Err(format!(
+            "Token metadata {} already exists for pool {}. Use `update-token-metadata` to change it",
+            metadata_address, pool_address
+        )
+        .into());
+    }
+
+    let instruction = spl_single_pool::instruction::create_token_metadata(
+        &spl_single_pool::id(),
+        &pool_address,
+        &payer.pubkey(),
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &config.with_compute_budget(&[instruction]).await,
+        Some(&payer.pubkey()),
+        &vec![payer],
+        config.get_blockhash().await?,
+    );
+
+    let signature = process_transaction(config, transaction).await?;
+
+    Ok(format_output(
+        config,
+        "CreateTokenMetadata".to_string(),
+        SignatureOutput { signature },
+    ))
+}
+
+// update token metadata
+async fn command_update_metadata(
+    config: &Config,
+    command_config: UpdateMetadataCli,
+    matches: &ArgMatches,
+    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+) -> CommandResult {
+    let payer = config.fee_payer()?;
+    let owner = config.default_signer()?;
+    let authorized_withdrawer = command_config
+        .authorized_withdrawer
+        .map(|source| resolve_signer(matches, &source, "authorized_withdrawer", wallet_manager))
+        .transpose()?
+        .unwrap_or(owner);
+
+    // first get the pool address
+    let pool_address = pool_address_from_args(
+        config,
+        command_config.pool_address,
+        command_config.vote_account_address,
+        command_config.validator_identity,
+    )
+    .await?;
+
+    println_display(
+        config,
+        format!("Updating token metadata for pool {}\n", pool_address),
+    );
+
+    // we always need the vote account
+    let vote_account_address = get_vote_address_from_pool(config, pool_address).await?;
+
+    let vote_authorized_withdrawer =
+        get_vote_authorized_withdrawer(config, vote_account_address).await?;
+    if authorized_withdrawer.pubkey() != vote_authorized_withdrawer {
+        return Err(format!(
+            "Invalid authorized withdrawer: got {}, actual {}",
+            authorized_withdrawer.pubkey(),
+            vote_authorized_withdrawer,
+        )
+        .into());
+    }
+
+    // show what is about to be replaced, so metadata is never overwritten blindly
+    println_display(
+        config,
+        format!(
+            "Metadata authority (vote authorized withdrawer): {}",
+            vote_authorized_withdrawer
+        ),
+    );
+    match get_token_metadata(config, pool_address).await? {
+        Some(token_metadata) => println_display(
+            config,
+            format!("Current token metadata:\n{}", token_metadata),
+        ),
+        None => println_display(config, "No existing token metadata\n".to_string()),
+    }
+
+    let instruction = spl_single_pool::instruction::update_token_metadata(
+        &spl_single_pool::id(),
+        &vote_account_address,
+        &authorized_withdrawer.pubkey(),
+        command_config.token_name,
+        command_config.token_symbol,
+        command_config.token_uri.unwrap_or_default(),
+    );
+
+    let mut signers = vec![];
+    for signer in [payer.clone(), authorized_withdrawer] {
+        if !signers.contains(&signer) {
+            signers.push(signer);
+        }
+    }
+
+    let transaction = Transaction::new_signed_with_payer(
+        &config.with_compute_budget(&[instruction]).await,
+        Some(&payer.pubkey()),
+        &signers,
+        config.get_blockhash().await?,
+    );
+
+    let signature = process_transaction(config, transaction).await?;
+
+    Ok(format_output(
+        config,
+        "UpdateTokenMetadata".to_string(),
+        SignatureOutput { signature },
+    ))
+}
+
+// show the key required to update token metadata
+async fn command_show_metadata_authority(
+    config: &Config,
+    command_config: ShowMetadataAuthorityCli,
+) -> CommandResult {
+    let pool_address = pool_address_from_args(
+        config,
+        command_config.pool_address,
+        command_config.vote_account_address,
+        command_config.validator_identity,
+    )
+    .await?;
+
+    let vote_account_address = get_vote_address_from_pool(config, pool_address).await?;
+    let authorized_withdrawer =
+        get_vote_authorized_withdrawer(config, vote_account_address).await?;
+    let token_metadata = get_token_metadata(config, pool_address).await?;
+
+    Ok(format_output(
+        config,
+        "ShowMetadataAuthority".to_string(),
+        MetadataAuthorityOutput {
+            pool_address,
+            vote_account_address,
+            authorized_withdrawer,
+            token_metadata,
+        },
+    ))
+}
+
+// decode instruction data offline, for debugging failed transactions
+fn command_decode_instruction(
+    config: &Config,
+    command_config: DecodeInstructionCli,
+) -> CommandResult {
+    let data = command_config.data.trim();
+    let bytes = match command_config.encoding {
+        Some(DataEncoding::Hex) => decode_hex(data).ok_or("Invalid hex instruction data")?,
+        Some(DataEncoding::Base64) => BASE64_STANDARD.decode(data)?,
+        None => decode_hex(data)
+            .or_else(|| BASE64_STANDARD.decode(data).ok())
+            .ok_or("Instruction data is neither hex nor base64")?,
+    };
+
+    let instruction = borsh::from_slice::<SinglePoolInstruction>(&bytes)
+        .map_err(|e| format!("Not a single-validator stake pool instruction: {}", e))?;
+
+    let mut output = DecodedInstructionOutput {
+        instruction: String::new(),
+        user_stake_authority: None,
+        token_amount: None,
+        lamports: None,
+        name: None,
+        symbol: None,
+        uri: None,
+    };
+
+    output.instruction = match instruction {
+        SinglePoolInstruction::InitializePool => "InitializePool",
+        SinglePoolInstruction::ReplenishPool => "ReplenishPool",
+        SinglePoolInstruction::DepositStake => "DepositStake",
+        SinglePoolInstruction::WithdrawStake {
+            user_stake_authority,
+            token_amount,
+        } => {
+            output.user_stake_authority = Some(user_stake_authority);
+            output.token_amount = Some(token_amount);
+            "WithdrawStake"
+        }
+        SinglePoolInstruction::CreateTokenMetadata => "CreateTokenMetadata",
+        SinglePoolInstruction::UpdateTokenMetadata { name, symbol, uri } => {
+            output.name = Some(name);
+            output.symbol = Some(symbol);
+            output.uri = Some(uri);
+            "UpdateTokenMetadata"
+        }
+        SinglePoolInstruction::InitializePoolOnRamp => "InitializePoolOnRamp",
+        SinglePoolInstruction::DepositSol { lamports } => {
+            output.lamports = Some(lamports);
+            "DepositSol"
+        }
+        SinglePoolInstruction::WithdrawSol { token_amount } => {
+            output.token_amount = Some(token_amount);
+            "WithdrawSol"
+        }
+        SinglePoolInstruction::ReactivateAfterDelinquency => "ReactivateAfterDelinquency",
+        SinglePoolInstruction::GetVersion => "GetVersion",
+    }
+    .to_string();
+
+    Ok(format_output(
+        config,
+        "DecodeInstruction".to_string(),
+        output,
+    ))
+}
+
+// print the borsh schema as json regardless of output format, since it only exists for tooling
+fn command_export_schema(config: &Config) -> CommandResult {
+    let schema = schema::export_schema();
+
+    Ok(match config.output_format {
+        OutputFormat::JsonCompact => serde_json::to_string(&schema)?,
+        _ => serde_json::to_string_pretty(&schema)?,
+    })
+}
+
+// sum the transfers `initialize` would fund a pool with. every pool costs the same, so the
+// vote account does not need to exist
+async fn command_estimate_init_cost(config: &Config) -> CommandResult {
+    let rent = config.client.stake_client().get_rent().await?;
+    let minimum_pool_balance = config.client.get_minimum_pool_balance().await?;
+
+    let vote_account_address = Pubkey::default();
+    let pool_addresses = PoolAddresses::derive(&spl_single_pool::id(), &vote_account_address);
+    let instructions = spl_single_pool::instruction::initialize(
+        &spl_single_pool::id(),
+        &vote_account_address,
+        &Pubkey::default(),
+        &rent,
+        minimum_pool_balance,
+    );
+
+    let transfer_to = |recipient: &Pubkey| {
+        instructions
+            .iter()
+            .find_map(|instruction| system_transfer_to(instruction, recipient))
+            .unwrap_or(0)
+    };
+    let pool_rent = transfer_to(&pool_addresses.pool);
+    let pool_stake_funding = transfer_to(&pool_addresses.stake);
+    let onramp_rent = transfer_to(&pool_addresses.onramp);
+    let mint_rent = transfer_to(&pool_addresses.mint);
+
+    Ok(format_output(
+        config,
+        "EstimateInitCost".to_string(),
+        EstimateInitCostOutput {
+            pool_rent,
+            pool_stake_funding,
+            minimum_pool_balance,
+            onramp_rent,
+            mint_rent,
+            total: pool_rent
+                .saturating_add(pool_stake_funding)
+                .saturating_add(onramp_rent)
+                .saturating_add(mint_rent),
+        },
+    ))
+}
+
+// print the cli version, and the deployed program version if requested
+async fn command_version(config: &Config, command_config: VersionCli) -> CommandResult {
+    let program_id = command_config
+        .program_id
+        .unwrap_or_else(spl_single_pool::id);
+
+    let onchain_version = if command_config.onchain {
+        Some(get_onchain_version(config, &program_id).await?)
+    } else {
+        None
+    };
+
+    Ok(format_output(
+        config,
+        "Version".to_string(),
+        VersionOutput {
+            cli_version: env!("CARGO_PKG_VERSION").to_string(),
+            program_id: command_config.onchain.then_some(program_id),
+            onchain_version: onchain_version.map(|version| version.to_string()),
+        },
+    ))
+}
+
+// simulate `GetVersion` and decode its return data. programs that predate the instruction
+// reject it as invalid instruction data, which we report as such rather than a raw error
+async fn get_onchain_version(
+    config: &Config,
+    program_id: &Pubkey,
+) -> Result<ProgramVersion, Error> {
+    let payer = config.fee_payer()?;
+    let transaction = Transaction::new_with_payer(
+        &[spl_single_pool::instruction::get_version(program_id)],
+        Some(&payer.pubkey()),
+    );
+
+    let simulation = config
+        .rpc_client
+        .simulate_transaction_with_config(
+            &transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(config.rpc_client.commitment()),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await?
+        .value;
+
+    if let Some(err) = simulation.err {
+        return Err(format!(
+            "Program {} did not return a version ({}). It may predate `GetVersion`, or not be \
+             a single-validator stake pool program",
+            program_id, err,
+        )
+        .into());
+    }
+
+    let return_data = simulation
+        .return_data
+        .filter(|return_data| return_data.program_id == program_id.to_string())
+        .ok_or_else(|| format!("Program {} did not return a version", program_id))?;
+    let (data, _) = return_data.data;
+    let bytes = BASE64_STANDARD.decode(data)?;
+
+    ProgramVersion::from_bytes(&bytes).ok_or_else(|| {
+        format!(
+            "Program {} returned malformed version data: {} bytes",
+            program_id,
+            bytes.len()
+        )
+        .into()
+    })
+}
+
+// show what a wallet's pool tokens are worth. a missing token account is just an empty position
+async fn command_position(config: &Config, command_config: PositionCli) -> CommandResult {
+    let owner_address = match command_config.owner_address {
+        Some(owner_address) => owner_address,
+        None => config.default_signer()?.pubkey(),
+    };
+
+    let pool_address = pool_address_from_args(
+        config,
+        command_config.pool_address,
+        command_config.vote_account_address,
+        command_config.validator_identity,
+    )
+    .await?;
+
+    pool_is_initialized(config, pool_address).await?;
+
+    let pool_mint_address = find_pool_mint_address(&spl_single_pool::id(), &pool_address);
+    let token_program_id = config.client.get_token_program(&pool_address).await?;
+    let token_account_address =
+        config.associated_token_address(&owner_address, &pool_mint_address, &token_program_id);
+
+    let token_balance = config
+        .client
+        .get_token_info(token_account_address, pool_mint_address)
+        .await?
+        .map(|token_account| token_account.amount)
+        .unwrap_or(0);
+
+    let exchange_rate = config.client.get_exchange_rate(&pool_address).await?;
+    let stake_value = spl_single_pool_client::price_stake_for_tokens(exchange_rate, token_balance);
+
+    Ok(format_output(
+        config,
+        "Position".to_string(),
+        PositionOutput {
+            pool_address,
+            owner_address,
+            token_account_address,
+            token_balance,
+            stake_value,
+        },
+    ))
+}
+
+// list stake accounts a wallet could deposit, by the same query `deposit --from-all-delegated` uses
+async fn command_list_stake_accounts(
+    config: &Config,
+    command_config: ListStakeAccountsCli,
+) -> CommandResult {
+    let owner_address = match command_config.owner_address {
+        Some(owner_address) => owner_address,
+        None => config.default_signer()?.pubkey(),
+    };
+
+    let pool_address = pool_address_from_args(
+        config,
+        command_config.pool_address,
+        command_config.vote_account_address,
+        command_config.validator_identity,
+    )
+    .await?;
+
+    let vote_account_address = get_vote_address_from_pool(config, pool_address).await?;
+    let clock = config.client.stake_client().get_clock().await?;
+
+    let stake_accounts = config
+        .client
+        .get_delegated_stake_accounts(&vote_account_address, &owner_address)
+        .await?
+        .into_iter()
+        .map(|(stake_account_address, meta, stake)| {
+            let status = spl_single_pool_client::StakeStatus::from_delegation(
+                &stake.delegation,
+                clock.epoch,
+            );
+
+            ListedStakeAccount {
+                stake_account_address,
+                status: status.into(),
+                stake: stake.delegation.stake,
+                depositable: stake.delegation.deactivation_epoch == u64::MAX
+                    && !meta.lockup.is_in_force(&clock, None),
+            }
+        })
+        .collect();
+
+    Ok(format_output(
+        config,
+        "ListStakeAccounts".to_string(),
+        ListStakeAccountsOutput {
+            pool_address,
+            vote_account_address,
+            owner_address,
+            stake_accounts,
+        },
+    ))
+}
+
+// audit a pool's accounts against everything the program derives and expects. every check
+// runs even if an earlier one fails, so one report shows everything wrong with a pool
+async fn command_verify(config: &Config, command_config: VerifyCli) -> CommandResult {
+    let pool_address = pool_address_from_args(
+        config,
+        command_config.pool_address,
+        command_config.vote_account_address,
+        command_config.validator_identity,
+    )
+    .await?;
+
+    let addresses = PoolAddresses::derive_from_pool(&spl_single_pool::id(), &pool_address);
+    let mut checks = vec![];
+
+    let pool = match config.get_initialized_account(pool_address).await? {
+        Some(account) => {
+            SinglePool::try_from_account(&account.owner, &account.data, &spl_single_pool::id())
+                .map_err(|e| format!("Account is not a valid pool: {}", e))
+        }
+        None => Err("Account does not exist".to_string()),
+    };
+    let vote_account_address = pool.as_ref().ok().map(|pool| pool.vote_account_address);
+    checks.push((
+        "Pool account",
+        pool.map(|pool| format!("Pool for vote account {}", pool.vote_account_address)),
+    ));
+
+    checks.push((
+        "Pool address",
+        match vote_account_address {
+            Some(vote_account_address) => {
+                let expected_pool_address =
+                    find_pool_address(&spl_single_pool::id(), &vote_account_address);
+                if expected_pool_address == pool_address {
+                    Ok("Derived from the vote account".to_string())
+                } else {
+                    Err(format!(
+                        "Vote account {} derives pool {}",
+                        vote_account_address, expected_pool_address
+                    ))
+                }
+            }
+            None => Err("Pool account could not be read".to_string()),
+        },
+    ));
+
+    for (name, stake_address, must_be_delegated) in [
+        ("Main stake account", addresses.stake, true),
+        ("On-ramp account", addresses.onramp, false),
+    ] {
+        let stake_state = config
+            .client
+            .stake_client()
+            .get_stake_state(&stake_address)
+            .await;
+        checks.push((
+            name,
+            verify_pool_stake_account(
+                stake_address,
+                stake_state,
+                &addresses.stake_authority,
+                vote_account_address,
+                must_be_delegated,
+            ),
+        ));
+    }
+
+    let mint = match config.get_initialized_account(addresses.mint).await? {
+        Some(account) if spl_single_pool_client::is_token_program(&account.owner) => account
+            .data
+            .get(..Mint::LEN)
+            .and_then(|data| Mint::unpack(data).ok())
+            .ok_or_else(|| format!("Account {} is not a token mint", addresses.mint)),
+        Some(_) => Err(format!(
+            "Account {} is not owned by a token program",
+            addresses.mint
+        )),
+        None => Err(format!("Mint {} does not exist", addresses.mint)),
+    };
+    let token_supply = mint.as_ref().ok().map(|mint| mint.supply);
+    checks.push((
+        "Pool mint",
+        mint.and_then(|mint| {
+            let mint_authority: Option<Pubkey> = mint.mint_authority.into();
+            if mint_authority != Some(addresses.mint_authority) {
+                Err(format!(
+                    "Mint authority is {}, not the pool mint authority {}",
+                    mint_authority.map_or("unset".to_string(), |authority| authority.to_string()),
+                    addresses.mint_authority
+                ))
+            } else if mint.freeze_authority.is_some() {
+                Err("Mint has a freeze authority".to_string())
+            } else {
+                Ok(format!(
+                    "Mint {} controlled by the pool mint authority",
+                    addresses.mint
+                ))
+            }
+        }),
+    ));
+
+    // the pool can never mint tokens without taking stake, so tokens with no stake
+    // behind them mean the pool accounts are not what the program left them as
+    checks.push((
+        "Token backing",
+        match token_supply {
+            Some(token_supply) => {
+                let (net_asset_value, _) = config.client.get_exchange_rate(&pool_address).await?;
+                if token_supply > 0 && net_asset_value == 0 {
+                    Err(format!(
+                        "{} tokens are outstanding with no stake",
+                        token_supply
+                    ))
+                } else {
+                    Ok(format!(
+                        "{} tokens backed by {} lamports",
+                        token_supply, net_asset_value
+                    ))
+                }
+            }
+            None => Err("Pool mint could not be read".to_string()),
+        },
+    ));
+
+    let checks = checks
+        .into_iter()
+        .map(|(name, result)| {
+            let (passed, detail) = match result {
+                Ok(detail) => (true, detail),
+                Err(detail) => (false, detail),
+            };
+            VerifyCheck {
+                name: name.to_string(),
+                passed,
+                detail,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(format_output(
+        config,
+        "Verify".to_string(),
+        VerifyOutput {
+            pool_address,
+            passed: checks.iter().all(|check| check.passed),
+            checks,
+        },
+    ))
+}
+
+// pool stake accounts are authorized entirely to the pool stake authority. the main account
+// is always delegated to the pool vote account; the on-ramp is too, whenever it is delegated
+fn verify_pool_stake_account(
+    stake_address: Pubkey,
+    stake_state: Result<Option<StakeStateV2>, Error>,
+    stake_authority: &Pubkey,
+    vote_account_address: Option<Pubkey>,
+    must_be_delegated: bool,
+) -> Result<String, String> {
+    let (meta, voter) = match stake_state.map_err(|e| e.to_string())? {
+        Some(StakeStateV2::Stake(meta, stake, _)) => (meta, Some(stake.delegation.voter_pubkey)),
+        Some(StakeStateV2::Initialized(meta)) if !must_be_delegated => (meta, None),
+        Some(_) => return Err(format!("Stake account {} is not delegated", stake_address)),
+        None => return Err(format!("Stake account {} does not exist", stake_address)),
+    };
+
+    if meta.authorized.staker != *stake_authority || meta.authorized.withdrawer != *stake_authority
+    {
+        return Err(format!(
+            "Stake account {} is not authorized to the pool stake authority {}",
+            stake_address, stake_authority
+        ));
+    }
+
+    match (voter, vote_account_address) {
+        (Some(voter), Some(vote_account_address)) if voter != vote_account_address => Err(format!(
+            "Stake account {} is delegated to {}, not the pool vote account {}",
+            stake_address, voter, vote_account_address
+        )),
+        (Some(voter), _) => Ok(format!(
+            "Stake account {} delegated to {}",
+            stake_address, voter
+        )),
+        (None, _) => Ok(format!("Stake account {} is undelegated", stake_address)),
+    }
+}
+
+// find the pool a mint belongs to. mints are not stored in pool accounts, so we derive each
+async fn command_lookup(config: &Config, command_config: LookupCli) -> CommandResult {
+    let mint_address = command_config.mint_address;
+
+    // fail fast on anything that cannot be a pool mint, before scanning every pool
+    match config.get_initialized_account(mint_address).await? {
+        Some(account) if spl_single_pool_client::is_token_program(&account.owner) => (),
+        Some(_) => return Err(format!("{} is not a token mint", mint_address).into()),
+        None => return Err(format!("Mint {} does not exist", mint_address).into()),
+    }
+
+    let Some((pool_address, vote_account_address)) = get_all_pool_and_vote_addresses(config)
+        .await?
+        .into_iter()
+        .find(|(pool_address, _)| {
+            find_pool_mint_address(&spl_single_pool::id(), pool_address) == mint_address
+        })
+    else {
+        return Err(format!(
+            "{} is not the mint of any single-validator pool",
+            mint_address
+        )
+        .into());
+    };
+
+    let Some(vote_account_data) = config.get_initialized_account(vote_account_address).await?
+    else {
+        return Err(format!("Vote account {} does not exist", vote_account_address).into());
+    };
+    let vote_account = VoteStateV4::deserialize(&vote_account_data.data, &vote_account_address)?;
+
+    Ok(format_output(
+        config,
+        "Lookup".to_string(),
+        LookupOutput {
+            mint_address,
+            pool_address,
+            vote_account_address,
+            validator_identity: vote_account.node_pubkey,
+        },
+    ))
+}
+
+// display stake pool(s)
+async fn command_display(config: &Config, command_config: DisplayCli) -> CommandResult {
+    let stake_rent_exempt_reserve = config
+        .with_rpc_retries(|| {
+            config
+                .rpc_client
+                .get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())
+        })
+        .await?;
+    let current_epoch = config
+        .with_rpc_retries(|| config.rpc_client.get_epoch_info())
+        .await?
+        .epoch;
+    let minimum_delegation = config.client.stake_client().minimum_delegation().await?;
+    let minimum_pool_balance = config.client.get_minimum_pool_balance().await?;
+
+    let pool_and_vote_addresses = if command_config.all {
+        let pool_and_vote_addresses = get_all_pool_and_vote_addresses(config).await?;
+
+        pool_and_vote_addresses
+            .into_iter()
+            .skip(command_config.offset.unwrap_or(0))
+            .take(command_config.limit.unwrap_or(usize::MAX))
+            .collect::<Vec<_>>()
+    } else {
+        let pool_address = pool_address_from_args(
+            config,
+            command_config.pool_address,
+            command_config.vote_account_address,
+            command_config.validator_identity,
+        )
+        .await?;
 
-    let Some(token_account) =
-        quarantine::get_token_info(config, token_account_address, pool_mint_address).await?
-    else {
-        return Err(format!("Token account {} does not exist", token_account_address).into());
+        vec![(
+            pool_address,
+            get_vote_address_from_pool(config, pool_address).await?,
+        )]
     };
 
-    let token_amount = match command_config.token_amount.sol_to_lamport() {
-        Amount::All => token_account.amount,
-        Amount::Raw(amount) => amount,
-        Amount::Decimal(_) => unreachable!(),
+    let pool_addresses = pool_and_vote_addresses
+        .iter()
+        .map(|(pool_address, _)| {
+            PoolAddresses::derive_from_pool(&spl_single_pool::id(), pool_address)
+        })
+        .collect::<Vec<_>>();
+
+    // stake, onramp, and mint accounts are independent, so fetch them all at once
+    let client = config
+        .client
+        .clone()
+        .with_max_concurrent_requests(command_config.max_concurrent_requests);
+    let stake_addresses = pool_addresses
+        .iter()
+        .map(|addresses| addresses.stake)
+        .collect::<Vec<_>>();
+    let onramp_addresses = pool_addresses
+        .iter()
+        .map(|addresses| addresses.onramp)
+        .collect::<Vec<_>>();
+    let mint_addresses = pool_addresses
+        .iter()
+        .map(|addresses| addresses.mint)
+        .collect::<Vec<_>>();
+
+    let (stake_summaries, onramp_summaries, token_supplies) = futures::try_join!(
+        client.get_stake_summaries(&stake_addresses, stake_rent_exempt_reserve, current_epoch),
+        client.get_stake_summaries(&onramp_addresses, stake_rent_exempt_reserve, current_epoch),
+        client.get_token_supplies(&mint_addresses),
+    )?;
+
+    let estimated_apys = if command_config.with_apy {
+        let vote_addresses = pool_and_vote_addresses
+            .iter()
+            .map(|(_, vote_account_address)| *vote_account_address)
+            .collect::<Vec<_>>();
+        let apy_parameters = client.get_apy_parameters().await?;
+
+        client
+            .get_estimated_apys(&vote_addresses, &apy_parameters, current_epoch)
+            .await?
+    } else {
+        vec![None; pool_and_vote_addresses.len()]
     };
 
-    println_display(
-        config,
-        format!(
-            "Withdrawing from pool {} into new stake account {}; burning {} tokens from {}\n",
-            pool_address, stake_account_address, token_amount, token_account_address,
-        ),
-    );
+    let mut displays = vec![];
+    for (
+        ((((pool_address, vote_account_address), stake_summary), onramp_summary), token_supply),
+        estimated_apy,
+    ) in pool_and_vote_addresses
+        .into_iter()
+        .zip(stake_summaries)
+        .zip(onramp_summaries)
+        .zip(token_supplies)
+        .zip(estimated_apys)
+    {
+        let net_asset_value = stake_summary.nav(onramp_summary);
+        let undelegated_lamports = stake_summary.excess_lamports(onramp_summary);
 
-    if token_amount == 0 {
-        return Err("Cannot withdraw zero tokens".into());
+        displays.push(StakePoolOutput {
+            pool_address,
+            vote_account_address,
+            net_asset_value,
+            available_stake: stake_summary.stake.saturating_sub(minimum_pool_balance),
+            undelegated_lamports,
+            token_supply,
+            exchange_rate: exchange_rate(net_asset_value, token_supply),
+            main_stake_dedelegated: stake_summary.dedelegated,
+            onramp_exists: onramp_summary.exists,
+            onramp_lamports: onramp_summary.usable_lamports,
+            onramp_activating: onramp_summary.status
+                == spl_single_pool_client::StakeStatus::Activating,
+            status: stake_summary.status.into(),
+            estimated_apy,
+            show_apy: command_config.with_apy,
+            minimum_delegation,
+            signature: None,
+        });
     }
 
-    if token_amount > token_account.amount {
-        return Err(format!(
-            "Withdraw amount {} exceeds tokens in account ({})",
-            token_amount, token_account.amount,
-        )
-        .into());
-    }
+    if command_config.all {
+        let output = StakePoolListOutput(displays);
+        if config.csv_output {
+            Ok(format_csv_output(&output))
+        } else if config.prometheus_output {
+            Ok(format_prometheus_output(&output.0))
+        } else {
+            Ok(format_output(config, "DisplayAll".to_string(), output))
+        }
+    } else {
+        if config.prometheus_output {
+            return Ok(format_prometheus_output(&displays));
+        }
 
-    // note a delegate authority is not allowed here because we must authorize the pool authority
-    if token_account.owner != token_authority.pubkey() {
-        return Err(format!(
-            "Invalid token authority: got {}, actual {}",
-            token_account.owner,
-            token_authority.pubkey(),
-        )
-        .into());
+        let output = displays.remove(0);
+        if config.csv_output {
+            Ok(format_csv_output(&output))
+        } else {
+            Ok(format_output(config, "Display".to_string(), output))
+        }
     }
+}
 
-    // create a blank stake account to withdraw into
-    let mut instructions = vec![
-        quarantine::create_uninitialized_stake_account_instruction(
-            config,
-            &payer.pubkey(),
-            &stake_account_address,
-        )
-        .await?,
-    ];
-
-    // perform the withdrawal
-    instructions.extend(spl_single_pool::instruction::withdraw(
-        &spl_single_pool::id(),
-        &pool_address,
-        &stake_account_address,
-        &stake_authority_address,
-        &token_account_address,
-        &token_authority.pubkey(),
-        token_amount,
-    ));
+// quote a deposit or withdrawal at the current exchange rate
+async fn command_quote(config: &Config, command_config: QuoteCli) -> CommandResult {
+    let pool_address = pool_address_from_args(
+        config,
+        command_config.pool_address,
+        command_config.vote_account_address,
+        command_config.validator_identity,
+    )
+    .await?;
 
-    // possibly deactivate the new stake account
-    if command_config.deactivate {
-        instructions.push(stake::instruction::deactivate_stake(
-            &stake_account_address,
-            &stake_authority_address,
-        ));
-    }
+    pool_is_initialized(config, pool_address).await?;
 
-    let mut signers = vec![];
-    for signer in [payer.as_ref(), token_authority.as_ref(), &stake_account] {
-        if !signers.contains(&signer) {
-            signers.push(signer);
-        }
-    }
+    let Amount::Raw(input_amount) = command_config.amount.sol_to_lamport() else {
+        unreachable!();
+    };
 
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &signers,
-        config.rpc_client.get_latest_blockhash().await?,
-    );
+    let (net_asset_value, token_supply) = config.client.get_exchange_rate(&pool_address).await?;
 
-    let signature = process_transaction(config, transaction).await?;
+    let output_amount = match command_config.direction {
+        QuoteDirection::StakeToToken => spl_single_pool_client::calculate_deposit_amount(
+            token_supply,
+            net_asset_value,
+            input_amount,
+        ),
+        QuoteDirection::TokenToStake => spl_single_pool_client::calculate_withdraw_amount(
+            token_supply,
+            net_asset_value,
+            input_amount,
+        ),
+    }
+    .ok_or("Quote overflowed")?;
 
-    let stake_amount = if config.dry_run {
-        None
-    } else if let Some((_, stake)) =
-        quarantine::get_stake_info(config, stake_account_address).await?
-    {
-        Some(stake.delegation.stake)
+    // includes rounding, so this may differ slightly from the pool ratio for small amounts
+    let effective_rate = if input_amount > 0 {
+        output_amount as f64 / input_amount as f64
     } else {
-        Some(0)
+        0.0
     };
 
     Ok(format_output(
         config,
-        "Withdraw".to_string(),
-        WithdrawOutput {
+        "Quote".to_string(),
+        QuoteOutput {
             pool_address,
-            stake_account_address,
-            stake_amount,
-            signature,
+            direction: command_config.direction,
+            input_amount,
+            output_amount,
+            effective_rate,
         },
     ))
 }
 
-// create token metadata
-async fn command_create_metadata(
-    config: &Config,
-    command_config: CreateMetadataCli,
-) -> CommandResult {
+// create pool on-ramp
+async fn command_create_onramp(config: &Config, command_config: CreateOnRampCli) -> CommandResult {
     let payer = config.fee_payer()?;
 
-    // first get the pool address
-    // i dont check metadata because i dont want to get entangled with mpl
     let pool_address = pool_address_from_args(
+        config,
         command_config.pool_address,
         command_config.vote_account_address,
-    );
+        command_config.validator_identity,
+    )
+    .await?;
+    let onramp_address = find_pool_onramp_address(&spl_single_pool::id(), &pool_address);
 
     println_display(
         config,
         format!(
-            "Creating default token metadata for pool {}\n",
-            pool_address
+            "Creating onramp stake account {} for pool {}\n",
+            onramp_address, pool_address
         ),
     );
 
     pool_is_initialized(config, pool_address).await?;
 
-    // and... i guess thats it?
+    if config
+        .get_initialized_account(onramp_address)
+        .await?
+        .is_some()
+    {
+        return Err(format!(
+            "Pool {} onramp {} already exists",
+            pool_address, onramp_address
+        )
+        .into());
+    }
 
-    let instruction = spl_single_pool::instruction::create_token_metadata(
+    let instructions = spl_single_pool::instruction::create_pool_onramp(
         &spl_single_pool::id(),
         &pool_address,
         &payer.pubkey(),
+        &config.client.stake_client().get_rent().await?,
     );
 
     let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
+        &config.with_compute_budget(&instructions).await,
         Some(&payer.pubkey()),
         &vec![payer],
-        config.rpc_client.get_latest_blockhash().await?,
+        config.get_blockhash().await?,
     );
 
     let signature = process_transaction(config, transaction).await?;
 
     Ok(format_output(
         config,
-        "CreateTokenMetadata".to_string(),
+        "InitializePoolOnRamp".to_string(),
         SignatureOutput { signature },
     ))
 }
 
-// update token metadata
-async fn command_update_metadata(
+// deposit liquid sol
+async fn command_deposit_sol(
     config: &Config,
-    command_config: UpdateMetadataCli,
+    command_config: DepositSolCli,
     matches: &ArgMatches,
     wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
 ) -> CommandResult {
     let payer = config.fee_payer()?;
     let owner = config.default_signer()?;
-    let authorized_withdrawer = command_config
-        .authorized_withdrawer
-        .and_then(|source| {
-            signer_from_source(matches, &source, "authorized_withdrawer", wallet_manager)
-                .ok()
-                .map(Arc::from)
-        })
-        .unwrap_or(owner);
 
-    // first get the pool address
-    // i dont check metadata because i dont want to get entangled with mpl
+    let deposit_source = command_config
+        .from
+        .map(|source| resolve_signer(matches, &source, "from", wallet_manager))
+        .transpose()?
+        .unwrap_or(owner.clone());
+
+    let deposit_amount = command_config.lamports;
+
     let pool_address = pool_address_from_args(
+        config,
         command_config.pool_address,
         command_config.vote_account_address,
-    );
+        command_config.validator_identity,
+    )
+    .await?;
 
-    println_display(
-        config,
-        format!("Updating token metadata for pool {}\n", pool_address),
-    );
+    let pool_addresses = PoolAddresses::derive_from_pool(&spl_single_pool::id(), &pool_address);
+
+    pool_is_initialized(config, pool_address).await?;
 
-    // we always need the vote account
     let vote_account_address = get_vote_address_from_pool(config, pool_address).await?;
 
-    if let Some(vote_account_data) = config.get_initialized_account(vote_account_address).await? {
-        let vote_account =
-            VoteStateV4::deserialize(&vote_account_data.data, &vote_account_address)?;
+    if config
+        .get_initialized_account(pool_addresses.onramp)
+        .await?
+        .is_none()
+    {
+        return Err(format!(
+            "Pool {} onramp {} does not exist; run `spl-single-pool manage create-on-ramp ...` \
+            to create it",
+            pool_address, pool_addresses.onramp
+        )
+        .into());
+    }
+
+    let current_epoch = config.rpc_client.get_epoch_info().await?.epoch;
 
-        if authorized_withdrawer.pubkey() != vote_account.authorized_withdrawer {
+    if let Some(StakeStateV2::Stake(_, stake, _)) = config
+        .client
+        .stake_client()
+        .get_stake_state(&pool_addresses.stake)
+        .await?
+    {
+        if stake.delegation.activation_epoch >= current_epoch {
             return Err(format!(
-                "Invalid authorized withdrawer: got {}, actual {}",
-                authorized_withdrawer.pubkey(),
-                vote_account.authorized_withdrawer,
+                "Pool {} stake {} is still activating; must be fully active",
+                pool_address, pool_addresses.stake
+            )
+            .into());
+        }
+
+        if stake.delegation.deactivation_epoch < u64::MAX {
+            return Err(format!(
+                "Pool {} stake {} is deactivating or deactivated",
+                pool_address, pool_addresses.stake
             )
             .into());
         }
     } else {
-        return Err(format!("Vote account {} does not exist", vote_account_address).into());
+        // pool existence already validated and pool exists => stake exists and is delegated
+        unreachable!();
+    };
+
+    {
+        let deposit_source_balance = config
+            .rpc_client
+            .get_account_with_commitment(&deposit_source.pubkey(), config.rpc_client.commitment())
+            .await?
+            .value
+            .map(|account| account.lamports)
+            .unwrap_or(0);
+
+        if deposit_source_balance < deposit_amount {
+            return Err(format!(
+                "Insufficient lamports in {} for deposit: has {}, needs {}",
+                deposit_source.pubkey(),
+                deposit_source_balance,
+                deposit_amount,
+            )
+            .into());
+        }
     }
 
-    let instruction = spl_single_pool::instruction::update_token_metadata(
-        &spl_single_pool::id(),
-        &vote_account_address,
-        &authorized_withdrawer.pubkey(),
-        command_config.token_name,
-        command_config.token_symbol,
-        command_config.token_uri.unwrap_or_default(),
+    println_display(
+        config,
+        format!(
+            "Depositing liquid sol from account {} into pool {}\n",
+            deposit_source.pubkey(),
+            pool_address
+        ),
+    );
+
+    let mut instructions = vec![];
+
+    // use token account provided, or get/create the associated account for the client keypair
+    let token_program_id = config.client.get_token_program(&pool_address).await?;
+    let token_account_address = if let Some(account) = command_config.token_account_address {
+        account
+    } else {
+        let ata_address = config.associated_token_address(
+            &owner.pubkey(),
+            &pool_addresses.mint,
+            &token_program_id,
+        );
+        if config
+            .client
+            .get_token_info(ata_address, pool_addresses.mint)
+            .await?
+            .is_none()
+        {
+            instructions.push(config.create_associated_token_account(
+                &payer.pubkey(),
+                &owner.pubkey(),
+                &pool_addresses.mint,
+                &token_program_id,
+            ));
+        }
+        ata_address
+    };
+
+    let previous_token_amount =
+        get_deposit_token_amount(config, token_account_address, pool_addresses.mint).await?;
+
+    // use escrow account for lamports to avoid exposing wallet signer to program
+    let escrow_deposit_account = Keypair::new();
+
+    instructions.extend(
+        spl_single_pool::instruction::deposit_liquid_with_token_program(
+            &spl_single_pool::id(),
+            &token_program_id,
+            &vote_account_address,
+            &deposit_source.pubkey(),
+            &escrow_deposit_account.pubkey(),
+            &token_account_address,
+            deposit_amount,
+        ),
     );
 
     let mut signers = vec![];
-    for signer in [payer.clone(), authorized_withdrawer] {
+    for signer in [
+        payer.clone(),
+        deposit_source,
+        Arc::new(escrow_deposit_account),
+    ] {
         if !signers.contains(&signer) {
             signers.push(signer);
         }
     }
 
     let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
+        &config.with_compute_budget(&instructions).await,
         Some(&payer.pubkey()),
         &signers,
-        config.rpc_client.get_latest_blockhash().await?,
+        config.get_blockhash().await?,
     );
 
     let signature = process_transaction(config, transaction).await?;
 
+    let token_amount = if config.dry_run || config.no_wait {
+        None
+    } else {
+        Some(
+            config
+                .client
+                .get_token_info(token_account_address, pool_addresses.mint)
+                .await?
+                .map(|token_account| token_account.amount)
+                .unwrap_or(0)
+                - previous_token_amount,
+        )
+    };
+
     Ok(format_output(
         config,
-        "UpdateTokenMetadata".to_string(),
-        SignatureOutput { signature },
+        "DepositSol".to_string(),
+        DepositSolOutput {
+            pool_address,
+            token_amount,
+            signature,
+        },
     ))
 }
 
-// display stake pool(s)
-async fn command_display(config: &Config, command_config: DisplayCli) -> CommandResult {
-    let stake_rent_exempt_reserve = config
-        .rpc_client
-        .get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())
+// every pool on chain, sorted by pool address because rpc order is arbitrary
+async fn get_all_pool_and_vote_addresses(config: &Config) -> Result<Vec<(Pubkey, Pubkey)>, Error> {
+    // the filter isn't necessary now but makes the cli forward-compatible
+    let program_id = spl_single_pool::id();
+    #[allow(deprecated)]
+    let pools = config
+        .with_rpc_retries(|| async move {
+            config
+                .rpc_client
+                .get_program_accounts_with_config(
+                    &program_id,
+                    RpcProgramAccountsConfig {
+                        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                            0,
+                            vec![1],
+                        ))]),
+                        ..RpcProgramAccountsConfig::default()
+                    },
+                )
+                .await
+        })
         .await?;
-    let current_epoch = config.rpc_client.get_epoch_info().await?.epoch;
-    let minimum_delegation = config.rpc_client.get_stake_minimum_delegation().await?;
 
-    let pool_and_vote_addresses = if command_config.all {
-        // the filter isn't necessary now but makes the cli forward-compatible
-        #[allow(deprecated)]
-        let pools = config
-            .rpc_client
-            .get_program_accounts_with_config(
-                &spl_single_pool::id(),
-                RpcProgramAccountsConfig {
-                    filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
-                        0,
-                        vec![1],
-                    ))]),
-                    ..RpcProgramAccountsConfig::default()
-                },
-            )
-            .await?;
+    let mut pool_and_vote_addresses = vec![];
+    for pool in pools.into_iter() {
+        let vote_account_address =
+            SinglePool::try_from_account(&pool.1.owner, &pool.1.data, &spl_single_pool::id())?
+                .vote_account_address;
+        pool_and_vote_addresses.push((pool.0, vote_account_address));
+    }
 
-        let mut pool_and_vote_addresses = vec![];
-        for pool in pools.into_iter() {
-            let vote_account_address =
-                try_from_slice_unchecked::<SinglePool>(&pool.1.data)?.vote_account_address;
-            pool_and_vote_addresses.push((pool.0, vote_account_address));
-        }
+    pool_and_vote_addresses.sort_unstable();
 
-        pool_and_vote_addresses
-    } else {
-        let pool_address = pool_address_from_args(
-            command_config.pool_address,
-            command_config.vote_account_address,
-        );
+    Ok(pool_and_vote_addresses)
+}
 
-        vec![(
-            pool_address,
-            get_vote_address_from_pool(config, pool_address).await?,
-        )]
+async fn get_vote_address_from_pool(
+    config: &Config,
+    pool_address: Pubkey,
+) -> Result<Pubkey, Error> {
+    let Some(pool_account) = config.get_initialized_account(pool_address).await? else {
+        return Err(CliError::PoolNotInitialized(pool_address).into());
     };
 
-    if pool_and_vote_addresses.len() > 100 {
-        return Err(
-            "Displaying more than 100 pools is not implemented; if you see \
-            this error, feel free to open an issue in the SVSP repo."
-                .into(),
-        );
+    if pool_account.owner != spl_single_pool::id() {
+        return Err(CliError::PoolNotOwnedByProgram(pool_address).into());
     }
 
-    let stake_summaries = {
-        let stake_addresses = pool_and_vote_addresses
-            .iter()
-            .map(|(pool_address, _)| find_pool_stake_address(&spl_single_pool::id(), pool_address))
-            .collect::<Vec<_>>();
+    if let Ok(pool) = SinglePool::try_from_account(
+        &pool_account.owner,
+        &pool_account.data,
+        &spl_single_pool::id(),
+    ) {
+        Ok(pool.vote_account_address)
+    } else {
+        Err(CliError::InvalidPoolAccount(pool_address).into())
+    }
+}
 
-        quarantine::get_stake_summaries(
-            config,
-            &stake_addresses,
-            stake_rent_exempt_reserve,
-            current_epoch,
-        )
-        .await?
+async fn get_vote_authorized_withdrawer(
+    config: &Config,
+    vote_account_address: Pubkey,
+) -> Result<Pubkey, Error> {
+    let Some(vote_account_data) = config.get_initialized_account(vote_account_address).await?
+    else {
+        return Err(format!("Vote account {} does not exist", vote_account_address).into());
     };
 
-    let onramp_summaries = {
-        let onramp_addresses = pool_and_vote_addresses
-            .iter()
-            .map(|(pool_address, _)| find_pool_onramp_address(&spl_single_pool::id(), pool_address))
-            .collect::<Vec<_>>();
+    let vote_account = VoteStateV4::deserialize(&vote_account_data.data, &vote_account_address)?;
+    Ok(vote_account.authorized_withdrawer)
+}
 
-        quarantine::get_stake_summaries(
-            config,
-            &onramp_addresses,
-            stake_rent_exempt_reserve,
-            current_epoch,
+// we only read the account key rather than deserializing the full mpl metadata layout
+async fn token_metadata_exists(config: &Config, metadata_address: Pubkey) -> Result<bool, Error> {
+    let Some(metadata_account) = config.get_initialized_account(metadata_address).await? else {
+        return Ok(false);
+    };
+
+    if metadata_account.owner != inline_mpl_token_metadata::id() {
+        return Err(format!(
+            "Metadata address {} is not owned by the token metadata program",
+            metadata_address
         )
-        .await?
+        .into());
+    }
+
+    Ok(metadata_account.data.first() == Some(&MPL_KEY_METADATA_V1))
+}
+
+// mpl metadata begins with the key, update authority, and mint, followed by name, symbol, and uri.
+// we only decode that prefix. mpl pads the strings with nuls to fixed lengths
+async fn get_token_metadata(
+    config: &Config,
+    pool_address: Pubkey,
+) -> Result<Option<TokenMetadata>, Error> {
+    let pool_mint_address = find_pool_mint_address(&spl_single_pool::id(), &pool_address);
+    let (metadata_address, _) = find_metadata_account(&pool_mint_address);
+    if !token_metadata_exists(config, metadata_address).await? {
+        return Ok(None);
+    }
+
+    // existence and ownership were checked above
+    let Some(metadata_account) = config.get_initialized_account(metadata_address).await? else {
+        return Ok(None);
     };
 
-    let token_supplies = {
-        let mint_addresses = pool_and_vote_addresses
-            .iter()
-            .map(|(pool_address, _)| find_pool_mint_address(&spl_single_pool::id(), pool_address))
-            .collect::<Vec<_>>();
+    let mut data = metadata_account
+        .data
+        .get(65..)
+        .ok_or_else(|| format!("Metadata account {} is too short", metadata_address))?;
+    let mut next_string = || -> Result<String, Error> {
+        let value: String = borsh::BorshDeserialize::deserialize(&mut data)?;
+        Ok(value.trim_end_matches('\0').to_string())
+    };
+
+    Ok(Some(TokenMetadata {
+        name: next_string()?,
+        symbol: next_string()?,
+        uri: next_string()?,
+    }))
+}
+
+async fn pool_is_initialized(config: &Config, pool_address: Pubkey) -> Result<(), Error> {
+    get_vote_address_from_pool(config, pool_address)
+        .await
+        .map(|_| ())
+}
+
+// validate a stake account for deposit, returning its pool address and whether it is active
+async fn check_deposit_stake_account(
+    config: &Config,
+    stake_account_address: Pubkey,
+    staker_authority_address: Option<Pubkey>,
+    stake_authority_address: &Pubkey,
+    provided_pool_address: Option<Pubkey>,
+    current_epoch: u64,
+) -> Result<(Pubkey, bool), Error> {
+    match config
+        .client
+        .stake_client()
+        .get_stake_state(&stake_account_address)
+        .await?
+    {
+        Some(StakeStateV2::Stake(meta, stake, _)) => {
+            let derived_pool_address =
+                find_pool_address(&spl_single_pool::id(), &stake.delegation.voter_pubkey);
+
+            if let Some(provided_pool_address) = provided_pool_address {
+                if provided_pool_address != derived_pool_address {
+                    return Err(format!(
+                        "Provided pool address {} does not match stake account-derived address {}",
+                        provided_pool_address, derived_pool_address,
+                    )
+                    .into());
+                }
+            }
+
+            if meta.authorized.withdrawer != *stake_authority_address {
+                return Err(format!(
+                    "Incorrect withdraw authority for stake account {}: got {}, expected {}",
+                    stake_account_address, meta.authorized.withdrawer, stake_authority_address,
+                )
+                .into());
+            }
+
+            // the withdraw authority may also reassign the staker, so only check an explicit one
+            if let Some(staker_authority_address) = staker_authority_address {
+                if meta.authorized.staker != staker_authority_address {
+                    return Err(format!(
+                        "Incorrect stake authority for stake account {}: got {}, expected {}",
+                        stake_account_address, meta.authorized.staker, staker_authority_address,
+                    )
+                    .into());
+                }
+            }
+
+            if stake.delegation.deactivation_epoch < u64::MAX {
+                return Err(format!(
+                    "Stake account {} is deactivating or deactivated",
+                    stake_account_address
+                )
+                .into());
+            }
+
+            // the program rejects locked accounts. only fetch the clock if there is a lockup at all
+            if meta.lockup != Lockup::default()
+                && meta
+                    .lockup
+                    .is_in_force(&config.client.stake_client().get_clock().await?, None)
+            {
+                return Err(format!(
+                    "Stake account {} has a lockup in force until epoch {} and unix timestamp {}",
+                    stake_account_address, meta.lockup.epoch, meta.lockup.unix_timestamp,
+                )
+                .into());
+            }
 
-        quarantine::get_token_supplies(config, &mint_addresses).await?
+            Ok((
+                derived_pool_address,
+                stake.delegation.activation_epoch < current_epoch,
+            ))
+        }
+        Some(_) => Err(format!("Stake account {} is not delegated", stake_account_address).into()),
+        None => Err(format!("Could not find stake account {}", stake_account_address).into()),
+    }
+}
+
+// a withdrawn stake account can only be merged into an account in the same state. returns the
+// stake already delegated in the destination
+async fn check_merge_destination(
+    config: &Config,
+    merge_into_address: Pubkey,
+    stake_authority_address: &Pubkey,
+    vote_account_address: &Pubkey,
+) -> Result<u64, Error> {
+    let Some(StakeStateV2::Stake(meta, stake, _)) = config
+        .client
+        .stake_client()
+        .get_stake_state(&merge_into_address)
+        .await?
+    else {
+        return Err(format!("Stake account {} is not delegated", merge_into_address).into());
     };
 
-    let mut displays = vec![];
-    for ((((pool_address, vote_account_address), stake_summary), onramp_summary), token_supply) in
-        pool_and_vote_addresses
-            .into_iter()
-            .zip(stake_summaries)
-            .zip(onramp_summaries)
-            .zip(token_supplies)
+    if stake.delegation.voter_pubkey != *vote_account_address {
+        return Err(format!(
+            "Cannot merge into stake account {}: delegated to {}, but pool vote account is {}",
+            merge_into_address, stake.delegation.voter_pubkey, vote_account_address,
+        )
+        .into());
+    }
+
+    if meta.authorized.staker != *stake_authority_address
+        || meta.authorized.withdrawer != *stake_authority_address
     {
-        let net_asset_value = stake_summary.nav(onramp_summary);
-        let undelegated_lamports = stake_summary.excess_lamports(onramp_summary);
+        return Err(format!(
+            "Cannot merge into stake account {}: authorities must both be {}",
+            merge_into_address, stake_authority_address,
+        )
+        .into());
+    }
 
-        displays.push(StakePoolOutput {
-            pool_address,
-            vote_account_address,
-            net_asset_value,
-            undelegated_lamports,
-            token_supply,
-            main_stake_dedelegated: stake_summary.dedelegated,
-            onramp_exists: onramp_summary.exists,
-            minimum_delegation,
-            signature: None,
-        });
+    let clock = config.client.stake_client().get_clock().await?;
+    if stake.delegation.activation_epoch >= clock.epoch
+        || stake.delegation.deactivation_epoch < u64::MAX
+    {
+        return Err(format!(
+            "Cannot merge into stake account {}: it must be fully active",
+            merge_into_address
+        )
+        .into());
     }
 
-    if command_config.all {
-        Ok(format_output(
-            config,
-            "DisplayAll".to_string(),
-            StakePoolListOutput(displays),
-        ))
-    } else {
-        Ok(format_output(
-            config,
-            "Display".to_string(),
-            displays.remove(0),
-        ))
+    if meta.lockup.is_in_force(&clock, None) {
+        return Err(format!(
+            "Cannot merge into stake account {}: it has a lockup in force",
+            merge_into_address
+        )
+        .into());
     }
+
+    Ok(stake.delegation.stake)
 }
 
-// create pool on-ramp
-async fn command_create_onramp(config: &Config, command_config: CreateOnRampCli) -> CommandResult {
-    let payer = config.fee_payer()?;
+// resolve a signer argument, eg a keypair file, ASK, or a hardware wallet. failures are
+// surfaced rather than falling back to the default signer, so a disconnected or declined
+// hardware wallet is reported as such instead of as a mismatched authority
+fn resolve_signer(
+    matches: &ArgMatches,
+    source: &SignerSource,
+    name: &str,
+    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+) -> Result<Arc<dyn Signer>, Error> {
+    signer_from_source(matches, source, name, wallet_manager)
+        .map(Arc::from)
+        .map_err(|err| format!("Failed to load {}: {}", name, err).into())
+}
 
-    let pool_address = pool_address_from_args(
-        command_config.pool_address,
-        command_config.vote_account_address,
-    );
-    let onramp_address = find_pool_onramp_address(&spl_single_pool::id(), &pool_address);
+// none if the string is not an even number of hex digits
+fn decode_hex(data: &str) -> Option<Vec<u8>> {
+    if !data.len().is_multiple_of(2) || !data.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..data.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&data[i..i + 2], 16).ok())
+        .collect()
+}
+
+// balance of the token account a deposit mints into, or zero if it is yet to be created. pool
+// mints have no freeze authority, so a frozen account could never be thawed; refuse it up front
+async fn get_deposit_token_amount(
+    config: &Config,
+    token_account_address: Pubkey,
+    pool_mint_address: Pubkey,
+) -> Result<u64, Error> {
+    match config
+        .client
+        .get_token_info(token_account_address, pool_mint_address)
+        .await?
+    {
+        Some(token_account) if token_account.is_frozen() => Err(format!(
+            "Token account {} is frozen and cannot receive pool tokens",
+            token_account_address
+        )
+        .into()),
+        Some(token_account) => Ok(token_account.amount),
+        None => Ok(0),
+    }
+}
+
+// load a keypair, or generate one and save it first if the file does not exist. an existing
+// keypair may belong to an account that is already initialized, which the caller must check
+fn read_or_write_keypair_file(config: &Config, path: &Path) -> Result<Arc<dyn Signer>, Error> {
+    if path.exists() {
+        let keypair = read_keypair_file(path)
+            .map_err(|e| format!("Could not read keypair file {}: {}", path.display(), e))?;
+        return Ok(Arc::new(keypair));
+    }
+
+    let keypair = Keypair::new();
+
+    // nothing is submitted in a dry run or when only signing, so there is no new account to keep
+    if config.dry_run || config.sign_only || config.instructions_only {
+        return Ok(Arc::new(keypair));
+    }
 
+    write_keypair_file(&keypair, path)
+        .map_err(|e| format!("Could not write keypair file {}: {}", path.display(), e))?;
     println_display(
         config,
         format!(
-            "Creating onramp stake account {} for pool {}\n",
-            onramp_address, pool_address
+            "Wrote keypair for new account {} to {}",
+            keypair.pubkey(),
+            path.display()
         ),
     );
 
-    pool_is_initialized(config, pool_address).await?;
+    Ok(Arc::new(keypair))
+}
 
-    if config
-        .get_initialized_account(onramp_address)
+async fn get_mint_decimals(config: &Config, mint_address: Pubkey) -> Result<u8, Error> {
+    let Some(mint_account) = config.get_initialized_account(mint_address).await? else {
+        return Err(format!("Mint {} does not exist", mint_address).into());
+    };
+
+    // token-2022 mints may carry extensions after the base state
+    mint_account
+        .data
+        .get(..Mint::LEN)
+        .and_then(|data| Mint::unpack(data).ok())
+        .map(|mint| mint.decimals)
+        .ok_or_else(|| format!("Account {} is not a token mint", mint_address).into())
+}
+
+// an amount as typed, in base units for the given decimals. `None` for ALL
+fn amount_to_base_units(amount: Amount, decimals: u8) -> Option<u64> {
+    match amount {
+        Amount::Decimal(ui_amount) => {
+            Some((ui_amount * 10f64.powi(i32::from(decimals))).round() as u64)
+        }
+        Amount::Raw(amount) => Some(amount),
+        Amount::All => None,
+    }
+}
+
+// whether a failed transaction was rejected by the pool program with a particular error
+fn is_single_pool_error(error: &Error, expected: SinglePoolError) -> bool {
+    let expected_code = expected as u32;
+    matches!(
+        error
+            .downcast_ref::<ClientError>()
+            .and_then(ClientError::get_transaction_error),
+        Some(TransactionError::InstructionError(_, InstructionError::Custom(code)))
+            if code == expected_code
+    )
+}
+
+// lamport value of one token, undefined for a pool with no tokens
+fn exchange_rate(net_asset_value: u64, token_supply: u64) -> Option<f64> {
+    if token_supply == 0 {
+        None
+    } else {
+        Some(net_asset_value as f64 / token_supply as f64)
+    }
+}
+
+// poll until the epoch after `current_epoch` begins, giving up after `timeout` if set
+async fn wait_for_next_epoch(
+    config: &Config,
+    current_epoch: u64,
+    timeout: Option<Duration>,
+) -> Result<u64, Error> {
+    println_display(
+        config,
+        format!("Current epoch {}, waiting for next epoch...", current_epoch),
+    );
+
+    let start = Instant::now();
+    loop {
+        let epoch_info = config.rpc_client.get_epoch_info().await?;
+        if epoch_info.epoch > current_epoch {
+            println_display(config, format!("Advanced to epoch {}", epoch_info.epoch));
+            return Ok(epoch_info.epoch);
+        }
+
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            return Err(format!(
+                "Timed out waiting for epoch {}; {} slots remain in epoch {}",
+                current_epoch.saturating_add(1),
+                epoch_info
+                    .slots_in_epoch
+                    .saturating_sub(epoch_info.slot_index),
+                current_epoch,
+            )
+            .into());
+        }
+
+        tokio::time::sleep(EPOCH_POLL_INTERVAL).await;
+    }
+}
+
+async fn pool_stake_is_active(
+    config: &Config,
+    pool_address: Pubkey,
+    current_epoch: u64,
+) -> Result<bool, Error> {
+    let pool_stake_address = find_pool_stake_address(&spl_single_pool::id(), &pool_address);
+    if let Some(StakeStateV2::Stake(_, stake, _)) = config
+        .client
+        .stake_client()
+        .get_stake_state(&pool_stake_address)
         .await?
-        .is_some()
     {
-        return Err(format!(
-            "Pool {} onramp {} already exists",
-            pool_address, onramp_address
-        )
-        .into());
+        Ok(stake.delegation.activation_epoch < current_epoch)
+    } else {
+        Err(format!("Could not find pool stake account {}", pool_stake_address).into())
+    }
+}
+
+// deactivating or deactivated, eg after `DeactivateDelinquent`
+async fn pool_stake_is_dedelegated(config: &Config, pool_address: Pubkey) -> Result<bool, Error> {
+    let pool_stake_address = find_pool_stake_address(&spl_single_pool::id(), &pool_address);
+    if let Some(StakeStateV2::Stake(_, stake, _)) = config
+        .client
+        .stake_client()
+        .get_stake_state(&pool_stake_address)
+        .await?
+    {
+        Ok(stake.delegation.deactivation_epoch != u64::MAX)
+    } else {
+        Err(format!("Could not find pool stake account {}", pool_stake_address).into())
     }
+}
 
-    let instructions = spl_single_pool::instruction::create_pool_onramp(
-        &spl_single_pool::id(),
-        &pool_address,
-        &payer.pubkey(),
-        &quarantine::get_rent(config).await?,
-    );
+// one vote account address per line, ignoring blank lines and comments
+fn parse_vote_account_file(path: &Path) -> Result<Vec<Pubkey>, Error> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read vote account file {}: {}", path.display(), e))?;
 
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &vec![payer],
-        config.rpc_client.get_latest_blockhash().await?,
-    );
+    let mut vote_account_addresses = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
 
-    let signature = process_transaction(config, transaction).await?;
+        vote_account_addresses.push(
+            Pubkey::from_str(line)
+                .map_err(|_| format!("Invalid address on vote account file line {}", i + 1))?,
+        );
+    }
 
-    Ok(format_output(
-        config,
-        "InitializePoolOnRamp".to_string(),
-        SignatureOutput { signature },
-    ))
+    Ok(vote_account_addresses)
 }
 
-// deposit liquid sol
-async fn command_deposit_sol(
-    config: &Config,
-    command_config: DepositSolCli,
-    matches: &ArgMatches,
-    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
-) -> CommandResult {
-    let payer = config.fee_payer()?;
-    let owner = config.default_signer()?;
-
-    let deposit_source = command_config
-        .from
-        .and_then(|source| {
-            signer_from_source(matches, &source, "from", wallet_manager)
-                .ok()
-                .map(Arc::from)
-        })
-        .unwrap_or(owner.clone());
-
-    let deposit_amount = command_config.lamports;
+// batch files are `STAKE_ACCOUNT,TOKEN_OWNER` per line, with blank lines and `#` comments ignored
+fn parse_batch_file(path: &Path) -> Result<Vec<(Pubkey, Pubkey)>, Error> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read batch file {}: {}", path.display(), e))?;
 
-    let pool_address = pool_address_from_args(
-        command_config.pool_address,
-        command_config.vote_account_address,
-    );
+    let mut entries = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
 
-    let pool_stake_address = find_pool_stake_address(&spl_single_pool::id(), &pool_address);
-    let onramp_address = find_pool_onramp_address(&spl_single_pool::id(), &pool_address);
-    let pool_mint_address = find_pool_mint_address(&spl_single_pool::id(), &pool_address);
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let entry = match fields[..] {
+            [stake_account, token_owner] => Pubkey::from_str(stake_account)
+                .and_then(|stake_account| Ok((stake_account, Pubkey::from_str(token_owner)?))),
+            _ => return Err(format!("Malformed batch file line {}: {}", i + 1, line).into()),
+        };
 
-    pool_is_initialized(config, pool_address).await?;
+        entries.push(entry.map_err(|_| format!("Invalid address on batch file line {}", i + 1))?);
+    }
 
-    let vote_account_address = get_vote_address_from_pool(config, pool_address).await?;
+    Ok(entries)
+}
 
-    if config
-        .get_initialized_account(onramp_address)
+// fail before submission if the fee payer cannot cover its system transfers plus the fee
+async fn check_fee_payer_balance(
+    config: &Config,
+    fee_payer: &Pubkey,
+    instructions: &[Instruction],
+    transaction: &Transaction,
+) -> Result<(), Error> {
+    let required_lamports = config
+        .rpc_client
+        .get_fee_for_message(&transaction.message)
         .await?
-        .is_none()
-    {
+        .saturating_add(fee_payer_transfers(fee_payer, instructions));
+
+    let fee_payer_balance = config.rpc_client.get_balance(fee_payer).await?;
+    if fee_payer_balance < required_lamports {
         return Err(format!(
-            "Pool {} onramp {} does not exist; run `spl-single-pool manage create-on-ramp ...` \
-            to create it",
-            pool_address, onramp_address
+            "Insufficient lamports in fee payer {}: has {}, needs {} ({} short)",
+            fee_payer,
+            fee_payer_balance,
+            required_lamports,
+            required_lamports - fee_payer_balance,
         )
         .into());
     }
 
-    let current_epoch = config.rpc_client.get_epoch_info().await?.epoch;
+    Ok(())
+}
 
-    if let Some((_, stake)) = quarantine::get_stake_info(config, pool_stake_address).await? {
-        if stake.delegation.activation_epoch >= current_epoch {
-            return Err(format!(
-                "Pool {} stake {} is still activating; must be fully active",
-                pool_address, pool_stake_address
-            )
-            .into());
+// lamports an instruction transfers to the recipient, if it is a system transfer there
+fn system_transfer_to(instruction: &Instruction, recipient: &Pubkey) -> Option<u64> {
+    match bincode::deserialize::<SystemInstruction>(&instruction.data) {
+        Ok(SystemInstruction::Transfer { lamports })
+            if instruction.program_id == system_program::id()
+                && instruction.accounts[1].pubkey == *recipient =>
+        {
+            Some(lamports)
         }
+        _ => None,
+    }
+}
 
-        if stake.delegation.deactivation_epoch < u64::MAX {
-            return Err(format!(
-                "Pool {} stake {} is deactivating or deactivated",
-                pool_address, pool_stake_address
-            )
-            .into());
-        }
-    } else {
-        // pool existence already validated and pool exists => stake exists
-        unreachable!();
-    };
+// initialize must fund the pool stake account with at least rent plus the minimum pool balance
+// the program derives from the minimum delegation. returns the lamports the program requires
+fn check_pool_stake_funding(
+    instructions: &[Instruction],
+    pool_stake_address: &Pubkey,
+    rent: &Rent,
+    minimum_delegation: u64,
+) -> Result<u64, Error> {
+    let required_lamports = rent
+        .minimum_balance(StakeStateV2::size_of())
+        .saturating_add(spl_single_pool::minimum_pool_balance(minimum_delegation));
+    let funded_lamports = instructions
+        .iter()
+        .find_map(|instruction| system_transfer_to(instruction, pool_stake_address))
+        .unwrap_or(0);
+
+    if funded_lamports < required_lamports {
+        return Err(format!(
+            "Initialize would fund pool stake account {} with {} lamports, but the program \
+            requires at least {} for a minimum delegation of {} lamports",
+            pool_stake_address, funded_lamports, required_lamports, minimum_delegation
+        )
+        .into());
+    }
 
-    {
-        let deposit_source_balance = config
-            .rpc_client
-            .get_account_with_commitment(&deposit_source.pubkey(), config.rpc_client.commitment())
-            .await?
-            .value
-            .map(|account| account.lamports)
-            .unwrap_or(0);
+    Ok(required_lamports)
+}
 
-        if deposit_source_balance < deposit_amount {
-            return Err(format!(
-                "Insufficient lamports in {} for deposit: has {}, needs {}",
-                deposit_source.pubkey(),
-                deposit_source_balance,
-                deposit_amount,
-            )
-            .into());
+// lamports the fee payer sends out of its own account, eg to fund pool rent
+fn fee_payer_transfers(fee_payer: &Pubkey, instructions: &[Instruction]) -> u64 {
+    let mut lamports_transferred = 0u64;
+    for instruction in instructions {
+        if instruction.program_id != system_program::id()
+            || instruction.accounts.first().map(|meta| &meta.pubkey) != Some(fee_payer)
+        {
+            continue;
+        }
+
+        if let Ok(SystemInstruction::Transfer { lamports }) =
+            bincode::deserialize::<SystemInstruction>(&instruction.data)
+        {
+            lamports_transferred = lamports_transferred.saturating_add(lamports);
         }
     }
 
+    lamports_transferred
+}
+
+// print what a transaction costs its fee payer. the fee for the same message without
+// compute budget instructions is the base fee, and anything above it is the priority fee
+async fn print_fee_estimate(config: &Config, transaction: &Transaction) -> Result<(), Error> {
+    let fee_payer = transaction.message.account_keys[0];
+    let instructions = decompile_instructions(transaction);
+
+    let fee = config
+        .with_rpc_retries(|| config.rpc_client.get_fee_for_message(&transaction.message))
+        .await?;
+
+    let unbudgeted_instructions = instructions
+        .iter()
+        .filter(|instruction| instruction.program_id != compute_budget::id())
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut unbudgeted_message =
+        Transaction::new_with_payer(&unbudgeted_instructions, Some(&fee_payer)).message;
+    unbudgeted_message.recent_blockhash = transaction.message.recent_blockhash;
+    let base_fee = config
+        .with_rpc_retries(|| config.rpc_client.get_fee_for_message(&unbudgeted_message))
+        .await?;
+
     println_display(
         config,
         format!(
-            "Depositing liquid sol from account {} into pool {}\n",
-            deposit_source.pubkey(),
-            pool_address
+            "Estimated fee: {} lamports ({} base, {} priority)",
+            fee,
+            base_fee,
+            fee.saturating_sub(base_fee)
         ),
     );
 
-    let mut instructions = vec![];
+    let lamports_transferred = fee_payer_transfers(&fee_payer, &instructions);
+    if lamports_transferred > 0 {
+        println_display(
+            config,
+            format!(
+                "Transferred from fee payer: {} lamports\nEstimated total cost: {} lamports",
+                lamports_transferred,
+                fee.saturating_add(lamports_transferred)
+            ),
+        );
+    }
 
-    // use token account provided, or get/create the associated account for the client keypair
-    let token_account_address = if let Some(account) = command_config.token_account_address {
-        account
-    } else {
-        let ata_address = get_associated_token_address(&owner.pubkey(), &pool_mint_address);
-        if quarantine::get_token_info(config, ata_address, pool_mint_address)
-            .await?
-            .is_none()
-        {
-            instructions.push(create_associated_token_account(
-                &payer.pubkey(),
-                &owner.pubkey(),
-                &pool_mint_address,
-                &spl_token::id(),
-            ));
-        }
-        ata_address
+    Ok(())
+}
+
+// rebuild the instructions a transaction was compiled from, for `--instructions-only`
+fn decompile_instructions(transaction: &Transaction) -> Vec<Instruction> {
+    let message = &transaction.message;
+
+    message
+        .instructions
+        .iter()
+        .map(|compiled_instruction| Instruction {
+            program_id: message.account_keys[compiled_instruction.program_id_index as usize],
+            accounts: compiled_instruction
+                .accounts
+                .iter()
+                .map(|i| {
+                    let i = *i as usize;
+                    AccountMeta {
+                        pubkey: message.account_keys[i],
+                        is_signer: message.is_signer(i),
+                        is_writable: message.is_maybe_writable(i, None),
+                    }
+                })
+                .collect(),
+            data: compiled_instruction.data.clone(),
+        })
+        .collect()
+}
+
+// simulate a deposit and return the pool tokens gained and stake moved into the pool.
+// returns `None` if the simulation fails, or the rpc node does not return account states
+async fn simulate_deposit_changes(
+    config: &Config,
+    transaction: &Transaction,
+    token_account_address: Pubkey,
+    pool_stake_address: Pubkey,
+) -> Result<Option<(u64, u64)>, Error> {
+    let addresses = [token_account_address, pool_stake_address];
+    let pre_accounts = config.rpc_client.get_multiple_accounts(&addresses).await?;
+
+    let Ok(simulation) = config
+        .rpc_client
+        .simulate_transaction_with_config(
+            transaction,
+            RpcSimulateTransactionConfig {
+                commitment: Some(config.rpc_client.commitment()),
+                accounts: Some(RpcSimulateTransactionAccountsConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    addresses: addresses
+                        .iter()
+                        .map(|address| address.to_string())
+                        .collect(),
+                }),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await
+    else {
+        return Ok(None);
     };
 
-    let previous_token_amount =
-        quarantine::get_token_info(config, token_account_address, pool_mint_address)
-            .await?
-            .map(|token_account| token_account.amount)
-            .unwrap_or(0);
+    let (None, Some(post_ui_accounts)) = (simulation.value.err, simulation.value.accounts) else {
+        return Ok(None);
+    };
+    let post_accounts = post_ui_accounts
+        .iter()
+        .map(|ui_account| ui_account.as_ref().and_then(|a| a.decode::<Account>()))
+        .collect::<Vec<_>>();
 
-    // use escrow account for lamports to avoid exposing wallet signer to program
-    let escrow_deposit_account = Keypair::new();
+    // a token account created in this transaction starts from zero
+    let token_amount = |account: Option<&Account>| {
+        account
+            .and_then(|account| account.data.get(..TokenAccount::LEN))
+            .and_then(|data| TokenAccount::unpack(data).ok())
+            .map(|token_account| token_account.amount)
+            .unwrap_or(0)
+    };
+    let stake_amount = |account: Option<&Account>| match account
+        .and_then(|account| bincode::deserialize::<StakeStateV2>(&account.data).ok())
+    {
+        Some(StakeStateV2::Stake(_, stake, _)) => stake.delegation.stake,
+        _ => 0,
+    };
 
-    instructions.extend(spl_single_pool::instruction::deposit_liquid(
-        &spl_single_pool::id(),
-        &vote_account_address,
-        &deposit_source.pubkey(),
-        &escrow_deposit_account.pubkey(),
-        &token_account_address,
-        deposit_amount,
-    ));
+    Ok(Some((
+        token_amount(post_accounts[0].as_ref())
+            .saturating_sub(token_amount(pre_accounts[0].as_ref())),
+        stake_amount(post_accounts[1].as_ref())
+            .saturating_sub(stake_amount(pre_accounts[1].as_ref())),
+    )))
+}
 
-    let mut signers = vec![];
-    for signer in [
-        payer.clone(),
-        deposit_source,
-        Arc::new(escrow_deposit_account),
-    ] {
-        if !signers.contains(&signer) {
-            signers.push(signer);
+// 75th percentile of fees recently paid to write the accounts these instructions write. an rpc
+// that does not support the method gets no priority fee rather than failing the command
+async fn estimate_compute_unit_price(config: &Config, instructions: &[Instruction]) -> u64 {
+    let mut writable_addresses = vec![];
+    for account_meta in instructions
+        .iter()
+        .flat_map(|instruction| &instruction.accounts)
+    {
+        if account_meta.is_writable && !writable_addresses.contains(&account_meta.pubkey) {
+            writable_addresses.push(account_meta.pubkey);
         }
     }
+    writable_addresses.truncate(MAX_PRIORITIZATION_FEE_ADDRESSES);
 
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &signers,
-        config.rpc_client.get_latest_blockhash().await?,
-    );
+    let Ok(recent_fees) = config
+        .rpc_client
+        .get_recent_prioritization_fees(&writable_addresses)
+        .await
+    else {
+        return 0;
+    };
 
-    let signature = process_transaction(config, transaction).await?;
+    let mut fees = recent_fees
+        .into_iter()
+        .map(|recent_fee| recent_fee.prioritization_fee)
+        .collect::<Vec<_>>();
+    fees.sort_unstable();
 
-    let token_amount = if config.dry_run {
-        None
-    } else {
-        Some(
-            quarantine::get_token_info(config, token_account_address, pool_mint_address)
-                .await?
-                .map(|token_account| token_account.amount)
-                .unwrap_or(0)
-                - previous_token_amount,
-        )
-    };
+    fees.get(fees.len().saturating_sub(1) * 3 / 4)
+        .copied()
+        .unwrap_or(0)
+}
 
-    Ok(format_output(
-        config,
-        "DepositSol".to_string(),
-        DepositOutput {
-            pool_address,
-            token_amount,
-            signature,
-        },
-    ))
+async fn process_transaction(
+    config: &Config,
+    transaction: Transaction,
+) -> Result<Option<Signature>, Error> {
+    let commitment = (!config.no_wait).then(|| config.rpc_client.commitment());
+    process_transaction_with_commitment(config, transaction, commitment).await
 }
 
-async fn get_vote_address_from_pool(
+// like `process_transaction()`, but waits for the given commitment rather than the client default.
+// with no commitment, the transaction is sent without waiting for confirmation at all
+async fn process_transaction_with_commitment(
     config: &Config,
-    pool_address: Pubkey,
-) -> Result<Pubkey, Error> {
-    let Some(pool_account) = config.get_initialized_account(pool_address).await? else {
-        return Err(format!("Pool {} has not been initialized", pool_address).into());
-    };
+    transaction: Transaction,
+    commitment: Option<CommitmentConfig>,
+) -> Result<Option<Signature>, Error> {
+    // the instructions stand in for the command output, like sign-only below
+    if config.instructions_only {
+        let instructions = decompile_instructions(&transaction);
+        let instructions_output = match config.output_format {
+            OutputFormat::JsonCompact => serde_json::to_string(&instructions)?,
+            _ => serde_json::to_string_pretty(&instructions)?,
+        };
+
+        config.push_offline_output(instructions_output);
+        return Ok(None);
+    }
 
-    if pool_account.owner != spl_single_pool::id() {
-        return Err(format!("{} is not owned by the SVSP program", pool_address).into());
+    // the dumped transaction is never sent. it carries whatever signatures we have, so with
+    // --sign-only it is a partially signed artifact for the remaining signers to complete
+    if config.dump_transaction {
+        config.push_offline_output(BASE64_STANDARD.encode(bincode::serialize(&transaction)?));
+        return Ok(None);
     }
 
-    if let Ok(pool) = try_from_slice_unchecked::<SinglePool>(&pool_account.data) {
-        Ok(pool.vote_account_address)
-    } else {
-        Err(format!(
-            "{} is owned by the SVSP program but not a valid pool account",
-            pool_address
+    // sign-only output stands in for the command output, in the same format the solana cli uses
+    if config.sign_only {
+        let signers_output = return_signers_with_config(
+            &transaction,
+            &config.output_format,
+            &ReturnSignersConfig {
+                dump_transaction_message: false,
+            },
         )
-        .into())
+        .map_err(|e| e.to_string())?;
+
+        config.push_offline_output(signers_output);
+        return Ok(None);
     }
-}
 
-async fn pool_is_initialized(config: &Config, pool_address: Pubkey) -> Result<(), Error> {
-    get_vote_address_from_pool(config, pool_address)
-        .await
-        .map(|_| ())
-}
+    if config.estimate_fee || config.verbose() {
+        print_fee_estimate(config, &transaction).await?;
+    }
 
-async fn process_transaction(
-    config: &Config,
-    transaction: Transaction,
-) -> Result<Option<Signature>, Error> {
     if config.dry_run {
-        let simulation_data = config.rpc_client.simulate_transaction(&transaction).await?;
+        let simulation_data = config
+            .with_rpc_retries(|| config.rpc_client.simulate_transaction(&transaction))
+            .await?;
 
         if simulation_data.value.err.is_none() && !config.verbose() {
             println_display(config, "Simulation succeeded".to_string());
@@ -1084,20 +3779,103 @@ async fn process_transaction(
                 }
             }
 
+            let limit_str = if let Some(compute_unit_limit) = config.compute_unit_limit {
+                format!(" (limit {})", compute_unit_limit)
+            } else {
+                String::new()
+            };
+
             println!(
-                "\nSimulation {}, consumed {} compute units",
+                "\nSimulation {}, consumed {} compute units{}",
                 status_str,
                 simulation_data.value.units_consumed.unwrap(),
+                limit_str,
             );
         }
 
         Ok(None)
-    } else {
-        Ok(Some(
+    } else if let Some(commitment) = commitment {
+        // resending the same signed transaction cannot double-spend it
+        let send_and_confirm = config.with_rpc_retries(|| {
             config
                 .rpc_client
-                .send_and_confirm_transaction_with_spinner(&transaction)
-                .await?,
-        ))
+                .send_and_confirm_transaction_with_spinner_and_commitment(&transaction, commitment)
+        });
+
+        let signature = match config.confirm_timeout {
+            Some(confirm_timeout) => tokio::time::timeout(confirm_timeout, send_and_confirm)
+                .await
+                .map_err(|_| {
+                    format!(
+                        "Transaction {} was not confirmed within {} seconds. It may still land; \
+                         check its status before retrying",
+                        transaction.signatures[0],
+                        confirm_timeout.as_secs(),
+                    )
+                })??,
+            None => send_and_confirm.await?,
+        };
+
+        Ok(Some(signature))
+    } else {
+        let signature = config
+            .with_rpc_retries(|| config.rpc_client.send_transaction(&transaction))
+            .await?;
+
+        Ok(Some(signature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_native_token::LAMPORTS_PER_SOL};
+
+    #[test]
+    fn test_check_pool_stake_funding() {
+        let rent = Rent::default();
+        let vote_account_address = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let pool_address = find_pool_address(&spl_single_pool::id(), &vote_account_address);
+        let pool_stake_address = find_pool_stake_address(&spl_single_pool::id(), &pool_address);
+        let stake_rent = rent.minimum_balance(StakeStateV2::size_of());
+
+        let initialize = |minimum_pool_balance| {
+            spl_single_pool::instruction::initialize(
+                &spl_single_pool::id(),
+                &vote_account_address,
+                &payer,
+                &rent,
+                minimum_pool_balance,
+            )
+        };
+
+        // minimum delegation has risen above 1 sol, so the pool balance must track it
+        let minimum_delegation = LAMPORTS_PER_SOL * 5;
+        let required_pool_balance = spl_single_pool::minimum_pool_balance(minimum_delegation);
+        assert_eq!(required_pool_balance, minimum_delegation);
+        assert_eq!(
+            check_pool_stake_funding(
+                &initialize(required_pool_balance),
+                &pool_stake_address,
+                &rent,
+                minimum_delegation,
+            )
+            .unwrap(),
+            stake_rent + minimum_delegation,
+        );
+
+        // funding for the old 1 sol floor falls short
+        assert!(check_pool_stake_funding(
+            &initialize(LAMPORTS_PER_SOL),
+            &pool_stake_address,
+            &rent,
+            minimum_delegation,
+        )
+        .is_err());
+
+        // so does funding nothing at all
+        assert!(
+            check_pool_stake_funding(&[], &pool_stake_address, &rent, minimum_delegation).is_err()
+        );
     }
 }
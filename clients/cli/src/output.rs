@@ -1,5 +1,5 @@
 use {
-    crate::config::Config,
+    crate::{cli::QuoteDirection, config::Config},
     console::style,
     serde::{Deserialize, Serialize},
     serde_with::{serde_as, DisplayFromStr},
@@ -9,11 +9,8 @@ use {
     },
     solana_pubkey::Pubkey,
     solana_signature::Signature,
-    spl_single_pool::{
-        self, find_pool_mint_address, find_pool_mint_authority_address,
-        find_pool_mpl_authority_address, find_pool_onramp_address, find_pool_stake_address,
-        find_pool_stake_authority_address,
-    },
+    spl_single_pool::{self, PoolAddresses},
+    spl_single_pool_client::{ReplenishPlan, StakeStatus},
     std::fmt::{Display, Formatter, Result, Write},
 };
 
@@ -55,6 +52,74 @@ where
     }
 }
 
+pub trait CsvDisplay {
+    fn write_csv(&self, w: &mut dyn Write) -> Result;
+}
+
+pub fn format_csv_output<T: CsvDisplay>(command_output: &T) -> String {
+    let mut output = String::new();
+    command_output.write_csv(&mut output).unwrap();
+
+    // callers println the result, so drop our own trailing newline
+    output.truncate(output.trim_end().len());
+    output
+}
+
+type PrometheusMetric = (&'static str, &'static str, fn(&StakePoolOutput) -> u64);
+
+// prometheus text exposition format, eg for a node_exporter textfile collector. each metric
+// has one sample per pool, labeled by pool and vote account, so cardinality is the pool count
+const PROMETHEUS_METRICS: &[PrometheusMetric] = &[
+    (
+        "single_pool_net_asset_value",
+        "Lamports of stake and excess lamports held by the pool",
+        |svsp| svsp.net_asset_value,
+    ),
+    (
+        "single_pool_available_stake",
+        "Lamports of stake available to withdraw from the pool",
+        |svsp| svsp.available_stake,
+    ),
+    (
+        "single_pool_undelegated_lamports",
+        "Lamports held by the pool that are not earning rewards",
+        |svsp| svsp.undelegated_lamports,
+    ),
+    (
+        "single_pool_onramp_lamports",
+        "Lamports held by the pool on-ramp, delegated or not",
+        |svsp| svsp.onramp_lamports,
+    ),
+    (
+        "single_pool_token_supply",
+        "Pool tokens outstanding",
+        |svsp| svsp.token_supply,
+    ),
+];
+
+pub fn format_prometheus_output(pools: &[StakePoolOutput]) -> String {
+    let mut output = String::new();
+    for (name, help, value) in PROMETHEUS_METRICS {
+        writeln!(output, "# HELP {} {}", name, help).unwrap();
+        writeln!(output, "# TYPE {} gauge", name).unwrap();
+        for svsp in pools {
+            writeln!(
+                output,
+                "{}{{pool=\"{}\",vote=\"{}\"}} {}",
+                name,
+                svsp.pool_address,
+                svsp.vote_account_address,
+                value(svsp)
+            )
+            .unwrap();
+        }
+    }
+
+    // callers println the result, so drop our own trailing newline
+    output.truncate(output.trim_end().len());
+    output
+}
+
 pub fn format_output<T>(config: &Config, command_name: String, command_output: T) -> String
 where
     T: Serialize + Display + QuietDisplay + VerboseDisplay,
@@ -88,6 +153,387 @@ impl Display for SignatureOutput {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InitializeStatus {
+    Created,
+    Skipped,
+    Failed,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeResult {
+    #[serde_as(as = "DisplayFromStr")]
+    pub vote_account_address: Pubkey,
+    #[serde_as(as = "DisplayFromStr")]
+    pub pool_address: Pubkey,
+    pub status: InitializeStatus,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub signature: Option<Signature>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeFromFileOutput {
+    pub results: Vec<InitializeResult>,
+}
+
+impl QuietDisplay for InitializeFromFileOutput {}
+impl VerboseDisplay for InitializeFromFileOutput {}
+
+impl Display for InitializeFromFileOutput {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        writeln!(f)?;
+
+        for result in &self.results {
+            let status = match (result.status, &result.error, result.signature) {
+                (InitializeStatus::Skipped, _, _) => "Skipped: pool already exists".to_string(),
+                (_, Some(error), _) => format!("Failed: {}", error),
+                (_, None, Some(signature)) => {
+                    format!("Created pool {}: {}", result.pool_address, signature)
+                }
+                (_, None, None) => format!("Created pool {}", result.pool_address),
+            };
+            writeln_name_value(f, &format!("  {}:", result.vote_account_address), &status)?;
+        }
+
+        let count = |status| {
+            self.results
+                .iter()
+                .filter(|result| result.status == status)
+                .count()
+        };
+        writeln!(f)?;
+        writeln_name_value(f, "Created:", &count(InitializeStatus::Created).to_string())?;
+        writeln_name_value(f, "Skipped:", &count(InitializeStatus::Skipped).to_string())?;
+        writeln_name_value(f, "Failed:", &count(InitializeStatus::Failed).to_string())?;
+
+        Ok(())
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplenishResult {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pool_address: Pubkey,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub signature: Option<Signature>,
+    /// What replenishing does for the pool. Pools with nothing to do are not sent
+    pub actions: Vec<ReplenishAction>,
+    pub error: Option<String>,
+}
+
+/// One step of `ReplenishPool`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReplenishAction {
+    Reactivate,
+    MoveStake,
+    MoveLamports,
+    DelegateOnRamp,
+}
+
+impl ReplenishAction {
+    pub fn from_plan(plan: ReplenishPlan) -> Vec<Self> {
+        [
+            (plan.reactivate, Self::Reactivate),
+            (plan.move_stake, Self::MoveStake),
+            (plan.move_lamports, Self::MoveLamports),
+            (plan.delegate_onramp, Self::DelegateOnRamp),
+        ]
+        .into_iter()
+        .filter_map(|(planned, action)| planned.then_some(action))
+        .collect()
+    }
+}
+
+impl Display for ReplenishAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let action = match self {
+            Self::Reactivate => "reactivate",
+            Self::MoveStake => "move stake",
+            Self::MoveLamports => "move lamports",
+            Self::DelegateOnRamp => "delegate on-ramp",
+        };
+
+        write!(f, "{}", action)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplenishAllOutput {
+    /// Whether this only reports planned actions, without sending
+    pub simulated: bool,
+    pub results: Vec<ReplenishResult>,
+}
+
+impl QuietDisplay for ReplenishAllOutput {}
+impl VerboseDisplay for ReplenishAllOutput {}
+
+impl Display for ReplenishAllOutput {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        writeln!(f)?;
+
+        for result in &self.results {
+            let actions = result
+                .actions
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let status = match (&result.error, result.signature) {
+                (Some(error), _) => format!("Failed: {}", error),
+                (None, _) if result.actions.is_empty() => "Nothing to do".to_string(),
+                (None, _) if self.simulated => format!("Would {}", actions),
+                (None, Some(signature)) => format!("{} ({})", signature, actions),
+                (None, None) => format!("Succeeded ({})", actions),
+            };
+            writeln_name_value(f, &format!("  {}:", result.pool_address), &status)?;
+        }
+
+        let with_work = self
+            .results
+            .iter()
+            .filter(|result| result.error.is_none() && !result.actions.is_empty())
+            .count();
+        writeln!(f)?;
+        writeln_name_value(
+            f,
+            if self.simulated {
+                "Would replenish:"
+            } else {
+                "Replenished:"
+            },
+            &format!("{} of {} pools", with_work, self.results.len()),
+        )?;
+
+        Ok(())
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LookupOutput {
+    #[serde_as(as = "DisplayFromStr")]
+    pub mint_address: Pubkey,
+    #[serde_as(as = "DisplayFromStr")]
+    pub pool_address: Pubkey,
+    #[serde_as(as = "DisplayFromStr")]
+    pub vote_account_address: Pubkey,
+    #[serde_as(as = "DisplayFromStr")]
+    pub validator_identity: Pubkey,
+}
+
+impl QuietDisplay for LookupOutput {}
+impl VerboseDisplay for LookupOutput {}
+
+impl Display for LookupOutput {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Pool mint address:", &self.mint_address.to_string())?;
+        writeln_name_value(f, "Pool address:", &self.pool_address.to_string())?;
+        writeln_name_value(
+            f,
+            "Vote account address:",
+            &self.vote_account_address.to_string(),
+        )?;
+        writeln_name_value(
+            f,
+            "Validator identity:",
+            &self.validator_identity.to_string(),
+        )?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimateInitCostOutput {
+    pub pool_rent: u64,
+    /// Rent plus the minimum pool balance
+    pub pool_stake_funding: u64,
+    pub minimum_pool_balance: u64,
+    pub onramp_rent: u64,
+    pub mint_rent: u64,
+    pub total: u64,
+}
+
+impl QuietDisplay for EstimateInitCostOutput {}
+impl VerboseDisplay for EstimateInitCostOutput {}
+
+impl Display for EstimateInitCostOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f)?;
+        writeln_name_value(
+            f,
+            "Pool account rent:",
+            &format!("{} lamports", self.pool_rent),
+        )?;
+        writeln_name_value(
+            f,
+            "Pool stake account:",
+            &format!(
+                "{} lamports, including a minimum pool balance of {}",
+                self.pool_stake_funding, self.minimum_pool_balance
+            ),
+        )?;
+        writeln_name_value(
+            f,
+            "On-ramp account rent:",
+            &format!("{} lamports", self.onramp_rent),
+        )?;
+        writeln_name_value(f, "Mint rent:", &format!("{} lamports", self.mint_rent))?;
+        writeln_name_value(f, "Total:", &build_balance_message(self.total, false, true))?;
+
+        Ok(())
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionOutput {
+    pub cli_version: String,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub program_id: Option<Pubkey>,
+    pub onchain_version: Option<String>,
+}
+
+impl QuietDisplay for VersionOutput {}
+impl VerboseDisplay for VersionOutput {}
+
+impl Display for VersionOutput {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        writeln!(f)?;
+        writeln_name_value(f, "CLI version:", &self.cli_version)?;
+        if let Some(program_id) = self.program_id {
+            writeln_name_value(f, "Program id:", &program_id.to_string())?;
+        }
+        if let Some(onchain_version) = &self.onchain_version {
+            writeln_name_value(f, "Onchain program version:", onchain_version)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedInstructionOutput {
+    pub instruction: String,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub user_stake_authority: Option<Pubkey>,
+    pub token_amount: Option<u64>,
+    pub lamports: Option<u64>,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub uri: Option<String>,
+}
+
+impl QuietDisplay for DecodedInstructionOutput {}
+impl VerboseDisplay for DecodedInstructionOutput {}
+
+impl Display for DecodedInstructionOutput {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Instruction:", &self.instruction)?;
+
+        // only show the fields this instruction has
+        if let Some(user_stake_authority) = self.user_stake_authority {
+            writeln_name_value(
+                f,
+                "User stake authority:",
+                &user_stake_authority.to_string(),
+            )?;
+        }
+        if let Some(token_amount) = self.token_amount {
+            writeln_name_value(f, "Token amount:", &token_amount.to_string())?;
+        }
+        if let Some(lamports) = self.lamports {
+            writeln_name_value(f, "Lamports:", &lamports.to_string())?;
+        }
+        if let Some(name) = &self.name {
+            writeln_name_value(f, "Name:", name)?;
+        }
+        if let Some(symbol) = &self.symbol {
+            writeln_name_value(f, "Symbol:", symbol)?;
+        }
+        if let Some(uri) = &self.uri {
+            writeln_name_value(f, "URI:", uri)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataAuthorityOutput {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pool_address: Pubkey,
+    #[serde_as(as = "DisplayFromStr")]
+    pub vote_account_address: Pubkey,
+    #[serde_as(as = "DisplayFromStr")]
+    pub authorized_withdrawer: Pubkey,
+    /// Current on-chain token metadata, or `None` if it has not been created
+    pub token_metadata: Option<TokenMetadata>,
+}
+
+impl QuietDisplay for MetadataAuthorityOutput {}
+impl VerboseDisplay for MetadataAuthorityOutput {}
+
+impl Display for MetadataAuthorityOutput {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Pool address:", &self.pool_address.to_string())?;
+        writeln_name_value(
+            f,
+            "Vote account address:",
+            &self.vote_account_address.to_string(),
+        )?;
+        writeln_name_value(
+            f,
+            "Metadata authority (vote authorized withdrawer):",
+            &self.authorized_withdrawer.to_string(),
+        )?;
+
+        match &self.token_metadata {
+            Some(token_metadata) => write!(f, "{}", token_metadata)?,
+            None => writeln_name_value(f, "Token metadata:", "none")?,
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+impl Display for TokenMetadata {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        writeln_name_value(f, "Token name:", &self.name)?;
+        writeln_name_value(f, "Token symbol:", &self.symbol)?;
+        writeln_name_value(f, "Token URI:", &self.uri)?;
+
+        Ok(())
+    }
+}
+
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -97,10 +543,24 @@ pub struct StakePoolOutput {
     #[serde_as(as = "DisplayFromStr")]
     pub vote_account_address: Pubkey,
     pub net_asset_value: u64,
+    pub available_stake: u64,
     pub undelegated_lamports: u64,
     pub token_supply: u64,
+    /// Lamports of net asset value per token, or `None` if there are no tokens
+    pub exchange_rate: Option<f64>,
     pub main_stake_dedelegated: bool,
     pub onramp_exists: bool,
+    /// Non-rent lamports in the on-ramp, delegated or not
+    pub onramp_lamports: u64,
+    /// Whether the on-ramp holds stake delegated this epoch, which replenish
+    /// moves into the main account once active
+    pub onramp_activating: bool,
+    pub status: PoolStatus,
+    /// Estimated annual yield as a fraction, if requested and enough vote
+    /// credit history exists
+    pub estimated_apy: Option<f64>,
+    #[serde(skip)]
+    pub show_apy: bool,
     #[serde(skip)]
     pub minimum_delegation: u64,
 
@@ -119,52 +579,55 @@ impl VerboseDisplay for StakePoolOutput {
             "  Vote account address:",
             &self.vote_account_address.to_string(),
         )?;
+        writeln_name_value(w, "  Status:", &self.status.to_string())?;
+
+        let pool_addresses =
+            PoolAddresses::derive_from_pool(&spl_single_pool::id(), &self.pool_address);
 
         writeln_name_value(
             w,
             "  Pool main stake account address:",
-            &find_pool_stake_address(&spl_single_pool::id(), &self.pool_address).to_string(),
+            &pool_addresses.stake.to_string(),
         )?;
         writeln_name_value(
             w,
             "  Pool onramp stake account address:",
-            &find_pool_onramp_address(&spl_single_pool::id(), &self.pool_address).to_string(),
-        )?;
-        writeln_name_value(
-            w,
-            "  Pool mint address:",
-            &find_pool_mint_address(&spl_single_pool::id(), &self.pool_address).to_string(),
+            &pool_addresses.onramp.to_string(),
         )?;
+        writeln_name_value(w, "  Pool mint address:", &pool_addresses.mint.to_string())?;
         writeln_name_value(
             w,
             "  Pool stake authority address:",
-            &find_pool_stake_authority_address(&spl_single_pool::id(), &self.pool_address)
-                .to_string(),
+            &pool_addresses.stake_authority.to_string(),
         )?;
         writeln_name_value(
             w,
             "  Pool mint authority address:",
-            &find_pool_mint_authority_address(&spl_single_pool::id(), &self.pool_address)
-                .to_string(),
+            &pool_addresses.mint_authority.to_string(),
         )?;
         writeln_name_value(
             w,
             "  Pool MPL authority address:",
-            &find_pool_mpl_authority_address(&spl_single_pool::id(), &self.pool_address)
-                .to_string(),
+            &pool_addresses.mpl_authority.to_string(),
         )?;
 
         writeln_name_value(w, "  Net asset value:", &self.net_asset_value.to_string())?;
+        writeln_name_value(w, "  Available stake:", &self.available_stake.to_string())?;
         writeln_name_value(
             w,
             "  Undelegated lamports:",
             &self.undelegated_lamports.to_string(),
         )?;
+        writeln_name_value(w, "  On-ramp lamports:", &self.onramp_lamports_display())?;
         writeln_name_value(
             w,
             "  Notional token supply:",
             &self.token_supply.to_string(),
         )?;
+        writeln_name_value(w, "  Exchange rate:", &self.exchange_rate_display())?;
+        if self.show_apy {
+            writeln_name_value(w, "  Estimated APY:", &self.estimated_apy_display())?;
+        }
 
         self.print_shared_warnings(w)?;
 
@@ -187,12 +650,18 @@ impl Display for StakePoolOutput {
             "  Vote account address:",
             &self.vote_account_address.to_string(),
         )?;
+        writeln_name_value(f, "  Status:", &self.status.to_string())?;
         writeln_name_value(f, "  Net asset value:", &self.net_asset_value.to_string())?;
+        writeln_name_value(f, "  On-ramp lamports:", &self.onramp_lamports_display())?;
         writeln_name_value(
             f,
             "  Notional token supply:",
             &self.token_supply.to_string(),
         )?;
+        writeln_name_value(f, "  Exchange rate:", &self.exchange_rate_display())?;
+        if self.show_apy {
+            writeln_name_value(f, "  Estimated APY:", &self.estimated_apy_display())?;
+        }
 
         self.print_shared_warnings(f)?;
 
@@ -205,7 +674,66 @@ impl Display for StakePoolOutput {
     }
 }
 
+/// Pool health, from the activation state of the main stake account
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PoolStatus {
+    Activating,
+    Active,
+    Deactivating,
+    /// Main stake is fully deactivated, which only happens to a delinquent validator
+    Delinquent,
+}
+
+impl From<StakeStatus> for PoolStatus {
+    fn from(stake_status: StakeStatus) -> Self {
+        match stake_status {
+            StakeStatus::Activating => Self::Activating,
+            StakeStatus::Active => Self::Active,
+            StakeStatus::Deactivating => Self::Deactivating,
+            StakeStatus::Inactive => Self::Delinquent,
+        }
+    }
+}
+
+impl Display for PoolStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let status = match self {
+            Self::Activating => "activating",
+            Self::Active => "active",
+            Self::Deactivating => "deactivating",
+            Self::Delinquent => "delinquent",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
 impl StakePoolOutput {
+    fn onramp_lamports_display(&self) -> String {
+        if !self.onramp_exists {
+            "n/a (no on-ramp)".to_string()
+        } else if self.onramp_activating {
+            format!("{} (activating)", self.onramp_lamports)
+        } else {
+            self.onramp_lamports.to_string()
+        }
+    }
+
+    fn exchange_rate_display(&self) -> String {
+        match self.exchange_rate {
+            Some(exchange_rate) => format!("{:.9} lamports per token", exchange_rate),
+            None => "n/a (no tokens outstanding)".to_string(),
+        }
+    }
+
+    fn estimated_apy_display(&self) -> String {
+        match self.estimated_apy {
+            Some(estimated_apy) => format!("{:.2}% (estimate)", estimated_apy * 100.0),
+            None => "n/a (no recent vote credits)".to_string(),
+        }
+    }
+
     fn print_shared_warnings(&self, w: &mut dyn Write) -> Result {
         // these are not mutually exclusive, we just use `else if` for ux reasons.
         // namely, dont tell the user to create an onramp if the pool is unusable,
@@ -238,9 +766,44 @@ impl StakePoolOutput {
     }
 }
 
+const STAKE_POOL_CSV_HEADER: &str =
+    "pool_address,vote_account_address,available_stake,excess_lamports,token_supply";
+
+impl StakePoolOutput {
+    fn write_csv_row(&self, w: &mut dyn Write) -> Result {
+        writeln!(
+            w,
+            "{},{},{},{},{}",
+            self.pool_address,
+            self.vote_account_address,
+            self.available_stake,
+            self.undelegated_lamports,
+            self.token_supply,
+        )
+    }
+}
+
+impl CsvDisplay for StakePoolOutput {
+    fn write_csv(&self, w: &mut dyn Write) -> Result {
+        writeln!(w, "{}", STAKE_POOL_CSV_HEADER)?;
+        self.write_csv_row(w)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StakePoolListOutput(pub Vec<StakePoolOutput>);
 
+impl CsvDisplay for StakePoolListOutput {
+    fn write_csv(&self, w: &mut dyn Write) -> Result {
+        writeln!(w, "{}", STAKE_POOL_CSV_HEADER)?;
+        for svsp in &self.0 {
+            svsp.write_csv_row(w)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl QuietDisplay for StakePoolListOutput {}
 impl VerboseDisplay for StakePoolListOutput {
     fn write_str(&self, w: &mut dyn Write) -> Result {
@@ -284,15 +847,70 @@ impl Display for StakePoolListOutput {
 pub struct DepositOutput {
     #[serde_as(as = "DisplayFromStr")]
     pub pool_address: Pubkey,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub stake_account_addresses: Vec<Pubkey>,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub skipped_stake_accounts: Vec<Pubkey>,
     pub token_amount: Option<u64>,
-    #[serde_as(as = "Option<DisplayFromStr>")]
-    pub signature: Option<Signature>,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub signatures: Vec<Signature>,
 }
 
 impl QuietDisplay for DepositOutput {}
 impl VerboseDisplay for DepositOutput {}
 
 impl Display for DepositOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Pool address:", &self.pool_address.to_string())?;
+        writeln_name_value(
+            f,
+            "Stake accounts deposited:",
+            &self.stake_account_addresses.len().to_string(),
+        )?;
+
+        if !self.skipped_stake_accounts.is_empty() {
+            writeln_name_value(
+                f,
+                "Stake accounts skipped:",
+                &self.skipped_stake_accounts.len().to_string(),
+            )?;
+        }
+
+        let token_amount = if let Some(amount) = self.token_amount {
+            &amount.to_string()
+        } else {
+            "(cannot display in simulation)"
+        };
+        writeln_name_value(f, "Token amount:", token_amount)?;
+
+        if !self.signatures.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{}", style("Signatures:").bold())?;
+            for signature in &self.signatures {
+                writeln!(f, "  {}", signature)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositSolOutput {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pool_address: Pubkey,
+    pub token_amount: Option<u64>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub signature: Option<Signature>,
+}
+
+impl QuietDisplay for DepositSolOutput {}
+impl VerboseDisplay for DepositSolOutput {}
+
+impl Display for DepositSolOutput {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         writeln!(f)?;
         writeln_name_value(f, "Pool address:", &self.pool_address.to_string())?;
@@ -313,6 +931,254 @@ impl Display for DepositOutput {
     }
 }
 
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositBatchOutput {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pool_address: Pubkey,
+    pub deposit_count: usize,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub signatures: Vec<Signature>,
+}
+
+impl QuietDisplay for DepositBatchOutput {}
+impl VerboseDisplay for DepositBatchOutput {}
+
+impl Display for DepositBatchOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Pool address:", &self.pool_address.to_string())?;
+        writeln_name_value(f, "Deposits:", &self.deposit_count.to_string())?;
+
+        if !self.signatures.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{}", style("Signatures:").bold())?;
+            for signature in &self.signatures {
+                writeln!(f, "  {}", signature)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteOutput {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pool_address: Pubkey,
+    pub direction: QuoteDirection,
+    pub input_amount: u64,
+    pub output_amount: u64,
+    pub effective_rate: f64,
+}
+
+impl QuietDisplay for QuoteOutput {}
+impl VerboseDisplay for QuoteOutput {}
+
+impl Display for QuoteOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let (input_label, output_label) = match self.direction {
+            QuoteDirection::StakeToToken => ("Stake deposited:", "Tokens received:"),
+            QuoteDirection::TokenToStake => ("Tokens burned:", "Stake received:"),
+        };
+
+        writeln!(f)?;
+        writeln_name_value(f, "Pool address:", &self.pool_address.to_string())?;
+        writeln_name_value(f, input_label, &self.input_amount.to_string())?;
+        writeln_name_value(f, output_label, &self.output_amount.to_string())?;
+        writeln_name_value(f, "Effective rate:", &self.effective_rate.to_string())?;
+
+        Ok(())
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionOutput {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pool_address: Pubkey,
+    #[serde_as(as = "DisplayFromStr")]
+    pub owner_address: Pubkey,
+    #[serde_as(as = "DisplayFromStr")]
+    pub token_account_address: Pubkey,
+    pub token_balance: u64,
+    /// Lamports of stake the token balance would withdraw at the current exchange rate
+    pub stake_value: u64,
+}
+
+impl QuietDisplay for PositionOutput {}
+impl VerboseDisplay for PositionOutput {}
+
+impl Display for PositionOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Pool address:", &self.pool_address.to_string())?;
+        writeln_name_value(f, "Owner:", &self.owner_address.to_string())?;
+        writeln_name_value(f, "Token account:", &self.token_account_address.to_string())?;
+        writeln_name_value(f, "Token balance:", &self.token_balance.to_string())?;
+        writeln_name_value(
+            f,
+            "Stake value:",
+            &build_balance_message(self.stake_value, false, true),
+        )?;
+
+        Ok(())
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListStakeAccountsOutput {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pool_address: Pubkey,
+    #[serde_as(as = "DisplayFromStr")]
+    pub vote_account_address: Pubkey,
+    #[serde_as(as = "DisplayFromStr")]
+    pub owner_address: Pubkey,
+    pub stake_accounts: Vec<ListedStakeAccount>,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListedStakeAccount {
+    #[serde_as(as = "DisplayFromStr")]
+    pub stake_account_address: Pubkey,
+    pub status: StakeAccountStatus,
+    /// Delegated lamports
+    pub stake: u64,
+    /// Whether `deposit` would accept the account: not deactivating, and not locked up
+    pub depositable: bool,
+}
+
+/// Activation state of a user stake account
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StakeAccountStatus {
+    Activating,
+    Active,
+    Deactivating,
+    Inactive,
+}
+
+impl From<StakeStatus> for StakeAccountStatus {
+    fn from(stake_status: StakeStatus) -> Self {
+        match stake_status {
+            StakeStatus::Activating => Self::Activating,
+            StakeStatus::Active => Self::Active,
+            StakeStatus::Deactivating => Self::Deactivating,
+            StakeStatus::Inactive => Self::Inactive,
+        }
+    }
+}
+
+impl Display for StakeAccountStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let status = match self {
+            Self::Activating => "activating",
+            Self::Active => "active",
+            Self::Deactivating => "deactivating",
+            Self::Inactive => "inactive",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+impl QuietDisplay for ListStakeAccountsOutput {}
+impl VerboseDisplay for ListStakeAccountsOutput {}
+
+impl Display for ListStakeAccountsOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Pool address:", &self.pool_address.to_string())?;
+        writeln_name_value(
+            f,
+            "Vote account address:",
+            &self.vote_account_address.to_string(),
+        )?;
+        writeln_name_value(f, "Owner:", &self.owner_address.to_string())?;
+
+        if self.stake_accounts.is_empty() {
+            writeln!(f, "No delegated stake accounts")?;
+        }
+
+        for stake_account in &self.stake_accounts {
+            writeln!(f)?;
+            writeln_name_value(
+                f,
+                "Stake account:",
+                &stake_account.stake_account_address.to_string(),
+            )?;
+            writeln_name_value(f, "  Status:", &stake_account.status.to_string())?;
+            writeln_name_value(
+                f,
+                "  Stake:",
+                &build_balance_message(stake_account.stake, false, true),
+            )?;
+            writeln_name_value(
+                f,
+                "  Depositable:",
+                if stake_account.depositable {
+                    "yes"
+                } else {
+                    "no"
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyOutput {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pool_address: Pubkey,
+    /// Whether every check passed
+    pub passed: bool,
+    pub checks: Vec<VerifyCheck>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyCheck {
+    pub name: String,
+    pub passed: bool,
+    /// What was found, or why the check failed
+    pub detail: String,
+}
+
+impl QuietDisplay for VerifyOutput {}
+impl VerboseDisplay for VerifyOutput {}
+
+impl Display for VerifyOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f)?;
+        writeln_name_value(f, "Pool address:", &self.pool_address.to_string())?;
+        for check in &self.checks {
+            let status = if check.passed { "PASS" } else { "FAIL" };
+            writeln!(f, "  [{}] {}: {}", status, check.name, check.detail)?;
+        }
+
+        let failures = self.checks.iter().filter(|check| !check.passed).count();
+        if failures == 0 {
+            writeln!(f, "\nAll checks passed")?;
+        } else {
+            writeln!(f, "\n{} of {} checks failed", failures, self.checks.len())?;
+        }
+
+        Ok(())
+    }
+}
+
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -321,8 +1187,12 @@ pub struct WithdrawOutput {
     pub pool_address: Pubkey,
     #[serde_as(as = "DisplayFromStr")]
     pub stake_account_address: Pubkey,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub merged_into_address: Option<Pubkey>,
     pub stake_amount: Option<u64>,
     #[serde_as(as = "Option<DisplayFromStr>")]
+    pub closed_token_account_address: Option<Pubkey>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
     pub signature: Option<Signature>,
 }
 
@@ -338,6 +1208,9 @@ impl Display for WithdrawOutput {
             "Stake account address:",
             &self.stake_account_address.to_string(),
         )?;
+        if let Some(merged_into_address) = self.merged_into_address {
+            writeln_name_value(f, "Merged into:", &merged_into_address.to_string())?;
+        }
 
         let stake_amount = if let Some(amount) = self.stake_amount {
             &amount.to_string()
@@ -346,6 +1219,14 @@ impl Display for WithdrawOutput {
         };
         writeln_name_value(f, "Stake amount:", stake_amount)?;
 
+        if let Some(closed_token_account_address) = self.closed_token_account_address {
+            writeln_name_value(
+                f,
+                "Closed token account:",
+                &closed_token_account_address.to_string(),
+            )?;
+        }
+
         if let Some(signature) = self.signature {
             writeln!(f)?;
             writeln_name_value(f, "Signature:", &signature.to_string())?;
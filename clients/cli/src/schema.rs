@@ -0,0 +1,96 @@
+use {
+    borsh::schema::{BorshSchemaContainer, Definition, Fields},
+    serde_json::{json, Map, Value},
+    spl_single_pool::{
+        instruction::SinglePoolInstruction,
+        state::{SinglePool, SinglePoolAccountType},
+    },
+};
+
+// borsh schema for the pool account and every instruction, as json for downstream codegen.
+// `types` lists the exported declarations; `definitions` describes every declaration
+// reachable from them, including borsh builtins like `u64` and `String`
+pub fn export_schema() -> Value {
+    let mut types = vec![];
+    let mut definitions = Map::new();
+
+    for container in [
+        BorshSchemaContainer::for_type::<SinglePool>(),
+        BorshSchemaContainer::for_type::<SinglePoolAccountType>(),
+        BorshSchemaContainer::for_type::<SinglePoolInstruction>(),
+    ] {
+        types.push(container.declaration().clone());
+
+        for (declaration, definition) in container.definitions() {
+            definitions.insert(declaration.clone(), definition_to_json(definition));
+        }
+    }
+
+    json!({
+        "types": types,
+        "definitions": definitions,
+    })
+}
+
+fn definition_to_json(definition: &Definition) -> Value {
+    match definition {
+        Definition::Primitive(size) => json!({
+            "kind": "primitive",
+            "size": size,
+        }),
+        Definition::Sequence {
+            length_width,
+            length_range,
+            elements,
+        } => json!({
+            "kind": "sequence",
+            "lengthWidth": length_width,
+            "lengthRange": [length_range.start(), length_range.end()],
+            "elements": elements,
+        }),
+        Definition::Tuple { elements } => json!({
+            "kind": "tuple",
+            "elements": elements,
+        }),
+        Definition::Enum {
+            tag_width,
+            variants,
+        } => json!({
+            "kind": "enum",
+            "tagWidth": tag_width,
+            "variants": variants
+                .iter()
+                .map(|(discriminant, name, declaration)| {
+                    json!({
+                        "discriminant": discriminant,
+                        "name": name,
+                        "declaration": declaration,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        }),
+        Definition::Struct { fields } => json!({
+            "kind": "struct",
+            "fields": fields_to_json(fields),
+        }),
+    }
+}
+
+fn fields_to_json(fields: &Fields) -> Value {
+    match fields {
+        Fields::NamedFields(fields) => fields
+            .iter()
+            .map(|(name, declaration)| {
+                json!({
+                    "name": name,
+                    "declaration": declaration,
+                })
+            })
+            .collect(),
+        Fields::UnnamedFields(fields) => fields
+            .iter()
+            .map(|declaration| json!({ "declaration": declaration }))
+            .collect(),
+        Fields::Empty => Value::Array(vec![]),
+    }
+}
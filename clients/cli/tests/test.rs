@@ -2,20 +2,24 @@
 
 use {
     agave_feature_set::stake_raise_minimum_delegation_to_1_sol,
+    base64::{prelude::BASE64_STANDARD, Engine},
+    futures::TryStreamExt,
     serial_test::serial,
     solana_cli_config::Config as SolanaConfig,
     solana_client::nonblocking::rpc_client::RpcClient,
     solana_clock::Epoch,
     solana_commitment_config::CommitmentConfig,
     solana_epoch_schedule::{EpochSchedule, MINIMUM_SLOTS_PER_EPOCH},
-    solana_keypair::{write_keypair_file, Keypair},
+    solana_instruction::Instruction,
+    solana_keypair::{read_keypair_file, write_keypair_file, Keypair},
     solana_native_token::LAMPORTS_PER_SOL,
     solana_pubkey::Pubkey,
     solana_rent::Rent,
-    solana_sdk_ids::bpf_loader_upgradeable,
+    solana_sdk_ids::{bpf_loader_upgradeable, compute_budget},
+    solana_signature::Signature,
     solana_signer::Signer,
     solana_stake_interface::instruction as stake_instruction,
-    solana_stake_interface::state::{Authorized, Lockup, StakeStateV2},
+    solana_stake_interface::state::{Authorized, Lockup, StakeAuthorize, StakeStateV2},
     solana_system_interface::{instruction as system_instruction, program as system_program},
     solana_test_validator::{TestValidator, TestValidatorGenesis, UpgradeableProgramInfo},
     solana_transaction::Transaction,
@@ -23,10 +27,15 @@ use {
         instruction::{self as vote_instruction, CreateVoteAccountConfig},
         state::{VoteInit, VoteStateV4},
     },
+    spl_associated_token_account_interface::address::get_associated_token_address,
     spl_single_pool::{
-        id,
+        find_pool_address, find_pool_mint_address, find_pool_onramp_address,
+        find_pool_stake_address, id, inline_mpl_token_metadata, inline_spl_token_2022,
         instruction::{self as ixn, SinglePoolInstruction},
+        minimum_pool_balance, ProgramVersion,
     },
+    spl_single_pool_client::{price_stake_for_tokens, price_tokens_for_stake, SinglePoolClient},
+    spl_token_interface as spl_token,
     std::{path::PathBuf, process::Command, str::FromStr, sync::Arc, time::Duration},
     tempfile::NamedTempFile,
     test_case::test_case,
@@ -215,6 +224,260 @@ async fn create_pool(rpc_client: &RpcClient, payer: &Keypair, config_file_path:
     vote_account
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn client_get_all_pools() {
+    let env = setup(false, true).await;
+    let client = SinglePoolClient::new(env.rpc_client.clone());
+
+    let second_vote_account = create_pool(&env.rpc_client, &env.payer, &env.config_file_path).await;
+
+    let mut expected = [env.vote_account, second_vote_account]
+        .into_iter()
+        .map(|vote_account| (find_pool_address(&id(), &vote_account), vote_account))
+        .collect::<Vec<_>>();
+    expected.sort_unstable();
+
+    let pools = client
+        .get_all_pools()
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(pool_address, pool)| (pool_address, pool.vote_account_address))
+        .collect::<Vec<_>>();
+    assert_eq!(pools, expected);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn price_matches_deposit_and_withdraw() {
+    let env = setup(false, true).await;
+    let client = SinglePoolClient::new(env.rpc_client.clone());
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let exchange_rate = client.get_exchange_rate(&pool_address).await.unwrap();
+    let expected_tokens = price_tokens_for_stake(exchange_rate, LAMPORTS_PER_SOL);
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+            "--output",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["commandOutput"]["tokenAmount"], expected_tokens);
+
+    // pricing the tokens back never yields more than was deposited
+    let exchange_rate = client.get_exchange_rate(&pool_address).await.unwrap();
+    let expected_stake = price_stake_for_tokens(exchange_rate, expected_tokens);
+    assert!(expected_stake <= LAMPORTS_PER_SOL);
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "ALL",
+            "--output",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["commandOutput"]["stakeAmount"], expected_stake);
+}
+
+#[test]
+fn rpc_retries() {
+    // nothing listens here, so every request fails in transport
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "--all",
+            "-u",
+            "http://127.0.0.1:1",
+            "--rpc-retries",
+            "2",
+            "--rpc-retry-delay",
+            "10",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("RPC request failed, retrying in 10ms"));
+    assert!(stderr.contains("RPC request failed, retrying in 20ms"));
+    assert!(!stderr.contains("retrying in 40ms"));
+}
+
+#[test]
+fn rpc_header_sent() {
+    // capture the first request the cli makes, then hang up so it fails fast
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = vec![];
+        let mut buf = [0; 1024];
+        while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+            let n = std::io::Read::read(&mut stream, &mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        String::from_utf8_lossy(&request).to_lowercase()
+    });
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "--all",
+            "-u",
+            &url,
+            "--rpc-header",
+            "X-Api-Key: test",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let request = server.join().unwrap();
+    assert!(request.contains("\r\nx-api-key: test\r\n"));
+}
+
+#[test]
+fn decode_instruction() {
+    let data = borsh::to_vec(&SinglePoolInstruction::UpdateTokenMetadata {
+        name: "name".to_string(),
+        symbol: "sym".to_string(),
+        uri: "uri".to_string(),
+    })
+    .unwrap();
+    let hex_data = data
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    let base64_data = BASE64_STANDARD.encode(&data);
+
+    // decoding is offline, so no validator is needed
+    for encoded_data in [hex_data, base64_data] {
+        let output = Command::new(SVSP_CLI)
+            .args(["decode-instruction", &encoded_data, "--output", "json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let decoded = &json["commandOutput"];
+        assert_eq!(decoded["instruction"], "UpdateTokenMetadata");
+        assert_eq!(decoded["name"], "name");
+        assert_eq!(decoded["symbol"], "sym");
+        assert_eq!(decoded["uri"], "uri");
+        assert_eq!(decoded["tokenAmount"], serde_json::Value::Null);
+    }
+
+    // trailing bytes are not a valid instruction
+    let output = Command::new(SVSP_CLI)
+        .args(["decode-instruction", "0100"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn export_schema() {
+    let output = Command::new(SVSP_CLI)
+        .args(["export-schema"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let types = json["types"].as_array().unwrap();
+    assert!(types.contains(&"SinglePool".into()));
+    assert!(types.contains(&"SinglePoolInstruction".into()));
+
+    // every instruction is described, with the discriminant it serializes with
+    let instruction = &json["definitions"]["SinglePoolInstruction"];
+    assert_eq!(instruction["kind"], "enum");
+    let variants = instruction["variants"].as_array().unwrap();
+    let all_variants = SinglePoolInstruction::all_variants();
+    assert_eq!(variants.len(), all_variants.len());
+    for (variant, instruction) in variants.iter().zip(all_variants) {
+        let discriminant = borsh::to_vec(&instruction).unwrap()[0];
+        assert_eq!(variant["discriminant"], discriminant);
+        assert!(json["definitions"]
+            .get(variant["declaration"].as_str().unwrap())
+            .is_some());
+    }
+
+    let pool = &json["definitions"]["SinglePool"];
+    assert_eq!(pool["kind"], "struct");
+    assert_eq!(pool["fields"][1]["name"], "vote_account_address");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn version_onchain() {
+    let env = setup(false, false).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "version",
+            "-C",
+            &env.config_file_path,
+            "--onchain",
+            "--output",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let version_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let version = &version_output["commandOutput"];
+    assert_eq!(version["programId"], id().to_string());
+    assert_eq!(
+        version["onchainVersion"],
+        ProgramVersion::CURRENT.to_string()
+    );
+
+    // a program that does not implement `GetVersion` is reported as an error
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "version",
+            "-C",
+            &env.config_file_path,
+            "--onchain",
+            "--program-id",
+            &system_program::id().to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
 async fn create_and_delegate_stake_account(
     rpc_client: &RpcClient,
     payer: &Keypair,
@@ -279,224 +542,3156 @@ async fn replenish_pool(raise_minimum_delegation: bool) {
 
     let status = Command::new(SVSP_CLI)
         .args([
-            "manage",
-            "replenish-pool",
+            "manage",
+            "replenish-pool",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn replenish_pool_with_amount() {
+    let env = setup(false, true).await;
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let pool_accounts = [
+        find_pool_stake_address(&id(), &pool_address),
+        find_pool_onramp_address(&id(), &pool_address),
+    ];
+    let pool_lamports = |accounts: Vec<Option<solana_account::Account>>| {
+        accounts
+            .into_iter()
+            .map(|account| account.unwrap().lamports)
+            .sum::<u64>()
+    };
+    let lamports_before = pool_lamports(
+        env.rpc_client
+            .get_multiple_accounts(&pool_accounts)
+            .await
+            .unwrap(),
+    );
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "replenish-pool",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--amount",
+            "0",
+        ])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "replenish-pool",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--amount",
+            "1sol",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let lamports_after = pool_lamports(
+        env.rpc_client
+            .get_multiple_accounts(&pool_accounts)
+            .await
+            .unwrap(),
+    );
+    assert_eq!(lamports_after, lamports_before + LAMPORTS_PER_SOL);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn initialize_token_2022() {
+    let env = setup(false, false).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "initialize",
+            "-C",
+            &env.config_file_path,
+            "--token-2022",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let mint = env
+        .rpc_client
+        .get_account(&find_pool_mint_address(&id(), &pool_address))
+        .await
+        .unwrap();
+    assert_eq!(mint.owner, inline_spl_token_2022::id());
+
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn initialize_from_file() {
+    let env = setup(false, false).await;
+
+    let existing_vote_account =
+        create_pool(&env.rpc_client, &env.payer, &env.config_file_path).await;
+    let not_vote_account = Pubkey::new_unique();
+
+    let vote_account_file = NamedTempFile::new().unwrap();
+    std::fs::write(
+        &vote_account_file,
+        format!(
+            "# new, existing, and invalid\n{}\n\n{}\n{}\n",
+            env.vote_account, existing_vote_account, not_vote_account,
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "initialize",
+            "-C",
+            &env.config_file_path,
+            "--from-file",
+            vote_account_file.path().to_str().unwrap(),
+            "--output",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let initialize_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let results = initialize_output["commandOutput"]["results"]
+        .as_array()
+        .unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(
+        results[0]["voteAccountAddress"],
+        env.vote_account.to_string()
+    );
+    assert_eq!(results[0]["status"], "created");
+    assert_eq!(results[1]["status"], "skipped");
+    assert_eq!(results[2]["status"], "failed");
+    assert!(results[2]["error"].is_string());
+
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    assert!(env.rpc_client.get_account(&pool_address).await.is_ok());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn replenish_all() {
+    let env = setup(false, true).await;
+
+    create_pool(&env.rpc_client, &env.payer, &env.config_file_path).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "replenish-all",
+            "-C",
+            &env.config_file_path,
+            "--output",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let replenish_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let results = replenish_output["commandOutput"]["results"]
+        .as_array()
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result["error"].is_null()));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn replenish_all_simulate() {
+    let env = setup(false, true).await;
+    let other_vote_account = create_pool(&env.rpc_client, &env.payer, &env.config_file_path).await;
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    // tip one pool, giving it lamports to move and delegate
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let other_pool_address = find_pool_address(&id(), &other_vote_account);
+    let blockhash = env.rpc_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &env.payer.pubkey(),
+            &find_pool_stake_address(&id(), &pool_address),
+            LAMPORTS_PER_SOL,
+        )],
+        Some(&env.payer.pubkey()),
+        &[&env.payer],
+        blockhash,
+    );
+    env.rpc_client
+        .send_and_confirm_transaction(&transaction)
+        .await
+        .unwrap();
+
+    let replenish_all = |simulate: bool| {
+        let mut args = vec![
+            "manage",
+            "replenish-all",
+            "-C",
+            &env.config_file_path,
+            "--output",
+            "json",
+        ];
+        if simulate {
+            args.push("--simulate");
+        }
+
+        let output = Command::new(SVSP_CLI).args(args).output().unwrap();
+        assert!(output.status.success());
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["commandOutput"]["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| {
+                (
+                    result["poolAddress"].as_str().unwrap().to_string(),
+                    result.clone(),
+                )
+            })
+            .collect::<std::collections::HashMap<_, _>>()
+    };
+
+    // simulating sends nothing
+    let results = replenish_all(true);
+    let result = &results[&pool_address.to_string()];
+    assert_eq!(
+        result["actions"],
+        serde_json::json!(["move-lamports", "delegate-on-ramp"])
+    );
+    assert!(result["signature"].is_null());
+    let other_result = &results[&other_pool_address.to_string()];
+    assert_eq!(other_result["actions"], serde_json::json!([]));
+
+    // a real run only sends the pool with work
+    let results = replenish_all(false);
+    let result = &results[&pool_address.to_string()];
+    assert!(result["error"].is_null());
+    assert!(result["signature"].is_string());
+    let other_result = &results[&other_pool_address.to_string()];
+    assert!(other_result["signature"].is_null());
+
+    // and afterwards, nothing is left to do
+    let results = replenish_all(true);
+    assert!(results
+        .values()
+        .all(|result| result["actions"] == serde_json::json!([])));
+}
+
+#[test_case(false; "one_lamp")]
+#[test_case(true; "one_sol")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit(raise_minimum_delegation: bool) {
+    let env = setup(raise_minimum_delegation, true).await;
+
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let args = vec![
+        "deposit".to_string(),
+        "-C".to_string(),
+        env.config_file_path,
+        stake_account.to_string(),
+        "--vote-account".to_string(),
+        env.vote_account.to_string(),
+    ];
+
+    let status = Command::new(SVSP_CLI).args(&args).status().unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_expect_vote() {
+    let env = setup(false, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--expect-vote",
+            &Pubkey::new_unique().to_string(),
+            &stake_account.to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not the expected"));
+    assert!(env.rpc_client.get_account(&stake_account).await.is_ok());
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--expect-vote",
+            &env.vote_account.to_string(),
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(env.rpc_client.get_account(&stake_account).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_staker_authority() {
+    let env = setup(false, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    // split the authorities so the payer remains only the withdrawer
+    let staker = Keypair::new();
+    let staker_file = NamedTempFile::new().unwrap();
+    write_keypair_file(&staker, &staker_file).unwrap();
+
+    let blockhash = env.rpc_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[stake_instruction::authorize(
+            &stake_account,
+            &env.payer.pubkey(),
+            &staker.pubkey(),
+            StakeAuthorize::Staker,
+            None,
+        )],
+        Some(&env.payer.pubkey()),
+        &[&env.payer],
+        blockhash,
+    );
+    env.rpc_client
+        .send_and_confirm_transaction(&transaction)
+        .await
+        .unwrap();
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    // a staker authority that does not match the stake account is rejected
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--staker-authority",
+            &env.keypair_file_path,
+            &stake_account.to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Incorrect stake authority"));
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--staker-authority",
+            staker_file.path().to_str().unwrap(),
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(env.rpc_client.get_account(&stake_account).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_token_owner() {
+    let env = setup(false, true).await;
+    let token_owner = Pubkey::new_unique();
+
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--token-owner",
+            &token_owner.to_string(),
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // the owner's associated token account was created and received the tokens
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let mint_address = find_pool_mint_address(&id(), &pool_address);
+    let token_account_address = get_associated_token_address(&token_owner, &mint_address);
+
+    let token_balance = env
+        .rpc_client
+        .get_token_account_balance(&token_account_address)
+        .await
+        .unwrap();
+    assert_eq!(token_balance.amount, LAMPORTS_PER_SOL.to_string());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_dry_run() {
+    let env = setup(false, true).await;
+
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--dry-run",
+            &stake_account.to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // a fresh pool prices stake one to one
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("Expected pool token gain: {}", LAMPORTS_PER_SOL)));
+    assert!(stdout.contains(&format!(
+        "Expected stake moved into pool: {} lamports",
+        LAMPORTS_PER_SOL
+    )));
+
+    // nothing was actually deposited
+    env.rpc_client.get_account(&stake_account).await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_with_compute_budget() {
+    let env = setup(false, true).await;
+
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    // the limit applies to simulation too, so too low a limit fails
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--dry-run",
+            "--with-compute-unit-limit",
+            "1000",
+            &stake_account.to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Simulation FAILED"));
+    assert!(stdout.contains("(limit 1000)"));
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--with-compute-unit-price",
+            "1000",
+            "--with-compute-unit-limit",
+            "200000",
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let account = env.rpc_client.get_account(&stake_account).await;
+    assert!(account.is_err());
+}
+
+#[test_case(false; "one_lamp")]
+#[test_case(true; "one_sol")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_batch(raise_minimum_delegation: bool) {
+    let env = setup(raise_minimum_delegation, true).await;
+
+    // one more than fits in a single transaction, so we exercise chunking
+    let mut batch = String::new();
+    let mut stake_accounts = vec![];
+    for _ in 0..=ixn::MAX_DEPOSIT_BATCH_SIZE {
+        let stake_account =
+            create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+        let token_owner = Pubkey::new_unique();
+
+        batch.push_str(&format!("{},{}\n", stake_account, token_owner));
+        stake_accounts.push(stake_account);
+    }
+
+    let batch_file = NamedTempFile::new().unwrap();
+    std::fs::write(&batch_file, batch).unwrap();
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--batch-file",
+            batch_file.path().to_str().unwrap(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    for stake_account in stake_accounts {
+        assert!(env.rpc_client.get_account(&stake_account).await.is_err());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_partial() {
+    let env = setup(false, true).await;
+
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    // test accounts are delegated with 1 sol, so this would leave nothing behind
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--amount",
+            "1",
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--amount",
+            "0.5",
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // the original account is left delegated with the remainder
+    let account = env.rpc_client.get_account(&stake_account).await.unwrap();
+    let StakeStateV2::Stake(_, stake, _) =
+        bincode::deserialize::<StakeStateV2>(&account.data).unwrap()
+    else {
+        panic!("stake account is not delegated");
+    };
+    assert_eq!(stake.delegation.stake, LAMPORTS_PER_SOL / 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_wait_for_epoch() {
+    let env = setup(false, true).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    // activating while the pool is active
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Activation status mismatch"));
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--wait-for-epoch",
+            "--timeout",
+            "120",
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let account = env.rpc_client.get_account(&stake_account).await;
+    assert!(account.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_multiple() {
+    let env = setup(false, true).await;
+
+    let mut stake_accounts = vec![];
+    for _ in 0..2 {
+        stake_accounts.push(
+            create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await,
+        );
+    }
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    // this one is activating while the pool is active, so it should be skipped
+    let late_stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_accounts[0].to_string(),
+            &stake_accounts[1].to_string(),
+            &late_stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    for stake_account in &stake_accounts {
+        assert!(env.rpc_client.get_account(stake_account).await.is_err());
+    }
+    assert!(env
+        .rpc_client
+        .get_account(&late_stake_account)
+        .await
+        .is_ok());
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--from-all-delegated",
+            "--vote-account",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    assert!(env
+        .rpc_client
+        .get_account(&late_stake_account)
+        .await
+        .is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_sign_only_chunked() {
+    let env = setup(false, true).await;
+
+    // one more than fits in a single transaction, so the deposit is signed in two
+    let mut stake_accounts = vec![];
+    for _ in 0..=ixn::MAX_DEPOSIT_BATCH_SIZE {
+        stake_accounts.push(
+            create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account)
+                .await
+                .to_string(),
+        );
+    }
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let blockhash = env.rpc_client.get_latest_blockhash().await.unwrap();
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--blockhash",
+            &blockhash.to_string(),
+            "--sign-only",
+            "--output",
+            "json-compact",
+        ])
+        .args(&stake_accounts)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // every transaction is signed, not just the first
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let signed_transactions = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(signed_transactions.len(), 2);
+    for signed_transaction in signed_transactions {
+        assert!(!signed_transaction["signers"].as_array().unwrap().is_empty());
+    }
+
+    // and nothing is submitted
+    for stake_account in &stake_accounts {
+        let stake_account = Pubkey::from_str(stake_account).unwrap();
+        assert!(env.rpc_client.get_account(&stake_account).await.is_ok());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_instructions_only_chunked() {
+    let env = setup(false, true).await;
+
+    // one more than fits in a single transaction, so the deposit is built as two
+    let mut stake_accounts = vec![];
+    for _ in 0..=ixn::MAX_DEPOSIT_BATCH_SIZE {
+        stake_accounts.push(
+            create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account)
+                .await
+                .to_string(),
+        );
+    }
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--instructions-only",
+            "--output",
+            "json-compact",
+        ])
+        .args(&stake_accounts)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // the instructions of every transaction are printed, not just the first
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let transactions = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<Vec<Instruction>>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(transactions.len(), 2);
+    for instructions in transactions {
+        assert!(instructions
+            .iter()
+            .any(|instruction| instruction.program_id == id()));
+    }
+}
+
+#[test_case(false; "one_lamp")]
+#[test_case(true; "one_sol")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn withdraw(raise_minimum_delegation: bool) {
+    let env = setup(raise_minimum_delegation, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "ALL",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn withdraw_fractional_tokens() {
+    let env = setup(false, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let pool_mint = find_pool_mint_address(&id(), &find_pool_address(&id(), &env.vote_account));
+    let token_account = get_associated_token_address(&env.payer.pubkey(), &pool_mint);
+    let token_balance = |rpc_client: Arc<RpcClient>| async move {
+        rpc_client
+            .get_token_account_balance(&token_account)
+            .await
+            .unwrap()
+            .amount
+            .parse::<u64>()
+            .unwrap()
+    };
+    let balance_before = token_balance(env.rpc_client.clone()).await;
+
+    // pool tokens have 9 decimals, so half a token is half a billion base units
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "0.5",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let balance_after = token_balance(env.rpc_client.clone()).await;
+    assert_eq!(balance_before - balance_after, LAMPORTS_PER_SOL / 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn withdraw_stake_account_keypair() {
+    let env = setup(false, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let keypair_dir = tempfile::tempdir().unwrap();
+    let keypair_path = keypair_dir.path().join("stake-account.json");
+    let withdraw = || {
+        Command::new(SVSP_CLI)
+            .args([
+                "withdraw",
+                "-C",
+                &env.config_file_path,
+                "--vote-account",
+                &env.vote_account.to_string(),
+                "--stake-account-keypair",
+                keypair_path.to_str().unwrap(),
+                "0.5",
+            ])
+            .status()
+            .unwrap()
+    };
+
+    // the keypair is generated, saved, and used for the new stake account
+    assert!(withdraw().success());
+    let new_stake_account = read_keypair_file(&keypair_path).unwrap().pubkey();
+    let client = SinglePoolClient::new(env.rpc_client.clone());
+    assert!(matches!(
+        client
+            .stake_client()
+            .get_stake_state(&new_stake_account)
+            .await
+            .unwrap(),
+        Some(StakeStateV2::Stake(..))
+    ));
+
+    // reusing the file means reusing the account, which is now initialized
+    assert!(!withdraw().success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn withdraw_close_token_account() {
+    let env = setup(false, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let token_account_address = get_associated_token_address(
+        &env.payer.pubkey(),
+        &find_pool_mint_address(&id(), &pool_address),
+    );
+
+    // a partial withdrawal would leave tokens behind, so the account cannot be closed
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--close-token-account",
+            "1",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("withdraw ALL to close it"));
+
+    let token_account_rent = env
+        .rpc_client
+        .get_balance(&token_account_address)
+        .await
+        .unwrap();
+    let payer_balance = env
+        .rpc_client
+        .get_balance(&env.payer.pubkey())
+        .await
+        .unwrap();
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--close-token-account",
+            "ALL",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    assert!(env
+        .rpc_client
+        .get_account_with_commitment(&token_account_address, CommitmentConfig::confirmed())
+        .await
+        .unwrap()
+        .value
+        .is_none());
+
+    // the payer funded the new stake account, but got the token account rent back
+    let stake_rent = env
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())
+        .await
+        .unwrap();
+    let new_payer_balance = env
+        .rpc_client
+        .get_balance(&env.payer.pubkey())
+        .await
+        .unwrap();
+    assert!(new_payer_balance > payer_balance - stake_rent);
+    assert!(new_payer_balance <= payer_balance - stake_rent + token_account_rent);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn withdraw_confirm() {
+    let env = setup(false, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // without a terminal to prompt on, --confirm requires --yes
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--confirm",
+            "ALL",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--yes"));
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--confirm",
+            "--yes",
+            "ALL",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn withdraw_existing_stake_account() {
+    let env = setup(false, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // a bare address that does not exist cannot be created
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--stake-account",
+            &Pubkey::new_unique().to_string(),
+            "ALL",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    // set up a blank stake account ahead of time
+    let existing_stake = Keypair::new();
+    let stake_rent = env
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())
+        .await
+        .unwrap();
+    let blockhash = env.rpc_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::create_account(
+            &env.payer.pubkey(),
+            &existing_stake.pubkey(),
+            stake_rent,
+            StakeStateV2::size_of() as u64,
+            &solana_stake_interface::program::id(),
+        )],
+        Some(&env.payer.pubkey()),
+        &[&env.payer, &existing_stake],
+        blockhash,
+    );
+    env.rpc_client
+        .send_and_confirm_transaction(&transaction)
+        .await
+        .unwrap();
+
+    // its address alone is enough to withdraw into it
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--stake-account",
+            &existing_stake.pubkey().to_string(),
+            "--output",
+            "json",
+            "ALL",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let withdraw_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        withdraw_output["commandOutput"]["stakeAccountAddress"],
+        existing_stake.pubkey().to_string(),
+    );
+
+    let account = env
+        .rpc_client
+        .get_account(&existing_stake.pubkey())
+        .await
+        .unwrap();
+    let StakeStateV2::Stake(meta, _, _) = bincode::deserialize(&account.data).unwrap() else {
+        panic!("withdrawn stake account is not delegated");
+    };
+    assert_eq!(meta.authorized.withdrawer, env.payer.pubkey());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn withdraw_redelegate() {
+    let env = setup(false, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // only one thing can happen to the withdrawn stake
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--deactivate",
+            "--redelegate",
+            &Pubkey::new_unique().to_string(),
+            "ALL",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    // the pool is active, so its stake cannot move to another validator yet
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--redelegate",
+            &Pubkey::new_unique().to_string(),
+            "ALL",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--deactivate"));
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--keep-stake-active",
+            "ALL",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn withdraw_stake_authority_signer() {
+    let env = setup(false, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let stake_authority = Keypair::new();
+    let stake_authority_file = NamedTempFile::new().unwrap();
+    write_keypair_file(&stake_authority, &stake_authority_file).unwrap();
+
+    // a bare address cannot sign the deactivation
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--stake-authority",
+            &stake_authority.pubkey().to_string(),
+            "--deactivate",
+            "ALL",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("stake_authority"));
+
+    // any signer source can
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--stake-authority",
+            stake_authority_file.path().to_str().unwrap(),
+            "--deactivate",
+            "--output",
+            "json",
+            "ALL",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let withdraw_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let withdrawn_stake = Pubkey::from_str(
+        withdraw_output["commandOutput"]["stakeAccountAddress"]
+            .as_str()
+            .unwrap(),
+    )
+    .unwrap();
+
+    let account = env.rpc_client.get_account(&withdrawn_stake).await.unwrap();
+    let StakeStateV2::Stake(meta, stake, _) = bincode::deserialize(&account.data).unwrap() else {
+        panic!("withdrawn stake account is not delegated");
+    };
+    assert_eq!(meta.authorized.staker, stake_authority.pubkey());
+    assert_eq!(meta.authorized.withdrawer, stake_authority.pubkey());
+    assert_ne!(stake.delegation.deactivation_epoch, u64::MAX);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn withdraw_merge_into() {
+    let env = setup(false, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+    let merge_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let account = env.rpc_client.get_account(&merge_account).await.unwrap();
+    let StakeStateV2::Stake(_, stake, _) = bincode::deserialize(&account.data).unwrap() else {
+        panic!("merge destination is not delegated");
+    };
+    let stake_before = stake.delegation.stake;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--merge-into",
+            &merge_account.to_string(),
+            "--output",
+            "json",
+            "ALL",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let withdraw_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let withdraw_output = &withdraw_output["commandOutput"];
+    assert_eq!(
+        withdraw_output["mergedIntoAddress"],
+        merge_account.to_string()
+    );
+
+    // the withdrawn stake ends up in the destination, and the new account is closed
+    let withdrawn_stake =
+        Pubkey::from_str(withdraw_output["stakeAccountAddress"].as_str().unwrap()).unwrap();
+    assert!(env
+        .rpc_client
+        .get_account_with_commitment(&withdrawn_stake, CommitmentConfig::confirmed())
+        .await
+        .unwrap()
+        .value
+        .is_none());
+
+    let account = env.rpc_client.get_account(&merge_account).await.unwrap();
+    let StakeStateV2::Stake(_, stake, _) = bincode::deserialize(&account.data).unwrap() else {
+        panic!("merge destination is not delegated");
+    };
+    assert_eq!(
+        stake.delegation.stake - stake_before,
+        withdraw_output["stakeAmount"].as_u64().unwrap(),
+    );
+    assert!(stake.delegation.stake > stake_before);
+}
+
+#[test_case(false; "one_lamp")]
+#[test_case(true; "one_sol")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn withdraw_deactivate_and_reclaim(raise_minimum_delegation: bool) {
+    let env = setup(raise_minimum_delegation, true).await;
+    let stake_account =
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            &stake_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "withdraw",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--deactivate",
+            "--output",
+            "json",
+            "ALL",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let withdraw_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let withdrawn_stake = Pubkey::from_str(
+        withdraw_output["commandOutput"]["stakeAccountAddress"]
+            .as_str()
+            .unwrap(),
+    )
+    .unwrap();
+
+    // new stake account is deactivating in the withdrawal epoch
+    let withdraw_epoch = env.rpc_client.get_epoch_info().await.unwrap().epoch;
+    let account = env.rpc_client.get_account(&withdrawn_stake).await.unwrap();
+    let StakeStateV2::Stake(_, stake, _) = bincode::deserialize(&account.data).unwrap() else {
+        panic!("withdrawn stake account is not delegated");
+    };
+    assert_eq!(stake.delegation.deactivation_epoch, withdraw_epoch);
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    // once deactivated, the full balance can be reclaimed, closing the account
+    let account = env.rpc_client.get_account(&withdrawn_stake).await.unwrap();
+    let blockhash = env.rpc_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[stake_instruction::withdraw(
+            &withdrawn_stake,
+            &env.payer.pubkey(),
+            &env.payer.pubkey(),
+            account.lamports,
+            None,
+        )],
+        Some(&env.payer.pubkey()),
+        &[&env.payer],
+        blockhash,
+    );
+    env.rpc_client
+        .send_and_confirm_transaction(&transaction)
+        .await
+        .unwrap();
+
+    let account = env
+        .rpc_client
+        .get_account_with_commitment(&withdrawn_stake, CommitmentConfig::confirmed())
+        .await
+        .unwrap()
+        .value;
+    assert!(account.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn initialize_await_finalized() {
+    let env = setup(false, false).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "initialize",
+            "-C",
+            &env.config_file_path,
+            "--await",
+            "finalized",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let pool_account = env
+        .rpc_client
+        .get_account_with_commitment(&pool_address, CommitmentConfig::finalized())
+        .await
+        .unwrap()
+        .value;
+    assert!(pool_account.is_some());
+}
+
+#[test_case(false; "one_lamp")]
+#[test_case(true; "one_sol")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn initialize_funds_minimum_pool_balance(raise_minimum_delegation: bool) {
+    let env = setup(raise_minimum_delegation, false).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "initialize",
+            "-C",
+            &env.config_file_path,
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // the pool stake account holds rent plus the minimum pool balance, whatever the minimum delegation
+    let minimum_delegation = env.rpc_client.get_stake_minimum_delegation().await.unwrap();
+    let stake_rent = env
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())
+        .await
+        .unwrap();
+    let pool_stake_address =
+        find_pool_stake_address(&id(), &find_pool_address(&id(), &env.vote_account));
+    let pool_stake_lamports = env
+        .rpc_client
+        .get_balance(&pool_stake_address)
+        .await
+        .unwrap();
+    assert_eq!(
+        pool_stake_lamports,
+        stake_rent + minimum_pool_balance(minimum_delegation)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn initialize_minimum_pool_balance_override() {
+    let env = setup(false, false).await;
+
+    let minimum_delegation = env.rpc_client.get_stake_minimum_delegation().await.unwrap();
+    let expected_minimum_pool_balance = minimum_pool_balance(minimum_delegation);
+    let initialize = |minimum_pool_balance: u64| {
+        Command::new(SVSP_CLI)
+            .args([
+                "manage",
+                "initialize",
+                "-C",
+                &env.config_file_path,
+                "--minimum-pool-balance",
+                &minimum_pool_balance.to_string(),
+                &env.vote_account.to_string(),
+            ])
+            .output()
+            .unwrap()
+    };
+
+    // a minimum pool balance below the program's own is rejected before anything is sent
+    let output = initialize(expected_minimum_pool_balance - 1);
+    assert!(!output.status.success());
+
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    assert!(env.rpc_client.get_account(&pool_address).await.is_err());
+
+    // one above it is accepted, with a warning that the excess is locked
+    let output = initialize(expected_minimum_pool_balance + 1);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("will be locked"));
+    assert!(env.rpc_client.get_account(&pool_address).await.is_ok());
+
+    let stake_rent = env
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())
+        .await
+        .unwrap();
+    let pool_stake_lamports = env
+        .rpc_client
+        .get_balance(&find_pool_stake_address(&id(), &pool_address))
+        .await
+        .unwrap();
+    assert_eq!(
+        pool_stake_lamports,
+        stake_rent + expected_minimum_pool_balance + 1
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn commitment_finalized() {
+    let env = setup(false, true).await;
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let pool_stake_address = find_pool_stake_address(&id(), &pool_address);
+    let pool_onramp_address = find_pool_onramp_address(&id(), &pool_address);
+    let finalized_pool_lamports = || async {
+        let mut lamports = 0;
+        for address in [pool_stake_address, pool_onramp_address] {
+            lamports += env
+                .rpc_client
+                .get_account_with_commitment(&address, CommitmentConfig::finalized())
+                .await
+                .unwrap()
+                .value
+                .unwrap()
+                .lamports;
+        }
+        lamports
+    };
+
+    let lamports_before = finalized_pool_lamports().await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "replenish-pool",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--amount",
+            "1sol",
+            "--commitment",
+            "finalized",
+            "--confirm-timeout",
+            "120",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // the command only returns once its transaction is finalized
+    assert_eq!(
+        finalized_pool_lamports().await,
+        lamports_before + LAMPORTS_PER_SOL
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn initialize_estimate_fee() {
+    let env = setup(false, false).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "initialize",
+            "-C",
+            &env.config_file_path,
+            "--estimate-fee",
+            "--with-compute-unit-price",
+            "1000",
+            &env.vote_account.to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let fee_line = stdout
+        .lines()
+        .find(|line| line.starts_with("Estimated fee:"))
+        .unwrap();
+    assert!(!fee_line.contains(" 0 priority"));
+
+    // the pool rent is paid by the fee payer, so it counts toward the total cost
+    let transferred = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Transferred from fee payer: "))
+        .unwrap()
+        .trim_end_matches(" lamports")
+        .parse::<u64>()
+        .unwrap();
+    let minimum_delegation = env.rpc_client.get_stake_minimum_delegation().await.unwrap();
+    assert!(transferred > minimum_pool_balance(minimum_delegation));
+    assert!(stdout.contains("Estimated total cost:"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn estimate_init_cost() {
+    let env = setup(false, false).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "estimate-init-cost",
+            "-C",
+            &env.config_file_path,
+            "--output",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let total = json["commandOutput"]["total"].as_u64().unwrap();
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "initialize",
+            "-C",
+            &env.config_file_path,
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // the new pool's accounts hold exactly what was estimated
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let mut funded = 0;
+    for address in [
+        pool_address,
+        find_pool_stake_address(&id(), &pool_address),
+        find_pool_onramp_address(&id(), &pool_address),
+        find_pool_mint_address(&id(), &pool_address),
+    ] {
+        funded += env.rpc_client.get_balance(&address).await.unwrap();
+    }
+    assert_eq!(funded, total);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn initialize_insufficient_balance() {
+    let env = setup(false, false).await;
+
+    // enough to pay fees, but nowhere near enough to fund the pool
+    let poor_payer = Keypair::new();
+    let poor_payer_file = NamedTempFile::new().unwrap();
+    write_keypair_file(&poor_payer, &poor_payer_file).unwrap();
+
+    let blockhash = env.rpc_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &env.payer.pubkey(),
+            &poor_payer.pubkey(),
+            LAMPORTS_PER_SOL / 100,
+        )],
+        Some(&env.payer.pubkey()),
+        &[&env.payer],
+        blockhash,
+    );
+    env.rpc_client
+        .send_and_confirm_transaction(&transaction)
+        .await
+        .unwrap();
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "initialize",
+            "-C",
+            &env.config_file_path,
+            "--fee-payer",
+            poor_payer_file.path().to_str().unwrap(),
+            &env.vote_account.to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Insufficient lamports in fee payer"));
+
+    // nothing was submitted
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    assert!(env
+        .rpc_client
+        .get_account_with_commitment(&pool_address, CommitmentConfig::confirmed())
+        .await
+        .unwrap()
+        .value
+        .is_none());
+}
+
+#[test_case(false; "one_lamp")]
+#[test_case(true; "one_sol")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn create_metadata(raise_minimum_delegation: bool) {
+    let env = setup(raise_minimum_delegation, false).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "initialize",
+            "-C",
+            &env.config_file_path,
+            "--skip-metadata",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "create-token-metadata",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // a second creation is refused before anything is sent
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "create-token-metadata",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn initialize_with_metadata() {
+    let env = setup(false, false).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "initialize",
+            "-C",
+            &env.config_file_path,
+            "--token-name",
+            "Branded Pool",
+            "--token-symbol",
+            "BRAND",
+            "--authorized-withdrawer",
+            &env.keypair_file_path,
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let mint_address = find_pool_mint_address(&id(), &pool_address);
+    let (metadata_address, _) =
+        inline_mpl_token_metadata::pda::find_metadata_account(&mint_address);
+
+    let metadata_account = env.rpc_client.get_account(&metadata_address).await.unwrap();
+    assert!(metadata_account
+        .data
+        .windows(b"Branded Pool".len())
+        .any(|window| window == b"Branded Pool"));
+}
+
+#[test_case(false; "one_lamp")]
+#[test_case(true; "one_sol")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn update_metadata(raise_minimum_delegation: bool) {
+    let env = setup(raise_minimum_delegation, true).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "update-token-metadata",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "whatever",
+            "idk",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // testing this flag because the match is rather torturous
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "update-token-metadata",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--authorized-withdrawer",
+            &env.keypair_file_path,
+            "something",
+            "new",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn update_metadata_sign_only() {
+    let env = setup(false, true).await;
+
+    let blockhash = env.rpc_client.get_latest_blockhash().await.unwrap();
+    let payer = env.payer.pubkey().to_string();
+
+    // sign offline, printing the signatures rather than submitting
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "update-token-metadata",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--blockhash",
+            &blockhash.to_string(),
+            "--sign-only",
+            "--output",
+            "json",
+            "offline",
+            "sig",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let sign_only_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let signers = sign_only_output["signers"].as_array().unwrap();
+    assert_eq!(signers.len(), 1);
+    let presigner = signers[0].as_str().unwrap();
+    assert!(presigner.starts_with(&payer));
+
+    // submit with only the pubkey and presigned signature
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "update-token-metadata",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--fee-payer",
+            &payer,
+            "--authorized-withdrawer",
+            &payer,
+            "--blockhash",
+            &blockhash.to_string(),
+            "--signer",
+            presigner,
+            "offline",
+            "sig",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn update_metadata_dump_transaction() {
+    let env = setup(false, true).await;
+
+    let blockhash = env.rpc_client.get_latest_blockhash().await.unwrap();
+
+    // dump the signed transaction rather than submitting it
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "update-token-metadata",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--blockhash",
+            &blockhash.to_string(),
+            "--sign-only",
+            "--dump-transaction",
+            "dumped",
+            "tx",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let transaction_bytes = BASE64_STANDARD
+        .decode(String::from_utf8(output.stdout).unwrap().trim())
+        .unwrap();
+    let transaction: Transaction = bincode::deserialize(&transaction_bytes).unwrap();
+    assert_eq!(transaction.message.recent_blockhash, blockhash);
+    assert!(transaction.is_signed());
+
+    // a relayer can submit it as-is
+    env.rpc_client
+        .send_and_confirm_transaction(&transaction)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn single_pool_config_file() {
+    let env = setup(false, true).await;
+
+    // single-pool settings are read from beside whichever config file is used
+    let config_dir = tempfile::tempdir().unwrap();
+    let config_file_path = config_dir.path().join("config.yml");
+    SolanaConfig::load(&env.config_file_path)
+        .unwrap()
+        .save(config_file_path.to_str().unwrap())
+        .unwrap();
+    std::fs::write(
+        config_dir.path().join("single-pool.yml"),
+        "compute_unit_price: 1000\ncommitment: finalized\n",
+    )
+    .unwrap();
+
+    let compute_unit_price = |extra_args: &[&str]| {
+        let output = Command::new(SVSP_CLI)
+            .args([
+                "manage",
+                "update-token-metadata",
+                "-C",
+                config_file_path.to_str().unwrap(),
+                "--vote-account",
+                &env.vote_account.to_string(),
+                "--instructions-only",
+            ])
+            .args(extra_args)
+            .args(["multisig", "ms"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let instructions: Vec<Instruction> = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].program_id, compute_budget::id());
+        instructions[0].data.clone()
+    };
+
+    assert_eq!(
+        compute_unit_price(&[]),
+        [&[3][..], &1000u64.to_le_bytes()].concat()
+    );
+
+    // flags take precedence
+    assert_eq!(
+        compute_unit_price(&["--with-compute-unit-price", "5"]),
+        [&[3][..], &5u64.to_le_bytes()].concat()
+    );
+
+    // a malformed file is an error rather than silently ignored
+    std::fs::write(
+        config_dir.path().join("single-pool.yml"),
+        "compute_unit_price: lots\n",
+    )
+    .unwrap();
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            config_file_path.to_str().unwrap(),
+            "--vote-account",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn update_metadata_instructions_only() {
+    let env = setup(false, true).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "update-token-metadata",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--instructions-only",
+            "multisig",
+            "ms",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let instructions: Vec<Instruction> = serde_json::from_slice(&output.stdout).unwrap();
+    let expected_instruction = ixn::update_token_metadata(
+        &id(),
+        &env.vote_account,
+        &env.payer.pubkey(),
+        "multisig".to_string(),
+        "ms".to_string(),
+        "".to_string(),
+    );
+
+    // signer and writable flags may differ from the builder, since the payer is also the withdrawer
+    assert_eq!(instructions.len(), 1);
+    assert_eq!(instructions[0].program_id, expected_instruction.program_id);
+    assert_eq!(instructions[0].data, expected_instruction.data);
+    assert_eq!(
+        instructions[0]
+            .accounts
+            .iter()
+            .map(|meta| meta.pubkey)
+            .collect::<Vec<_>>(),
+        expected_instruction
+            .accounts
+            .iter()
+            .map(|meta| meta.pubkey)
+            .collect::<Vec<_>>(),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn update_metadata_compute_unit_price_auto() {
+    let env = setup(false, true).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "update-token-metadata",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--with-compute-unit-price",
+            "auto",
+            "--instructions-only",
+            "multisig",
+            "ms",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // an idle test validator has no recent fees, so the estimate is zero
+    let instructions: Vec<Instruction> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(instructions.len(), 2);
+    assert_eq!(instructions[0].program_id, compute_budget::id());
+    assert_eq!(
+        instructions[0].data,
+        [&[3][..], &0u64.to_le_bytes()].concat()
+    );
+    assert_eq!(instructions[1].program_id, id());
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "update-token-metadata",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--with-compute-unit-price",
+            "auto",
+            "multisig",
+            "ms",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn lookup() {
+    let env = setup(false, true).await;
+
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let mint_address = find_pool_mint_address(&id(), &pool_address);
+
+    let vote_account = env.rpc_client.get_account(&env.vote_account).await.unwrap();
+    let validator_identity = VoteStateV4::deserialize(&vote_account.data, &env.vote_account)
+        .unwrap()
+        .node_pubkey;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "lookup",
+            "-C",
+            &env.config_file_path,
+            "--mint",
+            &mint_address.to_string(),
+            "--output",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let lookup_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let lookup_output = &lookup_output["commandOutput"];
+    assert_eq!(lookup_output["poolAddress"], pool_address.to_string());
+    assert_eq!(
+        lookup_output["voteAccountAddress"],
+        env.vote_account.to_string()
+    );
+    assert_eq!(
+        lookup_output["validatorIdentity"],
+        validator_identity.to_string()
+    );
+
+    // not a pool mint
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "lookup",
+            "-C",
+            &env.config_file_path,
+            "--mint",
+            &Pubkey::new_unique().to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn show_metadata_authority() {
+    let env = setup(false, true).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "show-metadata-authority",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--output",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&env.payer.pubkey().to_string()));
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let token_metadata = &json["commandOutput"]["tokenMetadata"];
+    let vote_account_str = env.vote_account.to_string();
+    assert_eq!(
+        token_metadata["name"],
+        format!("SPL Single Pool {}", &vote_account_str[0..15])
+    );
+    assert_eq!(
+        token_metadata["symbol"],
+        format!("st{}", &vote_account_str[0..7])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn display() {
+    let env = setup(false, true).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--verbose",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--output",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let display_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let pool = &display_output["commandOutput"];
+    let net_asset_value = pool["netAssetValue"].as_u64().unwrap();
+    let token_supply = pool["tokenSupply"].as_u64().unwrap();
+    assert_eq!(
+        pool["exchangeRate"].as_f64().unwrap(),
+        net_asset_value as f64 / token_supply as f64
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn display_status() {
+    let env = setup(false, true).await;
+
+    // the pool stake is delegated on creation, so it is active from the next epoch
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--output",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let display_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let pool = &display_output["commandOutput"];
+    assert_eq!(pool["status"], "active");
+    assert_eq!(pool["onrampExists"], true);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn display_onramp() {
+    let env = setup(false, true).await;
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let display_pool = || async {
+        let output = Command::new(SVSP_CLI)
+            .args([
+                "display",
+                "-C",
+                &env.config_file_path,
+                "--vote-account",
+                &env.vote_account.to_string(),
+                "--output",
+                "json",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let display_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        display_output["commandOutput"].clone()
+    };
+
+    let pool = display_pool().await;
+    assert_eq!(pool["onrampLamports"], 0);
+    assert_eq!(pool["onrampActivating"], false);
+
+    // lamports sent to the on-ramp sit undelegated until a replenish
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let onramp_address = find_pool_onramp_address(&id(), &pool_address);
+    let blockhash = env.rpc_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &env.payer.pubkey(),
+            &onramp_address,
+            LAMPORTS_PER_SOL,
+        )],
+        Some(&env.payer.pubkey()),
+        &[&env.payer],
+        blockhash,
+    );
+    env.rpc_client
+        .send_and_confirm_transaction(&transaction)
+        .await
+        .unwrap();
+
+    let pool = display_pool().await;
+    assert_eq!(pool["onrampLamports"], LAMPORTS_PER_SOL);
+    assert_eq!(pool["onrampActivating"], false);
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "replenish-pool",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let pool = display_pool().await;
+    assert_eq!(pool["onrampLamports"], LAMPORTS_PER_SOL);
+    assert_eq!(pool["onrampActivating"], true);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn display_with_apy() {
+    let env = setup(false, true).await;
+
+    // nothing votes on the test vote account, so there is no history to estimate from
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--with-apy",
+            "--output",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let display_output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(display_output["commandOutput"]["estimatedApy"].is_null());
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--with-apy",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Estimated APY"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn display_by_validator_identity() {
+    let env = setup(false, true).await;
+
+    // node pubkey follows the vote state version discriminant
+    let vote_account_data = env
+        .rpc_client
+        .get_account_data(&env.vote_account)
+        .await
+        .unwrap();
+    let validator_identity = Pubkey::try_from(&vote_account_data[4..36]).unwrap();
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--validator-identity",
+            &validator_identity.to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&pool_address.to_string()));
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--validator-identity",
+            &Pubkey::new_unique().to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn display_all() {
+    let env = setup(false, true).await;
+
+    create_pool(&env.rpc_client, &env.payer, &env.config_file_path).await;
+    create_pool(&env.rpc_client, &env.payer, &env.config_file_path).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args(["display", "-C", &env.config_file_path, "--all"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let pools = stdout
+        .lines()
+        .filter(|line| line.starts_with("  Pool address:"))
+        .map(|line| Pubkey::from_str(line.split_whitespace().last().unwrap()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(pools.len(), 3);
+    assert!(pools.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--all",
+            "--offset",
+            "1",
+            "--limit",
+            "1",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let window = stdout
+        .lines()
+        .filter(|line| line.starts_with("  Pool address:"))
+        .map(|line| Pubkey::from_str(line.split_whitespace().last().unwrap()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(window, pools[1..2]);
+
+    // serial fetching returns the same pools in the same order
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--all",
+            "--max-concurrent-requests",
+            "1",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let serial_pools = stdout
+        .lines()
+        .filter(|line| line.starts_with("  Pool address:"))
+        .map(|line| Pubkey::from_str(line.split_whitespace().last().unwrap()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(serial_pools, pools);
+
+    let output = Command::new(SVSP_CLI)
+        .args(["display", "-C", &env.config_file_path, "--all", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let pools = stdout
+        .lines()
+        .filter(|line| line.starts_with("  Pool address:"))
+        .count();
+    assert_eq!(pools, 3);
+
+    let stakes = stdout
+        .lines()
+        .filter(|line| line.starts_with("  Pool main stake account address:"))
+        .count();
+    assert_eq!(stakes, 3);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn display_csv() {
+    let env = setup(false, true).await;
+
+    create_pool(&env.rpc_client, &env.payer, &env.config_file_path).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--all",
+            "--output",
+            "csv",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "pool_address,vote_account_address,available_stake,excess_lamports,token_supply"
+    );
+
+    let rows = lines.collect::<Vec<_>>();
+    assert_eq!(rows.len(), 2);
+    for row in rows {
+        let fields = row.split(',').collect::<Vec<_>>();
+        assert_eq!(fields.len(), 5);
+        Pubkey::from_str(fields[0]).unwrap();
+        Pubkey::from_str(fields[1]).unwrap();
+        for field in &fields[2..] {
+            field.parse::<u64>().unwrap();
+        }
+    }
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--output",
+            "csv",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 2);
+
+    // csv is only implemented for display
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
+            "-C",
+            &env.config_file_path,
+            "--output",
+            "csv",
+            &Pubkey::new_unique().to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn display_output_file() {
+    let env = setup(false, true).await;
+    let output_file = NamedTempFile::new().unwrap();
+    let output_file_path = output_file.path().to_str().unwrap();
+
+    let display = |append: bool| {
+        let mut args = vec![
+            "display",
+            "-C",
+            env.config_file_path.as_str(),
+            "--all",
+            "--output",
+            "json-compact",
+            "--output-file",
+            output_file_path,
+        ];
+        if append {
+            args.push("--append");
+        }
+
+        Command::new(SVSP_CLI).args(args).output().unwrap()
+    };
+
+    // the result goes to the file rather than stdout
+    let output = display(false);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let contents = std::fs::read_to_string(output_file.path()).unwrap();
+    assert_eq!(contents.lines().count(), 1);
+    let json: serde_json::Value = serde_json::from_str(contents.trim()).unwrap();
+    assert_eq!(json["commandName"], "DisplayAll");
+
+    // overwriting leaves one result, appending adds another
+    assert!(display(false).status.success());
+    let contents = std::fs::read_to_string(output_file.path()).unwrap();
+    assert_eq!(contents.lines().count(), 1);
+
+    assert!(display(true).status.success());
+    let contents = std::fs::read_to_string(output_file.path()).unwrap();
+    assert_eq!(contents.lines().count(), 2);
+    for line in contents.lines() {
+        serde_json::from_str::<serde_json::Value>(line).unwrap();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn instructions_only_output_file() {
+    let env = setup(false, true).await;
+    let output_file = NamedTempFile::new().unwrap();
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "update-token-metadata",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--instructions-only",
+            "--output",
+            "json-compact",
+            "--output-file",
+            output_file.path().to_str().unwrap(),
+            "multisig",
+            "ms",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // the instructions stand in for the result, so they go to the file as well
+    assert!(output.stdout.is_empty());
+    let contents = std::fs::read_to_string(output_file.path()).unwrap();
+    assert_eq!(contents.lines().count(), 1);
+    let instructions: Vec<Instruction> = serde_json::from_str(contents.trim()).unwrap();
+    assert_eq!(instructions.len(), 1);
+    assert_eq!(instructions[0].program_id, id());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn display_rpc_header() {
+    let env = setup(false, true).await;
+
+    let display = |headers: &[&str]| {
+        let mut args = vec![
+            "display",
+            "-C",
+            env.config_file_path.as_str(),
+            "--all",
+            "--output",
+            "json",
+        ];
+        for header in headers {
+            args.push("--rpc-header");
+            args.push(header);
+        }
+
+        Command::new(SVSP_CLI).args(args).output().unwrap()
+    };
+
+    // the validator ignores headers it does not use, so requests still go through
+    let output = display(&["X-Api-Key: test", "X-Client-Id:single-pool"]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["commandName"], "DisplayAll");
+
+    // malformed headers are rejected before anything is sent
+    for header in ["X-Api-Key test", "Bad Name: test", "X-Api-Key: bad\nvalue"] {
+        assert!(!display(&[header]).status.success());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn display_prometheus() {
+    let env = setup(false, true).await;
+
+    create_pool(&env.rpc_client, &env.payer, &env.config_file_path).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "display",
+            "-C",
+            &env.config_file_path,
+            "--all",
+            "--output",
+            "prometheus",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // five metrics, each with help and type lines and one sample per pool
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let samples = stdout
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>();
+    assert_eq!(samples.len(), 10);
+    assert_eq!(stdout.lines().count(), 20);
+
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let sample_prefix = format!(
+        "single_pool_token_supply{{pool=\"{}\",vote=\"{}\"}} ",
+        pool_address, env.vote_account
+    );
+    let token_supply = samples
+        .iter()
+        .find_map(|line| line.strip_prefix(&sample_prefix))
+        .unwrap();
+    token_supply.parse::<u64>().unwrap();
+
+    // prometheus is only implemented for display
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "deposit",
             "-C",
             &env.config_file_path,
-            "--vote-account",
-            &env.vote_account.to_string(),
+            "--output",
+            "prometheus",
+            &Pubkey::new_unique().to_string(),
         ])
         .status()
         .unwrap();
-    assert!(status.success());
+    assert!(!status.success());
 }
 
-#[test_case(false; "one_lamp")]
-#[test_case(true; "one_sol")]
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 #[serial]
-async fn deposit(raise_minimum_delegation: bool) {
-    let env = setup(raise_minimum_delegation, true).await;
-
-    let stake_account =
-        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
-
-    wait_for_next_epoch(&env.rpc_client).await;
-
-    let args = vec![
-        "deposit".to_string(),
-        "-C".to_string(),
-        env.config_file_path,
-        stake_account.to_string(),
-        "--vote-account".to_string(),
-        env.vote_account.to_string(),
-    ];
+async fn quote() {
+    let env = setup(false, true).await;
 
-    let status = Command::new(SVSP_CLI).args(&args).status().unwrap();
-    assert!(status.success());
+    // a fresh pool is worth exactly its phantom tokens, so the rate is one
+    for direction in ["stake-to-token", "token-to-stake"] {
+        let output = Command::new(SVSP_CLI)
+            .args([
+                "quote",
+                "-C",
+                &env.config_file_path,
+                "--vote-account",
+                &env.vote_account.to_string(),
+                "--direction",
+                direction,
+                "--output",
+                "json",
+                "1.5",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let quote = &json["commandOutput"];
+        assert_eq!(quote["direction"], direction);
+        assert_eq!(quote["inputAmount"], LAMPORTS_PER_SOL * 3 / 2);
+        assert_eq!(quote["outputAmount"], LAMPORTS_PER_SOL * 3 / 2);
+        assert_eq!(quote["effectiveRate"], 1.0);
+    }
 }
 
-#[test_case(false; "one_lamp")]
-#[test_case(true; "one_sol")]
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 #[serial]
-async fn withdraw(raise_minimum_delegation: bool) {
-    let env = setup(raise_minimum_delegation, true).await;
-    let stake_account =
-        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await;
-
+async fn position() {
+    let env = setup(false, true).await;
     wait_for_next_epoch(&env.rpc_client).await;
 
     let status = Command::new(SVSP_CLI)
         .args([
-            "deposit",
-            "-C",
-            &env.config_file_path,
-            &stake_account.to_string(),
-        ])
-        .status()
-        .unwrap();
-    assert!(status.success());
-
-    let status = Command::new(SVSP_CLI)
-        .args([
-            "withdraw",
+            "deposit-sol",
             "-C",
             &env.config_file_path,
             "--vote-account",
             &env.vote_account.to_string(),
-            "ALL",
+            "1sol",
         ])
         .status()
         .unwrap();
     assert!(status.success());
-}
-
-#[test_case(false; "one_lamp")]
-#[test_case(true; "one_sol")]
-#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-#[serial]
-async fn create_metadata(raise_minimum_delegation: bool) {
-    let env = setup(raise_minimum_delegation, false).await;
 
-    let status = Command::new(SVSP_CLI)
+    let output = Command::new(SVSP_CLI)
         .args([
-            "manage",
-            "initialize",
+            "position",
             "-C",
             &env.config_file_path,
-            "--skip-metadata",
+            "--vote-account",
             &env.vote_account.to_string(),
+            "--output",
+            "json",
         ])
-        .status()
+        .output()
         .unwrap();
-    assert!(status.success());
+    assert!(output.status.success());
 
-    let status = Command::new(SVSP_CLI)
+    // the deposit fee stays in the pool, so each token is worth slightly more than a lamport
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let position = &json["commandOutput"];
+    assert_eq!(position["ownerAddress"], env.payer.pubkey().to_string());
+    let token_balance = position["tokenBalance"].as_u64().unwrap();
+    assert!(token_balance > 0);
+    assert!(position["stakeValue"].as_u64().unwrap() >= token_balance);
+
+    // a wallet with no token account holds nothing, which is not an error
+    let output = Command::new(SVSP_CLI)
         .args([
-            "manage",
-            "create-token-metadata",
+            "position",
             "-C",
             &env.config_file_path,
             "--vote-account",
             &env.vote_account.to_string(),
+            "--owner",
+            &Pubkey::new_unique().to_string(),
+            "--output",
+            "json",
         ])
-        .status()
+        .output()
         .unwrap();
-    assert!(status.success());
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let position = &json["commandOutput"];
+    assert_eq!(position["tokenBalance"], 0);
+    assert_eq!(position["stakeValue"], 0);
 }
 
-#[test_case(false; "one_lamp")]
-#[test_case(true; "one_sol")]
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 #[serial]
-async fn update_metadata(raise_minimum_delegation: bool) {
-    let env = setup(raise_minimum_delegation, true).await;
+async fn associated_token_program_override() {
+    let env = setup(false, true).await;
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let pool_mint_address = find_pool_mint_address(&id(), &pool_address);
+
+    let position_token_account = |associated_token_program: Option<Pubkey>| {
+        let mut args = vec![
+            "position".to_string(),
+            "-C".to_string(),
+            env.config_file_path.clone(),
+            "--vote-account".to_string(),
+            env.vote_account.to_string(),
+            "--output".to_string(),
+            "json".to_string(),
+        ];
+        if let Some(associated_token_program) = associated_token_program {
+            args.push("--associated-token-program".to_string());
+            args.push(associated_token_program.to_string());
+        }
 
-    let status = Command::new(SVSP_CLI)
+        let output = Command::new(SVSP_CLI).args(args).output().unwrap();
+        assert!(output.status.success());
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        Pubkey::from_str(
+            json["commandOutput"]["tokenAccountAddress"]
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap()
+    };
+
+    // the canonical program is the default
+    assert_eq!(
+        position_token_account(None),
+        get_associated_token_address(&env.payer.pubkey(), &pool_mint_address),
+    );
+
+    // an override changes the derivation
+    let associated_token_program = Pubkey::new_unique();
+    let (expected_address, _) = Pubkey::find_program_address(
+        &[
+            env.payer.pubkey().as_ref(),
+            spl_token::id().as_ref(),
+            pool_mint_address.as_ref(),
+        ],
+        &associated_token_program,
+    );
+    assert_eq!(
+        position_token_account(Some(associated_token_program)),
+        expected_address,
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn pool_error_exit_codes() {
+    let env = setup(false, false).await;
+
+    // nonexistent pool
+    let output = Command::new(SVSP_CLI)
         .args([
-            "manage",
-            "update-token-metadata",
+            "position",
             "-C",
             &env.config_file_path,
-            "--vote-account",
-            &env.vote_account.to_string(),
-            "whatever",
-            "idk",
+            "--pool",
+            &Pubkey::new_unique().to_string(),
         ])
-        .status()
+        .output()
         .unwrap();
-    assert!(status.success());
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("has not been initialized"));
 
-    // testing this flag because the match is rather torturous
-    let status = Command::new(SVSP_CLI)
+    // account owned by another program
+    let output = Command::new(SVSP_CLI)
         .args([
-            "manage",
-            "update-token-metadata",
+            "position",
             "-C",
             &env.config_file_path,
-            "--vote-account",
-            &env.vote_account.to_string(),
-            "--authorized-withdrawer",
-            &env.keypair_file_path,
-            "something",
-            "new",
+            "--pool",
+            &env.payer.pubkey().to_string(),
         ])
-        .status()
+        .output()
         .unwrap();
-    assert!(status.success());
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not owned by the SVSP program"));
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 #[serial]
-async fn display() {
+async fn list_stake_accounts() {
     let env = setup(false, true).await;
 
-    let status = Command::new(SVSP_CLI)
+    let mut stake_accounts = vec![
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await,
+        create_and_delegate_stake_account(&env.rpc_client, &env.payer, &env.vote_account).await,
+    ];
+    stake_accounts.sort();
+
+    let output = Command::new(SVSP_CLI)
         .args([
-            "display",
+            "list-stake-accounts",
             "-C",
             &env.config_file_path,
             "--vote-account",
             &env.vote_account.to_string(),
+            "--output",
+            "json",
         ])
-        .status()
+        .output()
         .unwrap();
-    assert!(status.success());
+    assert!(output.status.success());
 
-    let status = Command::new(SVSP_CLI)
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let listed = json["commandOutput"]["stakeAccounts"].as_array().unwrap();
+    assert_eq!(listed.len(), 2);
+    for (listed, stake_account) in listed.iter().zip(stake_accounts) {
+        assert_eq!(listed["stakeAccountAddress"], stake_account.to_string());
+        assert_eq!(listed["status"], "activating");
+        assert_eq!(listed["stake"], LAMPORTS_PER_SOL);
+        assert_eq!(listed["depositable"], true);
+    }
+
+    // stake owned by another wallet is not listed
+    let output = Command::new(SVSP_CLI)
         .args([
-            "display",
+            "list-stake-accounts",
             "-C",
             &env.config_file_path,
             "--vote-account",
             &env.vote_account.to_string(),
-            "--verbose",
+            "--owner",
+            &Pubkey::new_unique().to_string(),
+            "--output",
+            "json",
         ])
-        .status()
+        .output()
         .unwrap();
-    assert!(status.success());
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["commandOutput"]["stakeAccounts"]
+        .as_array()
+        .unwrap()
+        .is_empty());
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 #[serial]
-async fn display_all() {
+async fn verify() {
     let env = setup(false, true).await;
 
-    create_pool(&env.rpc_client, &env.payer, &env.config_file_path).await;
-    create_pool(&env.rpc_client, &env.payer, &env.config_file_path).await;
-
     let output = Command::new(SVSP_CLI)
-        .args(["display", "-C", &env.config_file_path, "--all"])
+        .args([
+            "verify",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--output",
+            "json",
+        ])
         .output()
         .unwrap();
     assert!(output.status.success());
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let pools = stdout
-        .lines()
-        .filter(|line| line.starts_with("  Pool address:"))
-        .count();
-    assert_eq!(pools, 3);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let verify = &json["commandOutput"];
+    let checks = verify["checks"].as_array().unwrap();
+    assert!(!checks.is_empty());
+    for check in checks {
+        assert_eq!(check["passed"], true, "{}", check);
+    }
+    assert_eq!(verify["passed"], true);
 
+    // an address that is not a pool fails every check, but still reports
     let output = Command::new(SVSP_CLI)
-        .args(["display", "-C", &env.config_file_path, "--all", "--verbose"])
+        .args([
+            "verify",
+            "-C",
+            &env.config_file_path,
+            &Pubkey::new_unique().to_string(),
+            "--output",
+            "json",
+        ])
         .output()
         .unwrap();
     assert!(output.status.success());
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let pools = stdout
-        .lines()
-        .filter(|line| line.starts_with("  Pool address:"))
-        .count();
-    assert_eq!(pools, 3);
-
-    let stakes = stdout
-        .lines()
-        .filter(|line| line.starts_with("  Pool main stake account address:"))
-        .count();
-    assert_eq!(stakes, 3);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let verify = &json["commandOutput"];
+    assert_eq!(verify["passed"], false);
+    assert_eq!(verify["checks"][0]["passed"], false);
 }
 
 #[test_case(false; "one_lamp")]
@@ -506,6 +3701,48 @@ async fn display_all() {
 async fn create_onramp(raise_minimum_delegation: bool) {
     let env = setup(raise_minimum_delegation, false).await;
 
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "initialize",
+            "-C",
+            &env.config_file_path,
+            &env.vote_account.to_string(),
+            "--skip-onramp",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let onramp_address =
+        find_pool_onramp_address(&id(), &find_pool_address(&id(), &env.vote_account));
+    assert!(env
+        .rpc_client
+        .get_account_with_commitment(&onramp_address, CommitmentConfig::confirmed())
+        .await
+        .unwrap()
+        .value
+        .is_none());
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "create-on-ramp",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn replenish_create_onramp_if_missing() {
+    let env = setup(false, false).await;
+
     let onramp_opcode = borsh::to_vec(&SinglePoolInstruction::InitializePoolOnRamp).unwrap();
     let instructions = ixn::initialize(
         &id(),
@@ -530,14 +3767,48 @@ async fn create_onramp(raise_minimum_delegation: bool) {
         .await
         .unwrap();
 
+    // replenish fails without an onramp
     let status = Command::new(SVSP_CLI)
         .args([
             "manage",
-            "create-on-ramp",
+            "replenish-pool",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "replenish-pool",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--create-onramp-if-missing",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let pool_address = find_pool_address(&id(), &env.vote_account);
+    let onramp_address = find_pool_onramp_address(&id(), &pool_address);
+    assert!(env.rpc_client.get_account(&onramp_address).await.is_ok());
+
+    // and is a no-op once the onramp exists
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "manage",
+            "replenish-pool",
             "-C",
             &env.config_file_path,
             "--vote-account",
             &env.vote_account.to_string(),
+            "--create-onramp-if-missing",
         ])
         .status()
         .unwrap();
@@ -563,3 +3834,48 @@ async fn deposit_sol() {
     let status = Command::new(SVSP_CLI).args(&args).status().unwrap();
     assert!(status.success());
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn deposit_sol_no_wait() {
+    let env = setup(false, true).await;
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let output = Command::new(SVSP_CLI)
+        .args([
+            "deposit-sol",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+            "--no-wait",
+            "--output",
+            "json",
+            "1sol",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // the signature comes back right away, and the outcome is not reported
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let deposit = &json["commandOutput"];
+    assert!(deposit["tokenAmount"].is_null());
+    let signature = Signature::from_str(deposit["signature"].as_str().unwrap()).unwrap();
+
+    // confirming is up to the caller
+    let mut confirmed = false;
+    for _ in 0..50 {
+        if env
+            .rpc_client
+            .confirm_transaction(&signature)
+            .await
+            .unwrap()
+        {
+            confirmed = true;
+            break;
+        }
+        sleep(Duration::from_millis(200)).await;
+    }
+    assert!(confirmed);
+}
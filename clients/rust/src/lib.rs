@@ -0,0 +1,1020 @@
+#![deny(missing_docs)]
+
+//! An RPC client for reading single-validator stake pool state
+
+use {
+    futures::{stream, Stream, StreamExt, TryStreamExt},
+    solana_account::Account,
+    solana_account_decoder_client_types::{UiAccountEncoding, UiDataSliceConfig},
+    solana_client::{
+        nonblocking::rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::{Memcmp, RpcFilterType},
+        rpc_request::MAX_MULTIPLE_ACCOUNTS,
+    },
+    solana_clock::{Epoch, DEFAULT_MS_PER_SLOT},
+    solana_program_pack::Pack,
+    solana_pubkey::Pubkey,
+    solana_stake_interface::{
+        program as stake_program,
+        state::{Delegation, Meta, Stake, StakeStateV2},
+    },
+    solana_vote_interface::{program as vote_program, state::VoteStateV4},
+    spl_single_pool::{
+        apy::{estimate_apy, ApyParameters},
+        find_pool_mint_address, find_pool_onramp_address, find_pool_stake_address,
+        inline_spl_token_2022, minimum_pool_balance,
+        state::{SinglePool, SinglePoolAccountType},
+    },
+    spl_token_interface::{
+        self as spl_token,
+        state::{Account as TokenAccount, Mint},
+    },
+    std::sync::Arc,
+};
+
+pub mod stake;
+
+pub use {
+    spl_single_pool::{
+        calculate_burn_amount, calculate_deposit_amount, calculate_withdraw_amount,
+        PHANTOM_TOKEN_AMOUNT,
+    },
+    stake::StakeClient,
+};
+
+// offsets into a bincode-serialized `StakeStateV2::Stake`
+const STAKE_WITHDRAWER_OFFSET: usize = 44;
+const STAKE_VOTER_OFFSET: usize = 124;
+
+// offset into any serialized `VoteStateVersions`
+const VOTE_NODE_PUBKEY_OFFSET: usize = 4;
+
+/// Number of recently completed epochs of vote credits `get_estimated_apys()`
+/// averages validator performance over.
+pub const APY_EPOCH_WINDOW: usize = 10;
+
+// performance samples are taken about once a minute
+const PERFORMANCE_SAMPLE_COUNT: usize = 60;
+
+// matches the year length the inflation schedule is defined with
+const SECONDS_PER_YEAR: f64 = 365.242_199 * 24.0 * 60.0 * 60.0;
+
+/// Default number of `getMultipleAccounts` requests a client keeps in flight
+/// at once when fetching many accounts.
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Errors are boxed so callers can mix them freely with RPC client errors.
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Condensed state of a pool stake account.
+#[derive(Clone, Copy, Debug)]
+pub struct StakeSummary {
+    /// `stake.delegation.stake` if activating or effective, but not if inactive
+    pub stake: u64,
+    /// All non-rent lamports, including stake
+    pub usable_lamports: u64,
+    /// Initialized, deactivating, or deactivated
+    pub dedelegated: bool,
+    /// Whether the account exists at all
+    pub exists: bool,
+    /// Activation state of the delegation as of the current epoch
+    pub status: StakeStatus,
+}
+
+/// Activation state of a pool stake account. Warmup and cooldown are not
+/// modeled, so stake counts as active from the epoch after activation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StakeStatus {
+    /// Delegated this epoch
+    Activating,
+    /// Delegated in an earlier epoch
+    Active,
+    /// Deactivated this epoch
+    Deactivating,
+    /// Not delegated, deactivated in an earlier epoch, or nonexistent
+    Inactive,
+}
+
+impl StakeStatus {
+    /// Activation state of a delegation as of the given epoch.
+    pub fn from_delegation(delegation: &Delegation, current_epoch: Epoch) -> Self {
+        if delegation.deactivation_epoch == u64::MAX {
+            if delegation.activation_epoch < current_epoch {
+                Self::Active
+            } else {
+                Self::Activating
+            }
+        } else if delegation.deactivation_epoch >= current_epoch {
+            Self::Deactivating
+        } else {
+            Self::Inactive
+        }
+    }
+}
+
+impl StakeSummary {
+    /// Summarize a pool stake account, which may not exist.
+    pub fn from_account(
+        stake_account_address: &Pubkey,
+        stake_account: Option<&Account>,
+        rent_exempt_reserve: u64,
+        current_epoch: Epoch,
+    ) -> Result<Self, Error> {
+        let account = match stake_account {
+            Some(account) if !account.data.is_empty() => account,
+            // impossible for main stake, possible if onramp never created.
+            // we ignore lamports in an uninitialized onramp since we will never use them for math
+            _ => {
+                return Ok(Self {
+                    stake: 0,
+                    usable_lamports: 0,
+                    dedelegated: true,
+                    exists: false,
+                    status: StakeStatus::Inactive,
+                })
+            }
+        };
+
+        let stake_state = match bincode::deserialize::<StakeStateV2>(&account.data) {
+            Ok(StakeStateV2::RewardsPool) => {
+                return Err(format!(
+                    "Account {} is a rewards pool, not a pool stake account",
+                    stake_account_address
+                )
+                .into())
+            }
+            Ok(stake_state @ (StakeStateV2::Stake(..) | StakeStateV2::Initialized(_))) => {
+                stake_state
+            }
+            _ => return Err(format!("Invalid stake account {}", stake_account_address).into()),
+        };
+
+        // if this ever fails, multistake or another account change has landed.
+        // this function should be updated to use real stake account sizes.
+        // we may be fetching hundreds of accounts here so memoize the rents
+        if account.data.len() != StakeStateV2::size_of() {
+            return Err(format!(
+                "Stake account {} is {} bytes, expected {}",
+                stake_account_address,
+                account.data.len(),
+                StakeStateV2::size_of(),
+            )
+            .into());
+        }
+
+        match stake_state {
+            // typical stake state. either activating or effective can be "pool stake"
+            StakeStateV2::Stake(_, Stake { delegation, .. }, _) => {
+                let stake = if delegation.activation_epoch <= current_epoch
+                    && delegation.deactivation_epoch >= current_epoch
+                {
+                    delegation.stake
+                } else {
+                    0
+                };
+
+                Ok(Self {
+                    stake,
+                    usable_lamports: account.lamports.saturating_sub(rent_exempt_reserve),
+                    dedelegated: delegation.deactivation_epoch != u64::MAX,
+                    exists: true,
+                    status: StakeStatus::from_delegation(&delegation, current_epoch),
+                })
+            }
+            // impossible for main stake, routine for onramp
+            _ => Ok(Self {
+                stake: 0,
+                usable_lamports: account.lamports.saturating_sub(rent_exempt_reserve),
+                dedelegated: true,
+                exists: true,
+                status: StakeStatus::Inactive,
+            }),
+        }
+    }
+
+    /// Net asset value of a pool, given summaries of its main and on-ramp accounts.
+    pub fn nav(self, other: Self) -> u64 {
+        self.usable_lamports.saturating_add(other.usable_lamports)
+    }
+
+    /// Lamports across a pool's main and on-ramp accounts that are not delegated.
+    pub fn excess_lamports(self, other: Self) -> u64 {
+        self.usable_lamports
+            .saturating_add(other.usable_lamports)
+            .saturating_sub(self.stake)
+            .saturating_sub(other.stake)
+    }
+}
+
+/// Work `ReplenishPool` would do for a pool, judged from summaries of its main
+/// and on-ramp accounts. Like `StakeStatus`, this does not model warmup or
+/// cooldown, so it may report work the program would defer to a later epoch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReplenishPlan {
+    /// Redelegate main stake deactivated by a delinquent validator
+    pub reactivate: bool,
+    /// Move active on-ramp stake into the main account
+    pub move_stake: bool,
+    /// Move undelegated lamports from the main account to the on-ramp
+    pub move_lamports: bool,
+    /// Delegate undelegated on-ramp lamports
+    pub delegate_onramp: bool,
+}
+
+impl ReplenishPlan {
+    /// Plan a replenish, mirroring the program's checks. The on-ramp must exist.
+    pub fn new(main: StakeSummary, onramp: StakeSummary, minimum_delegation: u64) -> Self {
+        // stake and lamports can only move once the main account is fully active
+        if main.status != StakeStatus::Active {
+            return Self {
+                reactivate: matches!(
+                    main.status,
+                    StakeStatus::Deactivating | StakeStatus::Inactive
+                ),
+                ..Self::default()
+            };
+        }
+
+        let move_stake = onramp.status == StakeStatus::Active;
+        let main_excess_lamports = main.usable_lamports.saturating_sub(main.stake);
+
+        // on-ramp lamports left undelegated after both moves
+        let onramp_lamports = onramp
+            .usable_lamports
+            .saturating_sub(if move_stake { onramp.stake } else { 0 })
+            .saturating_add(main_excess_lamports);
+        let delegate_onramp = onramp_lamports >= minimum_delegation
+            && (onramp.status != StakeStatus::Activating || onramp_lamports > onramp.stake);
+
+        Self {
+            reactivate: false,
+            move_stake,
+            move_lamports: main_excess_lamports > 0,
+            delegate_onramp,
+        }
+    }
+
+    /// Whether a replenish would change nothing.
+    pub fn is_noop(self) -> bool {
+        self == Self::default()
+    }
+}
+
+/// The smallest quantity of stake that mints at least one token, the inverse of
+/// [`calculate_deposit_amount`]. A pool with no tokens prices at one to one.
+pub fn minimum_deposit_amount(token_supply: u64, net_asset_value: u64) -> u64 {
+    if net_asset_value > 0 && token_supply > 0 {
+        net_asset_value.div_ceil(token_supply)
+    } else {
+        1
+    }
+}
+
+/// Tokens minted for depositing a quantity of stake, given an exchange rate from
+/// [`SinglePoolClient::get_exchange_rate`]. Rounds down, exactly as the program
+/// does. A pool with no tokens prices at one to one.
+pub fn price_tokens_for_stake(exchange_rate: (u64, u64), stake_amount: u64) -> u64 {
+    let (net_asset_value, token_supply) = exchange_rate;
+    calculate_deposit_amount(token_supply, net_asset_value, stake_amount).unwrap_or(u64::MAX)
+}
+
+/// Stake received for burning a quantity of tokens, given an exchange rate from
+/// [`SinglePoolClient::get_exchange_rate`]. Rounds down, exactly as the program
+/// does. A pool with no tokens prices at one to one.
+///
+/// This is a price, not a promise: the minimum pool balance cannot be withdrawn,
+/// so compare against [`SinglePoolClient::get_available_stake`] before withdrawing.
+pub fn price_stake_for_tokens(exchange_rate: (u64, u64), token_amount: u64) -> u64 {
+    let (net_asset_value, token_supply) = exchange_rate;
+    if token_supply == 0 {
+        return token_amount;
+    }
+
+    calculate_withdraw_amount(token_supply, net_asset_value, token_amount).unwrap_or(u64::MAX)
+}
+
+/// Whether an address is a token program that a pool mint may belong to.
+pub fn is_token_program(address: &Pubkey) -> bool {
+    *address == spl_token::id() || *address == inline_spl_token_2022::id()
+}
+
+/// Typed access to single-validator stake pool accounts over RPC.
+#[derive(Clone)]
+pub struct SinglePoolClient {
+    rpc_client: Arc<RpcClient>,
+    stake_client: StakeClient,
+    max_concurrent_requests: usize,
+}
+
+impl SinglePoolClient {
+    /// Create a client that reads through the given RPC client, at its commitment.
+    pub fn new(rpc_client: Arc<RpcClient>) -> Self {
+        let stake_client = StakeClient::new(rpc_client.clone());
+        Self {
+            rpc_client,
+            stake_client,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+        }
+    }
+
+    /// Bound how many `getMultipleAccounts` requests are in flight at once when
+    /// fetching more accounts than fit in one request. Zero is treated as one.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests.max(1);
+        self
+    }
+
+    /// The underlying RPC client.
+    pub fn rpc_client(&self) -> &Arc<RpcClient> {
+        &self.rpc_client
+    }
+
+    /// A stake program client sharing the same RPC client.
+    pub fn stake_client(&self) -> &StakeClient {
+        &self.stake_client
+    }
+
+    /// Fetch an account, treating accounts with no data as nonexistent.
+    pub async fn get_initialized_account(&self, pubkey: Pubkey) -> Result<Option<Account>, Error> {
+        Ok(self
+            .rpc_client
+            .get_account_with_commitment(&pubkey, self.rpc_client.commitment())
+            .await?
+            .value
+            .filter(|account| !account.data.is_empty()))
+    }
+
+    /// The minimum lamports that must remain delegated in a pool.
+    pub async fn get_minimum_pool_balance(&self) -> Result<u64, Error> {
+        Ok(minimum_pool_balance(
+            self.stake_client.minimum_delegation().await?,
+        ))
+    }
+
+    /// Fetch and deserialize a pool account.
+    pub async fn get_pool(&self, pool_address: &Pubkey) -> Result<SinglePool, Error> {
+        match self.get_initialized_account(*pool_address).await? {
+            Some(account) if account.owner == spl_single_pool::id() => {
+                Ok(SinglePool::try_from_account(
+                    &account.owner,
+                    &account.data,
+                    &spl_single_pool::id(),
+                )?)
+            }
+            _ => Err(format!("Pool {} does not exist", pool_address).into()),
+        }
+    }
+
+    /// Stream every pool, in address order. Addresses are found up front without
+    /// any account data, then pools are fetched and decoded a page at a time, so
+    /// callers need not hold every pool in memory at once.
+    pub async fn get_all_pools(
+        &self,
+    ) -> Result<impl Stream<Item = Result<(Pubkey, SinglePool), Error>> + '_, Error> {
+        #[allow(deprecated)]
+        let accounts = self
+            .rpc_client
+            .get_program_accounts_with_config(
+                &spl_single_pool::id(),
+                RpcProgramAccountsConfig {
+                    filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                        0,
+                        vec![SinglePoolAccountType::Pool as u8],
+                    ))]),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        data_slice: Some(UiDataSliceConfig {
+                            offset: 0,
+                            length: 0,
+                        }),
+                        ..RpcAccountInfoConfig::default()
+                    },
+                    ..RpcProgramAccountsConfig::default()
+                },
+            )
+            .await?;
+
+        let mut pool_addresses = accounts
+            .into_iter()
+            .map(|(address, _)| address)
+            .collect::<Vec<_>>();
+
+        // rpc order is arbitrary
+        pool_addresses.sort_unstable();
+
+        let pages = pool_addresses
+            .chunks(MAX_MULTIPLE_ACCOUNTS)
+            .map(<[Pubkey]>::to_vec)
+            .collect::<Vec<_>>();
+
+        Ok(stream::iter(pages)
+            .then(move |page| self.get_pool_page(page))
+            .map_ok(|pools| stream::iter(pools.into_iter().map(Ok)))
+            .try_flatten())
+    }
+
+    /// Find every delegated stake account for a vote account with the given
+    /// withdraw authority, sorted by address. This includes stake accounts in
+    /// any activation state.
+    pub async fn get_delegated_stake_accounts(
+        &self,
+        vote_account_address: &Pubkey,
+        withdraw_authority: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Meta, Stake)>, Error> {
+        #[allow(deprecated)]
+        let accounts = self
+            .rpc_client
+            .get_program_accounts_with_config(
+                &stake_program::id(),
+                RpcProgramAccountsConfig {
+                    filters: Some(vec![
+                        RpcFilterType::DataSize(StakeStateV2::size_of() as u64),
+                        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                            0,
+                            2u32.to_le_bytes().to_vec(),
+                        )),
+                        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                            STAKE_WITHDRAWER_OFFSET,
+                            withdraw_authority.to_bytes().to_vec(),
+                        )),
+                        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                            STAKE_VOTER_OFFSET,
+                            vote_account_address.to_bytes().to_vec(),
+                        )),
+                    ]),
+                    ..RpcProgramAccountsConfig::default()
+                },
+            )
+            .await?;
+
+        let mut stake_accounts = vec![];
+        for (address, account) in accounts {
+            if let StakeStateV2::Stake(meta, stake, _) =
+                bincode::deserialize::<StakeStateV2>(&account.data)?
+            {
+                stake_accounts.push((address, meta, stake));
+            }
+        }
+
+        // rpc order is arbitrary
+        stake_accounts.sort_unstable_by_key(|(address, _, _)| *address);
+
+        Ok(stake_accounts)
+    }
+
+    /// Find every vote account whose node pubkey is the given validator
+    /// identity, sorted by address.
+    pub async fn get_vote_accounts_by_identity(
+        &self,
+        validator_identity: &Pubkey,
+    ) -> Result<Vec<Pubkey>, Error> {
+        #[allow(deprecated)]
+        let accounts = self
+            .rpc_client
+            .get_program_accounts_with_config(
+                &vote_program::id(),
+                RpcProgramAccountsConfig {
+                    filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                        VOTE_NODE_PUBKEY_OFFSET,
+                        validator_identity.to_bytes().to_vec(),
+                    ))]),
+                    // only the addresses are used, so fetch just the node pubkey
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        data_slice: Some(UiDataSliceConfig {
+                            offset: VOTE_NODE_PUBKEY_OFFSET,
+                            length: std::mem::size_of::<Pubkey>(),
+                        }),
+                        ..RpcAccountInfoConfig::default()
+                    },
+                    ..RpcProgramAccountsConfig::default()
+                },
+            )
+            .await?;
+
+        let mut vote_account_addresses = accounts
+            .into_iter()
+            .map(|(address, _)| address)
+            .collect::<Vec<_>>();
+
+        // rpc order is arbitrary
+        vote_account_addresses.sort_unstable();
+
+        Ok(vote_account_addresses)
+    }
+
+    /// Determine which token program owns a pool, from the owner of its mint.
+    pub async fn get_token_program(&self, pool_address: &Pubkey) -> Result<Pubkey, Error> {
+        let mint_address = find_pool_mint_address(&spl_single_pool::id(), pool_address);
+        match self.get_initialized_account(mint_address).await? {
+            Some(account) if is_token_program(&account.owner) => Ok(account.owner),
+            _ => Err(format!("Pool mint {} does not exist", mint_address).into()),
+        }
+    }
+
+    /// Fetch a token account for the given mint. Returns `None` if it does not
+    /// exist, and an error if it exists but is not a token account for the mint.
+    pub async fn get_token_info(
+        &self,
+        token_account_address: Pubkey,
+        mint_address: Pubkey,
+    ) -> Result<Option<TokenAccount>, Error> {
+        if let Some(account) = self.get_initialized_account(token_account_address).await? {
+            // token-2022 accounts may carry extensions after the base account layout
+            match account
+                .data
+                .get(..TokenAccount::LEN)
+                .map(TokenAccount::unpack)
+            {
+                Some(Ok(token_account))
+                    if is_token_program(&account.owner) && token_account.mint == mint_address =>
+                {
+                    Ok(Some(token_account))
+                }
+                _ => Err(format!("Invalid token account {}", token_account_address).into()),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Summarize many pool stake accounts, in as few RPC calls as possible.
+    pub async fn get_stake_summaries(
+        &self,
+        stake_account_addresses: &[Pubkey],
+        rent_exempt_reserve: u64,
+        current_epoch: Epoch,
+    ) -> Result<Vec<StakeSummary>, Error> {
+        let stake_accounts = self.get_multiple_accounts(stake_account_addresses).await?;
+
+        let mut summaries = vec![];
+        for (stake_account_address, stake_account) in
+            stake_account_addresses.iter().zip(&stake_accounts)
+        {
+            summaries.push(StakeSummary::from_account(
+                stake_account_address,
+                stake_account.as_ref(),
+                rent_exempt_reserve,
+                current_epoch,
+            )?);
+        }
+
+        Ok(summaries)
+    }
+
+    /// Fetch notional token supplies, including phantom tokens, for many mints,
+    /// in as few RPC calls as possible.
+    pub async fn get_token_supplies(&self, mint_addresses: &[Pubkey]) -> Result<Vec<u64>, Error> {
+        let mint_accounts = self.get_multiple_accounts(mint_addresses).await?;
+
+        let mut supplies = vec![];
+        for mint_account in &mint_accounts {
+            let supply = if let Some(account) = mint_account {
+                match Mint::unpack(&account.data) {
+                    Ok(mint) => mint.supply,
+                    _ => 0,
+                }
+            } else {
+                0
+            };
+
+            supplies.push(supply.saturating_add(PHANTOM_TOKEN_AMOUNT));
+        }
+
+        Ok(supplies)
+    }
+
+    /// Fetch the network-wide inputs to `estimate_apy()`. Slot time is measured
+    /// from recent performance samples, falling back to the default slot time.
+    pub async fn get_apy_parameters(&self) -> Result<ApyParameters, Error> {
+        let inflation_rate = self.rpc_client.get_inflation_rate().await?.validator;
+
+        let vote_accounts = self.rpc_client.get_vote_accounts().await?;
+        let total_stake = vote_accounts
+            .current
+            .iter()
+            .chain(vote_accounts.delinquent.iter())
+            .map(|vote_account| vote_account.activated_stake)
+            .fold(0, u64::saturating_add);
+        let total_supply = self.rpc_client.supply().await?.value.total;
+        let staked_ratio = total_stake as f64 / total_supply as f64;
+
+        let slots_per_epoch = self.rpc_client.get_epoch_info().await?.slots_in_epoch;
+
+        let samples = self
+            .rpc_client
+            .get_recent_performance_samples(Some(PERFORMANCE_SAMPLE_COUNT))
+            .await?;
+        let (sample_slots, sample_secs) =
+            samples.iter().fold((0u64, 0u64), |(slots, secs), sample| {
+                (
+                    slots.saturating_add(sample.num_slots),
+                    secs.saturating_add(u64::from(sample.sample_period_secs)),
+                )
+            });
+        let secs_per_slot = if sample_slots > 0 && sample_secs > 0 {
+            sample_secs as f64 / sample_slots as f64
+        } else {
+            DEFAULT_MS_PER_SLOT as f64 / 1000.0
+        };
+        let epochs_per_year = SECONDS_PER_YEAR / (secs_per_slot * slots_per_epoch as f64);
+
+        Ok(ApyParameters {
+            inflation_rate,
+            staked_ratio,
+            slots_per_epoch,
+            epochs_per_year,
+        })
+    }
+
+    /// Estimate the APY of many pools, given their vote accounts, from up to
+    /// `APY_EPOCH_WINDOW` recently completed epochs of vote credits. An estimate
+    /// is `None` if the vote account is missing or earned no credits in that
+    /// window. See `estimate_apy()` for the assumptions involved.
+    pub async fn get_estimated_apys(
+        &self,
+        vote_account_addresses: &[Pubkey],
+        parameters: &ApyParameters,
+        current_epoch: Epoch,
+    ) -> Result<Vec<Option<f64>>, Error> {
+        let vote_accounts = self.get_multiple_accounts(vote_account_addresses).await?;
+
+        let mut estimates = vec![];
+        for (vote_account_address, vote_account) in vote_account_addresses.iter().zip(vote_accounts)
+        {
+            let estimate = vote_account
+                .and_then(|account| {
+                    VoteStateV4::deserialize(&account.data, vote_account_address).ok()
+                })
+                .and_then(|vote_state| {
+                    let completed_epoch_credits = vote_state
+                        .epoch_credits
+                        .iter()
+                        .filter(|(epoch, _, _)| *epoch < current_epoch)
+                        .rev()
+                        .take(APY_EPOCH_WINDOW)
+                        .copied()
+                        .collect::<Vec<_>>();
+
+                    // `estimate_apy()` takes whole percents, so round to the nearest
+                    let commission = vote_state
+                        .inflation_rewards_commission_bps
+                        .saturating_add(50)
+                        / 100;
+
+                    estimate_apy(
+                        &completed_epoch_credits,
+                        u8::try_from(commission).unwrap_or(u8::MAX),
+                        parameters,
+                    )
+                });
+
+            estimates.push(estimate);
+        }
+
+        Ok(estimates)
+    }
+
+    /// The net asset value and notional token supply of a pool. Their ratio is
+    /// the lamport value of one token.
+    pub async fn get_exchange_rate(&self, pool_address: &Pubkey) -> Result<(u64, u64), Error> {
+        let (main_summary, onramp_summary) = self.get_pool_stake_summaries(pool_address).await?;
+
+        let mint_address = find_pool_mint_address(&spl_single_pool::id(), pool_address);
+        let token_supply = self.get_token_supplies(&[mint_address]).await?[0];
+
+        Ok((main_summary.nav(onramp_summary), token_supply))
+    }
+
+    /// Active stake that can currently be withdrawn from a pool. This is the
+    /// main account delegation, less the minimum balance that must stay behind.
+    pub async fn get_available_stake(&self, pool_address: &Pubkey) -> Result<u64, Error> {
+        let (main_summary, _) = self.get_pool_stake_summaries(pool_address).await?;
+        let minimum_pool_balance = self.get_minimum_pool_balance().await?;
+
+        Ok(main_summary.stake.saturating_sub(minimum_pool_balance))
+    }
+
+    // rpc caps the number of accounts per request, so fetch in chunks, a bounded
+    // number at a time. `buffered` yields results in order, so accounts line up with keys
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, Error> {
+        let chunks = stream::iter(pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS))
+            .map(|chunk| self.rpc_client.get_multiple_accounts(chunk))
+            .buffered(self.max_concurrent_requests)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
+    async fn get_pool_page(
+        &self,
+        pool_addresses: Vec<Pubkey>,
+    ) -> Result<Vec<(Pubkey, SinglePool)>, Error> {
+        let accounts = self
+            .rpc_client
+            .get_multiple_accounts(&pool_addresses)
+            .await?;
+
+        let mut pools = vec![];
+        for (pool_address, account) in pool_addresses.into_iter().zip(accounts) {
+            if let Some(account) = account {
+                let pool = SinglePool::try_from_account(
+                    &account.owner,
+                    &account.data,
+                    &spl_single_pool::id(),
+                )?;
+                pools.push((pool_address, pool));
+            }
+        }
+
+        Ok(pools)
+    }
+
+    async fn get_pool_stake_summaries(
+        &self,
+        pool_address: &Pubkey,
+    ) -> Result<(StakeSummary, StakeSummary), Error> {
+        let stake_rent_exempt_reserve = self
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())
+            .await?;
+        let current_epoch = self.rpc_client.get_epoch_info().await?.epoch;
+
+        let stake_addresses = [
+            find_pool_stake_address(&spl_single_pool::id(), pool_address),
+            find_pool_onramp_address(&spl_single_pool::id(), pool_address),
+        ];
+        let stake_summaries = self
+            .get_stake_summaries(&stake_addresses, stake_rent_exempt_reserve, current_epoch)
+            .await?;
+
+        Ok((stake_summaries[0], stake_summaries[1]))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::arithmetic_side_effects)]
+mod tests {
+    use {super::*, solana_stake_interface::stake_flags::StakeFlags};
+
+    // exchange rates as `(net_asset_value, token_supply)`, including phantom tokens
+    const EXCHANGE_RATES: [(u64, u64); 4] = [
+        (PHANTOM_TOKEN_AMOUNT, PHANTOM_TOKEN_AMOUNT),
+        (PHANTOM_TOKEN_AMOUNT * 3 / 2, PHANTOM_TOKEN_AMOUNT),
+        (7_654_321_987, 5_432_198_765),
+        (5_432_198_765, 7_654_321_987),
+    ];
+
+    #[test]
+    fn test_minimum_deposit_amount() {
+        for (net_asset_value, token_supply) in EXCHANGE_RATES {
+            let minimum = minimum_deposit_amount(token_supply, net_asset_value);
+
+            // the program mints for the minimum, but not for a lamport less
+            assert!(
+                spl_single_pool::calculate_deposit_amount(token_supply, net_asset_value, minimum)
+                    .unwrap()
+                    >= 1
+            );
+            assert_eq!(
+                spl_single_pool::calculate_deposit_amount(
+                    token_supply,
+                    net_asset_value,
+                    minimum - 1
+                ),
+                Some(0)
+            );
+        }
+
+        // an empty pool is one to one
+        assert_eq!(minimum_deposit_amount(0, 0), 1);
+    }
+
+    #[test]
+    fn test_price_matches_program_math() {
+        for (net_asset_value, token_supply) in EXCHANGE_RATES {
+            for amount in [1, 2, 999, PHANTOM_TOKEN_AMOUNT, 123_456_789_012] {
+                assert_eq!(
+                    price_tokens_for_stake((net_asset_value, token_supply), amount),
+                    spl_single_pool::calculate_deposit_amount(
+                        token_supply,
+                        net_asset_value,
+                        amount
+                    )
+                    .unwrap()
+                );
+                assert_eq!(
+                    price_stake_for_tokens((net_asset_value, token_supply), amount),
+                    spl_single_pool::calculate_withdraw_amount(
+                        token_supply,
+                        net_asset_value,
+                        amount
+                    )
+                    .unwrap()
+                );
+            }
+        }
+
+        // an empty pool is one to one
+        assert_eq!(price_tokens_for_stake((0, 0), 1000), 1000);
+        assert_eq!(price_stake_for_tokens((0, 0), 1000), 1000);
+        assert_eq!(price_tokens_for_stake((7500, 0), 1000), 1000);
+        assert_eq!(price_stake_for_tokens((7500, 0), 1000), 1000);
+
+        // results too large for a u64 saturate
+        assert_eq!(price_tokens_for_stake((1, u64::MAX), 2), u64::MAX);
+        assert_eq!(price_stake_for_tokens((u64::MAX, 1), 2), u64::MAX);
+    }
+
+    #[test]
+    fn test_stake_filter_offsets() {
+        let withdrawer = Pubkey::new_unique();
+        let voter = Pubkey::new_unique();
+
+        let mut meta = Meta::default();
+        meta.authorized.withdrawer = withdrawer;
+        let mut stake = Stake::default();
+        stake.delegation.voter_pubkey = voter;
+
+        let data =
+            bincode::serialize(&StakeStateV2::Stake(meta, stake, StakeFlags::empty())).unwrap();
+
+        assert_eq!(&data[..4], &2u32.to_le_bytes());
+        assert_eq!(
+            &data[STAKE_WITHDRAWER_OFFSET..STAKE_WITHDRAWER_OFFSET + 32],
+            withdrawer.as_ref()
+        );
+        assert_eq!(
+            &data[STAKE_VOTER_OFFSET..STAKE_VOTER_OFFSET + 32],
+            voter.as_ref()
+        );
+    }
+
+    #[test]
+    fn test_stake_summary_from_account() {
+        let stake_account_address = Pubkey::new_unique();
+        let rent_exempt_reserve = 1000;
+        let stake_account = |stake_state: &StakeStateV2| Account {
+            lamports: rent_exempt_reserve + 5000,
+            data: bincode::serialize(stake_state).unwrap(),
+            owner: stake_program::id(),
+            ..Account::default()
+        };
+
+        // a nonexistent account summarizes as empty
+        let summary =
+            StakeSummary::from_account(&stake_account_address, None, rent_exempt_reserve, 5)
+                .unwrap();
+        assert!(!summary.exists);
+
+        // a stake account that is not the canonical size is an error rather than a panic
+        let mut initialized_account = stake_account(&StakeStateV2::Initialized(Meta::default()));
+        let error = StakeSummary::from_account(
+            &stake_account_address,
+            Some(&initialized_account),
+            rent_exempt_reserve,
+            5,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("bytes"));
+
+        initialized_account.data.resize(StakeStateV2::size_of(), 0);
+        let summary = StakeSummary::from_account(
+            &stake_account_address,
+            Some(&initialized_account),
+            rent_exempt_reserve,
+            5,
+        )
+        .unwrap();
+        assert!(summary.exists);
+        assert_eq!(summary.usable_lamports, 5000);
+        assert_eq!(summary.status, StakeStatus::Inactive);
+
+        // a rewards pool is an error rather than a panic
+        let error = StakeSummary::from_account(
+            &stake_account_address,
+            Some(&stake_account(&StakeStateV2::RewardsPool)),
+            rent_exempt_reserve,
+            5,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("rewards pool"));
+
+        let error = StakeSummary::from_account(
+            &stake_account_address,
+            Some(&stake_account(&StakeStateV2::Uninitialized)),
+            rent_exempt_reserve,
+            5,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("Invalid stake account"));
+    }
+
+    #[test]
+    fn test_stake_status_from_delegation() {
+        let mut delegation = Delegation {
+            activation_epoch: 5,
+            ..Delegation::default()
+        };
+
+        assert_eq!(
+            StakeStatus::from_delegation(&delegation, 5),
+            StakeStatus::Activating
+        );
+        assert_eq!(
+            StakeStatus::from_delegation(&delegation, 6),
+            StakeStatus::Active
+        );
+
+        delegation.deactivation_epoch = 8;
+        assert_eq!(
+            StakeStatus::from_delegation(&delegation, 8),
+            StakeStatus::Deactivating
+        );
+        assert_eq!(
+            StakeStatus::from_delegation(&delegation, 9),
+            StakeStatus::Inactive
+        );
+    }
+
+    fn summary(status: StakeStatus, stake: u64, usable_lamports: u64) -> StakeSummary {
+        StakeSummary {
+            stake,
+            usable_lamports,
+            dedelegated: matches!(status, StakeStatus::Deactivating | StakeStatus::Inactive),
+            exists: true,
+            status,
+        }
+    }
+
+    #[test]
+    fn test_replenish_plan() {
+        let minimum_delegation = 1000;
+        let empty_onramp = summary(StakeStatus::Inactive, 0, 0);
+
+        // a settled pool has nothing to do
+        let main = summary(StakeStatus::Active, 5000, 5000);
+        assert!(ReplenishPlan::new(main, empty_onramp, minimum_delegation).is_noop());
+
+        // nothing moves until the main account is active
+        let main = summary(StakeStatus::Activating, 5000, 9000);
+        assert!(ReplenishPlan::new(main, empty_onramp, minimum_delegation).is_noop());
+
+        // deactivated main stake is reactivated, and nothing else
+        let main = summary(StakeStatus::Inactive, 0, 9000);
+        assert_eq!(
+            ReplenishPlan::new(main, empty_onramp, minimum_delegation),
+            ReplenishPlan {
+                reactivate: true,
+                ..ReplenishPlan::default()
+            }
+        );
+
+        // excess main lamports move to the on-ramp, which is delegated if they are enough
+        let main = summary(StakeStatus::Active, 5000, 6000);
+        assert_eq!(
+            ReplenishPlan::new(main, empty_onramp, minimum_delegation),
+            ReplenishPlan {
+                move_lamports: true,
+                delegate_onramp: true,
+                ..ReplenishPlan::default()
+            }
+        );
+        let main = summary(StakeStatus::Active, 5000, 5500);
+        assert_eq!(
+            ReplenishPlan::new(main, empty_onramp, minimum_delegation),
+            ReplenishPlan {
+                move_lamports: true,
+                ..ReplenishPlan::default()
+            }
+        );
+
+        // active on-ramp stake moves to the main account
+        let main = summary(StakeStatus::Active, 5000, 5000);
+        let onramp = summary(StakeStatus::Active, 2000, 2000);
+        assert_eq!(
+            ReplenishPlan::new(main, onramp, minimum_delegation),
+            ReplenishPlan {
+                move_stake: true,
+                ..ReplenishPlan::default()
+            }
+        );
+
+        // an activating on-ramp is only redelegated if it has gained lamports
+        let onramp = summary(StakeStatus::Activating, 2000, 2000);
+        assert!(ReplenishPlan::new(main, onramp, minimum_delegation).is_noop());
+        let onramp = summary(StakeStatus::Activating, 2000, 3000);
+        assert_eq!(
+            ReplenishPlan::new(main, onramp, minimum_delegation),
+            ReplenishPlan {
+                delegate_onramp: true,
+                ..ReplenishPlan::default()
+            }
+        );
+    }
+}
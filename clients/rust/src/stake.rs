@@ -0,0 +1,104 @@
+//! An RPC client for reading stake program state
+
+use {
+    crate::Error,
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_clock::Clock,
+    solana_instruction::Instruction,
+    solana_pubkey::Pubkey,
+    solana_rent::Rent,
+    solana_stake_interface::{self as stake, state::StakeStateV2},
+    solana_system_interface::instruction as system_instruction,
+    solana_sysvar as sysvar,
+    std::sync::Arc,
+};
+
+/// Reads stake accounts and stake program parameters over RPC.
+#[derive(Clone)]
+pub struct StakeClient {
+    rpc_client: Arc<RpcClient>,
+}
+
+impl StakeClient {
+    /// Create a client that reads through the given RPC client, at its commitment.
+    pub fn new(rpc_client: Arc<RpcClient>) -> Self {
+        Self { rpc_client }
+    }
+
+    /// Fetch and deserialize a stake account. Returns `None` if it does not exist.
+    pub async fn get_stake_state(
+        &self,
+        stake_account_address: &Pubkey,
+    ) -> Result<Option<StakeStateV2>, Error> {
+        let maybe_account = self
+            .rpc_client
+            .get_account_with_commitment(stake_account_address, self.rpc_client.commitment())
+            .await?
+            .value
+            .filter(|account| !account.data.is_empty());
+
+        match maybe_account {
+            Some(account) if account.owner == stake::program::id() => {
+                Ok(Some(bincode::deserialize::<StakeStateV2>(&account.data)?))
+            }
+            Some(_) => Err(format!(
+                "Account {} is not owned by the stake program",
+                stake_account_address
+            )
+            .into()),
+            None => Ok(None),
+        }
+    }
+
+    /// Fetch the rent sysvar.
+    pub async fn get_rent(&self) -> Result<Rent, Error> {
+        let rent_data = self
+            .rpc_client
+            .get_account_with_commitment(&sysvar::rent::id(), self.rpc_client.commitment())
+            .await?
+            .value
+            .ok_or("Rent sysvar not found")?;
+        let rent = bincode::deserialize::<Rent>(&rent_data.data)?;
+
+        Ok(rent)
+    }
+
+    /// Fetch the clock sysvar, eg to check whether a stake lockup is in force.
+    pub async fn get_clock(&self) -> Result<Clock, Error> {
+        let clock_data = self
+            .rpc_client
+            .get_account_with_commitment(&sysvar::clock::id(), self.rpc_client.commitment())
+            .await?
+            .value
+            .ok_or("Clock sysvar not found")?;
+        let clock = bincode::deserialize::<Clock>(&clock_data.data)?;
+
+        Ok(clock)
+    }
+
+    /// The stake program minimum delegation.
+    pub async fn minimum_delegation(&self) -> Result<u64, Error> {
+        Ok(self.rpc_client.get_stake_minimum_delegation().await?)
+    }
+
+    /// Build an instruction creating a rent-exempt stake account for the stake
+    /// program to initialize.
+    pub async fn create_uninitialized_stake_account_instruction(
+        &self,
+        payer: &Pubkey,
+        stake_account: &Pubkey,
+    ) -> Result<Instruction, Error> {
+        let rent_amount = self
+            .get_rent()
+            .await?
+            .minimum_balance(StakeStateV2::size_of());
+
+        Ok(system_instruction::create_account(
+            payer,
+            stake_account,
+            rent_amount,
+            StakeStateV2::size_of() as u64,
+            &stake::program::id(),
+        ))
+    }
+}
@@ -0,0 +1,134 @@
+//! Yield estimation from on-chain data
+
+use solana_clock::Epoch;
+
+/// Maximum vote credits a validator can earn per slot under timely vote credits.
+pub const MAX_VOTE_CREDITS_PER_SLOT: u64 = 16;
+
+/// Network-wide inputs to `estimate_apy()`. These are the same for every pool,
+/// so callers typically fetch them once and reuse them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ApyParameters {
+    /// Annual validator inflation rate, ie `getInflationRate().validator`.
+    pub inflation_rate: f64,
+    /// Fraction of the total supply that is actively staked, in `(0, 1]`.
+    pub staked_ratio: f64,
+    /// Number of slots in an epoch.
+    pub slots_per_epoch: u64,
+    /// Number of epochs in a year, derived from slot time and epoch length.
+    pub epochs_per_year: f64,
+}
+
+/// Estimate the annual percentage yield of a pool, as a fraction, from its
+/// vote account's recent epoch credits.
+///
+/// `epoch_credits` has the same layout as `VoteState::epoch_credits`, and
+/// should only contain completed epochs. Validator performance is taken to be
+/// the mean credits earned per epoch divided by the maximum possible, and the
+/// per-epoch reward rate is the validator inflation rate, scaled by performance
+/// and the vote account commission, spread across all active stake. The rate is
+/// compounded once per epoch, since pool rewards are restaked automatically.
+///
+/// Rewards are proportional to stake, so the result does not depend on the size
+/// of the pool. It also assumes the inflation rate, staked ratio, and validator
+/// performance stay constant for the year, so it is an estimate, not a promise.
+///
+/// Returns `None` if there are no epoch credits or the parameters are degenerate.
+pub fn estimate_apy(
+    epoch_credits: &[(Epoch, u64, u64)],
+    commission: u8,
+    parameters: &ApyParameters,
+) -> Option<f64> {
+    if epoch_credits.is_empty()
+        || commission > 100
+        || parameters.staked_ratio <= 0.0
+        || parameters.slots_per_epoch == 0
+        || parameters.epochs_per_year <= 0.0
+    {
+        return None;
+    }
+
+    let earned_credits: u64 = epoch_credits
+        .iter()
+        .map(|(_, credits, prev_credits)| credits.saturating_sub(*prev_credits))
+        .fold(0, u64::saturating_add);
+    let max_credits = (epoch_credits.len() as u64)
+        .saturating_mul(parameters.slots_per_epoch)
+        .saturating_mul(MAX_VOTE_CREDITS_PER_SLOT);
+    let performance = (earned_credits as f64 / max_credits as f64).min(1.0);
+
+    let staker_share = 1.0 - f64::from(commission) / 100.0;
+    let epoch_rate =
+        parameters.inflation_rate / parameters.staked_ratio * performance * staker_share
+            / parameters.epochs_per_year;
+
+    Some((1.0 + epoch_rate).powf(parameters.epochs_per_year) - 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, approx::assert_relative_eq};
+
+    const PARAMETERS: ApyParameters = ApyParameters {
+        inflation_rate: 0.05,
+        staked_ratio: 0.5,
+        slots_per_epoch: 1000,
+        epochs_per_year: 100.0,
+    };
+
+    #[test]
+    fn estimate_apy_perfect_validator() {
+        let epoch_credits = [(10, 16_000, 0), (11, 32_000, 16_000)];
+        let apy = estimate_apy(&epoch_credits, 0, &PARAMETERS).unwrap();
+
+        // 10% apr, compounded over 100 epochs
+        assert_relative_eq!(apy, 1.001_f64.powf(100.0) - 1.0);
+    }
+
+    #[test]
+    fn estimate_apy_performance_and_commission() {
+        let epoch_credits = [(10, 8_000, 0), (11, 16_000, 8_000)];
+        let apy = estimate_apy(&epoch_credits, 10, &PARAMETERS).unwrap();
+
+        // half performance and 10% commission leaves 4.5% apr
+        assert_relative_eq!(apy, 1.00045_f64.powf(100.0) - 1.0);
+    }
+
+    #[test]
+    fn estimate_apy_performance_capped() {
+        let capped = estimate_apy(&[(10, 20_000, 0)], 0, &PARAMETERS).unwrap();
+        let perfect = estimate_apy(&[(10, 16_000, 0)], 0, &PARAMETERS).unwrap();
+
+        assert_relative_eq!(capped, perfect);
+    }
+
+    #[test]
+    fn estimate_apy_degenerate() {
+        let epoch_credits = [(10, 16_000, 0)];
+
+        assert_eq!(estimate_apy(&[], 0, &PARAMETERS), None);
+        assert_eq!(estimate_apy(&epoch_credits, 101, &PARAMETERS), None);
+        assert_eq!(
+            estimate_apy(
+                &epoch_credits,
+                0,
+                &ApyParameters {
+                    staked_ratio: 0.0,
+                    ..PARAMETERS
+                }
+            ),
+            None
+        );
+        assert_eq!(
+            estimate_apy(
+                &epoch_credits,
+                0,
+                &ApyParameters {
+                    slots_per_epoch: 0,
+                    ..PARAMETERS
+                }
+            ),
+            None
+        );
+    }
+}
@@ -57,6 +57,8 @@ pub enum SinglePoolError {
     /// This typically means the value exists in the pool as activating stake,
     /// and an epoch is required for it to become available. Otherwise, it means
     /// active stake in the on-ramp must be moved via `ReplenishPool`.
+    /// `WithdrawStake` only returns this if the splittable stake is below the
+    /// minimum delegation; otherwise it withdraws as much as it can.
     #[error("WithdrawalTooLarge")]
     WithdrawalTooLarge,
     /// Required signature is missing.
@@ -113,6 +115,26 @@ pub enum SinglePoolError {
     /// The user-owned lamport source cannot be validated for `DepositSol`.
     #[error("InvalidDepositSolSource")]
     InvalidDepositSolSource,
+
+    // 25
+    /// Provided user token account is not a token account for the pool mint.
+    #[error("InvalidTokenAccountMint")]
+    InvalidTokenAccountMint,
+    /// Not enough undelegated lamports in the pool to service this withdrawal
+    /// as liquid sol. Withdraw stake instead, or wait for more deposits.
+    #[error("InsufficientLiquidity")]
+    InsufficientLiquidity,
+    /// `ReactivateAfterDelinquency` was called, but the pool stake account is not
+    /// deactivating or deactivated.
+    #[error("PoolStakeNotDeactivated")]
+    PoolStakeNotDeactivated,
+    /// Stake account to deposit has a lockup in force.
+    #[error("StakeLockupActive")]
+    StakeLockupActive,
+    /// Pool account has an account type this program version does not recognize,
+    /// ie it was written by a newer version of the program.
+    #[error("UnsupportedPoolVersion")]
+    UnsupportedPoolVersion,
 }
 impl From<SinglePoolError> for ProgramError {
     fn from(e: SinglePoolError) -> Self {
@@ -176,6 +198,39 @@ impl ToStr for SinglePoolError {
                     This can only occur if the Stake Program minimum delegation increases above 1 sol.",
             SinglePoolError::InvalidDepositSolSource =>
                 "Error: The user-owned lamport source cannot be validated for `DepositSol`.",
+            SinglePoolError::InvalidTokenAccountMint =>
+                "Error: Provided user token account is not a token account for the pool mint.",
+            SinglePoolError::InsufficientLiquidity =>
+                "Error: Not enough undelegated lamports in the pool to service this withdrawal \
+                    as liquid sol. Withdraw stake instead, or wait for more deposits.",
+            SinglePoolError::PoolStakeNotDeactivated =>
+                "Error: The pool stake account is not deactivating or deactivated, so there is nothing to reactivate.",
+            SinglePoolError::StakeLockupActive =>
+                "Error: Stake account to deposit has a lockup in force. Wait for it to expire before depositing.",
+            SinglePoolError::UnsupportedPoolVersion =>
+                "Error: Pool account has an account type this program version does not recognize. \
+                    It may have been created by a newer version of the program.",
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, num_traits::FromPrimitive};
+
+    #[test]
+    fn error_code_roundtrip() {
+        let mut code = 0;
+        while let Ok(error) = SinglePoolError::try_from(code) {
+            assert_eq!(
+                ProgramError::from(error.clone()),
+                ProgramError::Custom(code)
+            );
+            assert_eq!(SinglePoolError::from_u32(code), Some(error.clone()));
+            assert!(error.to_str().starts_with("Error: "));
+            code += 1;
+        }
+
+        assert_eq!(code, SinglePoolError::UnsupportedPoolVersion as u32 + 1);
+    }
+}
@@ -0,0 +1,13 @@
+//! Inline Token-2022 program id to avoid a direct dependency on `spl-token-2022-interface'.
+
+use {solana_instruction::Instruction, solana_pubkey::Pubkey};
+
+solana_pubkey::declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Point a token instruction at the given token program. Pool mints never use
+/// extensions, so every instruction the pool issues has the same layout under
+/// both programs, but the original builders reject any other program id.
+pub(crate) fn retarget(mut instruction: Instruction, token_program_id: &Pubkey) -> Instruction {
+    instruction.program_id = *token_program_id;
+    instruction
+}
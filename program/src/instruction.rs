@@ -4,13 +4,13 @@
 
 use {
     crate::{
-        find_pool_address, find_pool_mint_address, find_pool_mint_authority_address,
-        find_pool_mpl_authority_address, find_pool_onramp_address, find_pool_stake_address,
-        find_pool_stake_authority_address,
+        find_pool_address, find_pool_mint_authority_address, find_pool_stake_authority_address,
         inline_mpl_token_metadata::{self, pda::find_metadata_account},
+        inline_spl_token_2022,
         state::SinglePool,
+        PoolAddresses,
     },
-    borsh::{BorshDeserialize, BorshSerialize},
+    borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
     solana_instruction::{AccountMeta, Instruction},
     solana_program_pack::Pack,
     solana_pubkey::Pubkey,
@@ -22,12 +22,15 @@ use {
 
 /// Instructions supported by the `SinglePool` program.
 #[repr(C)]
-#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
 pub enum SinglePoolInstruction {
     ///   Initialize the mint and main stake account for a new single-validator
     ///   stake pool. The pool stake account must contain the rent-exempt
     ///   minimum plus the minimum balance of 1 sol. No tokens will be minted;
     ///   to deposit more, use `Deposit` after `InitializeStake` and `InitializePoolOnRamp`.
+    ///   The mint is created under the token program provided, which may be either
+    ///   the original token program or Token-2022. Every later instruction that
+    ///   touches the mint must provide the same program.
     ///
     ///   0. `[]` Validator vote account
     ///   1. `[w]` Pool account
@@ -38,7 +41,8 @@ pub enum SinglePoolInstruction {
     ///   6. `[]` Rent sysvar
     ///   7. `[]` Clock sysvar
     ///   8. `[]` Stake history sysvar
-    ///   9. `[]` Stake config sysvar
+    ///   9. `[]` Stake config sysvar (deprecated and optional; if omitted, later
+    ///      accounts move up one place)
     ///  10. `[]` System program
     ///  11. `[]` Token program
     ///  12. `[]` Stake program
@@ -73,7 +77,8 @@ pub enum SinglePoolInstruction {
     ///   4. `[]` Pool stake authority
     ///   5. `[]` Clock sysvar
     ///   6. `[]` Stake history sysvar
-    ///   7. `[]` Stake config sysvar
+    ///   7. `[]` Stake config sysvar (deprecated and optional; if omitted, later
+    ///      accounts move up one place)
     ///   8. `[]` Stake program
     ReplenishPool,
 
@@ -183,7 +188,8 @@ pub enum SinglePoolInstruction {
     ///   8. `[w]` User account to receive pool tokens
     ///   9. `[]` Clock sysvar
     ///  10. `[]` Stake history sysvar
-    ///  11. `[]` Stake config sysvar
+    ///  11. `[]` Stake config sysvar (deprecated and optional; if omitted, later
+    ///      accounts move up one place)
     ///  12. `[]` System program
     ///  13. `[]` Token program
     ///  14. `[]` Stake program
@@ -192,9 +198,133 @@ pub enum SinglePoolInstruction {
         /// Amount of sol to deposit
         lamports: u64,
     },
+
+    ///   Redeem pool tokens for liquid sol, paid from lamports in the pool
+    ///   stake accounts that are not delegated. Tokens are converted at the
+    ///   current ratio, less a fee of `WITHDRAW_SOL_FEE_BPS` which stays in the
+    ///   pool, so that exiting without a cooldown is never free at the expense
+    ///   of other holders. The on-ramp is drawn from first, then the main
+    ///   account, which always retains the minimum pool balance. Fails with
+    ///   `InsufficientLiquidity` if too few lamports are free; use
+    ///   `WithdrawStake` instead in that case.
+    ///   The pool mint authority must be approved to burn the tokens.
+    ///
+    ///   0. `[]` Pool account
+    ///   1. `[w]` Pool stake account
+    ///   2. `[w]` Pool on-ramp account
+    ///   3. `[w]` Pool token mint
+    ///   4. `[]` Pool stake authority
+    ///   5. `[]` Pool mint authority
+    ///   6. `[w]` User account with pool tokens to burn from
+    ///   7. `[w]` User account to receive lamports
+    ///   8. `[]` Clock sysvar
+    ///   9. `[]` Stake history sysvar
+    ///  10. `[]` Token program
+    ///  11. `[]` Stake program
+    WithdrawSol {
+        /// Amount of tokens to redeem for lamports
+        token_amount: u64,
+    },
+
+    ///   Redelegate the main pool stake account after it was deactivated, eg
+    ///   by `DeactivateDelinquent`. This is the same reactivation `ReplenishPool`
+    ///   performs, without moving stake or lamports between the pool accounts.
+    ///   Unlike `ReplenishPool`, it fails with `PoolStakeNotDeactivated` rather
+    ///   than doing nothing if the pool stake account does not need it.
+    ///
+    ///   0. `[]` Validator vote account
+    ///   1. `[]` Pool account
+    ///   2. `[w]` Pool stake account
+    ///   3. `[]` Pool stake authority
+    ///   4. `[]` Clock sysvar
+    ///   5. `[]` Stake history sysvar
+    ///   6. `[]` Stake config sysvar (deprecated and optional; if omitted, later
+    ///      accounts move up one place)
+    ///   7. `[]` Stake program
+    ReactivateAfterDelinquency,
+
+    ///   Return the program version, so a deployment can be identified without
+    ///   relying on its behavior. The version is set as return data, encoded as
+    ///   described by `ProgramVersion::to_bytes`. Takes no accounts, and is
+    ///   intended to be simulated rather than executed.
+    GetVersion,
+}
+
+impl SinglePoolInstruction {
+    /// One of every instruction, in discriminant order, with default field
+    /// values. For tests and tooling that need to enumerate the instruction set.
+    pub fn all_variants() -> Vec<Self> {
+        vec![
+            Self::InitializePool,
+            Self::ReplenishPool,
+            Self::DepositStake,
+            Self::WithdrawStake {
+                user_stake_authority: Pubkey::default(),
+                token_amount: 0,
+            },
+            Self::CreateTokenMetadata,
+            Self::UpdateTokenMetadata {
+                name: String::new(),
+                symbol: String::new(),
+                uri: String::new(),
+            },
+            Self::InitializePoolOnRamp,
+            Self::DepositSol { lamports: 0 },
+            Self::WithdrawSol { token_amount: 0 },
+            Self::ReactivateAfterDelinquency,
+            Self::GetVersion,
+        ]
+    }
+}
+
+/// Program and sysvar addresses that instructions pass alongside the pool's
+/// own accounts. `Default` gives the canonical addresses for a pool whose mint
+/// is owned by the original token program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExternalAddresses {
+    /// The token program that owns the pool mint.
+    pub token_program: Pubkey,
+    /// The stake program.
+    pub stake_program: Pubkey,
+    /// The system program.
+    pub system_program: Pubkey,
+    /// The Metaplex token metadata program.
+    pub token_metadata_program: Pubkey,
+    /// The rent sysvar.
+    pub rent_sysvar: Pubkey,
+    /// The clock sysvar.
+    pub clock_sysvar: Pubkey,
+    /// The stake history sysvar.
+    pub stake_history_sysvar: Pubkey,
+    /// The deprecated stake config account. Not passed when built with
+    /// `omit-stake-config`.
+    pub stake_config: Pubkey,
+}
+impl Default for ExternalAddresses {
+    fn default() -> Self {
+        Self::with_token_program(&spl_token::id())
+    }
+}
+impl ExternalAddresses {
+    /// Canonical addresses for a pool whose mint is owned by `token_program_id`.
+    pub fn with_token_program(token_program_id: &Pubkey) -> Self {
+        Self {
+            token_program: *token_program_id,
+            stake_program: stake::program::id(),
+            system_program: system_program::id(),
+            token_metadata_program: inline_mpl_token_metadata::id(),
+            rent_sysvar: sysvar::rent::id(),
+            clock_sysvar: sysvar::clock::id(),
+            stake_history_sysvar: stake_history::id(),
+            #[allow(deprecated)]
+            stake_config: stake::config::id(),
+        }
+    }
 }
 
 /// Creates all necessary instructions to initialize the stake pool.
+/// The pool mint is owned by the original token program; see
+/// `initialize_with_token_program()` for Token-2022 pools.
 pub fn initialize(
     program_id: &Pubkey,
     vote_account_address: &Pubkey,
@@ -202,56 +332,119 @@ pub fn initialize(
     rent: &Rent,
     minimum_pool_balance: u64,
 ) -> Vec<Instruction> {
-    let pool_address = find_pool_address(program_id, vote_account_address);
+    initialize_with_token_program(
+        program_id,
+        &spl_token::id(),
+        vote_account_address,
+        payer,
+        rent,
+        minimum_pool_balance,
+    )
+}
+
+/// Creates all necessary instructions to initialize a stake pool whose mint is
+/// created under `token_program_id`, either the original token program or
+/// Token-2022.
+pub fn initialize_with_token_program(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    payer: &Pubkey,
+    rent: &Rent,
+    minimum_pool_balance: u64,
+) -> Vec<Instruction> {
+    let pool_addresses = PoolAddresses::derive(program_id, vote_account_address);
     let pool_rent = rent.minimum_balance(SinglePool::size_of());
 
-    let stake_address = find_pool_stake_address(program_id, &pool_address);
-    let onramp_address = find_pool_onramp_address(program_id, &pool_address);
     let stake_space = stake::state::StakeStateV2::size_of();
     let stake_rent = rent.minimum_balance(stake_space);
     let stake_rent_plus_minimum = stake_rent.saturating_add(minimum_pool_balance);
 
-    let mint_address = find_pool_mint_address(program_id, &pool_address);
     let mint_rent = rent.minimum_balance(spl_token::state::Mint::LEN);
 
+    let mut instructions = vec![
+        system_instruction::transfer(payer, &pool_addresses.pool, pool_rent),
+        system_instruction::transfer(payer, &pool_addresses.stake, stake_rent_plus_minimum),
+        system_instruction::transfer(payer, &pool_addresses.onramp, stake_rent),
+        system_instruction::transfer(payer, &pool_addresses.mint, mint_rent),
+    ];
+    instructions.extend(initialize_without_funding(
+        program_id,
+        token_program_id,
+        vote_account_address,
+        payer,
+    ));
+
+    instructions
+}
+
+/// Creates the instructions to initialize a stake pool whose accounts the
+/// caller has already funded, for composing pool creation into a larger
+/// transaction. The pool, pool stake, on-ramp, and mint accounts must hold
+/// the same lamports `initialize()` would transfer, or `InitializePool` fails
+/// with `WrongRentAmount`. `payer` only pays for token metadata.
+pub fn initialize_without_funding(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    payer: &Pubkey,
+) -> Vec<Instruction> {
+    let pool_address = PoolAddresses::derive(program_id, vote_account_address).pool;
+
     vec![
-        system_instruction::transfer(payer, &pool_address, pool_rent),
-        system_instruction::transfer(payer, &stake_address, stake_rent_plus_minimum),
-        system_instruction::transfer(payer, &onramp_address, stake_rent),
-        system_instruction::transfer(payer, &mint_address, mint_rent),
-        initialize_pool(program_id, vote_account_address),
+        initialize_pool_with_token_program(program_id, token_program_id, vote_account_address),
         initialize_pool_onramp(program_id, &pool_address),
         create_token_metadata(program_id, &pool_address, payer),
     ]
 }
 
 /// Creates an `InitializePool` instruction.
+/// The pool mint is owned by the original token program; see
+/// `initialize_pool_with_token_program()` for Token-2022 pools.
 pub fn initialize_pool(program_id: &Pubkey, vote_account_address: &Pubkey) -> Instruction {
-    let pool_address = find_pool_address(program_id, vote_account_address);
-    let mint_address = find_pool_mint_address(program_id, &pool_address);
+    initialize_pool_with_token_program(program_id, &spl_token::id(), vote_account_address)
+}
 
+/// Creates an `InitializePool` instruction for a pool whose mint is created
+/// under `token_program_id`.
+pub fn initialize_pool_with_token_program(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+) -> Instruction {
+    initialize_pool_with_accounts(
+        program_id,
+        vote_account_address,
+        &PoolAddresses::derive(program_id, vote_account_address),
+        &ExternalAddresses::with_token_program(token_program_id),
+    )
+}
+
+/// Creates an `InitializePool` instruction with the given pool and external
+/// addresses rather than deriving them, eg to test a program deployed at
+/// another address or to pass deliberately wrong accounts.
+pub fn initialize_pool_with_accounts(
+    program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    pool_addresses: &PoolAddresses,
+    external_addresses: &ExternalAddresses,
+) -> Instruction {
     let data = borsh::to_vec(&SinglePoolInstruction::InitializePool).unwrap();
     let accounts = vec![
         AccountMeta::new_readonly(*vote_account_address, false),
-        AccountMeta::new(pool_address, false),
-        AccountMeta::new(find_pool_stake_address(program_id, &pool_address), false),
-        AccountMeta::new(mint_address, false),
-        AccountMeta::new_readonly(
-            find_pool_stake_authority_address(program_id, &pool_address),
-            false,
-        ),
-        AccountMeta::new_readonly(
-            find_pool_mint_authority_address(program_id, &pool_address),
-            false,
-        ),
-        AccountMeta::new_readonly(sysvar::rent::id(), false),
-        AccountMeta::new_readonly(sysvar::clock::id(), false),
-        AccountMeta::new_readonly(stake_history::id(), false),
-        #[allow(deprecated)]
-        AccountMeta::new_readonly(stake::config::id(), false),
-        AccountMeta::new_readonly(system_program::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(stake::program::id(), false),
+        AccountMeta::new(pool_addresses.pool, false),
+        AccountMeta::new(pool_addresses.stake, false),
+        AccountMeta::new(pool_addresses.mint, false),
+        AccountMeta::new_readonly(pool_addresses.stake_authority, false),
+        AccountMeta::new_readonly(pool_addresses.mint_authority, false),
+        AccountMeta::new_readonly(external_addresses.rent_sysvar, false),
+        AccountMeta::new_readonly(external_addresses.clock_sysvar, false),
+        AccountMeta::new_readonly(external_addresses.stake_history_sysvar, false),
+        #[cfg(not(feature = "omit-stake-config"))]
+        AccountMeta::new_readonly(external_addresses.stake_config, false),
+        AccountMeta::new_readonly(external_addresses.system_program, false),
+        AccountMeta::new_readonly(external_addresses.token_program, false),
+        AccountMeta::new_readonly(external_addresses.stake_program, false),
     ];
 
     Instruction {
@@ -263,23 +456,34 @@ pub fn initialize_pool(program_id: &Pubkey, vote_account_address: &Pubkey) -> In
 
 /// Creates a `ReplenishPool` instruction.
 pub fn replenish_pool(program_id: &Pubkey, vote_account_address: &Pubkey) -> Instruction {
-    let pool_address = find_pool_address(program_id, vote_account_address);
+    replenish_pool_with_accounts(
+        program_id,
+        vote_account_address,
+        &PoolAddresses::derive(program_id, vote_account_address),
+        &ExternalAddresses::default(),
+    )
+}
 
+/// Creates a `ReplenishPool` instruction with the given pool and external
+/// addresses rather than deriving them. See `initialize_pool_with_accounts()`.
+pub fn replenish_pool_with_accounts(
+    program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    pool_addresses: &PoolAddresses,
+    external_addresses: &ExternalAddresses,
+) -> Instruction {
     let data = borsh::to_vec(&SinglePoolInstruction::ReplenishPool).unwrap();
     let accounts = vec![
         AccountMeta::new_readonly(*vote_account_address, false),
-        AccountMeta::new_readonly(pool_address, false),
-        AccountMeta::new(find_pool_stake_address(program_id, &pool_address), false),
-        AccountMeta::new(find_pool_onramp_address(program_id, &pool_address), false),
-        AccountMeta::new_readonly(
-            find_pool_stake_authority_address(program_id, &pool_address),
-            false,
-        ),
-        AccountMeta::new_readonly(sysvar::clock::id(), false),
-        AccountMeta::new_readonly(stake_history::id(), false),
-        #[allow(deprecated)]
-        AccountMeta::new_readonly(stake::config::id(), false),
-        AccountMeta::new_readonly(stake::program::id(), false),
+        AccountMeta::new_readonly(pool_addresses.pool, false),
+        AccountMeta::new(pool_addresses.stake, false),
+        AccountMeta::new(pool_addresses.onramp, false),
+        AccountMeta::new_readonly(pool_addresses.stake_authority, false),
+        AccountMeta::new_readonly(external_addresses.clock_sysvar, false),
+        AccountMeta::new_readonly(external_addresses.stake_history_sysvar, false),
+        #[cfg(not(feature = "omit-stake-config"))]
+        AccountMeta::new_readonly(external_addresses.stake_config, false),
+        AccountMeta::new_readonly(external_addresses.stake_program, false),
     ];
 
     Instruction {
@@ -290,6 +494,8 @@ pub fn replenish_pool(program_id: &Pubkey, vote_account_address: &Pubkey) -> Ins
 }
 
 /// Creates all necessary instructions to deposit stake.
+/// The pool mint is owned by the original token program; see
+/// `deposit_with_token_program()` for Token-2022 pools.
 pub fn deposit(
     program_id: &Pubkey,
     pool_address: &Pubkey,
@@ -297,13 +503,60 @@ pub fn deposit(
     user_token_account: &Pubkey,
     user_lamport_account: &Pubkey,
     user_withdraw_authority: &Pubkey,
+) -> Vec<Instruction> {
+    deposit_with_token_program(
+        program_id,
+        &spl_token::id(),
+        pool_address,
+        user_stake_account,
+        user_token_account,
+        user_lamport_account,
+        user_withdraw_authority,
+    )
+}
+
+/// Creates all necessary instructions to deposit stake into a pool whose mint
+/// is owned by `token_program_id`.
+pub fn deposit_with_token_program(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_stake_account: &Pubkey,
+    user_token_account: &Pubkey,
+    user_lamport_account: &Pubkey,
+    user_withdraw_authority: &Pubkey,
+) -> Vec<Instruction> {
+    deposit_with_stake_authority(
+        program_id,
+        token_program_id,
+        pool_address,
+        user_stake_account,
+        user_token_account,
+        user_lamport_account,
+        user_withdraw_authority,
+        user_withdraw_authority,
+    )
+}
+
+/// Creates all necessary instructions to deposit stake from an account whose
+/// stake authority differs from its withdraw authority. Each authority signs
+/// the instruction reassigning its own role to the pool.
+pub fn deposit_with_stake_authority(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_stake_account: &Pubkey,
+    user_token_account: &Pubkey,
+    user_lamport_account: &Pubkey,
+    user_stake_authority: &Pubkey,
+    user_withdraw_authority: &Pubkey,
 ) -> Vec<Instruction> {
     let pool_stake_authority = find_pool_stake_authority_address(program_id, pool_address);
 
     vec![
         stake::instruction::authorize(
             user_stake_account,
-            user_withdraw_authority,
+            user_stake_authority,
             &pool_stake_authority,
             stake::state::StakeAuthorize::Staker,
             None,
@@ -315,8 +568,9 @@ pub fn deposit(
             stake::state::StakeAuthorize::Withdrawer,
             None,
         ),
-        deposit_stake(
+        deposit_stake_with_token_program(
             program_id,
+            token_program_id,
             pool_address,
             user_stake_account,
             user_token_account,
@@ -325,36 +579,189 @@ pub fn deposit(
     ]
 }
 
+/// Maximum number of deposits whose `deposit_batch()` output fits in a single
+/// legacy transaction. This assumes every deposit has a distinct stake account,
+/// token account, lamport recipient, and withdraw authority, and that the fee
+/// payer is a separate signer.
+pub const MAX_DEPOSIT_BATCH_SIZE: usize = 2;
+
+/// Accounts for a single user deposit within `deposit_batch()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchDeposit {
+    /// User stake account to join to the pool
+    pub user_stake_account: Pubkey,
+    /// User account to receive pool tokens
+    pub user_token_account: Pubkey,
+    /// User account to receive lamports
+    pub user_lamport_account: Pubkey,
+    /// Current withdraw authority on the user stake account
+    pub user_withdraw_authority: Pubkey,
+}
+
+/// Creates all necessary instructions to deposit several independent stake
+/// accounts into one pool. This allows a relayer to pay fees for multiple
+/// users in one transaction. Callers are responsible for chunking deposits
+/// so the resulting transaction fits; see `MAX_DEPOSIT_BATCH_SIZE`.
+/// The pool mint is owned by the original token program; see
+/// `deposit_batch_with_token_program()` for Token-2022 pools.
+pub fn deposit_batch(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+    deposits: &[BatchDeposit],
+) -> Vec<Instruction> {
+    deposit_batch_with_token_program(program_id, &spl_token::id(), pool_address, deposits)
+}
+
+/// Creates all necessary instructions to deposit several independent stake
+/// accounts into a pool whose mint is owned by `token_program_id`.
+pub fn deposit_batch_with_token_program(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_address: &Pubkey,
+    deposits: &[BatchDeposit],
+) -> Vec<Instruction> {
+    deposits
+        .iter()
+        .flat_map(|deposit_accounts| {
+            deposit_with_token_program(
+                program_id,
+                token_program_id,
+                pool_address,
+                &deposit_accounts.user_stake_account,
+                &deposit_accounts.user_token_account,
+                &deposit_accounts.user_lamport_account,
+                &deposit_accounts.user_withdraw_authority,
+            )
+        })
+        .collect()
+}
+
+/// Instructions for the two phases of depositing stake from a new stake
+/// account, as returned by `create_stake_and_prepare_deposit()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StakeDepositInstructions {
+    /// Creates the user stake account and delegates it to the pool's
+    /// validator. The stake account keypair must sign
+    pub create_and_delegate: Vec<Instruction>,
+    /// Deposits the stake account into the pool. These must be sent once the
+    /// new stake is in the same activation state as the pool stake: for an
+    /// active pool, in the epoch after `create_and_delegate` lands at the
+    /// earliest
+    pub deposit: Vec<Instruction>,
+}
+
+/// Creates all necessary instructions to stake `stake_amount` lamports from a
+/// wallet and deposit the resulting stake account into the pool for
+/// `vote_account_address`. The wallet funds the stake account, is its stake
+/// and withdraw authority, receives the pool tokens in `user_token_account`,
+/// and is refunded the stake account rent on deposit. The two groups must be
+/// sent in separate transactions, usually across an epoch boundary.
+pub fn create_stake_and_prepare_deposit(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    user_wallet: &Pubkey,
+    user_stake_account: &Pubkey,
+    user_token_account: &Pubkey,
+    rent: &Rent,
+    stake_amount: u64,
+) -> StakeDepositInstructions {
+    let pool_address = find_pool_address(program_id, vote_account_address);
+    let stake_rent = rent.minimum_balance(stake::state::StakeStateV2::size_of());
+
+    let mut create_and_delegate = stake::instruction::create_account(
+        user_wallet,
+        user_stake_account,
+        &stake::state::Authorized::auto(user_wallet),
+        &stake::state::Lockup::default(),
+        stake_rent.saturating_add(stake_amount),
+    );
+    create_and_delegate.push(stake::instruction::delegate_stake(
+        user_stake_account,
+        user_wallet,
+        vote_account_address,
+    ));
+
+    StakeDepositInstructions {
+        create_and_delegate,
+        deposit: deposit_with_token_program(
+            program_id,
+            token_program_id,
+            &pool_address,
+            user_stake_account,
+            user_token_account,
+            user_wallet,
+            user_wallet,
+        ),
+    }
+}
+
 /// Creates a `DepositStake` instruction.
+/// The pool mint is owned by the original token program; see
+/// `deposit_stake_with_token_program()` for Token-2022 pools.
 pub fn deposit_stake(
     program_id: &Pubkey,
     pool_address: &Pubkey,
     user_stake_account: &Pubkey,
     user_token_account: &Pubkey,
     user_lamport_account: &Pubkey,
+) -> Instruction {
+    deposit_stake_with_token_program(
+        program_id,
+        &spl_token::id(),
+        pool_address,
+        user_stake_account,
+        user_token_account,
+        user_lamport_account,
+    )
+}
+
+/// Creates a `DepositStake` instruction for a pool whose mint is owned by
+/// `token_program_id`.
+pub fn deposit_stake_with_token_program(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_stake_account: &Pubkey,
+    user_token_account: &Pubkey,
+    user_lamport_account: &Pubkey,
+) -> Instruction {
+    deposit_stake_with_accounts(
+        program_id,
+        &PoolAddresses::derive_from_pool(program_id, pool_address),
+        &ExternalAddresses::with_token_program(token_program_id),
+        user_stake_account,
+        user_token_account,
+        user_lamport_account,
+    )
+}
+
+/// Creates a `DepositStake` instruction with the given pool and external
+/// addresses rather than deriving them. See `initialize_pool_with_accounts()`.
+pub fn deposit_stake_with_accounts(
+    program_id: &Pubkey,
+    pool_addresses: &PoolAddresses,
+    external_addresses: &ExternalAddresses,
+    user_stake_account: &Pubkey,
+    user_token_account: &Pubkey,
+    user_lamport_account: &Pubkey,
 ) -> Instruction {
     let data = borsh::to_vec(&SinglePoolInstruction::DepositStake).unwrap();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*pool_address, false),
-        AccountMeta::new(find_pool_stake_address(program_id, pool_address), false),
-        AccountMeta::new_readonly(find_pool_onramp_address(program_id, pool_address), false),
-        AccountMeta::new(find_pool_mint_address(program_id, pool_address), false),
-        AccountMeta::new_readonly(
-            find_pool_stake_authority_address(program_id, pool_address),
-            false,
-        ),
-        AccountMeta::new_readonly(
-            find_pool_mint_authority_address(program_id, pool_address),
-            false,
-        ),
+        AccountMeta::new_readonly(pool_addresses.pool, false),
+        AccountMeta::new(pool_addresses.stake, false),
+        AccountMeta::new_readonly(pool_addresses.onramp, false),
+        AccountMeta::new(pool_addresses.mint, false),
+        AccountMeta::new_readonly(pool_addresses.stake_authority, false),
+        AccountMeta::new_readonly(pool_addresses.mint_authority, false),
         AccountMeta::new(*user_stake_account, false),
         AccountMeta::new(*user_token_account, false),
         AccountMeta::new(*user_lamport_account, false),
-        AccountMeta::new_readonly(sysvar::clock::id(), false),
-        AccountMeta::new_readonly(stake_history::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(stake::program::id(), false),
+        AccountMeta::new_readonly(external_addresses.clock_sysvar, false),
+        AccountMeta::new_readonly(external_addresses.stake_history_sysvar, false),
+        AccountMeta::new_readonly(external_addresses.token_program, false),
+        AccountMeta::new_readonly(external_addresses.stake_program, false),
     ];
 
     Instruction {
@@ -370,6 +777,8 @@ pub fn deposit_stake(
 /// a best practice for safety given its untrammeled authority.
 /// The escrow account does not have to meet rent exemption because it is
 /// opened and closed in the span of one transaction.
+/// The pool mint is owned by the original token program; see
+/// `deposit_liquid_with_token_program()` for Token-2022 pools.
 pub fn deposit_liquid(
     program_id: &Pubkey,
     vote_account_address: &Pubkey,
@@ -377,11 +786,34 @@ pub fn deposit_liquid(
     escrow_deposit_account: &Pubkey,
     user_token_account: &Pubkey,
     lamports: u64,
+) -> Vec<Instruction> {
+    deposit_liquid_with_token_program(
+        program_id,
+        &spl_token::id(),
+        vote_account_address,
+        user_wallet,
+        escrow_deposit_account,
+        user_token_account,
+        lamports,
+    )
+}
+
+/// Creates the necessary instructions to deposit liquid sol into a pool whose
+/// mint is owned by `token_program_id`. See `deposit_liquid()`.
+pub fn deposit_liquid_with_token_program(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    user_wallet: &Pubkey,
+    escrow_deposit_account: &Pubkey,
+    user_token_account: &Pubkey,
+    lamports: u64,
 ) -> Vec<Instruction> {
     vec![
         system_instruction::transfer(user_wallet, escrow_deposit_account, lamports),
-        deposit_sol(
+        deposit_sol_with_token_program(
             program_id,
+            token_program_id,
             vote_account_address,
             escrow_deposit_account,
             user_token_account,
@@ -393,6 +825,8 @@ pub fn deposit_liquid(
 /// Creates a `DepositSol` instruction.
 /// It is recommended as a matter of hygiene to use the `deposit_liquid()` helper,
 /// to isolate user wallet signing authority from the program.
+/// The pool mint is owned by the original token program; see
+/// `deposit_sol_with_token_program()` for Token-2022 pools.
 pub fn deposit_sol(
     program_id: &Pubkey,
     vote_account_address: &Pubkey,
@@ -400,32 +834,66 @@ pub fn deposit_sol(
     user_token_account: &Pubkey,
     lamports: u64,
 ) -> Instruction {
-    let pool_address = find_pool_address(program_id, vote_account_address);
+    deposit_sol_with_token_program(
+        program_id,
+        &spl_token::id(),
+        vote_account_address,
+        user_deposit_account,
+        user_token_account,
+        lamports,
+    )
+}
+
+/// Creates a `DepositSol` instruction for a pool whose mint is owned by
+/// `token_program_id`.
+pub fn deposit_sol_with_token_program(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    user_deposit_account: &Pubkey,
+    user_token_account: &Pubkey,
+    lamports: u64,
+) -> Instruction {
+    deposit_sol_with_accounts(
+        program_id,
+        vote_account_address,
+        &PoolAddresses::derive(program_id, vote_account_address),
+        &ExternalAddresses::with_token_program(token_program_id),
+        user_deposit_account,
+        user_token_account,
+        lamports,
+    )
+}
 
+/// Creates a `DepositSol` instruction with the given pool and external
+/// addresses rather than deriving them. See `initialize_pool_with_accounts()`.
+pub fn deposit_sol_with_accounts(
+    program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    pool_addresses: &PoolAddresses,
+    external_addresses: &ExternalAddresses,
+    user_deposit_account: &Pubkey,
+    user_token_account: &Pubkey,
+    lamports: u64,
+) -> Instruction {
     let data = borsh::to_vec(&SinglePoolInstruction::DepositSol { lamports }).unwrap();
     let accounts = vec![
         AccountMeta::new_readonly(*vote_account_address, false),
-        AccountMeta::new_readonly(pool_address, false),
-        AccountMeta::new(find_pool_stake_address(program_id, &pool_address), false),
-        AccountMeta::new(find_pool_onramp_address(program_id, &pool_address), false),
-        AccountMeta::new(find_pool_mint_address(program_id, &pool_address), false),
-        AccountMeta::new_readonly(
-            find_pool_stake_authority_address(program_id, &pool_address),
-            false,
-        ),
-        AccountMeta::new_readonly(
-            find_pool_mint_authority_address(program_id, &pool_address),
-            false,
-        ),
+        AccountMeta::new_readonly(pool_addresses.pool, false),
+        AccountMeta::new(pool_addresses.stake, false),
+        AccountMeta::new(pool_addresses.onramp, false),
+        AccountMeta::new(pool_addresses.mint, false),
+        AccountMeta::new_readonly(pool_addresses.stake_authority, false),
+        AccountMeta::new_readonly(pool_addresses.mint_authority, false),
         AccountMeta::new(*user_deposit_account, true),
         AccountMeta::new(*user_token_account, false),
-        AccountMeta::new_readonly(sysvar::clock::id(), false),
-        AccountMeta::new_readonly(stake_history::id(), false),
-        #[allow(deprecated)]
-        AccountMeta::new_readonly(stake::config::id(), false),
-        AccountMeta::new_readonly(system_program::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(stake::program::id(), false),
+        AccountMeta::new_readonly(external_addresses.clock_sysvar, false),
+        AccountMeta::new_readonly(external_addresses.stake_history_sysvar, false),
+        #[cfg(not(feature = "omit-stake-config"))]
+        AccountMeta::new_readonly(external_addresses.stake_config, false),
+        AccountMeta::new_readonly(external_addresses.system_program, false),
+        AccountMeta::new_readonly(external_addresses.token_program, false),
+        AccountMeta::new_readonly(external_addresses.stake_program, false),
         AccountMeta::new_readonly(*program_id, false),
     ];
 
@@ -440,6 +908,8 @@ pub fn deposit_sol(
 /// account. If a new stake account is required, the user should first include
 /// `system_instruction::create_account` with account size
 /// `stake::state::StakeStateV2::size_of()` and owner `stake::program::id()`.
+/// The pool mint is owned by the original token program; see
+/// `withdraw_with_token_program()` for Token-2022 pools.
 pub fn withdraw(
     program_id: &Pubkey,
     pool_address: &Pubkey,
@@ -448,19 +918,47 @@ pub fn withdraw(
     user_token_account: &Pubkey,
     user_token_authority: &Pubkey,
     token_amount: u64,
+) -> Vec<Instruction> {
+    withdraw_with_token_program(
+        program_id,
+        &spl_token::id(),
+        pool_address,
+        user_stake_account,
+        user_stake_authority,
+        user_token_account,
+        user_token_authority,
+        token_amount,
+    )
+}
+
+/// Creates all necessary instructions to withdraw stake from a pool whose mint
+/// is owned by `token_program_id`. See `withdraw()`.
+pub fn withdraw_with_token_program(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_stake_account: &Pubkey,
+    user_stake_authority: &Pubkey,
+    user_token_account: &Pubkey,
+    user_token_authority: &Pubkey,
+    token_amount: u64,
 ) -> Vec<Instruction> {
     vec![
-        spl_token::instruction::approve(
-            &spl_token::id(),
-            user_token_account,
-            &find_pool_mint_authority_address(program_id, pool_address),
-            user_token_authority,
-            &[],
-            token_amount,
-        )
-        .unwrap(),
-        withdraw_stake(
+        inline_spl_token_2022::retarget(
+            spl_token::instruction::approve(
+                &spl_token::id(),
+                user_token_account,
+                &find_pool_mint_authority_address(program_id, pool_address),
+                user_token_authority,
+                &[],
+                token_amount,
+            )
+            .unwrap(),
+            token_program_id,
+        ),
+        withdraw_stake_with_token_program(
             program_id,
+            token_program_id,
             pool_address,
             user_stake_account,
             user_stake_authority,
@@ -471,6 +969,8 @@ pub fn withdraw(
 }
 
 /// Creates a `WithdrawStake` instruction.
+/// The pool mint is owned by the original token program; see
+/// `withdraw_stake_with_token_program()` for Token-2022 pools.
 pub fn withdraw_stake(
     program_id: &Pubkey,
     pool_address: &Pubkey,
@@ -478,6 +978,50 @@ pub fn withdraw_stake(
     user_stake_authority: &Pubkey,
     user_token_account: &Pubkey,
     token_amount: u64,
+) -> Instruction {
+    withdraw_stake_with_token_program(
+        program_id,
+        &spl_token::id(),
+        pool_address,
+        user_stake_account,
+        user_stake_authority,
+        user_token_account,
+        token_amount,
+    )
+}
+
+/// Creates a `WithdrawStake` instruction for a pool whose mint is owned by
+/// `token_program_id`.
+pub fn withdraw_stake_with_token_program(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_stake_account: &Pubkey,
+    user_stake_authority: &Pubkey,
+    user_token_account: &Pubkey,
+    token_amount: u64,
+) -> Instruction {
+    withdraw_stake_with_accounts(
+        program_id,
+        &PoolAddresses::derive_from_pool(program_id, pool_address),
+        &ExternalAddresses::with_token_program(token_program_id),
+        user_stake_account,
+        user_stake_authority,
+        user_token_account,
+        token_amount,
+    )
+}
+
+/// Creates a `WithdrawStake` instruction with the given pool and external
+/// addresses rather than deriving them. See `initialize_pool_with_accounts()`.
+pub fn withdraw_stake_with_accounts(
+    program_id: &Pubkey,
+    pool_addresses: &PoolAddresses,
+    external_addresses: &ExternalAddresses,
+    user_stake_account: &Pubkey,
+    user_stake_authority: &Pubkey,
+    user_token_account: &Pubkey,
+    token_amount: u64,
 ) -> Instruction {
     let data = borsh::to_vec(&SinglePoolInstruction::WithdrawStake {
         user_stake_authority: *user_stake_authority,
@@ -486,23 +1030,148 @@ pub fn withdraw_stake(
     .unwrap();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*pool_address, false),
-        AccountMeta::new(find_pool_stake_address(program_id, pool_address), false),
-        AccountMeta::new_readonly(find_pool_onramp_address(program_id, pool_address), false),
-        AccountMeta::new(find_pool_mint_address(program_id, pool_address), false),
-        AccountMeta::new_readonly(
-            find_pool_stake_authority_address(program_id, pool_address),
-            false,
+        AccountMeta::new_readonly(pool_addresses.pool, false),
+        AccountMeta::new(pool_addresses.stake, false),
+        AccountMeta::new_readonly(pool_addresses.onramp, false),
+        AccountMeta::new(pool_addresses.mint, false),
+        AccountMeta::new_readonly(pool_addresses.stake_authority, false),
+        AccountMeta::new_readonly(pool_addresses.mint_authority, false),
+        AccountMeta::new(*user_stake_account, false),
+        AccountMeta::new(*user_token_account, false),
+        AccountMeta::new_readonly(external_addresses.clock_sysvar, false),
+        AccountMeta::new_readonly(external_addresses.token_program, false),
+        AccountMeta::new_readonly(external_addresses.stake_program, false),
+    ];
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates all necessary instructions to withdraw liquid sol.
+/// The pool mint is owned by the original token program; see
+/// `withdraw_liquid_with_token_program()` for Token-2022 pools.
+pub fn withdraw_liquid(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_token_account: &Pubkey,
+    user_token_authority: &Pubkey,
+    user_lamport_account: &Pubkey,
+    token_amount: u64,
+) -> Vec<Instruction> {
+    withdraw_liquid_with_token_program(
+        program_id,
+        &spl_token::id(),
+        pool_address,
+        user_token_account,
+        user_token_authority,
+        user_lamport_account,
+        token_amount,
+    )
+}
+
+/// Creates all necessary instructions to withdraw liquid sol from a pool whose
+/// mint is owned by `token_program_id`.
+pub fn withdraw_liquid_with_token_program(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_token_account: &Pubkey,
+    user_token_authority: &Pubkey,
+    user_lamport_account: &Pubkey,
+    token_amount: u64,
+) -> Vec<Instruction> {
+    vec![
+        inline_spl_token_2022::retarget(
+            spl_token::instruction::approve(
+                &spl_token::id(),
+                user_token_account,
+                &find_pool_mint_authority_address(program_id, pool_address),
+                user_token_authority,
+                &[],
+                token_amount,
+            )
+            .unwrap(),
+            token_program_id,
         ),
-        AccountMeta::new_readonly(
-            find_pool_mint_authority_address(program_id, pool_address),
-            false,
+        withdraw_sol_with_token_program(
+            program_id,
+            token_program_id,
+            pool_address,
+            user_token_account,
+            user_lamport_account,
+            token_amount,
         ),
-        AccountMeta::new(*user_stake_account, false),
+    ]
+}
+
+/// Creates a `WithdrawSol` instruction.
+/// The pool mint is owned by the original token program; see
+/// `withdraw_sol_with_token_program()` for Token-2022 pools.
+pub fn withdraw_sol(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_token_account: &Pubkey,
+    user_lamport_account: &Pubkey,
+    token_amount: u64,
+) -> Instruction {
+    withdraw_sol_with_token_program(
+        program_id,
+        &spl_token::id(),
+        pool_address,
+        user_token_account,
+        user_lamport_account,
+        token_amount,
+    )
+}
+
+/// Creates a `WithdrawSol` instruction for a pool whose mint is owned by
+/// `token_program_id`.
+pub fn withdraw_sol_with_token_program(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_token_account: &Pubkey,
+    user_lamport_account: &Pubkey,
+    token_amount: u64,
+) -> Instruction {
+    withdraw_sol_with_accounts(
+        program_id,
+        &PoolAddresses::derive_from_pool(program_id, pool_address),
+        &ExternalAddresses::with_token_program(token_program_id),
+        user_token_account,
+        user_lamport_account,
+        token_amount,
+    )
+}
+
+/// Creates a `WithdrawSol` instruction with the given pool and external
+/// addresses rather than deriving them. See `initialize_pool_with_accounts()`.
+pub fn withdraw_sol_with_accounts(
+    program_id: &Pubkey,
+    pool_addresses: &PoolAddresses,
+    external_addresses: &ExternalAddresses,
+    user_token_account: &Pubkey,
+    user_lamport_account: &Pubkey,
+    token_amount: u64,
+) -> Instruction {
+    let data = borsh::to_vec(&SinglePoolInstruction::WithdrawSol { token_amount }).unwrap();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(pool_addresses.pool, false),
+        AccountMeta::new(pool_addresses.stake, false),
+        AccountMeta::new(pool_addresses.onramp, false),
+        AccountMeta::new(pool_addresses.mint, false),
+        AccountMeta::new_readonly(pool_addresses.stake_authority, false),
+        AccountMeta::new_readonly(pool_addresses.mint_authority, false),
         AccountMeta::new(*user_token_account, false),
-        AccountMeta::new_readonly(sysvar::clock::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(stake::program::id(), false),
+        AccountMeta::new(*user_lamport_account, false),
+        AccountMeta::new_readonly(external_addresses.clock_sysvar, false),
+        AccountMeta::new_readonly(external_addresses.stake_history_sysvar, false),
+        AccountMeta::new_readonly(external_addresses.token_program, false),
+        AccountMeta::new_readonly(external_addresses.stake_program, false),
     ];
 
     Instruction {
@@ -518,25 +1187,39 @@ pub fn create_token_metadata(
     pool_address: &Pubkey,
     payer: &Pubkey,
 ) -> Instruction {
-    let pool_mint = find_pool_mint_address(program_id, pool_address);
-    let (token_metadata, _) = find_metadata_account(&pool_mint);
+    let pool_addresses = PoolAddresses::derive_from_pool(program_id, pool_address);
+    let (token_metadata, _) = find_metadata_account(&pool_addresses.mint);
+
+    create_token_metadata_with_accounts(
+        program_id,
+        &pool_addresses,
+        &ExternalAddresses::default(),
+        payer,
+        &token_metadata,
+    )
+}
+
+/// Creates a `CreateTokenMetadata` instruction with the given pool, external,
+/// and metadata addresses rather than deriving them. See
+/// `initialize_pool_with_accounts()`.
+pub fn create_token_metadata_with_accounts(
+    program_id: &Pubkey,
+    pool_addresses: &PoolAddresses,
+    external_addresses: &ExternalAddresses,
+    payer: &Pubkey,
+    token_metadata: &Pubkey,
+) -> Instruction {
     let data = borsh::to_vec(&SinglePoolInstruction::CreateTokenMetadata).unwrap();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*pool_address, false),
-        AccountMeta::new_readonly(pool_mint, false),
-        AccountMeta::new_readonly(
-            find_pool_mint_authority_address(program_id, pool_address),
-            false,
-        ),
-        AccountMeta::new_readonly(
-            find_pool_mpl_authority_address(program_id, pool_address),
-            false,
-        ),
+        AccountMeta::new_readonly(pool_addresses.pool, false),
+        AccountMeta::new_readonly(pool_addresses.mint, false),
+        AccountMeta::new_readonly(pool_addresses.mint_authority, false),
+        AccountMeta::new_readonly(pool_addresses.mpl_authority, false),
         AccountMeta::new(*payer, true),
-        AccountMeta::new(token_metadata, false),
-        AccountMeta::new_readonly(inline_mpl_token_metadata::id(), false),
-        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(*token_metadata, false),
+        AccountMeta::new_readonly(external_addresses.token_metadata_program, false),
+        AccountMeta::new_readonly(external_addresses.system_program, false),
     ];
 
     Instruction {
@@ -555,22 +1238,46 @@ pub fn update_token_metadata(
     symbol: String,
     uri: String,
 ) -> Instruction {
-    let pool_address = find_pool_address(program_id, vote_account_address);
-    let pool_mint = find_pool_mint_address(program_id, &pool_address);
-    let (token_metadata, _) = find_metadata_account(&pool_mint);
+    let pool_addresses = PoolAddresses::derive(program_id, vote_account_address);
+    let (token_metadata, _) = find_metadata_account(&pool_addresses.mint);
+
+    update_token_metadata_with_accounts(
+        program_id,
+        vote_account_address,
+        &pool_addresses,
+        &ExternalAddresses::default(),
+        authorized_withdrawer,
+        &token_metadata,
+        name,
+        symbol,
+        uri,
+    )
+}
+
+/// Creates an `UpdateTokenMetadata` instruction with the given pool, external,
+/// and metadata addresses rather than deriving them. See
+/// `initialize_pool_with_accounts()`.
+pub fn update_token_metadata_with_accounts(
+    program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    pool_addresses: &PoolAddresses,
+    external_addresses: &ExternalAddresses,
+    authorized_withdrawer: &Pubkey,
+    token_metadata: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Instruction {
     let data =
         borsh::to_vec(&SinglePoolInstruction::UpdateTokenMetadata { name, symbol, uri }).unwrap();
 
     let accounts = vec![
         AccountMeta::new_readonly(*vote_account_address, false),
-        AccountMeta::new_readonly(pool_address, false),
-        AccountMeta::new_readonly(
-            find_pool_mpl_authority_address(program_id, &pool_address),
-            false,
-        ),
+        AccountMeta::new_readonly(pool_addresses.pool, false),
+        AccountMeta::new_readonly(pool_addresses.mpl_authority, false),
         AccountMeta::new_readonly(*authorized_withdrawer, true),
-        AccountMeta::new(token_metadata, false),
-        AccountMeta::new_readonly(inline_mpl_token_metadata::id(), false),
+        AccountMeta::new(*token_metadata, false),
+        AccountMeta::new_readonly(external_addresses.token_metadata_program, false),
     ];
 
     Instruction {
@@ -580,19 +1287,81 @@ pub fn update_token_metadata(
     }
 }
 
+/// Creates a `ReactivateAfterDelinquency` instruction.
+pub fn reactivate_pool_stake(program_id: &Pubkey, vote_account_address: &Pubkey) -> Instruction {
+    reactivate_pool_stake_with_accounts(
+        program_id,
+        vote_account_address,
+        &PoolAddresses::derive(program_id, vote_account_address),
+        &ExternalAddresses::default(),
+    )
+}
+
+/// Creates a `ReactivateAfterDelinquency` instruction with the given pool and
+/// external addresses rather than deriving them. See
+/// `initialize_pool_with_accounts()`.
+pub fn reactivate_pool_stake_with_accounts(
+    program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    pool_addresses: &PoolAddresses,
+    external_addresses: &ExternalAddresses,
+) -> Instruction {
+    let data = borsh::to_vec(&SinglePoolInstruction::ReactivateAfterDelinquency).unwrap();
+    let accounts = vec![
+        AccountMeta::new_readonly(*vote_account_address, false),
+        AccountMeta::new_readonly(pool_addresses.pool, false),
+        AccountMeta::new(pool_addresses.stake, false),
+        AccountMeta::new_readonly(pool_addresses.stake_authority, false),
+        AccountMeta::new_readonly(external_addresses.clock_sysvar, false),
+        AccountMeta::new_readonly(external_addresses.stake_history_sysvar, false),
+        #[cfg(not(feature = "omit-stake-config"))]
+        AccountMeta::new_readonly(external_addresses.stake_config, false),
+        AccountMeta::new_readonly(external_addresses.stake_program, false),
+    ];
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates a `GetVersion` instruction.
+pub fn get_version(program_id: &Pubkey) -> Instruction {
+    let data = borsh::to_vec(&SinglePoolInstruction::GetVersion).unwrap();
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![],
+        data,
+    }
+}
+
 /// Creates a `InitializePoolOnRamp` instruction.
 pub fn initialize_pool_onramp(program_id: &Pubkey, pool_address: &Pubkey) -> Instruction {
+    initialize_pool_onramp_with_accounts(
+        program_id,
+        &PoolAddresses::derive_from_pool(program_id, pool_address),
+        &ExternalAddresses::default(),
+    )
+}
+
+/// Creates a `InitializePoolOnRamp` instruction with the given pool and
+/// external addresses rather than deriving them. See
+/// `initialize_pool_with_accounts()`.
+pub fn initialize_pool_onramp_with_accounts(
+    program_id: &Pubkey,
+    pool_addresses: &PoolAddresses,
+    external_addresses: &ExternalAddresses,
+) -> Instruction {
     let data = borsh::to_vec(&SinglePoolInstruction::InitializePoolOnRamp).unwrap();
     let accounts = vec![
-        AccountMeta::new_readonly(*pool_address, false),
-        AccountMeta::new(find_pool_onramp_address(program_id, pool_address), false),
-        AccountMeta::new_readonly(
-            find_pool_stake_authority_address(program_id, pool_address),
-            false,
-        ),
-        AccountMeta::new_readonly(sysvar::rent::id(), false),
-        AccountMeta::new_readonly(system_program::id(), false),
-        AccountMeta::new_readonly(stake::program::id(), false),
+        AccountMeta::new_readonly(pool_addresses.pool, false),
+        AccountMeta::new(pool_addresses.onramp, false),
+        AccountMeta::new_readonly(pool_addresses.stake_authority, false),
+        AccountMeta::new_readonly(external_addresses.rent_sysvar, false),
+        AccountMeta::new_readonly(external_addresses.system_program, false),
+        AccountMeta::new_readonly(external_addresses.stake_program, false),
     ];
 
     Instruction {
@@ -611,12 +1380,286 @@ pub fn create_pool_onramp(
     payer: &Pubkey,
     rent: &Rent,
 ) -> Vec<Instruction> {
-    let onramp_address = find_pool_onramp_address(program_id, pool_address);
+    create_pool_onramp_with_accounts(
+        program_id,
+        &PoolAddresses::derive_from_pool(program_id, pool_address),
+        &ExternalAddresses::default(),
+        payer,
+        rent,
+    )
+}
+
+/// Creates a `InitializePoolOnRamp` instruction plus the transfer to fund it,
+/// with the given pool and external addresses rather than deriving them. See
+/// `initialize_pool_with_accounts()`.
+pub fn create_pool_onramp_with_accounts(
+    program_id: &Pubkey,
+    pool_addresses: &PoolAddresses,
+    external_addresses: &ExternalAddresses,
+    payer: &Pubkey,
+    rent: &Rent,
+) -> Vec<Instruction> {
     let stake_space = stake::state::StakeStateV2::size_of();
     let stake_rent = rent.minimum_balance(stake_space);
 
     vec![
-        system_instruction::transfer(payer, &onramp_address, stake_rent),
-        initialize_pool_onramp(program_id, pool_address),
+        system_instruction::transfer(payer, &pool_addresses.onramp, stake_rent),
+        initialize_pool_onramp_with_accounts(program_id, pool_addresses, external_addresses),
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_variants_is_complete() {
+        let variants = SinglePoolInstruction::all_variants();
+
+        for (i, variant) in variants.iter().enumerate() {
+            let data = borsh::to_vec(variant).unwrap();
+            assert_eq!(data[0] as usize, i);
+            assert_eq!(
+                SinglePoolInstruction::try_from_slice(&data).unwrap(),
+                *variant
+            );
+        }
+
+        // if this fails, a new instruction was added without updating `all_variants()`
+        assert!(SinglePoolInstruction::try_from_slice(&[variants.len() as u8]).is_err());
+    }
+
+    #[test]
+    fn with_accounts_builders_match_derived() {
+        let program_id = crate::id();
+        let vote_account_address = Pubkey::new_unique();
+        let pool_addresses = PoolAddresses::derive(&program_id, &vote_account_address);
+        let external_addresses = ExternalAddresses::default();
+        let (token_metadata, _) = find_metadata_account(&pool_addresses.mint);
+        let user_account = Pubkey::new_unique();
+        let rent = Rent::default();
+
+        assert_eq!(
+            initialize_pool(&program_id, &vote_account_address),
+            initialize_pool_with_accounts(
+                &program_id,
+                &vote_account_address,
+                &pool_addresses,
+                &external_addresses,
+            )
+        );
+        assert_eq!(
+            replenish_pool(&program_id, &vote_account_address),
+            replenish_pool_with_accounts(
+                &program_id,
+                &vote_account_address,
+                &pool_addresses,
+                &external_addresses,
+            )
+        );
+        assert_eq!(
+            deposit_stake(
+                &program_id,
+                &pool_addresses.pool,
+                &user_account,
+                &user_account,
+                &user_account,
+            ),
+            deposit_stake_with_accounts(
+                &program_id,
+                &pool_addresses,
+                &external_addresses,
+                &user_account,
+                &user_account,
+                &user_account,
+            )
+        );
+        assert_eq!(
+            withdraw_stake(
+                &program_id,
+                &pool_addresses.pool,
+                &user_account,
+                &user_account,
+                &user_account,
+                1,
+            ),
+            withdraw_stake_with_accounts(
+                &program_id,
+                &pool_addresses,
+                &external_addresses,
+                &user_account,
+                &user_account,
+                &user_account,
+                1,
+            )
+        );
+        assert_eq!(
+            create_token_metadata(&program_id, &pool_addresses.pool, &user_account),
+            create_token_metadata_with_accounts(
+                &program_id,
+                &pool_addresses,
+                &external_addresses,
+                &user_account,
+                &token_metadata,
+            )
+        );
+        assert_eq!(
+            update_token_metadata(
+                &program_id,
+                &vote_account_address,
+                &user_account,
+                "name".to_string(),
+                "symbol".to_string(),
+                "uri".to_string(),
+            ),
+            update_token_metadata_with_accounts(
+                &program_id,
+                &vote_account_address,
+                &pool_addresses,
+                &external_addresses,
+                &user_account,
+                &token_metadata,
+                "name".to_string(),
+                "symbol".to_string(),
+                "uri".to_string(),
+            )
+        );
+        assert_eq!(
+            initialize_pool_onramp(&program_id, &pool_addresses.pool),
+            initialize_pool_onramp_with_accounts(&program_id, &pool_addresses, &external_addresses)
+        );
+        assert_eq!(
+            create_pool_onramp(&program_id, &pool_addresses.pool, &user_account, &rent),
+            create_pool_onramp_with_accounts(
+                &program_id,
+                &pool_addresses,
+                &external_addresses,
+                &user_account,
+                &rent,
+            )
+        );
+        assert_eq!(
+            deposit_sol(
+                &program_id,
+                &vote_account_address,
+                &user_account,
+                &user_account,
+                1,
+            ),
+            deposit_sol_with_accounts(
+                &program_id,
+                &vote_account_address,
+                &pool_addresses,
+                &external_addresses,
+                &user_account,
+                &user_account,
+                1,
+            )
+        );
+        assert_eq!(
+            withdraw_sol(
+                &program_id,
+                &pool_addresses.pool,
+                &user_account,
+                &user_account,
+                1,
+            ),
+            withdraw_sol_with_accounts(
+                &program_id,
+                &pool_addresses,
+                &external_addresses,
+                &user_account,
+                &user_account,
+                1,
+            )
+        );
+        assert_eq!(
+            reactivate_pool_stake(&program_id, &vote_account_address),
+            reactivate_pool_stake_with_accounts(
+                &program_id,
+                &vote_account_address,
+                &pool_addresses,
+                &external_addresses,
+            )
+        );
+
+        // the token program is the only external address that varies by pool
+        let token_2022_addresses =
+            ExternalAddresses::with_token_program(&inline_spl_token_2022::id());
+        assert_eq!(
+            deposit_sol_with_token_program(
+                &program_id,
+                &inline_spl_token_2022::id(),
+                &vote_account_address,
+                &user_account,
+                &user_account,
+                1,
+            ),
+            deposit_sol_with_accounts(
+                &program_id,
+                &vote_account_address,
+                &pool_addresses,
+                &token_2022_addresses,
+                &user_account,
+                &user_account,
+                1,
+            )
+        );
+    }
+
+    #[test]
+    fn create_stake_and_prepare_deposit_groups() {
+        let program_id = crate::id();
+        let vote_account_address = Pubkey::new_unique();
+        let user_wallet = Pubkey::new_unique();
+        let user_stake_account = Pubkey::new_unique();
+        let user_token_account = Pubkey::new_unique();
+        let rent = Rent::default();
+        let stake_amount = 1_000_000_000;
+
+        let instructions = create_stake_and_prepare_deposit(
+            &program_id,
+            &spl_token::id(),
+            &vote_account_address,
+            &user_wallet,
+            &user_stake_account,
+            &user_token_account,
+            &rent,
+            stake_amount,
+        );
+
+        // the stake account is funded with rent plus the stake amount, then delegated
+        let stake_rent = rent.minimum_balance(stake::state::StakeStateV2::size_of());
+        assert_eq!(
+            instructions.create_and_delegate[0],
+            system_instruction::create_account(
+                &user_wallet,
+                &user_stake_account,
+                stake_rent.saturating_add(stake_amount),
+                stake::state::StakeStateV2::size_of() as u64,
+                &stake::program::id(),
+            )
+        );
+        assert_eq!(
+            instructions.create_and_delegate.last().unwrap(),
+            &stake::instruction::delegate_stake(
+                &user_stake_account,
+                &user_wallet,
+                &vote_account_address,
+            )
+        );
+
+        // the deposit is the ordinary deposit into the vote account's pool
+        assert_eq!(
+            instructions.deposit,
+            deposit(
+                &program_id,
+                &find_pool_address(&program_id, &vote_account_address),
+                &user_stake_account,
+                &user_token_account,
+                &user_wallet,
+                &user_wallet,
+            )
+        );
+    }
+}
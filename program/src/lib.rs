@@ -2,8 +2,10 @@
 
 //! A program for liquid staking with a single validator
 
+pub mod apy;
 pub mod error;
 pub mod inline_mpl_token_metadata;
+pub mod inline_spl_token_2022;
 pub mod instruction;
 pub mod processor;
 pub mod state;
@@ -20,8 +22,24 @@ solana_pubkey::declare_id!("SVSPxpvHdN29nkVg9rPapPNDddN5DipNLRUFhyjFThE");
 /// taken if using this number for calculations in third-party libraries or programs,
 /// as it is not guaranteed to remain at this value.
 pub const DEPOSIT_SOL_FEE_BPS: u64 = 100;
+/// Fee charged for the `WithdrawSol` instruction, which stays in the pool. Liquid
+/// withdrawals skip the cooldown `WithdrawStake` is subject to and spend lamports
+/// that would otherwise be delegated for every holder, so this is never lower than
+/// `DEPOSIT_SOL_FEE_BPS`. As with that fee, it is not guaranteed to remain at this value.
+pub const WITHDRAW_SOL_FEE_BPS: u64 = 100;
+const _: () = assert!(WITHDRAW_SOL_FEE_BPS >= DEPOSIT_SOL_FEE_BPS);
 const MAX_BPS: u64 = 10_000;
 
+/// Prefix of the machine-readable lines the program logs for deposits and
+/// withdrawals. It is followed by the format version, the event name, and
+/// space-separated `key=value` fields, eg:
+/// `single-pool-event: v1 deposit_stake token_amount=1000 lamports=1000`.
+/// Fields may be appended within a version, but existing fields will not be
+/// removed or change meaning without a version bump.
+pub const EVENT_LOG_PREFIX: &str = "single-pool-event:";
+/// Current version of the event log format
+pub const EVENT_LOG_VERSION: u8 = 1;
+
 const POOL_PREFIX: &[u8] = b"pool";
 const POOL_STAKE_PREFIX: &[u8] = b"stake";
 const POOL_ONRAMP_PREFIX: &[u8] = b"onramp";
@@ -30,7 +48,10 @@ const POOL_MINT_AUTHORITY_PREFIX: &[u8] = b"mint_authority";
 const POOL_STAKE_AUTHORITY_PREFIX: &[u8] = b"stake_authority";
 const POOL_MPL_AUTHORITY_PREFIX: &[u8] = b"mpl_authority";
 
-const PHANTOM_TOKEN_AMOUNT: u64 = LAMPORTS_PER_SOL;
+/// Tokens the program counts beyond the mint supply when pricing deposits and
+/// withdrawals, offsetting the minimum pool balance that no tokens are minted
+/// for. This keeps the exchange rate of an empty pool from being manipulated.
+pub const PHANTOM_TOKEN_AMOUNT: u64 = LAMPORTS_PER_SOL;
 const MINT_DECIMALS: u8 = 9;
 const PERPETUAL_NEW_WARMUP_COOLDOWN_RATE_EPOCH: Option<u64> = Some(0);
 
@@ -38,23 +59,41 @@ const VOTE_STATE_DISCRIMINATOR_END: usize = 4;
 const VOTE_STATE_AUTHORIZED_WITHDRAWER_START: usize = 36;
 const VOTE_STATE_AUTHORIZED_WITHDRAWER_END: usize = 68;
 
-fn find_pool_address_and_bump(program_id: &Pubkey, vote_account_address: &Pubkey) -> (Pubkey, u8) {
+/// Find the canonical pool address for a given vote account, and its bump seed.
+pub fn find_pool_address_and_bump(
+    program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[POOL_PREFIX, vote_account_address.as_ref()], program_id)
 }
 
-fn find_pool_stake_address_and_bump(program_id: &Pubkey, pool_address: &Pubkey) -> (Pubkey, u8) {
+/// Find the canonical main stake account address for a given pool account,
+/// and its bump seed.
+pub fn find_pool_stake_address_and_bump(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[POOL_STAKE_PREFIX, pool_address.as_ref()], program_id)
 }
 
-fn find_pool_onramp_address_and_bump(program_id: &Pubkey, pool_address: &Pubkey) -> (Pubkey, u8) {
+/// Find the canonical stake on-ramp account address for a given pool account,
+/// and its bump seed.
+pub fn find_pool_onramp_address_and_bump(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[POOL_ONRAMP_PREFIX, pool_address.as_ref()], program_id)
 }
 
-fn find_pool_mint_address_and_bump(program_id: &Pubkey, pool_address: &Pubkey) -> (Pubkey, u8) {
+/// Find the canonical token mint address for a given pool account, and its
+/// bump seed.
+pub fn find_pool_mint_address_and_bump(program_id: &Pubkey, pool_address: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[POOL_MINT_PREFIX, pool_address.as_ref()], program_id)
 }
 
-fn find_pool_stake_authority_address_and_bump(
+/// Find the canonical stake authority address for a given pool account, and
+/// its bump seed, eg to reconstruct the signer seeds the program uses.
+pub fn find_pool_stake_authority_address_and_bump(
     program_id: &Pubkey,
     pool_address: &Pubkey,
 ) -> (Pubkey, u8) {
@@ -64,7 +103,9 @@ fn find_pool_stake_authority_address_and_bump(
     )
 }
 
-fn find_pool_mint_authority_address_and_bump(
+/// Find the canonical mint authority address for a given pool account, and
+/// its bump seed.
+pub fn find_pool_mint_authority_address_and_bump(
     program_id: &Pubkey,
     pool_address: &Pubkey,
 ) -> (Pubkey, u8) {
@@ -74,7 +115,9 @@ fn find_pool_mint_authority_address_and_bump(
     )
 }
 
-fn find_pool_mpl_authority_address_and_bump(
+/// Find the canonical MPL authority address for a given pool account, and
+/// its bump seed.
+pub fn find_pool_mpl_authority_address_and_bump(
     program_id: &Pubkey,
     pool_address: &Pubkey,
 ) -> (Pubkey, u8) {
@@ -118,3 +161,177 @@ pub fn find_pool_mint_authority_address(program_id: &Pubkey, pool_address: &Pubk
 pub fn find_pool_mpl_authority_address(program_id: &Pubkey, pool_address: &Pubkey) -> Pubkey {
     find_pool_mpl_authority_address_and_bump(program_id, pool_address).0
 }
+
+/// Minimum balance of delegated stake required to create a pool, given the stake
+/// program minimum delegation. We require at least 1 sol to avoid minting tokens
+/// for these lamports (locking them in the pool) since they will *become* locked
+/// after the BPF Stake 5.0.0 upgrade.
+///
+/// We also track any future (currently unplanned) minimum delegation increase, to
+/// ensure a new pool is always valid for `DelegateStake`.
+pub fn minimum_pool_balance(stake_minimum_delegation: u64) -> u64 {
+    std::cmp::max(stake_minimum_delegation, LAMPORTS_PER_SOL)
+}
+
+/// Calculate pool tokens to mint, given outstanding token supply, pool NAV, and deposit amount.
+/// Token supply includes `PHANTOM_TOKEN_AMOUNT`, and NAV is all non-rent lamports in the pool
+/// stake account and on-ramp. Rounds down.
+pub fn calculate_deposit_amount(
+    pre_token_supply: u64,
+    pre_pool_nav: u64,
+    user_deposit_amount: u64,
+) -> Option<u64> {
+    if pre_pool_nav > 0 && pre_token_supply > 0 {
+        u64::try_from(
+            (user_deposit_amount as u128)
+                .checked_mul(pre_token_supply as u128)?
+                .checked_div(pre_pool_nav as u128)?,
+        )
+        .ok()
+    } else {
+        // this is unreachable for real pools but exists to satisfy unit tests
+        Some(user_deposit_amount)
+    }
+}
+
+/// Calculate pool value to return, given outstanding token supply, pool NAV, and tokens to redeem.
+/// Inputs are as for `calculate_deposit_amount()`. Rounds down.
+pub fn calculate_withdraw_amount(
+    pre_token_supply: u64,
+    pre_pool_nav: u64,
+    user_tokens_to_burn: u64,
+) -> Option<u64> {
+    let numerator = (user_tokens_to_burn as u128).checked_mul(pre_pool_nav as u128)?;
+    let denominator = pre_token_supply as u128;
+    if numerator >= denominator && denominator > 0 {
+        u64::try_from(numerator.checked_div(denominator)?).ok()
+    } else {
+        Some(0)
+    }
+}
+
+/// Calculate pool tokens to burn for a given quantity of pool value, rounding up in favor of the pool.
+/// Inputs are as for `calculate_deposit_amount()`. Returns `None` for a pool with no value.
+pub fn calculate_burn_amount(
+    pre_token_supply: u64,
+    pre_pool_nav: u64,
+    stake_to_withdraw: u64,
+) -> Option<u64> {
+    if pre_pool_nav == 0 {
+        return None;
+    }
+
+    let numerator = (stake_to_withdraw as u128).checked_mul(pre_token_supply as u128)?;
+    u64::try_from(numerator.div_ceil(pre_pool_nav as u128)).ok()
+}
+
+/// All canonical addresses belonging to a single pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolAddresses {
+    /// The pool account.
+    pub pool: Pubkey,
+    /// The main stake account.
+    pub stake: Pubkey,
+    /// The stake on-ramp account.
+    pub onramp: Pubkey,
+    /// The token mint.
+    pub mint: Pubkey,
+    /// The stake authority.
+    pub stake_authority: Pubkey,
+    /// The mint authority.
+    pub mint_authority: Pubkey,
+    /// The MPL authority.
+    pub mpl_authority: Pubkey,
+}
+impl PoolAddresses {
+    /// Find all canonical pool addresses for a given vote account.
+    pub fn derive(program_id: &Pubkey, vote_account_address: &Pubkey) -> Self {
+        Self::derive_from_pool(
+            program_id,
+            &find_pool_address(program_id, vote_account_address),
+        )
+    }
+
+    /// Find all canonical pool addresses for a given pool account.
+    pub fn derive_from_pool(program_id: &Pubkey, pool_address: &Pubkey) -> Self {
+        Self {
+            pool: *pool_address,
+            stake: find_pool_stake_address(program_id, pool_address),
+            onramp: find_pool_onramp_address(program_id, pool_address),
+            mint: find_pool_mint_address(program_id, pool_address),
+            stake_authority: find_pool_stake_authority_address(program_id, pool_address),
+            mint_authority: find_pool_mint_authority_address(program_id, pool_address),
+            mpl_authority: find_pool_mpl_authority_address(program_id, pool_address),
+        }
+    }
+}
+
+/// A program version, as returned by the `GetVersion` instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProgramVersion {
+    /// Major version.
+    pub major: u32,
+    /// Minor version.
+    pub minor: u32,
+    /// Patch version.
+    pub patch: u32,
+}
+impl ProgramVersion {
+    /// The version of this build of the program.
+    pub const CURRENT: Self = Self {
+        major: parse_version_component(env!("CARGO_PKG_VERSION_MAJOR")),
+        minor: parse_version_component(env!("CARGO_PKG_VERSION_MINOR")),
+        patch: parse_version_component(env!("CARGO_PKG_VERSION_PATCH")),
+    };
+
+    /// Length of the encoded version.
+    pub const LEN: usize = 12;
+
+    /// Encode as `GetVersion` return data: major, minor, and patch, each as a
+    /// little-endian u32. This layout will not change.
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        bytes[0..4].copy_from_slice(&self.major.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.minor.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.patch.to_le_bytes());
+        bytes
+    }
+
+    /// Decode `GetVersion` return data. Returns `None` if it is the wrong length.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::LEN {
+            return None;
+        }
+
+        let (major, rest) = bytes.split_at(4);
+        let (minor, patch) = rest.split_at(4);
+        Some(Self {
+            major: u32::from_le_bytes(major.try_into().unwrap()),
+            minor: u32::from_le_bytes(minor.try_into().unwrap()),
+            patch: u32::from_le_bytes(patch.try_into().unwrap()),
+        })
+    }
+}
+impl std::fmt::Display for ProgramVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+// cargo guarantees version components are plain decimal integers, so this only
+// fails (at compile time) if the manifest version is absurd
+const fn parse_version_component(component: &str) -> u32 {
+    let mut bytes = component.as_bytes();
+    let mut value: u32 = 0;
+    while let [digit, rest @ ..] = bytes {
+        let Some(shifted) = value.checked_mul(10) else {
+            panic!("version component out of range");
+        };
+        let Some(next) = shifted.checked_add(digit.wrapping_sub(b'0') as u32) else {
+            panic!("version component out of range");
+        };
+        value = next;
+        bytes = rest;
+    }
+    value
+}
@@ -2,6 +2,7 @@
 
 use {
     crate::{
+        calculate_burn_amount, calculate_deposit_amount, calculate_withdraw_amount,
         error::SinglePoolError,
         inline_mpl_token_metadata::{
             self,
@@ -9,21 +10,24 @@ use {
             pda::find_metadata_account,
             state::DataV2,
         },
+        inline_spl_token_2022,
         instruction::{self as svsp_instruction, SinglePoolInstruction},
+        minimum_pool_balance,
         state::{SinglePool, SinglePoolAccountType},
-        DEPOSIT_SOL_FEE_BPS, MAX_BPS, MINT_DECIMALS, PERPETUAL_NEW_WARMUP_COOLDOWN_RATE_EPOCH,
-        PHANTOM_TOKEN_AMOUNT, POOL_MINT_AUTHORITY_PREFIX, POOL_MINT_PREFIX,
-        POOL_MPL_AUTHORITY_PREFIX, POOL_ONRAMP_PREFIX, POOL_PREFIX, POOL_STAKE_AUTHORITY_PREFIX,
-        POOL_STAKE_PREFIX, VOTE_STATE_AUTHORIZED_WITHDRAWER_END,
-        VOTE_STATE_AUTHORIZED_WITHDRAWER_START, VOTE_STATE_DISCRIMINATOR_END,
+        ProgramVersion, DEPOSIT_SOL_FEE_BPS, EVENT_LOG_PREFIX, EVENT_LOG_VERSION, MAX_BPS,
+        MINT_DECIMALS, PERPETUAL_NEW_WARMUP_COOLDOWN_RATE_EPOCH, PHANTOM_TOKEN_AMOUNT,
+        POOL_MINT_AUTHORITY_PREFIX, POOL_MINT_PREFIX, POOL_MPL_AUTHORITY_PREFIX,
+        POOL_ONRAMP_PREFIX, POOL_PREFIX, POOL_STAKE_AUTHORITY_PREFIX, POOL_STAKE_PREFIX,
+        VOTE_STATE_AUTHORIZED_WITHDRAWER_END, VOTE_STATE_AUTHORIZED_WITHDRAWER_START,
+        VOTE_STATE_DISCRIMINATOR_END, WITHDRAW_SOL_FEE_BPS,
     },
     borsh::BorshDeserialize,
     solana_account_info::{next_account_info, AccountInfo},
     solana_borsh::v1::try_from_slice_unchecked,
     solana_clock::Clock,
-    solana_cpi::{invoke, invoke_signed},
+    solana_cpi::{invoke, invoke_signed, set_return_data},
+    solana_instruction::Instruction,
     solana_msg::msg,
-    solana_native_token::LAMPORTS_PER_SOL,
     solana_program_entrypoint::ProgramResult,
     solana_program_error::ProgramError,
     solana_program_pack::Pack,
@@ -37,7 +41,10 @@ use {
     solana_system_interface::{instruction as system_instruction, program as system_program},
     solana_sysvar::{Sysvar, SysvarSerialize},
     solana_vote_interface::program as vote_program,
-    spl_token_interface::{self as spl_token, state::Mint},
+    spl_token_interface::{
+        self as spl_token,
+        state::{Account as TokenAccount, Mint},
+    },
 };
 
 /// Determine the canonical value of the pool from its staked and stake-able lamports
@@ -63,40 +70,6 @@ fn pool_net_asset_value(
     main_stake_value.saturating_add(onramp_value)
 }
 
-/// Calculate pool tokens to mint, given outstanding token supply, pool NAV, and deposit amount
-fn calculate_deposit_amount(
-    pre_token_supply: u64,
-    pre_pool_nav: u64,
-    user_deposit_amount: u64,
-) -> Option<u64> {
-    if pre_pool_nav > 0 && pre_token_supply > 0 {
-        u64::try_from(
-            (user_deposit_amount as u128)
-                .checked_mul(pre_token_supply as u128)?
-                .checked_div(pre_pool_nav as u128)?,
-        )
-        .ok()
-    } else {
-        // this is unreachable for real pools but exists to satisfy unit tests
-        Some(user_deposit_amount)
-    }
-}
-
-/// Calculate pool value to return, given outstanding token supply, pool NAV, and tokens to redeem
-fn calculate_withdraw_amount(
-    pre_token_supply: u64,
-    pre_pool_nav: u64,
-    user_tokens_to_burn: u64,
-) -> Option<u64> {
-    let numerator = (user_tokens_to_burn as u128).checked_mul(pre_pool_nav as u128)?;
-    let denominator = pre_token_supply as u128;
-    if numerator >= denominator && denominator > 0 {
-        u64::try_from(numerator.checked_div(denominator)?).ok()
-    } else {
-        Some(0)
-    }
-}
-
 /// Deserialize the stake state from `AccountInfo`
 fn get_stake_state(stake_account_info: &AccountInfo) -> Result<(Meta, Stake), ProgramError> {
     match deserialize_stake(stake_account_info) {
@@ -117,6 +90,50 @@ fn deserialize_stake(stake_account_info: &AccountInfo) -> Result<StakeStateV2, P
     )?)
 }
 
+/// Determine how many lamports the stake program will release from a pool stake account
+/// without touching its delegation, while always leaving `retained_lamports` behind
+fn liquid_lamports(
+    stake_account_info: &AccountInfo,
+    rent: &Rent,
+    epoch: u64,
+    stake_history: &StakeHistorySysvar,
+    retained_lamports: u64,
+) -> Result<u64, ProgramError> {
+    // this mirrors the stake program: stake is locked unless deactivation has begun
+    let locked_lamports = match deserialize_stake(stake_account_info)? {
+        StakeStateV2::Stake(_, stake, _) if epoch >= stake.delegation.deactivation_epoch => {
+            stake
+                .delegation
+                .stake_activating_and_deactivating(
+                    epoch,
+                    stake_history,
+                    PERPETUAL_NEW_WARMUP_COOLDOWN_RATE_EPOCH,
+                )
+                .effective
+        }
+        StakeStateV2::Stake(_, stake, _) => stake.delegation.stake,
+        StakeStateV2::Initialized(_) => 0,
+        _ => return Err(SinglePoolError::WrongStakeState.into()),
+    };
+
+    Ok(stake_account_info
+        .lamports()
+        .saturating_sub(rent.minimum_balance(stake_account_info.data_len()))
+        .saturating_sub(std::cmp::max(locked_lamports, retained_lamports)))
+}
+
+/// Log a machine-readable event for tokens minted or burned against lamports moved
+fn log_event(event: &str, token_amount: u64, lamports: u64) {
+    msg!(
+        "{} v{} {} token_amount={} lamports={}",
+        EVENT_LOG_PREFIX,
+        EVENT_LOG_VERSION,
+        event,
+        token_amount,
+        lamports
+    );
+}
+
 /// Determine if stake is fully active with history
 fn is_stake_fully_active(stake_activation_status: &StakeActivationStatus) -> bool {
     matches!(stake_activation_status, StakeActivationStatus {
@@ -126,6 +143,18 @@ fn is_stake_fully_active(stake_activation_status: &StakeActivationStatus) -> boo
         } if *effective > 0)
 }
 
+/// Determine if pool stake is deactivating this epoch, or has fully deactivated, and so must be redelegated.
+/// This may happen as a result of `DeactivateDelinquent`
+fn is_pool_stake_deactivated(
+    pool_stake: &Stake,
+    pool_stake_status: &StakeActivationStatus,
+    current_epoch: u64,
+) -> bool {
+    let deactivation_epoch = pool_stake.delegation.deactivation_epoch;
+    deactivation_epoch == current_epoch
+        || (deactivation_epoch < current_epoch && pool_stake_status.effective == 0)
+}
+
 /// Determine if stake is newly activating with history
 fn is_stake_newly_activating(stake_activation_status: &StakeActivationStatus) -> bool {
     matches!(stake_activation_status, StakeActivationStatus {
@@ -270,6 +299,40 @@ fn check_pool_pda(
     }
 }
 
+/// Take the deprecated stake config account if it is next. The stake program ignores it,
+/// and it is being removed from clusters, so callers may pass it or omit it
+fn next_stake_config_account_info<'a, 'b>(
+    account_info_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+) -> Option<&'a AccountInfo<'b>> {
+    #[allow(deprecated)]
+    let is_stake_config = account_info_iter
+        .as_slice()
+        .first()
+        .is_some_and(|account_info| *account_info.key == stake::config::id());
+
+    if is_stake_config {
+        account_info_iter.next()
+    } else {
+        None
+    }
+}
+
+/// Drop the stake config account from an instruction we are about to invoke, if our
+/// caller omitted it, so we never ask for an account we were not given
+fn match_stake_config(
+    mut instruction: Instruction,
+    stake_config_info: Option<&AccountInfo>,
+) -> Instruction {
+    if stake_config_info.is_none() {
+        #[allow(deprecated)]
+        instruction
+            .accounts
+            .retain(|account_meta| account_meta.pubkey != stake::config::id());
+    }
+
+    instruction
+}
+
 /// Check vote account is owned by the vote program and not a legacy variant
 fn check_vote_account(vote_account_info: &AccountInfo) -> Result<(), ProgramError> {
     check_account_owner(vote_account_info, &vote_program::id())?;
@@ -301,6 +364,31 @@ fn check_pool_mint_with_supply(
     Ok(pool_mint.supply.saturating_add(PHANTOM_TOKEN_AMOUNT))
 }
 
+/// Check user token account is a token account for the pool mint
+fn check_user_token_account_mint(
+    user_token_account_info: &AccountInfo,
+    pool_mint_address: &Pubkey,
+) -> Result<(), ProgramError> {
+    let user_token_account_data = user_token_account_info.try_borrow_data()?;
+
+    // token-2022 accounts may carry extensions after the base account layout
+    let user_token_account = user_token_account_data
+        .get(..TokenAccount::LEN)
+        .ok_or(ProgramError::InvalidAccountData)
+        .and_then(TokenAccount::unpack)?;
+
+    if user_token_account.mint != *pool_mint_address {
+        msg!(
+            "Expected token account for mint {}, received token account for mint {}",
+            pool_mint_address,
+            user_token_account.mint,
+        );
+        Err(SinglePoolError::InvalidTokenAccountMint.into())
+    } else {
+        Ok(())
+    }
+}
+
 /// Check MPL metadata account address for the pool mint
 fn check_mpl_metadata_account_address(
     metadata_address: &Pubkey,
@@ -328,12 +416,13 @@ fn check_system_program(program_id: &Pubkey) -> Result<(), ProgramError> {
     }
 }
 
-/// Check token program address
+/// Check token program address. Either the original token program or Token-2022 is accepted
 fn check_token_program(address: &Pubkey) -> Result<(), ProgramError> {
-    if *address != spl_token::id() {
+    if *address != spl_token::id() && *address != inline_spl_token_2022::id() {
         msg!(
-            "Incorrect token program, expected {}, received {}",
+            "Incorrect token program, expected {} or {}, received {}",
             spl_token::id(),
+            inline_spl_token_2022::id(),
             address
         );
         Err(ProgramError::IncorrectProgramId)
@@ -387,17 +476,10 @@ fn check_account_owner(
     }
 }
 
-/// Minimum balance of delegated stake required to create a pool. We require at least
-/// 1 sol to avoid minting tokens for these lamports (locking them in the pool) since
-/// they will *become* locked after the BPF Stake 5.0.0 upgrade.
-///
-/// We also track any future (currently unplanned) minimum delegation increase, to ensure
-/// a new pool is always valid for `DelegateStake`.
-fn minimum_pool_balance() -> Result<u64, ProgramError> {
-    Ok(std::cmp::max(
-        stake::tools::get_minimum_delegation()?,
-        LAMPORTS_PER_SOL,
-    ))
+/// Minimum balance of delegated stake required to create a pool, under the current
+/// stake program minimum delegation.
+fn get_minimum_pool_balance() -> Result<u64, ProgramError> {
+    Ok(minimum_pool_balance(stake::tools::get_minimum_delegation()?))
 }
 
 /// Program state handler.
@@ -564,13 +646,14 @@ impl Processor {
         let signers = &[&authority_seeds[..]];
 
         let ix = spl_token::instruction::mint_to(
-            token_program.key,
+            &spl_token::id(),
             mint.key,
             destination.key,
             authority.key,
             &[],
             amount,
         )?;
+        let ix = inline_spl_token_2022::retarget(ix, token_program.key);
 
         invoke_signed(&ix, &[mint, destination, authority], signers)
     }
@@ -593,13 +676,14 @@ impl Processor {
         let signers = &[&authority_seeds[..]];
 
         let ix = spl_token::instruction::burn(
-            token_program.key,
+            &spl_token::id(),
             burn_account.key,
             mint.key,
             authority.key,
             &[],
             amount,
         )?;
+        let ix = inline_spl_token_2022::retarget(ix, token_program.key);
 
         invoke_signed(&ix, &[burn_account, mint, authority], signers)
     }
@@ -616,7 +700,7 @@ impl Processor {
         let rent = &Rent::from_account_info(rent_info)?;
         let clock_info = next_account_info(account_info_iter)?;
         let stake_history_info = next_account_info(account_info_iter)?;
-        let stake_config_info = next_account_info(account_info_iter)?;
+        let stake_config_info = next_stake_config_account_info(account_info_iter);
         let system_program_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
         let stake_program_info = next_account_info(account_info_iter)?;
@@ -714,20 +798,23 @@ impl Processor {
         )?;
 
         invoke_signed(
-            &spl_token::instruction::initialize_mint2(
+            &inline_spl_token_2022::retarget(
+                spl_token::instruction::initialize_mint2(
+                    &spl_token::id(),
+                    pool_mint_info.key,
+                    pool_mint_authority_info.key,
+                    None,
+                    MINT_DECIMALS,
+                )?,
                 token_program_info.key,
-                pool_mint_info.key,
-                pool_mint_authority_info.key,
-                None,
-                MINT_DECIMALS,
-            )?,
+            ),
             core::slice::from_ref(pool_mint_info),
             mint_authority_signers,
         )?;
 
         // create the pool stake account. user has already transferred in rent plus at
         // least the minimum
-        let minimum_pool_balance = minimum_pool_balance()?;
+        let minimum_pool_balance = get_minimum_pool_balance()?;
         let stake_space = StakeStateV2::size_of();
         let stake_rent_plus_initial = rent
             .minimum_balance(stake_space)
@@ -764,19 +851,24 @@ impl Processor {
 
         // delegate stake so it activates
         invoke_signed(
-            &stake::instruction::delegate_stake(
-                pool_stake_info.key,
-                pool_stake_authority_info.key,
-                vote_account_info.key,
+            &match_stake_config(
+                stake::instruction::delegate_stake(
+                    pool_stake_info.key,
+                    pool_stake_authority_info.key,
+                    vote_account_info.key,
+                ),
+                stake_config_info,
             ),
             &[
                 pool_stake_info.clone(),
                 vote_account_info.clone(),
                 clock_info.clone(),
                 stake_history_info.clone(),
-                stake_config_info.clone(),
                 pool_stake_authority_info.clone(),
-            ],
+            ]
+            .into_iter()
+            .chain(stake_config_info.cloned())
+            .collect::<Vec<_>>(),
             stake_authority_signers,
         )?;
 
@@ -793,7 +885,7 @@ impl Processor {
         let clock_info = next_account_info(account_info_iter)?;
         let clock = &Clock::from_account_info(clock_info)?;
         let stake_history_info = next_account_info(account_info_iter)?;
-        let stake_config_info = next_account_info(account_info_iter)?;
+        let stake_config_info = next_stake_config_account_info(account_info_iter);
         let stake_program_info = next_account_info(account_info_iter)?;
 
         let rent = Rent::get()?;
@@ -854,25 +946,26 @@ impl Processor {
         let stake_authority_signers = &[&stake_authority_seeds[..]];
 
         // if pool stake is deactivating this epoch, or has fully deactivated, delegate it
-        // this may happen as a result of `DeactivateDelinquent`
-        if pool_stake_state.delegation.deactivation_epoch == clock.epoch
-            || (pool_stake_state.delegation.deactivation_epoch < clock.epoch
-                && pool_stake_status.effective == 0)
-        {
+        if is_pool_stake_deactivated(&pool_stake_state, &pool_stake_status, clock.epoch) {
             invoke_signed(
-                &stake::instruction::delegate_stake(
-                    pool_stake_info.key,
-                    pool_stake_authority_info.key,
-                    vote_account_info.key,
+                &match_stake_config(
+                    stake::instruction::delegate_stake(
+                        pool_stake_info.key,
+                        pool_stake_authority_info.key,
+                        vote_account_info.key,
+                    ),
+                    stake_config_info,
                 ),
                 &[
                     pool_stake_info.clone(),
                     vote_account_info.clone(),
                     clock_info.clone(),
                     stake_history_info.clone(),
-                    stake_config_info.clone(),
                     pool_stake_authority_info.clone(),
-                ],
+                ]
+                .into_iter()
+                .chain(stake_config_info.cloned())
+                .collect::<Vec<_>>(),
                 stake_authority_signers,
             )?;
         }
@@ -955,19 +1048,24 @@ impl Processor {
 
             if must_delegate_onramp {
                 invoke_signed(
-                    &stake::instruction::delegate_stake(
-                        pool_onramp_info.key,
-                        pool_stake_authority_info.key,
-                        vote_account_info.key,
+                    &match_stake_config(
+                        stake::instruction::delegate_stake(
+                            pool_onramp_info.key,
+                            pool_stake_authority_info.key,
+                            vote_account_info.key,
+                        ),
+                        stake_config_info,
                     ),
                     &[
                         pool_onramp_info.clone(),
                         vote_account_info.clone(),
                         clock_info.clone(),
                         stake_history_info.clone(),
-                        stake_config_info.clone(),
                         pool_stake_authority_info.clone(),
-                    ],
+                    ]
+                    .into_iter()
+                    .chain(stake_config_info.cloned())
+                    .collect::<Vec<_>>(),
                     stake_authority_signers,
                 )?;
             }
@@ -976,6 +1074,81 @@ impl Processor {
         Ok(())
     }
 
+    fn process_reactivate_pool_stake(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let vote_account_info = next_account_info(account_info_iter)?;
+        let pool_info = next_account_info(account_info_iter)?;
+        let pool_stake_info = next_account_info(account_info_iter)?;
+        let pool_stake_authority_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(clock_info)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let stake_config_info = next_stake_config_account_info(account_info_iter);
+        let stake_program_info = next_account_info(account_info_iter)?;
+
+        let stake_history = &StakeHistorySysvar(clock.epoch);
+
+        check_vote_account(vote_account_info)?;
+        check_pool_address(program_id, vote_account_info.key, pool_info.key)?;
+
+        SinglePool::from_account_info(pool_info, program_id)?;
+
+        check_pool_stake_address(program_id, pool_info.key, pool_stake_info.key)?;
+        let stake_authority_bump_seed = check_pool_stake_authority_address(
+            program_id,
+            pool_info.key,
+            pool_stake_authority_info.key,
+        )?;
+        check_stake_program(stake_program_info.key)?;
+
+        let (_, pool_stake_state) = get_stake_state(pool_stake_info)?;
+        let pool_stake_status = pool_stake_state
+            .delegation
+            .stake_activating_and_deactivating(
+                clock.epoch,
+                stake_history,
+                PERPETUAL_NEW_WARMUP_COOLDOWN_RATE_EPOCH,
+            );
+
+        if !is_pool_stake_deactivated(&pool_stake_state, &pool_stake_status, clock.epoch) {
+            return Err(SinglePoolError::PoolStakeNotDeactivated.into());
+        }
+
+        let stake_authority_seeds = &[
+            POOL_STAKE_AUTHORITY_PREFIX,
+            pool_info.key.as_ref(),
+            &[stake_authority_bump_seed],
+        ];
+        let stake_authority_signers = &[&stake_authority_seeds[..]];
+
+        invoke_signed(
+            &match_stake_config(
+                stake::instruction::delegate_stake(
+                    pool_stake_info.key,
+                    pool_stake_authority_info.key,
+                    vote_account_info.key,
+                ),
+                stake_config_info,
+            ),
+            &[
+                pool_stake_info.clone(),
+                vote_account_info.clone(),
+                clock_info.clone(),
+                stake_history_info.clone(),
+                pool_stake_authority_info.clone(),
+            ]
+            .into_iter()
+            .chain(stake_config_info.cloned())
+            .collect::<Vec<_>>(),
+            stake_authority_signers,
+        )?;
+
+        Ok(())
+    }
+
     fn process_deposit_stake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let pool_info = next_account_info(account_info_iter)?;
@@ -1012,6 +1185,7 @@ impl Processor {
             pool_mint_authority_info.key,
         )?;
         check_token_program(token_program_info.key)?;
+        check_account_owner(pool_mint_info, token_program_info.key)?;
         check_stake_program(stake_program_info.key)?;
 
         if pool_stake_info.key == user_stake_info.key {
@@ -1022,6 +1196,9 @@ impl Processor {
             return Err(SinglePoolError::InvalidPoolStakeAccountUsage.into());
         }
 
+        // the mint cpi would also fail, but opaquely
+        check_user_token_account_mint(user_token_account_info, pool_mint_info.key)?;
+
         let (pre_pool_stake, pool_is_active, pool_is_activating) = {
             let (_, pool_stake_state) = get_stake_state(pool_stake_info)?;
             let pool_stake_status = pool_stake_state
@@ -1080,11 +1257,14 @@ impl Processor {
         // user must have set authority to pool and have no lockup for merge to succeed
         if user_stake_meta.authorized
             != stake::state::Authorized::auto(pool_stake_authority_info.key)
-            || user_stake_meta.lockup.is_in_force(clock, None)
         {
             return Err(SinglePoolError::WrongStakeState.into());
         }
 
+        if user_stake_meta.lockup.is_in_force(clock, None) {
+            return Err(SinglePoolError::StakeLockupActive.into());
+        }
+
         // user can deposit active stake into an active pool, or activating or inactive stake into an activating pool
         if pool_is_active && is_stake_fully_active(&user_stake_status) {
             // ok: active <- active
@@ -1158,6 +1338,8 @@ impl Processor {
             )?;
         }
 
+        log_event("deposit_stake", new_pool_tokens, new_stake_added);
+
         Ok(())
     }
 
@@ -1200,6 +1382,7 @@ impl Processor {
             pool_mint_authority_info.key,
         )?;
         check_token_program(token_program_info.key)?;
+        check_account_owner(pool_mint_info, token_program_info.key)?;
         check_stake_program(stake_program_info.key)?;
 
         if pool_stake_info.key == user_stake_info.key {
@@ -1263,7 +1446,7 @@ impl Processor {
         let pre_total_nav = pool_net_asset_value(pool_stake_info, pool_onramp_info, rent);
 
         // withdraw amount is determined off pool NAV just like deposit amount
-        let stake_to_withdraw =
+        let mut stake_to_withdraw =
             calculate_withdraw_amount(token_supply, pre_total_nav, token_amount)
                 .ok_or(SinglePoolError::UnexpectedMathError)?;
 
@@ -1272,21 +1455,37 @@ impl Processor {
             return Err(SinglePoolError::WithdrawalTooSmall.into());
         }
 
+        // if we do not have enough value to service this withdrawal, we withdraw as much as we can
+        // and only burn the tokens it corresponds to. this does *not* mean the value isnt in the pool,
+        // merely that it is not duly splittable: it may be activating, sitting in the onramp until
+        // a `ReplenishPool` cycle, or stranded by a minimum delegation increase
+        let mut tokens_to_burn = token_amount;
+        if stake_to_withdraw > withdrawable_value {
+            stake_to_withdraw = withdrawable_value;
+            tokens_to_burn = calculate_burn_amount(token_supply, pre_total_nav, stake_to_withdraw)
+                .ok_or(SinglePoolError::UnexpectedMathError)?;
+
+            msg!(
+                "Withdrawal clamped to {} lamports, burning {} tokens; {} tokens were not redeemed",
+                stake_to_withdraw,
+                tokens_to_burn,
+                token_amount.saturating_sub(tokens_to_burn),
+            );
+        }
+
         // this is impossible but we guard explicitly because it would put the pool in an unrecoverable state
         if stake_to_withdraw == pool_stake_info.lamports() {
             return Err(SinglePoolError::WithdrawalViolatesPoolRequirements.into());
         }
 
-        // if the destination would be in any non-inactive state it must meet minimum delegation
+        // if the destination would be in any non-inactive state it must meet minimum delegation.
+        // if the user asked for enough but we clamped, the user must wait for more splittable stake
         if !pool_is_fully_inactive && stake_to_withdraw < minimum_delegation {
-            return Err(SinglePoolError::WithdrawalTooSmall.into());
-        }
-
-        // if we do not have enough value to service this withdrawal, the user must wait a `ReplenishPool` cycle.
-        // this does *not* mean the value isnt in the pool, merely that it is not duly splittable.
-        // this check should always come last to avoid returning it if the withdrawal is actually invalid
-        if stake_to_withdraw > withdrawable_value {
-            return Err(SinglePoolError::WithdrawalTooLarge.into());
+            return if tokens_to_burn < token_amount {
+                Err(SinglePoolError::WithdrawalTooLarge.into())
+            } else {
+                Err(SinglePoolError::WithdrawalTooSmall.into())
+            };
         }
 
         // burn user tokens corresponding to the amount of stake they wish to withdraw
@@ -1297,7 +1496,7 @@ impl Processor {
             pool_mint_info.clone(),
             pool_mint_authority_info.clone(),
             mint_authority_bump_seed,
-            token_amount,
+            tokens_to_burn,
         )?;
 
         // split stake into a blank stake account the user has created for this purpose
@@ -1320,6 +1519,8 @@ impl Processor {
             clock_info.clone(),
         )?;
 
+        log_event("withdraw_stake", tokens_to_burn, stake_to_withdraw);
+
         Ok(())
     }
 
@@ -1583,7 +1784,7 @@ impl Processor {
         let clock_info = next_account_info(account_info_iter)?;
         let clock = &Clock::from_account_info(clock_info)?;
         let stake_history_info = next_account_info(account_info_iter)?;
-        let stake_config_info = next_account_info(account_info_iter)?;
+        let stake_config_info = next_stake_config_account_info(account_info_iter);
         let system_program_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
         let stake_program_info = next_account_info(account_info_iter)?;
@@ -1612,6 +1813,7 @@ impl Processor {
         )?;
         check_system_program(system_program_info.key)?;
         check_token_program(token_program_info.key)?;
+        check_account_owner(pool_mint_info, token_program_info.key)?;
         check_stake_program(stake_program_info.key)?;
         if svsp_program_info.key != program_id {
             msg!(
@@ -1711,7 +1913,10 @@ impl Processor {
 
         // replenish to delegate the deposit. this safely returns Ok if onramp doesnt meet minimum delegation
         invoke(
-            &svsp_instruction::replenish_pool(program_id, vote_account_info.key),
+            &match_stake_config(
+                svsp_instruction::replenish_pool(program_id, vote_account_info.key),
+                stake_config_info,
+            ),
             &[
                 vote_account_info.clone(),
                 pool_info.clone(),
@@ -1720,11 +1925,159 @@ impl Processor {
                 pool_stake_authority_info.clone(),
                 clock_info.clone(),
                 stake_history_info.clone(),
-                stake_config_info.clone(),
                 stake_program_info.clone(),
-            ],
+            ]
+            .into_iter()
+            .chain(stake_config_info.cloned())
+            .collect::<Vec<_>>(),
         )?;
 
+        log_event("deposit_sol", new_pool_tokens, deposit_amount);
+
+        Ok(())
+    }
+
+    fn process_withdraw_sol(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        token_amount: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let pool_stake_info = next_account_info(account_info_iter)?;
+        let pool_onramp_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let pool_stake_authority_info = next_account_info(account_info_iter)?;
+        let pool_mint_authority_info = next_account_info(account_info_iter)?;
+        let user_token_account_info = next_account_info(account_info_iter)?;
+        let user_lamport_account_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(clock_info)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
+
+        let rent = &Rent::get()?;
+        let stake_history = &StakeHistorySysvar(clock.epoch);
+
+        SinglePool::from_account_info(pool_info, program_id)?;
+
+        check_pool_stake_address(program_id, pool_info.key, pool_stake_info.key)?;
+        check_pool_onramp_address(program_id, pool_info.key, pool_onramp_info.key)?;
+        let token_supply = check_pool_mint_with_supply(program_id, pool_info.key, pool_mint_info)?;
+        let stake_authority_bump_seed = check_pool_stake_authority_address(
+            program_id,
+            pool_info.key,
+            pool_stake_authority_info.key,
+        )?;
+        let mint_authority_bump_seed = check_pool_mint_authority_address(
+            program_id,
+            pool_info.key,
+            pool_mint_authority_info.key,
+        )?;
+        check_token_program(token_program_info.key)?;
+        check_account_owner(pool_mint_info, token_program_info.key)?;
+        check_stake_program(stake_program_info.key)?;
+
+        if pool_stake_info.key == user_lamport_account_info.key {
+            return Err(SinglePoolError::InvalidPoolStakeAccountUsage.into());
+        }
+
+        if pool_onramp_info.key == user_lamport_account_info.key {
+            return Err(SinglePoolError::InvalidPoolStakeAccountUsage.into());
+        }
+
+        if token_amount == 0 {
+            return Err(SinglePoolError::WithdrawalTooSmall.into());
+        }
+
+        // onramp must exist, for the same reason as in `WithdrawStake`
+        match deserialize_stake(pool_onramp_info) {
+            Ok(StakeStateV2::Initialized(_)) | Ok(StakeStateV2::Stake(_, _, _)) => (),
+            _ => return Err(SinglePoolError::OnRampDoesntExist.into()),
+        };
+
+        // withdraw amount is determined off pool NAV exactly as in `WithdrawStake`, less a fee
+        // which stays in the pool. this prices skipping the cooldown, so there is no free unstake
+        let pre_total_nav = pool_net_asset_value(pool_stake_info, pool_onramp_info, rent);
+        let lamports_to_withdraw = {
+            let raw_lamports = calculate_withdraw_amount(token_supply, pre_total_nav, token_amount)
+                .ok_or(SinglePoolError::UnexpectedMathError)?;
+
+            // we round division down and reject withdrawals too small to generate a fee, as in `DepositSol`
+            let withdraw_sol_fee = raw_lamports
+                .checked_mul(WITHDRAW_SOL_FEE_BPS)
+                .and_then(|n| n.checked_div(MAX_BPS))
+                .ok_or(SinglePoolError::UnexpectedMathError)?;
+
+            if withdraw_sol_fee == 0 {
+                return Err(SinglePoolError::WithdrawalTooSmall.into());
+            }
+
+            raw_lamports.saturating_sub(withdraw_sol_fee)
+        };
+
+        // sanity, should be impossible per above
+        if lamports_to_withdraw == 0 {
+            return Err(SinglePoolError::UnexpectedMathError.into());
+        }
+
+        // we never touch delegations here, so the main account keeps at least the minimum pool balance
+        let onramp_liquid_lamports =
+            liquid_lamports(pool_onramp_info, rent, clock.epoch, stake_history, 0)?;
+        let main_liquid_lamports = liquid_lamports(
+            pool_stake_info,
+            rent,
+            clock.epoch,
+            stake_history,
+            get_minimum_pool_balance()?,
+        )?;
+
+        if lamports_to_withdraw > onramp_liquid_lamports.saturating_add(main_liquid_lamports) {
+            return Err(SinglePoolError::InsufficientLiquidity.into());
+        }
+
+        // burn user tokens corresponding to the amount of sol they wish to withdraw
+        Self::token_burn(
+            pool_info.key,
+            token_program_info.clone(),
+            user_token_account_info.clone(),
+            pool_mint_info.clone(),
+            pool_mint_authority_info.clone(),
+            mint_authority_bump_seed,
+            token_amount,
+        )?;
+
+        // drain the onramp first, since its lamports are typically waiting to be delegated anyway
+        let lamports_from_onramp = std::cmp::min(lamports_to_withdraw, onramp_liquid_lamports);
+        let lamports_from_main = lamports_to_withdraw.saturating_sub(lamports_from_onramp);
+
+        for (stake_account_info, lamports) in [
+            (pool_onramp_info, lamports_from_onramp),
+            (pool_stake_info, lamports_from_main),
+        ] {
+            if lamports > 0 {
+                Self::stake_withdraw(
+                    pool_info.key,
+                    stake_account_info.clone(),
+                    pool_stake_authority_info.clone(),
+                    stake_authority_bump_seed,
+                    user_lamport_account_info.clone(),
+                    clock_info.clone(),
+                    stake_history_info.clone(),
+                    lamports,
+                )?;
+            }
+        }
+
+        log_event("withdraw_sol", token_amount, lamports_to_withdraw);
+
+        Ok(())
+    }
+
+    fn process_get_version() -> ProgramResult {
+        set_return_data(&ProgramVersion::CURRENT.to_bytes());
+
         Ok(())
     }
 
@@ -1772,6 +2125,18 @@ impl Processor {
                 msg!("Instruction: DepositSol");
                 Self::process_deposit_sol(program_id, accounts, lamports)
             }
+            SinglePoolInstruction::WithdrawSol { token_amount } => {
+                msg!("Instruction: WithdrawSol");
+                Self::process_withdraw_sol(program_id, accounts, token_amount)
+            }
+            SinglePoolInstruction::ReactivateAfterDelinquency => {
+                msg!("Instruction: ReactivateAfterDelinquency");
+                Self::process_reactivate_pool_stake(program_id, accounts)
+            }
+            SinglePoolInstruction::GetVersion => {
+                msg!("Instruction: GetVersion");
+                Self::process_get_version()
+            }
         }
     }
 }
@@ -1788,6 +2153,7 @@ mod tests {
             seq::IteratorRandom,
             RngExt, SeedableRng,
         },
+        solana_native_token::LAMPORTS_PER_SOL,
         std::collections::BTreeMap,
         test_case::test_case,
     };
@@ -1920,6 +2286,22 @@ mod tests {
         assert_relative_eq!(pool.share(&alice), 1.0);
     }
 
+    #[test]
+    fn burn_amount_rounds_up() {
+        // 1:1 ratio is exact
+        assert_eq!(calculate_burn_amount(5000, 5000, 1000), Some(1000));
+
+        // tokens are worth 1.5 stake. 1000 stake needs 666.67 tokens, which rounds to 667
+        assert_eq!(calculate_burn_amount(5000, 7500, 1000), Some(667));
+
+        // burning the result must never yield less than requested
+        let tokens = calculate_burn_amount(5000, 7500, 1000).unwrap();
+        assert!(calculate_withdraw_amount(5000, 7500, tokens).unwrap() >= 1000);
+
+        // empty pools cannot be priced
+        assert_eq!(calculate_burn_amount(5000, 0, 1000), None);
+    }
+
     // this stochastically tests calculate_deposit_amount and
     // calculate_withdraw_amount the objective is specifically to ensure that
     // the math does not fail on any combination of state changes the no_minimum
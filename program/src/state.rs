@@ -38,22 +38,43 @@ impl SinglePool {
         account_info: &AccountInfo,
         program_id: &Pubkey,
     ) -> Result<Self, ProgramError> {
-        // pool is allocated and owned by this program
-        if account_info.data_len() == 0 || account_info.owner != program_id {
+        let pool =
+            Self::try_from_account(account_info.owner, &account_info.data.borrow(), program_id)?;
+
+        // pool vote account address is properly configured. in practice this is
+        // irrefutable because the pool is initialized from the address that
+        // derives it, and never modified
+        if *account_info.key != find_pool_address(program_id, &pool.vote_account_address) {
             return Err(SinglePoolError::InvalidPoolAccount.into());
         }
 
-        let pool = try_from_slice_unchecked::<SinglePool>(&account_info.data.borrow())?;
+        Ok(pool)
+    }
 
-        // pool is well-typed
-        if pool.account_type != SinglePoolAccountType::Pool {
+    /// Create a `SinglePool` struct from the owner and data of an account fetched over RPC.
+    /// This performs the same ownership and type checks as `from_account_info()`, but since
+    /// no address is given, callers who need to confirm the pool matches its vote account
+    /// should compare against `find_pool_address()`
+    pub fn try_from_account(
+        owner: &Pubkey,
+        data: &[u8],
+        program_id: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        // pool is allocated and owned by this program
+        if data.is_empty() || owner != program_id {
             return Err(SinglePoolError::InvalidPoolAccount.into());
         }
 
-        // pool vote account address is properly configured. in practice this is
-        // irrefutable because the pool is initialized from the address that
-        // derives it, and never modified
-        if *account_info.key != find_pool_address(program_id, &pool.vote_account_address) {
+        // an account type we cannot parse was written by a newer program version. this is
+        // distinct from an uninitialized or mistyped pool, so clients can tell the difference
+        if SinglePoolAccountType::try_from_slice(&data[..1]).is_err() {
+            return Err(SinglePoolError::UnsupportedPoolVersion.into());
+        }
+
+        let pool = try_from_slice_unchecked::<SinglePool>(data)?;
+
+        // pool is well-typed
+        if pool.account_type != SinglePoolAccountType::Pool {
             return Err(SinglePoolError::InvalidPoolAccount.into());
         }
 
@@ -69,4 +90,47 @@ mod tests {
     fn single_pool_size_of() {
         assert_eq!(SinglePool::size_of(), get_packed_len::<SinglePool>());
     }
+
+    #[test]
+    fn single_pool_try_from_account() {
+        let program_id = Pubkey::new_unique();
+        let pool = SinglePool {
+            account_type: SinglePoolAccountType::Pool,
+            vote_account_address: Pubkey::new_unique(),
+        };
+        let mut data = borsh::to_vec(&pool).unwrap();
+
+        assert_eq!(
+            SinglePool::try_from_account(&program_id, &data, &program_id).unwrap(),
+            pool
+        );
+
+        // wrong owner
+        assert_eq!(
+            SinglePool::try_from_account(&Pubkey::new_unique(), &data, &program_id).unwrap_err(),
+            SinglePoolError::InvalidPoolAccount.into()
+        );
+
+        // uninitialized
+        data = borsh::to_vec(&SinglePool::default()).unwrap();
+        assert_eq!(
+            SinglePool::try_from_account(&program_id, &data, &program_id).unwrap_err(),
+            SinglePoolError::InvalidPoolAccount.into()
+        );
+
+        // unknown account type
+        data = borsh::to_vec(&pool).unwrap();
+        data[0] = 2;
+        assert_eq!(
+            SinglePool::try_from_account(&program_id, &data, &program_id).unwrap_err(),
+            SinglePoolError::UnsupportedPoolVersion.into()
+        );
+
+        // unallocated
+        data = vec![];
+        assert_eq!(
+            SinglePool::try_from_account(&program_id, &data, &program_id).unwrap_err(),
+            SinglePoolError::InvalidPoolAccount.into()
+        );
+    }
 }
@@ -1,4 +1,12 @@
-#![allow(dead_code)] // needed because cargo doesn't understand test usage
+//! Helpers for testing against a real single-validator stake pool in
+//! `solana-program-test`, for this program's own tests and for programs that
+//! CPI into it.
+//!
+//! `program_test` loads the stake, MPL token metadata, and single-pool programs
+//! by name, so the stake program builds named in `StakeProgramVersion`,
+//! `mpl_token_metadata.so`, and `spl_single_pool.so` must be in
+//! `tests/fixtures` or `SBF_OUT_DIR`.
+
 #![allow(clippy::arithmetic_side_effects)]
 #![allow(clippy::uninlined_format_args)]
 
@@ -22,7 +30,7 @@ use {
         instruction as vote_instruction,
         state::{VoteInit, VoteStateV4},
     },
-    spl_associated_token_account_interface::address::get_associated_token_address,
+    spl_associated_token_account_interface::address::get_associated_token_address_with_program_id,
     spl_single_pool::{
         find_pool_address, find_pool_mint_address, find_pool_mint_authority_address,
         find_pool_mpl_authority_address, find_pool_onramp_address, find_pool_stake_address,
@@ -39,6 +47,7 @@ pub mod stake;
 pub use stake::*;
 
 pub const FIRST_NORMAL_EPOCH: u64 = 15;
+pub const PACKET_DATA_SIZE: usize = 1232; // max serialized transaction size
 pub const USER_STARTING_LAMPORTS: u64 = 10_000_000_000_000; // 10k sol
 
 // this is a convenience to test multiple versions that regularly change without updating test cases
@@ -124,8 +133,9 @@ impl SinglePoolAccounts {
             advance_epoch(context).await;
         }
 
-        let instructions = instruction::deposit(
+        let instructions = instruction::deposit_with_token_program(
             &id(),
+            &self.token_program_id,
             &self.pool,
             &self.alice_stake.pubkey(),
             &self.alice_token,
@@ -155,8 +165,9 @@ impl SinglePoolAccounts {
         .await;
 
         if maybe_bob_amount.is_some() {
-            let instructions = instruction::deposit(
+            let instructions = instruction::deposit_with_token_program(
                 &id(),
+                &self.token_program_id,
                 &self.pool,
                 &self.bob_stake.pubkey(),
                 &self.bob_token,
@@ -279,8 +290,9 @@ impl SinglePoolAccounts {
         )
         .await;
 
-        let instructions = instruction::initialize(
+        let instructions = instruction::initialize_with_token_program(
             &id(),
+            &self.token_program_id,
             &self.vote_account.pubkey(),
             &context.payer.pubkey(),
             &rent,
@@ -323,6 +335,7 @@ impl SinglePoolAccounts {
             &self.alice.pubkey(),
             &context.last_blockhash,
             &self.mint,
+            &self.token_program_id,
         )
         .await;
 
@@ -332,6 +345,7 @@ impl SinglePoolAccounts {
             &self.bob.pubkey(),
             &context.last_blockhash,
             &self.mint,
+            &self.token_program_id,
         )
         .await;
 
@@ -340,6 +354,11 @@ impl SinglePoolAccounts {
 }
 impl Default for SinglePoolAccounts {
     fn default() -> Self {
+        Self::with_token_program(spl_token::id())
+    }
+}
+impl SinglePoolAccounts {
+    pub fn with_token_program(token_program_id: Pubkey) -> Self {
         let vote_account = Keypair::new();
         let alice = Keypair::new();
         let bob = Keypair::new();
@@ -360,11 +379,19 @@ impl Default for SinglePoolAccounts {
             vote_account,
             alice_stake: Keypair::new(),
             bob_stake: Keypair::new(),
-            alice_token: get_associated_token_address(&alice.pubkey(), &mint),
-            bob_token: get_associated_token_address(&bob.pubkey(), &mint),
+            alice_token: get_associated_token_address_with_program_id(
+                &alice.pubkey(),
+                &mint,
+                &token_program_id,
+            ),
+            bob_token: get_associated_token_address_with_program_id(
+                &bob.pubkey(),
+                &mint,
+                &token_program_id,
+            ),
             alice,
             bob,
-            token_program_id: spl_token::id(),
+            token_program_id,
         }
     }
 }
@@ -1,13 +1,12 @@
-#![allow(dead_code)] // needed because cargo doesn't understand test usage
 #![allow(clippy::arithmetic_side_effects)]
 
 use {
     crate::get_account,
     bincode::deserialize,
     solana_account::AccountSharedData,
+    solana_clock::Clock,
     solana_hash::Hash,
     solana_keypair::Keypair,
-    solana_native_token::LAMPORTS_PER_SOL,
     solana_program_test::BanksClient,
     solana_program_test::ProgramTestContext,
     solana_pubkey::Pubkey,
@@ -19,6 +18,7 @@ use {
     },
     solana_system_interface::instruction as system_instruction,
     solana_transaction::Transaction,
+    spl_single_pool::minimum_pool_balance,
     std::convert::TryInto,
 };
 
@@ -73,7 +73,7 @@ pub async fn get_minimum_pool_balance(
     recent_blockhash: &Hash,
 ) -> u64 {
     let stake_program_minimum = get_minimum_delegation(banks_client, payer, recent_blockhash).await;
-    std::cmp::max(stake_program_minimum, LAMPORTS_PER_SOL)
+    minimum_pool_balance(stake_program_minimum)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -159,17 +159,49 @@ pub async fn force_deactivate_stake_account(context: &mut ProgramTestContext, pu
         stake: MANGLED_DELEGATION,
         ..stake.unwrap().delegation
     };
+
+    set_stake_account(
+        context,
+        pubkey,
+        meta,
+        Stake {
+            delegation,
+            ..stake.unwrap()
+        },
+    )
+    .await;
+}
+
+// as if deactivated this epoch, eg by `DeactivateDelinquent`, without needing a delinquent validator
+pub async fn force_deactivating_stake_account(context: &mut ProgramTestContext, pubkey: &Pubkey) {
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    let (meta, stake, _) = get_stake_account(&mut context.banks_client, pubkey).await;
+    let mut stake = stake.unwrap();
+    stake.delegation.deactivation_epoch = clock.epoch;
+
+    set_stake_account(context, pubkey, meta, stake).await;
+}
+
+// leaves `amount` of the pool's delegation as undelegated lamports. NAV is unchanged but the stake
+// available to split shrinks, exactly as if minimum delegation rose by `amount`
+pub async fn strand_pool_stake(context: &mut ProgramTestContext, pubkey: &Pubkey, amount: u64) {
+    let (meta, stake, _) = get_stake_account(&mut context.banks_client, pubkey).await;
+    let mut stake = stake.unwrap();
+    stake.delegation.stake = stake.delegation.stake.checked_sub(amount).unwrap();
+
+    set_stake_account(context, pubkey, meta, stake).await;
+}
+
+async fn set_stake_account(
+    context: &mut ProgramTestContext,
+    pubkey: &Pubkey,
+    meta: Meta,
+    stake: Stake,
+) {
     let mut account_data = vec![0; std::mem::size_of::<StakeStateV2>()];
     bincode::serialize_into(
         &mut account_data[..],
-        &StakeStateV2::Stake(
-            meta,
-            Stake {
-                delegation,
-                ..stake.unwrap()
-            },
-            StakeFlags::empty(),
-        ),
+        &StakeStateV2::Stake(meta, stake, StakeFlags::empty()),
     )
     .unwrap();
 
@@ -1,5 +1,3 @@
-#![allow(dead_code)]
-
 use {
     borsh::BorshDeserialize,
     solana_borsh::v1::try_from_slice_unchecked,
@@ -12,10 +10,7 @@ use {
     solana_transaction::Transaction,
     spl_associated_token_account_interface::instruction::create_associated_token_account,
     spl_single_pool::inline_mpl_token_metadata::pda::find_metadata_account,
-    spl_token_interface::{
-        self as spl_token,
-        state::{Account, Mint},
-    },
+    spl_token_interface::state::{Account, Mint},
 };
 
 pub async fn create_ata(
@@ -24,9 +19,10 @@ pub async fn create_ata(
     owner: &Pubkey,
     recent_blockhash: &Hash,
     pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
 ) {
     let instruction =
-        create_associated_token_account(&payer.pubkey(), owner, pool_mint, &spl_token::id());
+        create_associated_token_account(&payer.pubkey(), owner, pool_mint, token_program_id);
     let transaction = Transaction::new_signed_with_payer(
         &[instruction],
         Some(&payer.pubkey()),
@@ -1,10 +1,7 @@
 #![allow(clippy::arithmetic_side_effects)]
 #![allow(clippy::items_after_test_module)]
 
-mod helpers;
-
 use {
-    helpers::*,
     solana_instruction::Instruction,
     solana_program_error::ProgramError,
     solana_program_test::*,
@@ -16,9 +13,14 @@ use {
     solana_transaction::Transaction,
     spl_single_pool::{
         error::SinglePoolError,
-        id,
-        instruction::{self, SinglePoolInstruction},
+        find_pool_address_and_bump, find_pool_mint_address_and_bump,
+        find_pool_mint_authority_address_and_bump, find_pool_mpl_authority_address_and_bump,
+        find_pool_onramp_address_and_bump, find_pool_stake_address_and_bump,
+        find_pool_stake_authority_address_and_bump, id,
+        instruction::{self, ExternalAddresses, SinglePoolInstruction},
+        PoolAddresses,
     },
+    spl_single_pool_test_utils::*,
     spl_token_interface as spl_token,
     test_case::test_matrix,
 };
@@ -30,14 +32,103 @@ enum TestMode {
     WithdrawStake,
     DepositSol,
 }
+impl TestMode {
+    // build the instruction under test from explicit addresses, so any one can be swapped out
+    fn build(&self, addresses: &TestAddresses, amount: u64) -> Instruction {
+        match self {
+            Self::InitializePool => instruction::initialize_pool_with_accounts(
+                &id(),
+                &addresses.vote_account,
+                &addresses.pool,
+                &addresses.external,
+            ),
+            Self::DepositStake => instruction::deposit_stake_with_accounts(
+                &id(),
+                &addresses.pool,
+                &addresses.external,
+                &addresses.user_stake,
+                &addresses.user_token,
+                &addresses.user_wallet,
+            ),
+            Self::WithdrawStake => instruction::withdraw_stake_with_accounts(
+                &id(),
+                &addresses.pool,
+                &addresses.external,
+                &addresses.user_stake,
+                &addresses.user_wallet,
+                &addresses.user_token,
+                amount,
+            ),
+            Self::DepositSol => instruction::deposit_sol_with_accounts(
+                &id(),
+                &addresses.vote_account,
+                &addresses.pool,
+                &addresses.external,
+                &addresses.user_wallet,
+                &addresses.user_token,
+                amount,
+            ),
+        }
+    }
+}
+
+// every address an instruction under test is built from
+#[derive(Clone, Debug)]
+struct TestAddresses {
+    vote_account: Pubkey,
+    pool: PoolAddresses,
+    external: ExternalAddresses,
+    user_stake: Pubkey,
+    user_token: Pubkey,
+    user_wallet: Pubkey,
+}
+impl TestAddresses {
+    const LEN: usize = 19;
+
+    fn new(accounts: &SinglePoolAccounts) -> Self {
+        Self {
+            vote_account: accounts.vote_account.pubkey(),
+            pool: PoolAddresses::derive(&id(), &accounts.vote_account.pubkey()),
+            external: ExternalAddresses::with_token_program(&accounts.token_program_id),
+            user_stake: accounts.alice_stake.pubkey(),
+            user_token: accounts.alice_token,
+            user_wallet: accounts.alice.pubkey(),
+        }
+    }
+
+    fn fields_mut(&mut self) -> [&mut Pubkey; Self::LEN] {
+        [
+            &mut self.vote_account,
+            &mut self.pool.pool,
+            &mut self.pool.stake,
+            &mut self.pool.onramp,
+            &mut self.pool.mint,
+            &mut self.pool.stake_authority,
+            &mut self.pool.mint_authority,
+            &mut self.pool.mpl_authority,
+            &mut self.external.token_program,
+            &mut self.external.stake_program,
+            &mut self.external.system_program,
+            &mut self.external.token_metadata_program,
+            &mut self.external.rent_sysvar,
+            &mut self.external.clock_sysvar,
+            &mut self.external.stake_history_sysvar,
+            &mut self.external.stake_config,
+            &mut self.user_stake,
+            &mut self.user_token,
+            &mut self.user_wallet,
+        ]
+    }
+}
 
 // build a full transaction for initialize, deposit, withdraw, and depositsol
-// this tests that dummying individual accounts triggers the appropriate errors
+// this tests that dummying individual accounts triggers the appropriate errors.
+// also returns the index and amount of the instruction under test
 async fn build_instructions(
     context: &mut ProgramTestContext,
     accounts: &SinglePoolAccounts,
     test_mode: TestMode,
-) -> (Vec<Instruction>, usize) {
+) -> (Vec<Instruction>, usize, u64) {
     let initialize_instructions = if test_mode == TestMode::InitializePool {
         let slot = context.genesis_config().epoch_schedule.first_normal_slot + 1;
         context.warp_to_slot(slot).unwrap();
@@ -70,8 +161,9 @@ async fn build_instructions(
         )
         .await;
 
-        instruction::initialize(
+        instruction::initialize_with_token_program(
             &id(),
+            &accounts.token_program_id,
             &accounts.vote_account.pubkey(),
             &accounts.alice.pubkey(),
             &rent,
@@ -86,8 +178,9 @@ async fn build_instructions(
         vec![]
     };
 
-    let deposit_stake_instructions = instruction::deposit(
+    let deposit_stake_instructions = instruction::deposit_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice_token,
@@ -95,6 +188,7 @@ async fn build_instructions(
         &accounts.alice.pubkey(),
     );
 
+    let mut withdraw_amount = 0;
     let withdraw_stake_instructions = if test_mode == TestMode::WithdrawStake {
         let transaction = Transaction::new_signed_with_payer(
             &deposit_stake_instructions,
@@ -118,22 +212,25 @@ async fn build_instructions(
         )
         .await;
 
-        instruction::withdraw(
+        withdraw_amount = get_token_balance(&mut context.banks_client, &accounts.alice_token).await;
+        instruction::withdraw_with_token_program(
             &id(),
+            &accounts.token_program_id,
             &accounts.pool,
             &accounts.alice_stake.pubkey(),
             &accounts.alice.pubkey(),
             &accounts.alice_token,
             &accounts.alice.pubkey(),
-            get_token_balance(&mut context.banks_client, &accounts.alice_token).await,
+            withdraw_amount,
         )
     } else {
         vec![]
     };
 
     // self-transfer is unidiomatic but this way we can test the full helper without messing with signers
-    let deposit_sol_instructions = instruction::deposit_liquid(
+    let deposit_sol_instructions = instruction::deposit_liquid_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.vote_account.pubkey(),
         &accounts.alice.pubkey(),
         &accounts.alice.pubkey(),
@@ -143,17 +240,21 @@ async fn build_instructions(
 
     // ints hardcoded to guard against instructions moving with code changes
     // if these asserts fail, update them to match the new multi-instruction builders
-    let (instructions, index, enum_tag) = match test_mode {
-        TestMode::InitializePool => (initialize_instructions, 4, 0),
-        TestMode::DepositStake => (deposit_stake_instructions, 2, 2),
-        TestMode::WithdrawStake => (withdraw_stake_instructions, 1, 3),
-        TestMode::DepositSol => (deposit_sol_instructions, 1, 7),
+    let (instructions, index, enum_tag, amount) = match test_mode {
+        TestMode::InitializePool => (initialize_instructions, 4, 0, 0),
+        TestMode::DepositStake => (deposit_stake_instructions, 2, 2, 0),
+        TestMode::WithdrawStake => (withdraw_stake_instructions, 1, 3, withdraw_amount),
+        TestMode::DepositSol => (deposit_sol_instructions, 1, 7, TEST_STAKE_AMOUNT),
     };
 
     assert_eq!(instructions[index].program_id, id());
     assert_eq!(instructions[index].data[0], enum_tag);
+    assert_eq!(
+        instructions[index],
+        test_mode.build(&TestAddresses::new(accounts), amount)
+    );
 
-    (instructions, index)
+    (instructions, index, amount)
 }
 
 // test that account addresses are checked properly
@@ -169,19 +270,45 @@ async fn fail_account_checks(stake_version: StakeProgramVersion, test_mode: Test
     let mut context = program_test.start_with_context().await;
 
     let accounts = SinglePoolAccounts::default();
-    let (instructions, i) = build_instructions(&mut context, &accounts, test_mode).await;
+    let (instructions, i, amount) =
+        build_instructions(&mut context, &accounts, test_mode.clone()).await;
+    let addresses = TestAddresses::new(&accounts);
     let bad_pubkey = pubkey!("BAD1111111111111111111111111111111111111111");
 
+    // swap each address the builder takes, then any account no builder input
+    // reaches, such as the program passing itself to `DepositSol`, directly
+    let mut bad_instructions = vec![];
+    for j in 0..TestAddresses::LEN {
+        let mut bad_addresses = addresses.clone();
+        let address = std::mem::replace(bad_addresses.fields_mut()[j], bad_pubkey);
+        let bad_instruction = test_mode.build(&bad_addresses, amount);
+
+        // addresses this instruction does not take leave it unchanged
+        if bad_instruction != instructions[i] {
+            bad_instructions.push((address, bad_instruction));
+        }
+    }
     for j in 0..instructions[i].accounts.len() {
+        let address = instructions[i].accounts[j].pubkey;
+        if bad_instructions
+            .iter()
+            .all(|(_, bad_instruction)| bad_instruction.accounts[j].pubkey == address)
+        {
+            let mut bad_instruction = instructions[i].clone();
+            bad_instruction.accounts[j].pubkey = bad_pubkey;
+            bad_instructions.push((address, bad_instruction));
+        }
+    }
+
+    for (instruction_pubkey, bad_instruction) in bad_instructions {
         let mut instructions = instructions.clone();
-        let instruction_pubkey = instructions[i].accounts[j].pubkey;
 
         // wallet address can be arbitrary
         if instruction_pubkey == accounts.alice.pubkey() {
             continue;
         }
 
-        instructions[i].accounts[j].pubkey = bad_pubkey;
+        instructions[i] = bad_instruction;
 
         let transaction = Transaction::new_signed_with_payer(
             &instructions,
@@ -236,36 +363,95 @@ async fn fail_account_checks(stake_version: StakeProgramVersion, test_mode: Test
         .unwrap();
 }
 
+// typed builders let a test pass wrong pool addresses without editing built instructions
+#[tokio::test]
+async fn fail_deposit_with_swapped_authorities() {
+    let Some(program_test) = program_test(StakeProgramVersion::Stable) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_deposit(&mut context, TEST_STAKE_AMOUNT, None)
+        .await;
+    advance_epoch(&mut context).await;
+
+    let mut pool_addresses = PoolAddresses::derive_from_pool(&id(), &accounts.pool);
+    std::mem::swap(
+        &mut pool_addresses.stake_authority,
+        &mut pool_addresses.mint_authority,
+    );
+
+    let mut instructions = instruction::deposit_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &accounts.alice_stake.pubkey(),
+        &accounts.alice_token,
+        &accounts.alice.pubkey(),
+        &accounts.alice.pubkey(),
+    );
+    *instructions.last_mut().unwrap() = instruction::deposit_stake_with_accounts(
+        &id(),
+        &pool_addresses,
+        &ExternalAddresses::with_token_program(&accounts.token_program_id),
+        &accounts.alice_stake.pubkey(),
+        &accounts.alice_token,
+        &accounts.alice.pubkey(),
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&accounts.alice.pubkey()),
+        &[&accounts.alice],
+        context.last_blockhash,
+    );
+
+    let e = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err();
+    check_error(e, SinglePoolError::InvalidPoolStakeAuthority);
+}
+
 // make an individual instruction for all program instructions
 // the match is just so this will error if new instructions are added
-// if you are reading this because of that error, add the case to the
-// `consistent_account_order` test!!!
+// if you are reading this because of that error, also add the new
+// instruction to `SinglePoolInstruction::all_variants()`!!!
 fn make_basic_instruction(
     accounts: &SinglePoolAccounts,
     instruction_type: SinglePoolInstruction,
 ) -> Instruction {
     match instruction_type {
-        SinglePoolInstruction::InitializePool => {
-            instruction::initialize_pool(&id(), &accounts.vote_account.pubkey())
-        }
+        SinglePoolInstruction::InitializePool => instruction::initialize_pool_with_token_program(
+            &id(),
+            &accounts.token_program_id,
+            &accounts.vote_account.pubkey(),
+        ),
         SinglePoolInstruction::ReplenishPool => {
             instruction::replenish_pool(&id(), &accounts.vote_account.pubkey())
         }
-        SinglePoolInstruction::DepositStake => instruction::deposit_stake(
+        SinglePoolInstruction::DepositStake => instruction::deposit_stake_with_token_program(
             &id(),
+            &accounts.token_program_id,
             &accounts.pool,
             &Pubkey::default(),
             &Pubkey::default(),
             &Pubkey::default(),
         ),
-        SinglePoolInstruction::WithdrawStake { .. } => instruction::withdraw_stake(
-            &id(),
-            &accounts.pool,
-            &Pubkey::default(),
-            &Pubkey::default(),
-            &Pubkey::default(),
-            0,
-        ),
+        SinglePoolInstruction::WithdrawStake { .. } => {
+            instruction::withdraw_stake_with_token_program(
+                &id(),
+                &accounts.token_program_id,
+                &accounts.pool,
+                &Pubkey::default(),
+                &Pubkey::default(),
+                &Pubkey::default(),
+                0,
+            )
+        }
         SinglePoolInstruction::CreateTokenMetadata => {
             instruction::create_token_metadata(&id(), &accounts.pool, &Pubkey::default())
         }
@@ -280,13 +466,26 @@ fn make_basic_instruction(
         SinglePoolInstruction::InitializePoolOnRamp => {
             instruction::initialize_pool_onramp(&id(), &accounts.pool)
         }
-        SinglePoolInstruction::DepositSol { .. } => instruction::deposit_sol(
+        SinglePoolInstruction::DepositSol { .. } => instruction::deposit_sol_with_token_program(
             &id(),
+            &accounts.token_program_id,
             &accounts.vote_account.pubkey(),
             &Pubkey::default(),
             &Pubkey::default(),
             0,
         ),
+        SinglePoolInstruction::WithdrawSol { .. } => instruction::withdraw_sol_with_token_program(
+            &id(),
+            &accounts.token_program_id,
+            &accounts.pool,
+            &Pubkey::default(),
+            &Pubkey::default(),
+            0,
+        ),
+        SinglePoolInstruction::ReactivateAfterDelinquency => {
+            instruction::reactivate_pool_stake(&id(), &accounts.vote_account.pubkey())
+        }
+        SinglePoolInstruction::GetVersion => instruction::get_version(&id()),
     }
 }
 
@@ -315,29 +514,10 @@ fn consistent_account_order() {
         accounts.mpl_authority,
     ];
 
-    let instructions = vec![
-        make_basic_instruction(&accounts, SinglePoolInstruction::InitializePool),
-        make_basic_instruction(&accounts, SinglePoolInstruction::ReplenishPool),
-        make_basic_instruction(&accounts, SinglePoolInstruction::DepositStake),
-        make_basic_instruction(
-            &accounts,
-            SinglePoolInstruction::WithdrawStake {
-                user_stake_authority: Pubkey::default(),
-                token_amount: 0,
-            },
-        ),
-        make_basic_instruction(&accounts, SinglePoolInstruction::CreateTokenMetadata),
-        make_basic_instruction(
-            &accounts,
-            SinglePoolInstruction::UpdateTokenMetadata {
-                name: "".to_string(),
-                symbol: "".to_string(),
-                uri: "".to_string(),
-            },
-        ),
-        make_basic_instruction(&accounts, SinglePoolInstruction::InitializePoolOnRamp),
-        make_basic_instruction(&accounts, SinglePoolInstruction::DepositSol { lamports: 0 }),
-    ];
+    let instructions = SinglePoolInstruction::all_variants()
+        .into_iter()
+        .map(|instruction_type| make_basic_instruction(&accounts, instruction_type))
+        .collect::<Vec<_>>();
 
     for instruction in instructions {
         let mut indexes = vec![];
@@ -355,3 +535,82 @@ fn consistent_account_order() {
         assert!(is_sorted(&indexes));
     }
 }
+
+// check that the bundled derivation agrees with the individual find functions
+#[test]
+fn pool_addresses_derive() {
+    let accounts = SinglePoolAccounts::default();
+
+    let expected = PoolAddresses {
+        pool: accounts.pool,
+        stake: accounts.stake_account,
+        onramp: accounts.onramp_account,
+        mint: accounts.mint,
+        stake_authority: accounts.stake_authority,
+        mint_authority: accounts.mint_authority,
+        mpl_authority: accounts.mpl_authority,
+    };
+
+    assert_eq!(
+        PoolAddresses::derive(&id(), &accounts.vote_account.pubkey()),
+        expected
+    );
+    assert_eq!(
+        PoolAddresses::derive_from_pool(&id(), &accounts.pool),
+        expected
+    );
+}
+
+// check that each exposed bump recreates its address from the documented seeds
+#[test]
+fn pool_address_bumps() {
+    let accounts = SinglePoolAccounts::default();
+    let vote_account_address = accounts.vote_account.pubkey();
+
+    let (pool, bump) = find_pool_address_and_bump(&id(), &vote_account_address);
+    assert_eq!(pool, accounts.pool);
+    assert_eq!(
+        Pubkey::create_program_address(&[b"pool", vote_account_address.as_ref(), &[bump]], &id())
+            .unwrap(),
+        pool
+    );
+
+    #[allow(clippy::type_complexity)]
+    let derivations: [(fn(&Pubkey, &Pubkey) -> (Pubkey, u8), &[u8], Pubkey); 6] = [
+        (
+            find_pool_stake_address_and_bump,
+            b"stake",
+            accounts.stake_account,
+        ),
+        (
+            find_pool_onramp_address_and_bump,
+            b"onramp",
+            accounts.onramp_account,
+        ),
+        (find_pool_mint_address_and_bump, b"mint", accounts.mint),
+        (
+            find_pool_stake_authority_address_and_bump,
+            b"stake_authority",
+            accounts.stake_authority,
+        ),
+        (
+            find_pool_mint_authority_address_and_bump,
+            b"mint_authority",
+            accounts.mint_authority,
+        ),
+        (
+            find_pool_mpl_authority_address_and_bump,
+            b"mpl_authority",
+            accounts.mpl_authority,
+        ),
+    ];
+
+    for (find, prefix, expected) in derivations {
+        let (address, bump) = find(&id(), &pool);
+        assert_eq!(address, expected);
+        assert_eq!(
+            Pubkey::create_program_address(&[prefix, pool.as_ref(), &[bump]], &id()).unwrap(),
+            address
+        );
+    }
+}
@@ -0,0 +1,134 @@
+#![allow(clippy::arithmetic_side_effects)]
+
+use {
+    solana_instruction::Instruction,
+    solana_program_test::*,
+    solana_signer::Signer,
+    solana_transaction::Transaction,
+    spl_single_pool::{id, instruction},
+    spl_single_pool_test_utils::*,
+    test_case::test_matrix,
+};
+
+// budgets for a single processor instruction, including its CPIs. these are deliberately
+// loose; they exist to catch large regressions, eg from stake program interop changes.
+// if an intentional change exceeds one, raise it and note why in the commit message
+const DEPOSIT_STAKE_BUDGET: u64 = 100_000;
+const WITHDRAW_STAKE_BUDGET: u64 = 100_000;
+
+async fn simulate_compute_units(context: &mut ProgramTestContext, instruction: Instruction) -> u64 {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+
+    let result = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    result.result.unwrap().unwrap();
+
+    result.simulation_details.unwrap().units_consumed
+}
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
+)]
+#[tokio::test]
+async fn deposit_stake(stake_version: StakeProgramVersion) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_deposit(&mut context, TEST_STAKE_AMOUNT, None)
+        .await;
+    advance_epoch(&mut context).await;
+
+    // hand the stake account to the pool so we can measure `DepositStake` on its own
+    let mut instructions = instruction::deposit_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &accounts.alice_stake.pubkey(),
+        &accounts.alice_token,
+        &accounts.alice.pubkey(),
+        &accounts.alice.pubkey(),
+    );
+    let deposit_instruction = instructions.pop().unwrap();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &accounts.alice],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let units_consumed = simulate_compute_units(&mut context, deposit_instruction).await;
+    assert!(
+        units_consumed <= DEPOSIT_STAKE_BUDGET,
+        "DepositStake consumed {} compute units, over budget of {}",
+        units_consumed,
+        DEPOSIT_STAKE_BUDGET,
+    );
+}
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
+)]
+#[tokio::test]
+async fn withdraw_stake(stake_version: StakeProgramVersion) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+
+    // approve the burn up front so we can measure `WithdrawStake` on its own
+    let token_amount = get_token_balance(&mut context.banks_client, &accounts.alice_token).await;
+    let mut instructions = instruction::withdraw_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &accounts.alice_stake.pubkey(),
+        &accounts.alice.pubkey(),
+        &accounts.alice_token,
+        &accounts.alice.pubkey(),
+        token_amount,
+    );
+    let withdraw_instruction = instructions.pop().unwrap();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &accounts.alice],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let units_consumed = simulate_compute_units(&mut context, withdraw_instruction).await;
+    assert!(
+        units_consumed <= WITHDRAW_STAKE_BUDGET,
+        "WithdrawStake consumed {} compute units, over budget of {}",
+        units_consumed,
+        WITHDRAW_STAKE_BUDGET,
+    );
+}
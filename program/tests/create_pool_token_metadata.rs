@@ -1,9 +1,6 @@
 #![allow(clippy::arithmetic_side_effects)]
 
-mod helpers;
-
 use {
-    helpers::*,
     solana_instruction_error::InstructionError,
     solana_program_test::*,
     solana_pubkey::Pubkey,
@@ -11,6 +8,7 @@ use {
     solana_system_interface::error::SystemError,
     solana_transaction::Transaction,
     spl_single_pool::{id, instruction},
+    spl_single_pool_test_utils::*,
 };
 
 fn assert_metadata(vote_account: &Pubkey, metadata: &Metadata) {
@@ -1,9 +1,6 @@
 #![allow(clippy::arithmetic_side_effects)]
 
-mod helpers;
-
 use {
-    helpers::*,
     solana_account::AccountSharedData,
     solana_keypair::Keypair,
     solana_native_token::LAMPORTS_PER_SOL,
@@ -12,6 +9,7 @@ use {
     solana_signer::Signer,
     solana_transaction::Transaction,
     spl_single_pool::{error::SinglePoolError, id, instruction},
+    spl_single_pool_test_utils::*,
     test_case::test_matrix,
 };
 
@@ -22,8 +20,9 @@ async fn deposit_sol(
 ) -> Result<(), BanksClientError> {
     let proxy_keypair = Keypair::new();
 
-    let instructions = instruction::deposit_liquid(
+    let instructions = instruction::deposit_liquid_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.vote_account.pubkey(),
         &accounts.alice.pubkey(),
         &proxy_keypair.pubkey(),
@@ -222,8 +221,9 @@ async fn fail_bad_deposit(stake_version: StakeProgramVersion) {
     )
     .await;
 
-    let instruction = instruction::deposit_sol(
+    let instruction = instruction::deposit_sol_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.vote_account.pubkey(),
         &proxy_keypair.pubkey(),
         &accounts.alice_token,
@@ -245,8 +245,9 @@ async fn fail_bad_deposit(stake_version: StakeProgramVersion) {
     // fail: not enough for deposit
     check_error(e, SinglePoolError::InvalidDepositSolSource);
 
-    let mut instruction = instruction::deposit_sol(
+    let mut instruction = instruction::deposit_sol_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.vote_account.pubkey(),
         &proxy_keypair.pubkey(),
         &accounts.alice_token,
@@ -270,8 +271,9 @@ async fn fail_bad_deposit(stake_version: StakeProgramVersion) {
     // fail: missing signer
     check_error(e, SinglePoolError::InvalidDepositSolSource);
 
-    let instruction = instruction::deposit_sol(
+    let instruction = instruction::deposit_sol_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.vote_account.pubkey(),
         &proxy_keypair.pubkey(),
         &accounts.alice_token,
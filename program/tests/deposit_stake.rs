@@ -1,9 +1,6 @@
 #![allow(clippy::arithmetic_side_effects)]
 
-mod helpers;
-
 use {
-    helpers::*,
     solana_clock::Clock,
     solana_keypair::Keypair,
     solana_program_test::*,
@@ -17,6 +14,7 @@ use {
     solana_transaction::Transaction,
     spl_associated_token_account_interface::address::get_associated_token_address,
     spl_single_pool::{error::SinglePoolError, id, instruction},
+    spl_single_pool_test_utils::*,
     test_case::test_matrix,
 };
 
@@ -59,8 +57,9 @@ async fn success(
     }
 
     if prior_deposit {
-        let instructions = instruction::deposit(
+        let instructions = instruction::deposit_with_token_program(
             &id(),
+            &accounts.token_program_id,
             &accounts.pool,
             &accounts.bob_stake.pubkey(),
             &accounts.bob_token,
@@ -107,8 +106,9 @@ async fn success(
         get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
     let pool_stake_before = pool_stake_before.unwrap().delegation.stake;
 
-    let instructions = instruction::deposit(
+    let instructions = instruction::deposit_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice_token,
@@ -235,8 +235,9 @@ async fn fail_uninitialized(stake_version: StakeProgramVersion, activate: bool)
         advance_epoch(&mut context).await;
     }
 
-    let instructions = instruction::deposit(
+    let instructions = instruction::deposit_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &stake_account.pubkey(),
         &token_account,
@@ -330,8 +331,9 @@ async fn fail_bad_account(
         BadDeposit::Onramp => accounts.onramp_account,
     };
 
-    let instruction = instruction::deposit_stake(
+    let instruction = instruction::deposit_stake_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &deposit_source_address,
         &accounts.alice_token,
@@ -357,6 +359,236 @@ async fn fail_bad_account(
     }
 }
 
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge],
+    [false, true]
+)]
+#[tokio::test]
+async fn fail_wrong_mint_token_account(stake_version: StakeProgramVersion, activate: bool) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_deposit(&mut context, TEST_STAKE_AMOUNT, None)
+        .await;
+
+    // a second pool gives us a valid token account for a different mint
+    let other_accounts = SinglePoolAccounts::default();
+    other_accounts.initialize(&mut context).await;
+
+    if activate {
+        advance_epoch(&mut context).await;
+    }
+
+    let instructions = instruction::deposit_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &accounts.alice_stake.pubkey(),
+        &other_accounts.alice_token,
+        &accounts.alice.pubkey(),
+        &accounts.alice.pubkey(),
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&accounts.alice.pubkey()),
+        &[&accounts.alice],
+        context.last_blockhash,
+    );
+
+    let e = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err();
+    check_error(e, SinglePoolError::InvalidTokenAccountMint);
+}
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge],
+    [false, true]
+)]
+#[tokio::test]
+async fn fail_lockup_in_force(stake_version: StakeProgramVersion, activate: bool) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_deposit(&mut context, TEST_STAKE_AMOUNT, None)
+        .await;
+
+    if activate {
+        advance_epoch(&mut context).await;
+    }
+
+    // the stake program will not reassign the withdrawer of a locked account without its custodian,
+    // so edit the account to look as if that already happened
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    let mut user_stake_account =
+        get_account(&mut context.banks_client, &accounts.alice_stake.pubkey()).await;
+    let mut user_stake_data: StakeStateV2 = bincode::deserialize(&user_stake_account.data).unwrap();
+
+    match user_stake_data {
+        StakeStateV2::Stake(ref mut meta, _, _) => {
+            meta.authorized = Authorized::auto(&accounts.stake_authority);
+            meta.lockup = Lockup {
+                unix_timestamp: 0,
+                epoch: clock.epoch + 1,
+                custodian: accounts.alice.pubkey(),
+            };
+        }
+        _ => unreachable!(),
+    }
+
+    user_stake_account.data = bincode::serialize(&user_stake_data).unwrap();
+    context.set_account(&accounts.alice_stake.pubkey(), &user_stake_account.into());
+
+    let instruction = instruction::deposit_stake_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &accounts.alice_stake.pubkey(),
+        &accounts.alice_token,
+        &accounts.alice.pubkey(),
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&accounts.alice.pubkey()),
+        &[&accounts.alice],
+        context.last_blockhash,
+    );
+
+    let e = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err();
+    check_error(e, SinglePoolError::StakeLockupActive);
+}
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
+)]
+#[tokio::test]
+async fn success_batch(stake_version: StakeProgramVersion) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts.initialize(&mut context).await;
+
+    // each token owner is the withdraw authority on their own stake account, so every deposit
+    // brings its own signer. this is the largest batch a relayer can be asked to submit
+    let mut deposits = vec![];
+    let mut owners = vec![];
+    for _ in 0..=instruction::MAX_DEPOSIT_BATCH_SIZE {
+        let stake_account = Keypair::new();
+        let owner = Keypair::new();
+
+        create_independent_stake_account(
+            &mut context.banks_client,
+            &context.payer,
+            &accounts.alice,
+            &context.last_blockhash,
+            &stake_account,
+            &Authorized::auto(&owner.pubkey()),
+            &Lockup::default(),
+            TEST_STAKE_AMOUNT,
+        )
+        .await;
+
+        delegate_stake_account(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &stake_account.pubkey(),
+            &owner,
+            &accounts.vote_account.pubkey(),
+        )
+        .await;
+
+        create_ata(
+            &mut context.banks_client,
+            &context.payer,
+            &owner.pubkey(),
+            &context.last_blockhash,
+            &accounts.mint,
+            &accounts.token_program_id,
+        )
+        .await;
+
+        deposits.push(instruction::BatchDeposit {
+            user_stake_account: stake_account.pubkey(),
+            user_token_account: get_associated_token_address(&owner.pubkey(), &accounts.mint),
+            user_lamport_account: owner.pubkey(),
+            user_withdraw_authority: owner.pubkey(),
+        });
+        owners.push(owner);
+    }
+
+    // one past the maximum batch size does not fit in a transaction
+    let instructions = instruction::deposit_batch_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &deposits,
+    );
+    let mut signers = vec![&context.payer];
+    signers.extend(owners.iter());
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &signers,
+        context.last_blockhash,
+    );
+    assert!(bincode::serialize(&transaction).unwrap().len() > PACKET_DATA_SIZE);
+
+    // the maximum batch size fits and succeeds
+    deposits.pop();
+    owners.pop();
+    let instructions = instruction::deposit_batch_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &deposits,
+    );
+    let mut signers = vec![&context.payer];
+    signers.extend(owners.iter());
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &signers,
+        context.last_blockhash,
+    );
+    assert!(bincode::serialize(&transaction).unwrap().len() <= PACKET_DATA_SIZE);
+
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    for (deposit, owner) in deposits.iter().zip(owners) {
+        assert!(context
+            .banks_client
+            .get_account(deposit.user_stake_account)
+            .await
+            .expect("get_account")
+            .is_none());
+
+        let token_account = get_associated_token_address(&owner.pubkey(), &accounts.mint);
+        assert!(get_token_balance(&mut context.banks_client, &token_account).await > 0);
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum UserStakeState {
     Initialized,
@@ -559,8 +791,9 @@ async fn all_activation_states(
     }
 
     // finally we can run the deposit
-    let instructions = instruction::deposit(
+    let instructions = instruction::deposit_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice_token,
@@ -671,8 +904,9 @@ async fn success_additional_value(stake_version: StakeProgramVersion, activate:
     // (plus rent/4 if pool is activating)
     // since there are SA notional tokens backed by 2SA stake and 2A extra lamports
 
-    let instructions = instruction::deposit(
+    let instructions = instruction::deposit_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice_token,
@@ -1,8 +1,6 @@
-mod helpers;
-
 use {
-    helpers::*, solana_native_token::LAMPORTS_PER_SOL, solana_program_test::*, std::path::Path,
-    strum::IntoEnumIterator, test_case::test_matrix,
+    solana_native_token::LAMPORTS_PER_SOL, solana_program_test::*, spl_single_pool_test_utils::*,
+    std::path::Path, strum::IntoEnumIterator, test_case::test_matrix,
 };
 
 // sanity: version -> file mappings resolve
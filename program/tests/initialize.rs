@@ -1,18 +1,19 @@
 #![allow(clippy::arithmetic_side_effects)]
 
-mod helpers;
-
 use {
-    helpers::*,
+    solana_account::Account,
     solana_native_token::LAMPORTS_PER_SOL,
+    solana_program_error::ProgramError,
     solana_program_pack::Pack,
     solana_program_test::*,
     solana_signer::Signer,
     solana_stake_interface::program as stake_program,
     solana_transaction::Transaction,
+    solana_vote_interface::{program as vote_program, state::VoteStateV4},
     spl_single_pool::{error::SinglePoolError, id, instruction},
+    spl_single_pool_test_utils::*,
     spl_token_interface::state::Mint,
-    test_case::test_matrix,
+    test_case::{test_case, test_matrix},
 };
 
 #[test_matrix(
@@ -66,6 +67,168 @@ async fn success(stake_version: StakeProgramVersion) {
     assert_eq!(stake_account.owner, stake_program::id());
 }
 
+#[tokio::test]
+async fn success_without_funding() {
+    let mut context = program_test_live().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    let slot = context.genesis_config().epoch_schedule.first_normal_slot + 1;
+    context.warp_to_slot(slot).unwrap();
+
+    create_vote(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &accounts.validator,
+        &accounts.voter.pubkey(),
+        &accounts.withdrawer.pubkey(),
+        &accounts.vote_account,
+    )
+    .await;
+
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let minimum_pool_balance = get_minimum_pool_balance(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+    )
+    .await;
+
+    let mut instructions = instruction::initialize_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.vote_account.pubkey(),
+        &context.payer.pubkey(),
+        &rent,
+        minimum_pool_balance,
+    );
+
+    // fund the pool accounts separately, as a caller composing its own transaction would
+    let funding_instructions = instructions.drain(..4).collect::<Vec<_>>();
+    let initialize_instructions = instruction::initialize_without_funding(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.vote_account.pubkey(),
+        &context.payer.pubkey(),
+    );
+    assert_eq!(initialize_instructions, instructions);
+
+    for instructions in [funding_instructions, initialize_instructions] {
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&context.payer.pubkey()),
+            &[&context.payer],
+            context.last_blockhash,
+        );
+
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    let mint_account = get_account(&mut context.banks_client, &accounts.mint).await;
+    Mint::unpack_from_slice(&mint_account.data).unwrap();
+
+    let stake_account = get_account(&mut context.banks_client, &accounts.stake_account).await;
+    assert_eq!(stake_account.owner, stake_program::id());
+}
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge],
+    [false, true]
+)]
+#[tokio::test]
+async fn success_with_or_without_stake_config(
+    stake_version: StakeProgramVersion,
+    omit_stake_config: bool,
+) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    let slot = context.genesis_config().epoch_schedule.first_normal_slot + 1;
+    context.warp_to_slot(slot).unwrap();
+
+    create_vote(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &accounts.validator,
+        &accounts.voter.pubkey(),
+        &accounts.withdrawer.pubkey(),
+        &accounts.vote_account,
+    )
+    .await;
+
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let minimum_pool_balance = get_minimum_pool_balance(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+    )
+    .await;
+
+    let mut instructions = instruction::initialize_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.vote_account.pubkey(),
+        &context.payer.pubkey(),
+        &rent,
+        minimum_pool_balance,
+    );
+    let mut replenish_instruction =
+        instruction::replenish_pool(&id(), &accounts.vote_account.pubkey());
+
+    #[allow(deprecated)]
+    let stake_config_address = solana_stake_interface::config::id();
+    if omit_stake_config {
+        for instruction in instructions
+            .iter_mut()
+            .chain(std::iter::once(&mut replenish_instruction))
+        {
+            instruction
+                .accounts
+                .retain(|account_meta| account_meta.pubkey != stake_config_address);
+        }
+    }
+
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let stake_account = get_account(&mut context.banks_client, &accounts.stake_account).await;
+    assert_eq!(stake_account.owner, stake_program::id());
+    let (_, stake, _) = get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    assert_eq!(
+        stake.unwrap().delegation.voter_pubkey,
+        accounts.vote_account.pubkey()
+    );
+
+    // replenish delegates through the stake program too, so it must accept either ordering
+    advance_epoch(&mut context).await;
+    let transaction = Transaction::new_signed_with_payer(
+        &[replenish_instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
 #[tokio::test]
 async fn fail_double_init() {
     let mut context = program_test_live().start_with_context().await;
@@ -74,8 +237,9 @@ async fn fail_double_init() {
     refresh_blockhash(&mut context).await;
 
     let rent = context.banks_client.get_rent().await.unwrap();
-    let instructions = instruction::initialize(
+    let instructions = instruction::initialize_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.vote_account.pubkey(),
         &context.payer.pubkey(),
         &rent,
@@ -129,8 +293,9 @@ async fn fail_below_pool_minimum(stake_version: StakeProgramVersion) {
     )
     .await;
 
-    let instructions = instruction::initialize(
+    let instructions = instruction::initialize_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.vote_account.pubkey(),
         &context.payer.pubkey(),
         &rent,
@@ -151,4 +316,110 @@ async fn fail_below_pool_minimum(stake_version: StakeProgramVersion) {
     check_error(e, SinglePoolError::WrongRentAmount);
 }
 
+#[test_case(0, SinglePoolError::LegacyVoteAccount; "legacy")]
+#[test_case(4, SinglePoolError::UnparseableVoteAccount; "unknown")]
+#[tokio::test]
+async fn fail_bad_vote_account_version(discriminator: u32, expected_error: SinglePoolError) {
+    let mut context = program_test_live().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+
+    // a vote account whose state is a version we do not support
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let mut data = vec![0; VoteStateV4::size_of()];
+    data[..4].copy_from_slice(&discriminator.to_le_bytes());
+    let vote_account = Account {
+        lamports: rent.minimum_balance(data.len()),
+        data,
+        owner: vote_program::id(),
+        executable: false,
+        rent_epoch: 0,
+    };
+    context.set_account(&accounts.vote_account.pubkey(), &vote_account.into());
+
+    let minimum_pool_balance = get_minimum_pool_balance(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+    )
+    .await;
+
+    let instructions = instruction::initialize_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.vote_account.pubkey(),
+        &context.payer.pubkey(),
+        &rent,
+        minimum_pool_balance,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+
+    let e = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err();
+    check_error(e, expected_error);
+}
+
+#[tokio::test]
+async fn fail_not_vote_account() {
+    let mut context = program_test_live().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+
+    // eg a closed vote account whose address has since been funded
+    transfer(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &accounts.vote_account.pubkey(),
+        LAMPORTS_PER_SOL,
+    )
+    .await;
+
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let minimum_pool_balance = get_minimum_pool_balance(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+    )
+    .await;
+
+    let instructions = instruction::initialize_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.vote_account.pubkey(),
+        &context.payer.pubkey(),
+        &rent,
+        minimum_pool_balance,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+
+    let e = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err();
+    check_error(e, ProgramError::IncorrectProgramId);
+
+    // nothing is left behind for the pool
+    for address in [accounts.pool, accounts.stake_account, accounts.mint] {
+        assert!(context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
+
 // TODO test that init can succeed without mpl program
@@ -1,9 +1,6 @@
 #![allow(clippy::arithmetic_side_effects)]
 
-mod helpers;
-
 use {
-    helpers::*,
     solana_clock::Clock,
     solana_program_test::*,
     solana_signer::Signer,
@@ -12,6 +9,7 @@ use {
     },
     solana_transaction::Transaction,
     spl_single_pool::{error::SinglePoolError, id, instruction},
+    spl_single_pool_test_utils::*,
     test_case::test_matrix,
 };
 
@@ -59,8 +57,9 @@ async fn reactivate_success(
         force_deactivate_stake_account(&mut context, &accounts.stake_account).await;
 
         // active deposit into deactivated pool fails
-        let instructions = instruction::deposit(
+        let instructions = instruction::deposit_with_token_program(
             &id(),
+            &accounts.token_program_id,
             &accounts.pool,
             &accounts.alice_stake.pubkey(),
             &accounts.alice_token,
@@ -82,8 +81,9 @@ async fn reactivate_success(
         check_error(e, SinglePoolError::ReplenishRequired);
 
         // inactive deposit into deactivated pool fails
-        let instructions = instruction::deposit(
+        let instructions = instruction::deposit_with_token_program(
             &id(),
+            &accounts.token_program_id,
             &accounts.pool,
             &accounts.bob_stake.pubkey(),
             &accounts.bob_token,
@@ -129,8 +129,9 @@ async fn reactivate_success(
     advance_epoch(&mut context).await;
 
     // deposit works in all cases
-    let instructions = instruction::deposit(
+    let instructions = instruction::deposit_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice_token,
@@ -549,8 +550,9 @@ async fn fail_onramp_doesnt_exist(stake_version: StakeProgramVersion, activate:
     )
     .await;
 
-    let mut instructions = instruction::initialize(
+    let mut instructions = instruction::initialize_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.vote_account.pubkey(),
         &context.payer.pubkey(),
         &rent,
@@ -623,3 +625,77 @@ async fn fail_onramp_doesnt_exist(stake_version: StakeProgramVersion, activate:
         .await
         .unwrap();
 }
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
+)]
+#[tokio::test]
+async fn reactivate_after_delinquency(stake_version: StakeProgramVersion) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_deposit(&mut context, TEST_STAKE_AMOUNT, None)
+        .await;
+    advance_epoch(&mut context).await;
+
+    let (_, _, onramp_lamports_before) =
+        get_stake_account(&mut context.banks_client, &accounts.onramp_account).await;
+
+    // active pool has nothing to reactivate
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::reactivate_pool_stake(
+            &id(),
+            &accounts.vote_account.pubkey(),
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+
+    let e = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err();
+    check_error(e, SinglePoolError::PoolStakeNotDeactivated);
+
+    force_deactivate_stake_account(&mut context, &accounts.stake_account).await;
+
+    refresh_blockhash(&mut context).await;
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::reactivate_pool_stake(
+            &id(),
+            &accounts.vote_account.pubkey(),
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // pool stake is delegated again, and the onramp is untouched
+    let (_, Some(pool_stake), _) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await
+    else {
+        unreachable!()
+    };
+    assert_eq!(pool_stake.delegation.deactivation_epoch, u64::MAX);
+    assert_eq!(
+        pool_stake.delegation.voter_pubkey,
+        accounts.vote_account.pubkey()
+    );
+
+    let (_, onramp_stake, onramp_lamports_after) =
+        get_stake_account(&mut context.banks_client, &accounts.onramp_account).await;
+    assert_eq!(onramp_stake, None);
+    assert_eq!(onramp_lamports_after, onramp_lamports_before);
+}
@@ -1,12 +1,10 @@
 #![allow(clippy::arithmetic_side_effects)]
-mod helpers;
-
 use {
-    helpers::*,
     solana_program_test::*,
     solana_signer::Signer,
     solana_transaction::Transaction,
     spl_single_pool::{error::SinglePoolError, id, instruction},
+    spl_single_pool_test_utils::*,
     test_case::test_case,
 };
 
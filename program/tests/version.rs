@@ -0,0 +1,40 @@
+use {
+    solana_program_test::*,
+    solana_signer::Signer,
+    solana_transaction::Transaction,
+    spl_single_pool::{id, instruction, ProgramVersion},
+    spl_single_pool_test_utils::*,
+};
+
+#[tokio::test]
+async fn get_version() {
+    let context = program_test_live().start_with_context().await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::get_version(&id())],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+
+    let result = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    result.result.unwrap().unwrap();
+
+    let return_data = result.simulation_details.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, id());
+
+    // the encoding is part of the interface, so check the raw bytes rather than roundtripping
+    let version = ProgramVersion::CURRENT;
+    let mut expected = vec![];
+    expected.extend_from_slice(&version.major.to_le_bytes());
+    expected.extend_from_slice(&version.minor.to_le_bytes());
+    expected.extend_from_slice(&version.patch.to_le_bytes());
+    assert_eq!(return_data.data, expected);
+
+    assert_eq!(ProgramVersion::from_bytes(&return_data.data), Some(version));
+    assert_eq!(version.to_string(), env!("CARGO_PKG_VERSION"));
+}
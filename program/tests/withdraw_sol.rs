@@ -0,0 +1,372 @@
+#![allow(clippy::arithmetic_side_effects)]
+
+use {
+    solana_program_test::*,
+    solana_signer::Signer,
+    solana_transaction::Transaction,
+    spl_single_pool::{
+        calculate_withdraw_amount, error::SinglePoolError, id, instruction, PHANTOM_TOKEN_AMOUNT,
+        WITHDRAW_SOL_FEE_BPS,
+    },
+    spl_single_pool_test_utils::*,
+    test_case::test_matrix,
+};
+
+async fn withdraw_sol(
+    context: &mut ProgramTestContext,
+    accounts: &SinglePoolAccounts,
+    token_amount: u64,
+) -> Result<(), BanksClientError> {
+    let instructions = instruction::withdraw_liquid_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &accounts.alice_token,
+        &accounts.alice.pubkey(),
+        &accounts.alice.pubkey(),
+        token_amount,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &accounts.alice],
+        context.last_blockhash,
+    );
+
+    context.banks_client.process_transaction(transaction).await
+}
+
+// lamports `WithdrawSol` pays for a token amount after its fee, per the program's own math
+fn withdraw_sol_payout(token_supply: u64, net_asset_value: u64, token_amount: u64) -> u64 {
+    let lamports = calculate_withdraw_amount(token_supply, net_asset_value, token_amount).unwrap();
+    lamports - lamports * WITHDRAW_SOL_FEE_BPS / 10_000
+}
+
+// notional token supply and net asset value, as the program prices them
+async fn get_pool_value(
+    banks_client: &mut BanksClient,
+    accounts: &SinglePoolAccounts,
+) -> (u64, u64) {
+    let stake_rent = get_stake_account_rent(banks_client).await;
+    let (_, _, pool_lamports) = get_stake_account(banks_client, &accounts.stake_account).await;
+    let (_, _, onramp_lamports) = get_stake_account(banks_client, &accounts.onramp_account).await;
+    let token_supply = get_token_supply(banks_client, &accounts.mint).await;
+
+    (
+        token_supply + PHANTOM_TOKEN_AMOUNT,
+        pool_lamports - stake_rent + onramp_lamports - stake_rent,
+    )
+}
+
+// lamports neither delegated nor reserved, which `WithdrawSol` may pay out
+async fn get_pool_liquidity(
+    banks_client: &mut BanksClient,
+    accounts: &SinglePoolAccounts,
+    minimum_pool_balance: u64,
+) -> u64 {
+    let stake_rent = get_stake_account_rent(banks_client).await;
+    let (_, pool_stake, pool_lamports) =
+        get_stake_account(banks_client, &accounts.stake_account).await;
+    let (_, onramp_stake, onramp_lamports) =
+        get_stake_account(banks_client, &accounts.onramp_account).await;
+
+    let pool_locked = std::cmp::max(pool_stake.unwrap().delegation.stake, minimum_pool_balance);
+    let onramp_locked = onramp_stake.map_or(0, |stake| stake.delegation.stake);
+
+    pool_lamports.saturating_sub(stake_rent + pool_locked)
+        + onramp_lamports.saturating_sub(stake_rent + onramp_locked)
+}
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
+)]
+#[tokio::test]
+async fn success(stake_version: StakeProgramVersion) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+
+    // undelegated lamports in the onramp are what make liquid withdrawals possible
+    transfer(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &accounts.onramp_account,
+        TEST_STAKE_AMOUNT * 2,
+    )
+    .await;
+
+    let wallet_lamports_before = get_account(&mut context.banks_client, &accounts.alice.pubkey())
+        .await
+        .lamports;
+
+    let onramp_lamports_before = get_account(&mut context.banks_client, &accounts.onramp_account)
+        .await
+        .lamports;
+
+    let (_, pool_stake_before, pool_lamports_before) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+
+    let user_tokens_before =
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await;
+    let token_amount = user_tokens_before / 2;
+
+    let (token_supply, net_asset_value) =
+        get_pool_value(&mut context.banks_client, &accounts).await;
+    let expected_lamports = withdraw_sol_payout(token_supply, net_asset_value, token_amount);
+
+    withdraw_sol(&mut context, &accounts, token_amount)
+        .await
+        .unwrap();
+
+    let wallet_lamports_after = get_account(&mut context.banks_client, &accounts.alice.pubkey())
+        .await
+        .lamports;
+
+    let onramp_lamports_after = get_account(&mut context.banks_client, &accounts.onramp_account)
+        .await
+        .lamports;
+
+    let (_, pool_stake_after, pool_lamports_after) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+
+    let user_tokens_after =
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await;
+
+    // user burned exactly what they asked to
+    assert_eq!(user_tokens_before - user_tokens_after, token_amount);
+
+    // user received lamports less the fee, and all of them came out of the onramp
+    let lamports_received = wallet_lamports_after - wallet_lamports_before;
+    assert_eq!(lamports_received, expected_lamports);
+    assert_eq!(
+        lamports_received,
+        onramp_lamports_before - onramp_lamports_after
+    );
+
+    // main pool account was untouched
+    assert_eq!(pool_lamports_after, pool_lamports_before);
+    assert_eq!(pool_stake_after, pool_stake_before);
+}
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
+)]
+#[tokio::test]
+async fn success_from_main(stake_version: StakeProgramVersion) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    let minimum_pool_balance = accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+
+    // excess lamports in the main account are liquid too, and paid out once the onramp is empty
+    let onramp_excess = TEST_STAKE_AMOUNT / 10;
+    for (address, lamports) in [
+        (accounts.onramp_account, onramp_excess),
+        (accounts.stake_account, TEST_STAKE_AMOUNT),
+    ] {
+        transfer(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &address,
+            lamports,
+        )
+        .await;
+    }
+
+    let stake_rent = get_stake_account_rent(&mut context.banks_client).await;
+    let wallet_lamports_before = get_account(&mut context.banks_client, &accounts.alice.pubkey())
+        .await
+        .lamports;
+    let (_, pool_stake_before, pool_lamports_before) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+
+    let token_amount =
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await / 2;
+    let (token_supply, net_asset_value) =
+        get_pool_value(&mut context.banks_client, &accounts).await;
+    let expected_lamports = withdraw_sol_payout(token_supply, net_asset_value, token_amount);
+
+    // this withdrawal needs more than the onramp holds
+    assert!(expected_lamports > onramp_excess);
+
+    withdraw_sol(&mut context, &accounts, token_amount)
+        .await
+        .unwrap();
+
+    let wallet_lamports_after = get_account(&mut context.banks_client, &accounts.alice.pubkey())
+        .await
+        .lamports;
+    let (_, _, onramp_lamports_after) =
+        get_stake_account(&mut context.banks_client, &accounts.onramp_account).await;
+    let (_, pool_stake_after, pool_lamports_after) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+
+    assert_eq!(
+        wallet_lamports_after - wallet_lamports_before,
+        expected_lamports
+    );
+
+    // the onramp was emptied first, and the main account paid the rest
+    assert_eq!(onramp_lamports_after, stake_rent);
+    assert_eq!(
+        pool_lamports_before - pool_lamports_after,
+        expected_lamports - onramp_excess
+    );
+
+    // the main account kept its delegation and the minimum pool balance
+    let pool_stake_after = pool_stake_after.unwrap();
+    assert_eq!(pool_stake_after, pool_stake_before.unwrap());
+    assert!(pool_lamports_after - stake_rent >= pool_stake_after.delegation.stake);
+    assert!(pool_lamports_after - stake_rent >= minimum_pool_balance);
+
+    // the fee stayed in the pool
+    let (_, net_asset_value_after) = get_pool_value(&mut context.banks_client, &accounts).await;
+    assert_eq!(net_asset_value_after, net_asset_value - expected_lamports);
+}
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
+)]
+#[tokio::test]
+async fn fail_one_lamport_over_liquidity(stake_version: StakeProgramVersion) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    let minimum_pool_balance = accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+
+    let starting_liquidity =
+        get_pool_liquidity(&mut context.banks_client, &accounts, minimum_pool_balance).await;
+    let token_amount =
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await / 4;
+    let (token_supply, net_asset_value) =
+        get_pool_value(&mut context.banks_client, &accounts).await;
+
+    // each lamport added to the onramp is liquid, but raises the payout by at most one lamport.
+    // so find the smallest addition the payout fits in: with a lamport less, it is exactly one over
+    let payout =
+        |added: u64| withdraw_sol_payout(token_supply, net_asset_value + added, token_amount);
+    let (mut low, mut high) = (0, net_asset_value);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if payout(mid) <= starting_liquidity + mid {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    let lamports_to_add = low;
+    assert_eq!(
+        payout(lamports_to_add),
+        starting_liquidity + lamports_to_add
+    );
+    assert_eq!(
+        payout(lamports_to_add - 1),
+        starting_liquidity + lamports_to_add
+    );
+
+    transfer(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &accounts.onramp_account,
+        lamports_to_add - 1,
+    )
+    .await;
+
+    let e = withdraw_sol(&mut context, &accounts, token_amount)
+        .await
+        .unwrap_err();
+
+    // fail: payout is one lamport more than the pool can release
+    check_error(e, SinglePoolError::InsufficientLiquidity);
+
+    transfer(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &accounts.onramp_account,
+        1,
+    )
+    .await;
+    refresh_blockhash(&mut context).await;
+
+    // with that lamport, the same withdrawal takes every liquid lamport
+    withdraw_sol(&mut context, &accounts, token_amount)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_pool_liquidity(&mut context.banks_client, &accounts, minimum_pool_balance).await,
+        0
+    );
+
+    // the main account never dips below the minimum pool balance
+    let stake_rent = get_stake_account_rent(&mut context.banks_client).await;
+    let (_, _, pool_lamports_after) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    assert!(pool_lamports_after - stake_rent >= minimum_pool_balance);
+}
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
+)]
+#[tokio::test]
+async fn fail_insufficient_liquidity(stake_version: StakeProgramVersion) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+
+    let user_tokens = get_token_balance(&mut context.banks_client, &accounts.alice_token).await;
+
+    let e = withdraw_sol(&mut context, &accounts, user_tokens)
+        .await
+        .unwrap_err();
+
+    // fail: all pool value is delegated
+    check_error(e, SinglePoolError::InsufficientLiquidity);
+}
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
+)]
+#[tokio::test]
+async fn fail_zero_amount(stake_version: StakeProgramVersion) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+
+    let e = withdraw_sol(&mut context, &accounts, 0).await.unwrap_err();
+
+    // fail: zero withdrawal
+    check_error(e, SinglePoolError::WithdrawalTooSmall);
+}
@@ -1,13 +1,16 @@
 #![allow(clippy::arithmetic_side_effects)]
-mod helpers;
-
 use {
-    helpers::*,
+    solana_clock::Clock,
+    solana_program_error::ProgramError,
     solana_program_pack::Pack,
     solana_program_test::*,
     solana_signer::Signer,
     solana_transaction::Transaction,
-    spl_single_pool::{error::SinglePoolError, id, instruction},
+    spl_single_pool::{
+        error::SinglePoolError, id, inline_spl_token_2022, instruction, EVENT_LOG_PREFIX,
+        EVENT_LOG_VERSION,
+    },
+    spl_single_pool_test_utils::*,
     spl_token_interface::state::Mint,
     test_case::{test_case, test_matrix},
 };
@@ -65,8 +68,9 @@ async fn success(
         .await;
     }
 
-    let instructions = instruction::withdraw(
+    let instructions = instruction::withdraw_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice.pubkey(),
@@ -180,8 +184,9 @@ async fn success_with_rewards(stake_version: StakeProgramVersion) {
     let pool_stake = pool_stake.unwrap().delegation.stake;
     let total_rewards = pool_stake - alice_deposit - bob_deposit - minimum_pool_balance;
 
-    let instructions = instruction::withdraw(
+    let instructions = instruction::withdraw_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice.pubkey(),
@@ -228,6 +233,133 @@ async fn success_with_rewards(stake_version: StakeProgramVersion) {
     );
 }
 
+#[tokio::test]
+async fn success_token_2022() {
+    let mut context = program_test_live().start_with_context().await;
+    let accounts = SinglePoolAccounts::with_token_program(inline_spl_token_2022::id());
+    accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+
+    let mint_account = get_account(&mut context.banks_client, &accounts.mint).await;
+    assert_eq!(mint_account.owner, inline_spl_token_2022::id());
+
+    let token_amount = get_token_balance(&mut context.banks_client, &accounts.alice_token).await;
+    assert!(token_amount > 0);
+
+    let instructions = instruction::withdraw_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &accounts.alice_stake.pubkey(),
+        &accounts.alice.pubkey(),
+        &accounts.alice_token,
+        &accounts.alice.pubkey(),
+        token_amount,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &accounts.alice],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let (_, alice_stake_after, _) =
+        get_stake_account(&mut context.banks_client, &accounts.alice_stake.pubkey()).await;
+    assert_eq!(
+        alice_stake_after.unwrap().delegation.stake,
+        TEST_STAKE_AMOUNT
+    );
+
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await,
+        0,
+    );
+}
+
+#[tokio::test]
+async fn fail_mismatched_token_program() {
+    let mut context = program_test_live().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+
+    // the pool mint belongs to the original token program, so token-2022 must be rejected
+    let instruction = instruction::withdraw_stake_with_token_program(
+        &id(),
+        &inline_spl_token_2022::id(),
+        &accounts.pool,
+        &accounts.alice_stake.pubkey(),
+        &accounts.alice.pubkey(),
+        &accounts.alice_token,
+        TEST_STAKE_AMOUNT,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+
+    let e = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err();
+    check_error(e, ProgramError::IncorrectProgramId);
+}
+
+#[tokio::test]
+async fn success_event_log() {
+    let mut context = program_test_live().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+
+    let token_amount = get_token_balance(&mut context.banks_client, &accounts.alice_token).await;
+    let instructions = instruction::withdraw_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &accounts.alice_stake.pubkey(),
+        &accounts.alice.pubkey(),
+        &accounts.alice_token,
+        &accounts.alice.pubkey(),
+        token_amount,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &accounts.alice],
+        context.last_blockhash,
+    );
+
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    result.result.unwrap();
+
+    let expected_log = format!(
+        "Program log: {} v{} withdraw_stake token_amount={} lamports={}",
+        EVENT_LOG_PREFIX, EVENT_LOG_VERSION, token_amount, TEST_STAKE_AMOUNT,
+    );
+    assert!(result
+        .metadata
+        .unwrap()
+        .log_messages
+        .contains(&expected_log));
+}
+
 #[test_case(true; "activated")]
 #[test_case(false; "activating")]
 #[tokio::test]
@@ -238,8 +370,9 @@ async fn fail_withdraw_to_pool(activate: bool) {
         .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, activate)
         .await;
 
-    let instructions = instruction::withdraw(
+    let instructions = instruction::withdraw_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.stake_account,
         &accounts.stake_authority,
@@ -270,8 +403,9 @@ async fn fail_withdraw_to_onramp() {
         .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
         .await;
 
-    let instructions = instruction::withdraw(
+    let instructions = instruction::withdraw_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.onramp_account,
         &accounts.stake_authority,
@@ -331,8 +465,9 @@ async fn success_withdraw_from_inactive(stake_version: StakeProgramVersion) {
         MANGLED_DELEGATION
     );
 
-    let instructions = instruction::withdraw(
+    let instructions = instruction::withdraw_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice.pubkey(),
@@ -361,6 +496,144 @@ async fn success_withdraw_from_inactive(stake_version: StakeProgramVersion) {
     );
 }
 
+// a pool whose validator is being retired can still be withdrawn from. the new
+// stake account is split from the pool stake, so it is deactivating too
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
+)]
+#[tokio::test]
+async fn success_withdraw_from_deactivating(stake_version: StakeProgramVersion) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+
+    force_deactivating_stake_account(&mut context, &accounts.stake_account).await;
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+
+    let instructions = instruction::withdraw_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &accounts.alice_stake.pubkey(),
+        &accounts.alice.pubkey(),
+        &accounts.alice_token,
+        &accounts.alice.pubkey(),
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&accounts.alice.pubkey()),
+        &[&accounts.alice],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let (_, alice_stake_after, _) =
+        get_stake_account(&mut context.banks_client, &accounts.alice_stake.pubkey()).await;
+    let delegation = alice_stake_after.unwrap().delegation;
+    assert_eq!(delegation.stake, TEST_STAKE_AMOUNT);
+    assert_eq!(delegation.voter_pubkey, accounts.vote_account.pubkey());
+    assert_eq!(delegation.deactivation_epoch, clock.epoch);
+
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await,
+        0,
+    );
+}
+
+#[test_matrix(
+    [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
+)]
+#[tokio::test]
+async fn success_withdraw_clamped(stake_version: StakeProgramVersion) {
+    let Some(program_test) = program_test(stake_version) else {
+        return;
+    };
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = SinglePoolAccounts::default();
+    accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+
+    let minimum_delegation = get_minimum_delegation(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+    )
+    .await;
+
+    // minimum delegation rises, locking up half of alice's stake in the pool
+    strand_pool_stake(&mut context, &accounts.stake_account, TEST_STAKE_AMOUNT / 2).await;
+
+    let (_, pool_stake_before, _) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    let pool_stake_before = pool_stake_before.unwrap().delegation.stake;
+    let alice_tokens_before =
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await;
+
+    // withdrawal that cannot be fully delivered as stake withdraws what it can
+    let instructions = instruction::withdraw_with_token_program(
+        &id(),
+        &accounts.token_program_id,
+        &accounts.pool,
+        &accounts.alice_stake.pubkey(),
+        &accounts.alice.pubkey(),
+        &accounts.alice_token,
+        &accounts.alice.pubkey(),
+        alice_tokens_before,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&accounts.alice.pubkey()),
+        &[&accounts.alice],
+        context.last_blockhash,
+    );
+
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    result.result.unwrap();
+
+    assert!(result
+        .metadata
+        .unwrap()
+        .log_messages
+        .iter()
+        .any(|log| log.contains("Withdrawal clamped")));
+
+    let (_, alice_stake_after, _) =
+        get_stake_account(&mut context.banks_client, &accounts.alice_stake.pubkey()).await;
+    let alice_stake_after = alice_stake_after.unwrap().delegation.stake;
+
+    let (_, pool_stake_after, _) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    let pool_stake_after = pool_stake_after.unwrap().delegation.stake;
+
+    // alice got all splittable stake, and the pool kept exactly minimum delegation
+    assert_eq!(alice_stake_after, pool_stake_before - minimum_delegation);
+    assert_eq!(pool_stake_after, minimum_delegation);
+
+    // alice keeps the tokens representing the stake she could not withdraw
+    let alice_tokens_after =
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await;
+    assert!(alice_tokens_after > 0);
+    assert!(alice_tokens_after < alice_tokens_before);
+}
+
 #[test_matrix(
     [StakeProgramVersion::Stable, StakeProgramVersion::Beta, StakeProgramVersion::Edge]
 )]
@@ -384,8 +657,9 @@ async fn fail_disallowed_withdraw(stake_version: StakeProgramVersion) {
     .await;
 
     // actually 0 withdraw fails
-    let instructions = instruction::withdraw(
+    let instructions = instruction::withdraw_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice.pubkey(),
@@ -408,8 +682,9 @@ async fn fail_disallowed_withdraw(stake_version: StakeProgramVersion) {
     check_error(e, SinglePoolError::WithdrawalTooSmall);
 
     // sub-minimum delegation withdraw fails
-    let instructions = instruction::withdraw(
+    let instructions = instruction::withdraw_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice.pubkey(),
@@ -441,9 +716,52 @@ async fn fail_disallowed_withdraw(stake_version: StakeProgramVersion) {
     )
     .await;
 
-    // withdrawal that cannot be delivered as stake fails
-    let instructions = instruction::withdraw(
+    // minimum delegation rises such that less than minimum delegation is splittable.
+    // a one-lamport minimum can always be met, so this only applies to higher minimums
+    let (_, pool_stake, _) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    let pool_stake = pool_stake.unwrap().delegation.stake;
+    if minimum_delegation > 1 {
+        let stranded = pool_stake - minimum_delegation - minimum_delegation / 2;
+        strand_pool_stake(&mut context, &accounts.stake_account, stranded).await;
+        refresh_blockhash(&mut context).await;
+
+        // withdrawal that cannot be delivered as stake fails
+        let instructions = instruction::withdraw_with_token_program(
+            &id(),
+            &accounts.token_program_id,
+            &accounts.pool,
+            &accounts.alice_stake.pubkey(),
+            &accounts.alice.pubkey(),
+            &accounts.alice_token,
+            &accounts.alice.pubkey(),
+            TEST_STAKE_AMOUNT,
+        );
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&accounts.alice.pubkey()),
+            &[&accounts.alice],
+            context.last_blockhash,
+        );
+
+        let e = context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err();
+        check_error(e, SinglePoolError::WithdrawalTooLarge);
+    }
+
+    // minimum delegation rises such that nothing is splittable
+    let (_, pool_stake, _) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    let stranded = pool_stake.unwrap().delegation.stake - minimum_delegation;
+    strand_pool_stake(&mut context, &accounts.stake_account, stranded).await;
+    refresh_blockhash(&mut context).await;
+
+    let instructions = instruction::withdraw_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice.pubkey(),
@@ -463,7 +781,7 @@ async fn fail_disallowed_withdraw(stake_version: StakeProgramVersion) {
         .process_transaction(transaction)
         .await
         .unwrap_err();
-    check_error(e, SinglePoolError::WithdrawalTooLarge);
+    check_error(e, SinglePoolError::WithdrawalViolatesPoolRequirements);
 
     // slash the pool percentage that one token represents
     let mut mint_account = get_account(&mut context.banks_client, &accounts.mint).await;
@@ -476,8 +794,9 @@ async fn fail_disallowed_withdraw(stake_version: StakeProgramVersion) {
     force_deactivate_stake_account(&mut context, &accounts.stake_account).await;
 
     // withdrawal that rounds to 0 fails
-    let instructions = instruction::withdraw(
+    let instructions = instruction::withdraw_with_token_program(
         &id(),
+        &accounts.token_program_id,
         &accounts.pool,
         &accounts.alice_stake.pubkey(),
         &accounts.alice.pubkey(),